@@ -1,23 +1,40 @@
-use crate::browser::GLOBAL_BROWSER;
+use crate::approval::{require_approval, url_matches_domains};
 use crate::config::Config;
+use crate::frontier::Admission;
 use crate::memory::GLOBAL_MEMORY;
-use crate::search::search_content;
+use crate::pagediff::diff_pages;
+use crate::plugins::NexusTool;
+use crate::providers::build_llm;
+use crate::replay::BrowserAction;
+use crate::retry::{with_retry, RetryPolicy};
+use crate::run::{RunContext, RunRecord, RunResult, RunState};
+use crate::search::{search_with_variants, SearchOptions};
+use crate::shapes::{
+    ComparisonReport, FaqReport, OutputPreset, RawDataReport, ReportShape, SchemaReport,
+    TimelineReport,
+};
+use crate::usage::MeteredLlm;
 use crate::GLOBAL_APP;
 use html_to_markdown_rs::convert;
-use radkit::agent::LlmWorker;
+use radkit::agent::{LlmFunction, LlmWorker};
 use radkit::macros::{tool, LLMOutput};
-use radkit::models::providers::{
-    AnthropicLlm, DeepSeekLlm, GeminiLlm, GrokLlm, OpenAILlm, OpenRouterLlm,
-};
-use radkit::models::BaseLlm;
-use radkit::tools::ToolResult;
+use radkit::models::{Event, Thread};
+use radkit::tools::{FunctionTool, ToolResult};
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use std::sync::Arc;
 use tauri::Emitter;
 
 // --- Structured Output Types ---
 
+/// Sub-questions the orchestrator hands to parallel sub-agents
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct ResearchSplit {
+    /// Independent, self-contained sub-questions that together answer the task.
+    pub sub_questions: Vec<String>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
 pub struct NexusReport {
     /// A detailed, synthesized report in Markdown format.
@@ -34,18 +51,39 @@ pub struct NexusReport {
 struct NavigateArgs {
     /// The URL to navigate to.
     url: String,
+    /// Maximum characters of page content to return (defaults to the configured limit).
+    max_chars: Option<usize>,
 }
 
 #[derive(Deserialize, JsonSchema)]
 struct FindInPageArgs {
-    /// The text to find in the current page.
+    /// The text to find in the current page. A regular expression unless literal is set.
     query: String,
+    /// Ignore letter case. Defaults to false.
+    case_insensitive: Option<bool>,
+    /// Match the query as plain text, so characters like ( or ? need no escaping. Defaults to false.
+    literal: Option<bool>,
+    /// Only match the query as a whole word. Defaults to false.
+    whole_word: Option<bool>,
+    /// Lines of surrounding context to return with each match. Defaults to the configured amount.
+    context_lines: Option<usize>,
+    /// Find lines resembling the query despite typos or slightly different wording, each with a
+    /// similarity score. Ignores the other matching flags. Defaults to false.
+    fuzzy: Option<bool>,
+    /// Treat the query as terms combined with AND, OR, NOT and parentheses, e.g.
+    /// price AND (annual OR yearly). Adjacent words form a phrase; quote a term to keep operator
+    /// words in it. Defaults to false.
+    boolean: Option<bool>,
+    /// Most matches to return, best first. Defaults to the configured limit.
+    max_results: Option<usize>,
 }
 
 #[derive(Deserialize, JsonSchema)]
 struct ClickArgs {
     /// CSS selector of the element to click.
     selector: String,
+    /// Maximum characters of page content to return (defaults to the configured limit).
+    max_chars: Option<usize>,
 }
 
 #[derive(Deserialize, JsonSchema)]
@@ -60,6 +98,8 @@ struct ScrollArgs {
     direction: String,
     /// Amount in pixels (default 500).
     amount: Option<i32>,
+    /// Maximum characters of page content to return (defaults to the configured limit).
+    max_chars: Option<usize>,
 }
 
 #[derive(Deserialize, JsonSchema)]
@@ -76,36 +116,276 @@ struct MemorizeArgs {
     note: String,
     /// Optional tags for categorization.
     tags: Option<Vec<String>>,
+    /// Days until the note goes stale and leaves memory, e.g. for prices or
+    /// availability. Omit for lasting facts.
+    ttl_days: Option<u64>,
+    /// How much the note matters, from 1 (trivia) to 5 (essential). Defaults
+    /// to 3; less important notes are dropped first when memory is full.
+    importance: Option<u8>,
+    /// Page the note comes from. Defaults to the page currently open; a note
+    /// restating an existing one keeps its original source.
+    source_url: Option<String>,
+    /// "session" for working notes only needed in this session, or
+    /// "long_term" (the default) for knowledge worth keeping.
+    scope: Option<crate::memory::MemoryScope>,
 }
 
 #[derive(Deserialize, JsonSchema)]
 struct RecallArgs {
     /// Optional query to filter memories.
     query: Option<String>,
+    /// Only return notes carrying at least one of these tags.
+    tags: Option<Vec<String>>,
+    /// Most notes to return, best matches first. Defaults to 10.
+    limit: Option<usize>,
+    /// Return the full text of long notes instead of their condensed summaries.
+    expand: Option<bool>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ExtractWithRecipeArgs {
+    /// Recipe name or domain. Defaults to the domain of the current page.
+    recipe: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ExtractStructuredArgs {
+    /// JSON Schema the extracted data must match.
+    schema: serde_json::Value,
+    /// Optional guidance on what to extract, e.g. "only products in stock".
+    instructions: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct RequestHumanActionArgs {
+    /// What the user needs to do, e.g. "Solve the captcha" or "Call the vendor for a quote".
+    description: String,
+    /// Page the task relates to, if any.
+    url: Option<String>,
+    /// When it needs doing by, if known.
+    due: Option<String>,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct BookmarkArgs {
+    /// Short name for the position, e.g. "pricing-table".
+    name: String,
 }
 
 // --- Helper Functions ---
 
-fn emit_event(event_type: &str, message: String) {
+pub fn emit_event(event_type: &str, message: String) {
     if let Some(app) = GLOBAL_APP.get() {
-        let _ = app.emit("agent-event", json!({
+        let mut payload = json!({
             "type": event_type,
-            "message": message,
+            "message": crate::redact::redact(&message),
             "timestamp": std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().as_millis() as u64
-        }));
+        });
+        if let Some(run) = crate::run::current() {
+            let usage = run.usage();
+            payload["run_id"] = json!(run.run_id);
+            if let Some(parent) = &run.parent_run_id {
+                payload["parent_run_id"] = json!(parent);
+            }
+            payload["cost"] = json!({
+                "prompt_tokens": usage.prompt_tokens,
+                "completion_tokens": usage.completion_tokens,
+                "cost_usd": usage.cost_usd
+            });
+        }
+        let _ = app.emit("agent-event", payload);
     }
 }
 
-fn process_content(html: String) -> String {
+/// Count a tool call against the run's step budget, first waiting out any
+/// pause while the user has taken over the browser
+async fn begin_step(tool: &str) -> Result<(), ToolResult> {
+    let Some(run) = crate::run::current() else {
+        return Ok(());
+    };
+    if run.is_paused() {
+        crate::trace_info!(
+            "nexus::agent::steps",
+            "Waiting for run to resume",
+            tool = tool
+        );
+        run.wait_while_paused().await;
+    }
+    run.record_tool(tool);
+    match run.next_step() {
+        Ok(step) => {
+            crate::trace_debug!("nexus::agent::steps", "Step started", tool = tool, step = step);
+            Ok(())
+        }
+        Err(reason) => {
+            crate::trace_warn!(
+                "nexus::agent::steps",
+                "Run budget exhausted",
+                tool = tool,
+                reason = reason
+            );
+            emit_event(
+                "system",
+                format!("Budget exhausted ({}), asking agent to wrap up", reason),
+            );
+            Err(ToolResult::error(format!(
+                "Budget exhausted: {} reached. Do not call any more tools; write your final report now from what you have gathered.",
+                reason
+            )))
+        }
+    }
+}
+
+/// Opening of every partial report
+const PARTIAL_NOTICE: &str = "> **Budget exhausted:**";
+
+/// Whether `report` is a partial report from a run cut off by its budget
+pub fn is_partial(report: &str) -> bool {
+    report.starts_with(PARTIAL_NOTICE)
+}
+
+/// Best-effort report used when the worker is cut off by a run budget
+fn partial_report(run: &RunContext, reason: &str) -> NexusReport {
+    let sources = run.sources();
+    let findings = run.findings();
+    let mut markdown = format!(
+        "{} the agent stopped after {} steps ({}). This report is partial.\n",
+        PARTIAL_NOTICE,
+        run.steps().min(run.config.max_steps),
+        reason
+    );
+    if !findings.is_empty() {
+        markdown.push_str("\n## Findings so far\n\n");
+        for finding in &findings {
+            markdown.push_str(&format!("- {}\n", finding));
+        }
+    }
+    if !sources.is_empty() {
+        markdown.push_str("\n## Pages visited\n\n");
+        for source in &sources {
+            markdown.push_str(&format!("- {}\n", source));
+        }
+    }
+    NexusReport {
+        markdown_report: markdown,
+        key_discoveries: findings,
+        sources,
+    }
+}
+
+/// Page Markdown cut to the content limit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PageContent {
+    pub content: String,
+    pub total_chars: usize,
+    pub truncated_fraction: f64,
+    pub condensed: bool,
+}
+
+/// Convert page HTML to Markdown within `max_chars` or the run's configured
+/// limit, condensing or truncating longer pages
+async fn process_content(html: String, max_chars: Option<usize>) -> PageContent {
+    let config = crate::run::current()
+        .map(|run| run.config.clone())
+        .unwrap_or_default();
+    page_content(html, max_chars, &config).await
+}
+
+/// [`process_content`] under an explicit config, for showing the user the
+/// page as the agent would see it
+pub async fn page_content(html: String, max_chars: Option<usize>, config: &Config) -> PageContent {
     let md = convert(&html, None).unwrap_or_else(|e| format!("Conversion failed: {}", e));
-    // Increased limit to 15k for better context on long pages (e.g. HN)
-    let limit = 15000;
+    let limit = max_chars.unwrap_or(config.max_page_content_chars);
+    let total_chars = md.chars().count();
+    if total_chars <= limit {
+        return PageContent {
+            content: md,
+            total_chars,
+            truncated_fraction: 0.0,
+            condensed: false,
+        };
+    }
+
+    if config.condense_long_pages {
+        match crate::condense::condense_page(config, &md, limit).await {
+            Ok(summary) => {
+                return PageContent {
+                    content: format!(
+                        "{}\n\n(condensed from {} chars; every section of the page is summarized above)",
+                        summary, total_chars
+                    ),
+                    total_chars,
+                    truncated_fraction: 0.0,
+                    condensed: true,
+                }
+            }
+            Err(e) => crate::trace_warn!(
+                "nexus::agent::content",
+                "Condensing page failed, truncating instead",
+                error = e
+            ),
+        }
+    }
+
+    let truncated_fraction = (total_chars - limit) as f64 / total_chars as f64;
     let truncated: String = md.chars().take(limit).collect();
-    if truncated.len() < md.len() {
-        format!("{}... (truncated, total length: {})", truncated, md.len())
-    } else {
-        md
+    PageContent {
+        content: format!(
+            "{}... (truncated {:.0}% of {} chars; pass a larger max_chars to read more)",
+            truncated,
+            truncated_fraction * 100.0,
+            total_chars
+        ),
+        total_chars,
+        truncated_fraction,
+        condensed: false,
+    }
+}
+
+/// Save a screenshot of the current page to the run's workspace, named by step
+async fn save_screenshot(run: &RunContext, browser: &crate::browser::BrowserManager) {
+    let result = async {
+        let png = browser.screenshot_png().await.map_err(|e| e.to_string())?;
+        let dir = run.workspace()?.join("screenshots");
+        std::fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        std::fs::write(dir.join(format!("{:03}.png", run.steps())), png).map_err(|e| e.to_string())
+    }
+    .await;
+    if let Err(e) = result {
+        crate::trace_warn!(
+            "nexus::agent::navigate",
+            "Failed to save screenshot",
+            error = e
+        );
+    }
+}
+
+/// Result for content after an in-page action: only the sections that
+/// changed since the previous content when that's much smaller than the page
+fn action_result(page: &PageContent) -> serde_json::Value {
+    let previous = crate::run::current().and_then(|run| run.replace_page(page.content.clone()));
+    if let Some(previous) = previous {
+        let diff = diff_pages(&previous, &page.content);
+        if diff.is_empty() {
+            return json!({
+                "unchanged": true,
+                "note": "The page content did not change."
+            });
+        }
+        if diff.worth_sending(page.content.chars().count()) {
+            return json!({
+                "diff": diff,
+                "total_chars": page.total_chars,
+                "note": "Only the sections added or removed since the previous content are shown."
+            });
+        }
     }
+    json!({
+        "content": page.content,
+        "total_chars": page.total_chars,
+        "truncated_fraction": page.truncated_fraction,
+        "condensed": page.condensed
+    })
 }
 
 // --- Tools ---
@@ -117,7 +397,12 @@ async fn navigate(args: NavigateArgs) -> ToolResult {
     crate::trace_info!("nexus::agent::navigate", "Tool called", url = args.url);
     emit_event("tool_call", format!("Navigating to {}", args.url));
 
-    let browser = match GLOBAL_BROWSER.get() {
+    if let Err(result) = begin_step("navigate").await {
+        return result;
+    }
+    let mut span = crate::trace_span!("nexus::agent::navigate", "navigate", url = args.url);
+
+    let browser = match crate::browser::current() {
         Some(b) => {
             crate::trace_debug!("nexus::agent::navigate", "Got browser reference");
             b
@@ -128,15 +413,91 @@ async fn navigate(args: NavigateArgs) -> ToolResult {
         }
     };
 
+    let sensitive = crate::run::current()
+        .map(|run| url_matches_domains(&args.url, &run.config.sensitive_domains))
+        .unwrap_or(false);
+    if sensitive {
+        emit_event("approval", format!("Waiting for approval to visit {}", args.url));
+        if let Err(e) = require_approval("navigate", json!({ "url": args.url })).await {
+            emit_event("error", e.clone());
+            return ToolResult::error(e);
+        }
+    }
+
+    let run = crate::run::current();
+    let mut first_visit = false;
+    if let Some(run) = &run {
+        let admission = run
+            .frontier
+            .lock()
+            .map(|mut f| f.admit(&args.url))
+            .unwrap_or(Admission::Admitted);
+        match admission {
+            Admission::Admitted => first_visit = true,
+            Admission::Revisit => crate::trace_debug!(
+                "nexus::agent::navigate",
+                "Returning to a page visited earlier in this run",
+                url = args.url
+            ),
+            Admission::DomainCapReached { domain, cap } => {
+                emit_event("tool_result", format!("Page cap reached for {}", domain));
+                return ToolResult::error(format!(
+                    "Page limit of {} reached for {}. Use other sources.",
+                    cap, domain
+                ));
+            }
+        }
+    }
+
     crate::trace_debug!("nexus::agent::navigate", "Calling navigate_and_get_content");
-    match browser.navigate_and_get_content(&args.url).await {
+    crate::replay::record(BrowserAction::Navigate {
+        url: args.url.clone(),
+    });
+    let navigation = with_retry("navigate", RetryPolicy::current(), || {
+        browser.navigate_and_get_content(&args.url)
+    })
+    .await;
+    match navigation {
         Ok(html) => {
             crate::trace_debug!(
                 "nexus::agent::navigate",
                 "Got HTML response",
                 html_len = html.len()
             );
-            let content = process_content(html);
+            let page = process_content(html, args.max_chars).await;
+            let content = &page.content;
+            if let Some(run) = &run {
+                let sample: String = content.chars().take(2000).collect();
+                if !crate::language::is_allowed(&sample, &run.config.allowed_languages) {
+                    let detected = crate::language::detect_reliable(&sample)
+                        .map(|l| l.eng_name().to_string())
+                        .unwrap_or_default();
+                    crate::trace_info!(
+                        "nexus::agent::navigate",
+                        "Page skipped by language filter",
+                        url = args.url,
+                        language = detected
+                    );
+                    emit_event(
+                        "tool_result",
+                        format!("Skipped {} (language: {})", args.url, detected),
+                    );
+                    return ToolResult::success(json!({
+                        "url": args.url,
+                        "skipped": true,
+                        "note": format!(
+                            "Page is in {}, which is not one of the allowed languages ({}). Look for another source.",
+                            detected,
+                            run.config.allowed_languages.join(", ")
+                        )
+                    }));
+                }
+                run.record_source(&args.url);
+                run.replace_page(content.clone());
+                if run.config.capture_screenshots {
+                    save_screenshot(run, &browser).await;
+                }
+            }
             crate::trace_info!(
                 "nexus::agent::navigate",
                 "Navigation complete",
@@ -152,15 +513,27 @@ async fn navigate(args: NavigateArgs) -> ToolResult {
             );
             ToolResult::success(json!({
                 "url": args.url,
-                "content": content
+                "content": content,
+                "total_chars": page.total_chars,
+                "truncated_fraction": page.truncated_fraction,
+                "condensed": page.condensed
             }))
         }
         Err(e) => {
+            span.fail(&e);
             crate::trace_error!(
                 "nexus::agent::navigate",
                 "Navigation failed",
                 error = e.to_string()
             );
+            if let Some(run) = &run {
+                if first_visit {
+                    if let Ok(mut frontier) = run.frontier.lock() {
+                        frontier.release(&args.url);
+                    }
+                }
+                run.record_failure(&format!("navigate {}: {}", args.url, e));
+            }
             emit_event("error", format!("Failed to navigate: {}", e));
             ToolResult::error(e.to_string())
         }
@@ -168,23 +541,69 @@ async fn navigate(args: NavigateArgs) -> ToolResult {
 }
 
 #[tool(
-    description = "Search for a specific string within the ALREADY LOADED content of the current page."
+    description = "Search the ALREADY LOADED content of the current page. Returns the most relevant matching lines first, with their line numbers and surrounding context."
 )]
 async fn find_in_page(args: FindInPageArgs, _ctx: &radkit::tools::ToolContext<'_>) -> ToolResult {
     emit_event("tool_call", format!("Finding '{}' in page", args.query));
 
-    let browser = match GLOBAL_BROWSER.get() {
+    if let Err(result) = begin_step("find_in_page").await {
+        return result;
+    }
+
+    let browser = match crate::browser::current() {
         Some(b) => b,
         None => return ToolResult::error("Browser not initialized"),
     };
 
-    match browser.get_content().await {
+    let variants = match crate::run::current() {
+        Some(run) if !run.config.query_languages.is_empty() => {
+            match crate::language::expand_query(
+                &run.config,
+                &args.query,
+                &run.config.query_languages,
+            )
+            .await
+            {
+                Ok(variants) => variants,
+                Err(e) => {
+                    crate::trace_warn!(
+                        "nexus::agent::find_in_page",
+                        "Query expansion failed, searching original query only",
+                        error = e
+                    );
+                    Vec::new()
+                }
+            }
+        }
+        _ => Vec::new(),
+    };
+
+    match with_retry("find_in_page", RetryPolicy::current(), || browser.get_content()).await {
         Ok(html) => {
             let content = convert(&html, None).unwrap_or_default();
-            match search_content(&content, &args.query) {
+            let options = SearchOptions {
+                case_insensitive: args.case_insensitive.unwrap_or(false),
+                literal: args.literal.unwrap_or(false),
+                whole_word: args.whole_word.unwrap_or(false),
+                context_lines: args
+                    .context_lines
+                    .or_else(|| crate::run::current().map(|run| run.config.search_context_lines)),
+                fuzzy: args.fuzzy.unwrap_or(false),
+                boolean: args.boolean.unwrap_or(false),
+                max_results: args
+                    .max_results
+                    .or_else(|| crate::run::current().map(|run| run.config.search_max_results)),
+            };
+            match search_with_variants(&content, &args.query, &variants, options) {
                 Ok(matches) => {
                     emit_event("tool_result", format!("Found {} matches", matches.len()));
-                    ToolResult::success(json!({ "matches": matches }))
+                    if variants.is_empty() {
+                        ToolResult::success(json!({ "matches": matches }))
+                    } else {
+                        ToolResult::success(
+                            json!({ "matches": matches, "localized_queries": variants }),
+                        )
+                    }
                 }
                 Err(e) => {
                     emit_event("error", format!("Find failed: {}", e));
@@ -208,7 +627,12 @@ async fn click(args: ClickArgs) -> ToolResult {
     );
     emit_event("tool_call", format!("Clicking '{}'", args.selector));
 
-    let browser = match GLOBAL_BROWSER.get() {
+    if let Err(result) = begin_step("click").await {
+        return result;
+    }
+    let mut span = crate::trace_span!("nexus::agent::click", "click", selector = args.selector);
+
+    let browser = match crate::browser::current() {
         Some(b) => b,
         None => {
             crate::trace_error!("nexus::agent::click", "Browser not initialized");
@@ -216,7 +640,23 @@ async fn click(args: ClickArgs) -> ToolResult {
         }
     };
 
+    if let Ok(true) = browser.is_form_submit(&args.selector).await {
+        emit_event(
+            "approval",
+            format!("Waiting for approval to submit form via '{}'", args.selector),
+        );
+        if let Err(e) = require_approval("submit_form", json!({ "selector": args.selector })).await
+        {
+            emit_event("error", e.clone());
+            return ToolResult::error(e);
+        }
+    }
+
     crate::trace_debug!("nexus::agent::click", "Calling click_element");
+    crate::replay::record(BrowserAction::Click {
+        selector: args.selector.clone(),
+    });
+    // Not retried: a click that timed out may still have submitted a form
     match browser.click_element(&args.selector).await {
         Ok(html) => {
             crate::trace_debug!(
@@ -224,7 +664,8 @@ async fn click(args: ClickArgs) -> ToolResult {
                 "Click succeeded",
                 html_len = html.len()
             );
-            let content = process_content(html);
+            let page = process_content(html, args.max_chars).await;
+            let content = &page.content;
             crate::trace_info!(
                 "nexus::agent::click",
                 "Click complete",
@@ -238,11 +679,10 @@ async fn click(args: ClickArgs) -> ToolResult {
                     content.len()
                 ),
             );
-            ToolResult::success(json!({
-                "content": content
-            }))
+            ToolResult::success(action_result(&page))
         }
         Err(e) => {
+            span.fail(&e);
             crate::trace_error!("nexus::agent::click", "Click failed", error = e.to_string());
             emit_event("error", format!("Failed to click: {}", e));
             ToolResult::error(e.to_string())
@@ -252,25 +692,39 @@ async fn click(args: ClickArgs) -> ToolResult {
 
 #[tool(description = "Type text into the focused element.")]
 async fn type_input(args: TypeArgs) -> ToolResult {
+    // Text typed into a password field is masked in all later events
+    if let Some(browser) = crate::browser::current() {
+        if browser.is_password_focused().await.unwrap_or(false) {
+            crate::redact::register_secret(&args.text);
+        }
+    }
     emit_event("tool_call", format!("Typing '{}'", args.text));
 
-    let browser = match GLOBAL_BROWSER.get() {
+    if let Err(result) = begin_step("type_input").await {
+        return result;
+    }
+    let mut span = crate::trace_span!("nexus::agent::type_input", "type_input");
+
+    let browser = match crate::browser::current() {
         Some(b) => b,
         None => return ToolResult::error("Browser not initialized"),
     };
 
+    crate::replay::record(BrowserAction::Type {
+        text: args.text.clone(),
+    });
     match browser.type_text(&args.text).await {
         Ok(html) => {
-            let content = process_content(html);
+            let page = process_content(html, None).await;
+            let content = &page.content;
             emit_event(
                 "tool_result",
                 format!("Typed text. Content length: {}", content.len()),
             );
-            ToolResult::success(json!({
-                "content": content
-            }))
+            ToolResult::success(action_result(&page))
         }
         Err(e) => {
+            span.fail(&e);
             emit_event("error", format!("Failed to type: {}", e));
             ToolResult::error(e.to_string())
         }
@@ -281,14 +735,28 @@ async fn type_input(args: TypeArgs) -> ToolResult {
 async fn scroll(args: ScrollArgs) -> ToolResult {
     emit_event("tool_call", format!("Scrolling {}", args.direction));
 
-    let browser = match GLOBAL_BROWSER.get() {
+    if let Err(result) = begin_step("scroll").await {
+        return result;
+    }
+    let mut span = crate::trace_span!("nexus::agent::scroll", "scroll", direction = args.direction);
+
+    let browser = match crate::browser::current() {
         Some(b) => b,
         None => return ToolResult::error("Browser not initialized"),
     };
 
-    match browser.scroll_page(&args.direction, args.amount).await {
+    crate::replay::record(BrowserAction::Scroll {
+        direction: args.direction.clone(),
+        amount: args.amount,
+    });
+    match with_retry("scroll", RetryPolicy::current(), || {
+        browser.scroll_page(&args.direction, args.amount)
+    })
+    .await
+    {
         Ok(html) => {
-            let content = process_content(html);
+            let page = process_content(html, args.max_chars).await;
+            let content = &page.content;
             emit_event(
                 "tool_result",
                 format!(
@@ -297,11 +765,10 @@ async fn scroll(args: ScrollArgs) -> ToolResult {
                     content.len()
                 ),
             );
-            ToolResult::success(json!({
-                "content": content
-            }))
+            ToolResult::success(action_result(&page))
         }
         Err(e) => {
+            span.fail(&e);
             emit_event("error", format!("Failed to scroll: {}", e));
             ToolResult::error(e.to_string())
         }
@@ -315,20 +782,45 @@ async fn upload(args: UploadArgs) -> ToolResult {
         format!("Uploading {} to {}", args.file_path, args.selector),
     );
 
-    let browser = match GLOBAL_BROWSER.get() {
+    if let Err(result) = begin_step("upload").await {
+        return result;
+    }
+
+    let browser = match crate::browser::current() {
         Some(b) => b,
         None => return ToolResult::error("Browser not initialized"),
     };
 
+    emit_event(
+        "approval",
+        format!("Waiting for approval to upload {}", args.file_path),
+    );
+    if let Err(e) = require_approval(
+        "upload",
+        json!({ "selector": args.selector, "file_path": args.file_path }),
+    )
+    .await
+    {
+        emit_event("error", e.clone());
+        return ToolResult::error(e);
+    }
+
     match browser.upload_file(&args.selector, &args.file_path).await {
         Ok(html) => {
-            let content = process_content(html);
+            let page = process_content(html, None).await;
+            let content = &page.content;
+            if let Some(run) = crate::run::current() {
+                run.replace_page(content.clone());
+            }
             emit_event(
                 "tool_result",
                 format!("Uploaded file. Content length: {}", content.len()),
             );
             ToolResult::success(json!({
-                "content": content
+                "content": content,
+                "total_chars": page.total_chars,
+                "truncated_fraction": page.truncated_fraction,
+                "condensed": page.condensed
             }))
         }
         Err(e) => {
@@ -343,87 +835,746 @@ async fn memorize(args: MemorizeArgs) -> ToolResult {
     crate::trace_info!("nexus::agent::memorize", "Tool called", note = args.note);
     emit_event("tool_call", format!("Memorizing note: {}", args.note));
 
+    if let Err(result) = begin_step("memorize").await {
+        return result;
+    }
+
+    // Summarize before taking the lock so it isn't held across the LLM call
+    let mut summary = None;
+    if crate::memory::needs_summary(&args.note) {
+        if let Some(run) = crate::run::current() {
+            match crate::memory::summarize(&run.config, &args.note).await {
+                Ok(s) => summary = Some(s),
+                Err(e) => crate::trace_warn!(
+                    "nexus::agent::memorize",
+                    "Failed to summarize long note, storing full text only",
+                    error = e
+                ),
+            }
+        }
+    }
+
+    let explicit_source = args.source_url.filter(|url| !url.trim().is_empty());
+    // The current page is only a fallback for notes without a source
+    let page_url = match (&explicit_source, crate::browser::current()) {
+        (None, Some(browser)) => browser
+            .get_current_url()
+            .await
+            .ok()
+            .filter(|url| url.starts_with("http")),
+        _ => None,
+    };
+
     if let Some(mem_lock) = GLOBAL_MEMORY.get() {
         crate::trace_debug!("nexus::agent::memorize", "Got memory lock reference");
-        if let Ok(mut mem) = mem_lock.lock() {
-            let tags = args.tags.unwrap_or_default();
-            crate::trace_debug!(
-                "nexus::agent::memorize",
-                "Adding to memory",
-                tags_count = tags.len()
-            );
-            mem.add(args.note.clone(), tags.clone());
-            crate::trace_info!("nexus::agent::memorize", "Note memorized successfully");
-            emit_event("tool_result", "Note memorized.".to_string());
-            return ToolResult::success(
-                json!({ "status": "memorized", "note": args.note, "tags": tags }),
-            );
+        let mut mem = mem_lock.write().await;
+        let tags = args.tags.unwrap_or_default();
+        crate::trace_debug!(
+            "nexus::agent::memorize",
+            "Adding to memory",
+            tags_count = tags.len(),
+            summarized = summary.is_some()
+        );
+        let ttl_days = args
+            .ttl_days
+            .or_else(|| crate::run::current().and_then(|run| run.config.memory_ttl_days));
+        let (entry, merged) = mem.add_expiring(
+            args.note.clone(),
+            summary.clone(),
+            tags.clone(),
+            crate::memory::expiry_after_days(ttl_days),
+        );
+        let scope = args.scope.unwrap_or_default();
+        if merged {
+            // Restating a fact never makes it matter less or last shorter
+            if let Some(importance) = args.importance {
+                entry.importance = entry.importance.max(importance.clamp(1, 5));
+            }
+            if scope == crate::memory::MemoryScope::LongTerm {
+                entry.scope = scope;
+            }
+            entry.source_url = explicit_source.or(entry.source_url.take()).or(page_url);
         } else {
-            crate::trace_error!("nexus::agent::memorize", "Failed to acquire memory lock");
+            if let Some(importance) = args.importance {
+                entry.importance = importance.clamp(1, 5);
+            }
+            entry.source_url = explicit_source.or(page_url);
+            entry.scope = scope;
+        }
+        // A merged note keeps the run that first recorded it
+        if entry.run_id.is_none() {
+            entry.run_id = crate::run::current().map(|run| run.run_id.clone());
+        }
+        if let Some(run) = crate::run::current() {
+            let evicted =
+                mem.enforce_limits(&crate::memory::MemoryLimits::from_config(&run.config));
+            if !evicted.is_empty() {
+                crate::trace_warn!(
+                    "nexus::agent::memorize",
+                    "Memory over its size cap, evicted notes",
+                    count = evicted.len(),
+                    policy = format!("{:?}", run.config.memory_eviction)
+                );
+            }
         }
+        if let Some(run) = crate::run::current() {
+            run.record_finding(&args.note);
+        }
+        crate::trace_info!("nexus::agent::memorize", "Note memorized successfully");
+        emit_event("tool_result", "Note memorized.".to_string());
+        return ToolResult::success(json!({
+            "status": "memorized",
+            "note": summary.unwrap_or(args.note),
+            "tags": tags
+        }));
     } else {
         crate::trace_error!("nexus::agent::memorize", "GLOBAL_MEMORY not initialized");
     }
     ToolResult::error("Failed to access memory".to_string())
 }
 
-#[tool(description = "Recall information from your long-term memory.")]
+#[tool(
+    description = "Recall information from your long-term memory, best matches first. Long notes are returned condensed unless expand is true."
+)]
 async fn recall(args: RecallArgs) -> ToolResult {
     emit_event(
         "tool_call",
         format!("Recalling memories. Query: {:?}", args.query),
     );
+
+    if let Err(result) = begin_step("recall").await {
+        return result;
+    }
+
     if let Some(mem_lock) = GLOBAL_MEMORY.get() {
-        if let Ok(mem) = mem_lock.lock() {
-            let notes = if let Some(q) = args.query {
-                mem.search(&q)
-            } else {
-                mem.get_all()
-            };
-            emit_event("tool_result", format!("Recalled {} notes", notes.len()));
-            return ToolResult::success(json!({ "notes": notes }));
+        // Recalling touches the returned notes, so it needs the write lock
+        let (notes, total) = mem_lock.write().await.recall(
+            args.query.as_deref(),
+            &args.tags.unwrap_or_default(),
+            args.limit
+                .unwrap_or(crate::memory::DEFAULT_RECALL_LIMIT)
+                .max(1),
+            crate::memory::now_secs(),
+        );
+        emit_event(
+            "tool_result",
+            format!("Recalled {} of {} matching notes", notes.len(), total),
+        );
+        if args.expand.unwrap_or(false) {
+            return ToolResult::success(json!({ "notes": notes, "total_matches": total }));
         }
+        let condensed: Vec<_> = notes.iter().map(|n| n.condensed()).collect();
+        return ToolResult::success(json!({
+            "notes": condensed,
+            "total_matches": total,
+            "hint": "Notes are ranked by relevance, importance and recency. Notes marked condensed are summaries; call recall with expand=true for the full text, or raise limit for more notes."
+        }));
     }
     ToolResult::error("Failed to access memory".to_string())
 }
 
-async fn execute_nexus_worker<L: BaseLlm + 'static>(
-    llm: L,
-    prompt: String,
-) -> Result<String, String> {
+#[tool(
+    description = "Extract structured data from the current page using a saved extraction recipe (by name or domain). Prefer this over reading the page when a recipe exists."
+)]
+async fn extract_with_recipe(args: ExtractWithRecipeArgs) -> ToolResult {
+    emit_event(
+        "tool_call",
+        format!("Extracting with recipe {:?}", args.recipe),
+    );
+
+    if let Err(result) = begin_step("extract_with_recipe").await {
+        return result;
+    }
+
+    let browser = match crate::browser::current() {
+        Some(b) => b,
+        None => return ToolResult::error("Browser not initialized"),
+    };
+
+    let key = match args.recipe {
+        Some(key) if !key.trim().is_empty() => key,
+        _ => {
+            let url = browser.get_current_url().await.unwrap_or_default();
+            match crate::approval::host_of(&url) {
+                Some(host) => host,
+                None => return ToolResult::error("No recipe given and no page is open"),
+            }
+        }
+    };
+
+    let recipe = match crate::db::with_db(|conn| crate::recipes::find(conn, &key)) {
+        Ok(Some(recipe)) => recipe,
+        Ok(None) => return ToolResult::error(format!("No extraction recipe found for '{}'", key)),
+        Err(e) => return ToolResult::error(e),
+    };
+
+    crate::trace_info!(
+        "nexus::agent::extract_with_recipe",
+        "Applying recipe",
+        recipe = recipe.name,
+        domain = recipe.domain
+    );
+    match with_retry("extract_with_recipe", RetryPolicy::current(), || {
+        browser.evaluate_json(recipe.to_script())
+    })
+    .await
+    {
+        Ok(data) => {
+            emit_event(
+                "tool_result",
+                format!("Extracted data with recipe '{}'", recipe.name),
+            );
+            ToolResult::success(json!({ "recipe": recipe.name, "data": data }))
+        }
+        Err(e) => {
+            emit_event("error", format!("Recipe extraction failed: {}", e));
+            ToolResult::error(e.to_string())
+        }
+    }
+}
+
+#[tool(
+    description = "Extract data from the current page as JSON matching a JSON Schema you provide. Use this when the task asks for machine-readable output."
+)]
+async fn extract_structured(args: ExtractStructuredArgs) -> ToolResult {
+    emit_event("tool_call", "Extracting structured data".to_string());
+
+    if let Err(result) = begin_step("extract_structured").await {
+        return result;
+    }
+
+    let browser = match crate::browser::current() {
+        Some(b) => b,
+        None => return ToolResult::error("Browser not initialized"),
+    };
+    let config = crate::run::current()
+        .map(|run| run.config.clone())
+        .unwrap_or_default();
+
+    match extract_structured_from(&browser, &config, &args.schema, args.instructions.as_deref())
+        .await
+    {
+        Ok(data) => {
+            emit_event("tool_result", "Extracted structured data".to_string());
+            ToolResult::success(json!({ "data": data }))
+        }
+        Err(e) => {
+            emit_event("error", format!("Structured extraction failed: {}", e));
+            ToolResult::error(e)
+        }
+    }
+}
+
+/// Run schema-driven extraction over the page currently open in `browser`
+pub async fn extract_structured_from(
+    browser: &crate::browser::BrowserManager,
+    config: &Config,
+    schema: &serde_json::Value,
+    instructions: Option<&str>,
+) -> Result<serde_json::Value, String> {
+    let url = browser.get_current_url().await.unwrap_or_default();
+    let html = browser.get_content().await.map_err(|e| e.to_string())?;
+    let page = convert(&html, None).map_err(|e| format!("Conversion failed: {}", e))?;
+    crate::structured::extract(config, &url, &page, schema, instructions).await
+}
+
+#[tool(
+    description = "Queue a task you cannot complete yourself (captcha, phone call, login with 2FA, payment) on the user's to-do list, then carry on with the rest of the research."
+)]
+async fn request_human_action(args: RequestHumanActionArgs) -> ToolResult {
+    emit_event(
+        "tool_call",
+        format!("Requesting human action: {}", args.description),
+    );
+
+    if let Err(result) = begin_step("request_human_action").await {
+        return result;
+    }
+
+    let action = crate::human_actions::HumanAction::new(
+        crate::run::current().map(|run| run.run_id.clone()),
+        args.description,
+        args.url,
+        args.due,
+    );
+    if let Err(e) = crate::db::with_db(|conn| crate::human_actions::insert(conn, &action)) {
+        crate::trace_error!(
+            "nexus::agent::request_human_action",
+            "Failed to store action",
+            error = e
+        );
+        return ToolResult::error(e);
+    }
+    if let Some(run) = crate::run::current() {
+        run.record_finding(&format!("Needs user action: {}", action.description));
+    }
+    if let Some(app) = GLOBAL_APP.get() {
+        let _ = app.emit("human-action", &action);
+    }
+    emit_event("tool_result", "Task added to the user's to-do list".to_string());
+    ToolResult::success(json!({ "status": "queued", "id": action.id }))
+}
+
+#[tool(
+    description = "Save the current position on the page (scroll offset and nearest heading) under a name, to jump back later with return_to_bookmark."
+)]
+async fn bookmark_position(args: BookmarkArgs) -> ToolResult {
+    emit_event("tool_call", format!("Bookmarking position '{}'", args.name));
+
+    if let Err(result) = begin_step("bookmark_position").await {
+        return result;
+    }
+
+    let Some(run) = crate::run::current() else {
+        return ToolResult::error("Bookmarks are only available during a run");
+    };
+    let browser = match crate::browser::current() {
+        Some(b) => b,
+        None => return ToolResult::error("Browser not initialized"),
+    };
+
+    let position = match browser.evaluate_json(crate::bookmarks::capture_script()).await {
+        Ok(value) => match serde_json::from_value(value) {
+            Ok(position) => position,
+            Err(e) => return ToolResult::error(format!("Unexpected position data: {}", e)),
+        },
+        Err(e) => {
+            emit_event("error", format!("Bookmark failed: {}", e));
+            return ToolResult::error(e.to_string());
+        }
+    };
+    let bookmark = crate::bookmarks::Bookmark::new(&args.name, position);
+    emit_event(
+        "tool_result",
+        format!(
+            "Bookmarked '{}' at {}",
+            bookmark.name,
+            bookmark.heading.as_deref().unwrap_or("current offset")
+        ),
+    );
+    let result = json!({ "bookmark": bookmark });
+    run.save_bookmark(bookmark);
+    ToolResult::success(result)
+}
+
+#[tool(
+    description = "Jump back to a position saved with bookmark_position and return the text of that section."
+)]
+async fn return_to_bookmark(args: BookmarkArgs) -> ToolResult {
+    emit_event("tool_call", format!("Returning to bookmark '{}'", args.name));
+
+    if let Err(result) = begin_step("return_to_bookmark").await {
+        return result;
+    }
+
+    let Some(run) = crate::run::current() else {
+        return ToolResult::error("Bookmarks are only available during a run");
+    };
+    let Some(bookmark) = run.bookmark(&args.name) else {
+        return ToolResult::error(format!(
+            "No bookmark named '{}'. Saved bookmarks: {:?}",
+            args.name,
+            run.bookmark_names()
+        ));
+    };
+    let browser = match crate::browser::current() {
+        Some(b) => b,
+        None => return ToolResult::error("Browser not initialized"),
+    };
+
+    let current_url = browser.get_current_url().await.unwrap_or_default();
+    if current_url != bookmark.url {
+        if let Err(e) = with_retry("return_to_bookmark", RetryPolicy::current(), || {
+            browser.navigate_and_get_content(&bookmark.url)
+        })
+        .await
+        {
+            return ToolResult::error(format!("Failed to reopen {}: {}", bookmark.url, e));
+        }
+    }
+
+    match browser.evaluate_json(bookmark.restore_script()).await {
+        Ok(value) => match serde_json::from_value::<crate::bookmarks::RestoredPosition>(value) {
+            Ok(restored) => {
+                emit_event(
+                    "tool_result",
+                    format!("Back at '{}' (by {})", bookmark.name, restored.method),
+                );
+                ToolResult::success(json!({
+                    "bookmark": bookmark.name,
+                    "url": bookmark.url,
+                    "heading": bookmark.heading,
+                    "found_by": restored.method,
+                    "section_text": restored.text
+                }))
+            }
+            Err(e) => ToolResult::error(format!("Unexpected position data: {}", e)),
+        },
+        Err(e) => {
+            emit_event("error", format!("Return to bookmark failed: {}", e));
+            ToolResult::error(e.to_string())
+        }
+    }
+}
+
+/// A tool the worker can offer the model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ToolInfo {
+    pub name: String,
+    /// The prompt pack's description when it overrides the built-in one
+    pub description: String,
+    /// JSON Schema of the tool's arguments
+    pub parameters: serde_json::Value,
+    pub enabled: bool,
+    pub plugin: bool,
+}
+
+/// Built-in description and argument schema of the tool called `name`
+fn builtin_tool(name: &str) -> Option<(&'static str, schemars::Schema)> {
+    use schemars::schema_for;
+    Some(match name {
+        "navigate" => (
+            "Navigate to a URL and return its body content as Markdown. Use this to visit specific sites.",
+            schema_for!(NavigateArgs),
+        ),
+        "find_in_page" => (
+            "Search the ALREADY LOADED content of the current page. Returns the most relevant matching lines first, with their line numbers and surrounding context.",
+            schema_for!(FindInPageArgs),
+        ),
+        "click" => (
+            "Click an element by CSS selector and return updated content.",
+            schema_for!(ClickArgs),
+        ),
+        "type_input" => ("Type text into the focused element.", schema_for!(TypeArgs)),
+        "scroll" => ("Scroll the page up or down.", schema_for!(ScrollArgs)),
+        "upload" => (
+            "Upload a file to a specific file input selector.",
+            schema_for!(UploadArgs),
+        ),
+        "memorize" => (
+            "Store context or findings in your long-term memory.",
+            schema_for!(MemorizeArgs),
+        ),
+        "recall" => (
+            "Recall information from your long-term memory, best matches first. Long notes are returned condensed unless expand is true.",
+            schema_for!(RecallArgs),
+        ),
+        "extract_with_recipe" => (
+            "Extract structured data from the current page using a saved extraction recipe (by name or domain). Prefer this over reading the page when a recipe exists.",
+            schema_for!(ExtractWithRecipeArgs),
+        ),
+        "extract_structured" => (
+            "Extract data from the current page as JSON matching a JSON Schema you provide. Use this when the task asks for machine-readable output.",
+            schema_for!(ExtractStructuredArgs),
+        ),
+        "request_human_action" => (
+            "Queue a task you cannot complete yourself (captcha, phone call, login with 2FA, payment) on the user's to-do list, then carry on with the rest of the research.",
+            schema_for!(RequestHumanActionArgs),
+        ),
+        "bookmark_position" => (
+            "Save the current position on the page (scroll offset and nearest heading) under a name, to jump back later with return_to_bookmark.",
+            schema_for!(BookmarkArgs),
+        ),
+        "return_to_bookmark" => (
+            "Jump back to a position saved with bookmark_position and return the text of that section.",
+            schema_for!(BookmarkArgs),
+        ),
+        _ => return None,
+    })
+}
+
+/// Every built-in and plugin tool, with whether `config` enables it
+pub fn list_tools(config: &Config) -> Vec<ToolInfo> {
+    let pack = crate::prompts::load();
+    let description = |name: &str, builtin: &str| {
+        pack.tool_descriptions
+            .get(name)
+            .filter(|d| !d.trim().is_empty())
+            .cloned()
+            .unwrap_or_else(|| builtin.to_string())
+    };
+    let mut tools: Vec<ToolInfo> = crate::prompts::BUILTIN_TOOLS
+        .iter()
+        .filter_map(|&name| {
+            let (builtin, schema) = builtin_tool(name)?;
+            Some(ToolInfo {
+                name: name.to_string(),
+                description: description(name, builtin),
+                parameters: serde_json::to_value(schema).unwrap_or_default(),
+                enabled: config.tool_enabled(name),
+                plugin: false,
+            })
+        })
+        .collect();
+    for plugin in crate::plugins::summaries() {
+        tools.push(ToolInfo {
+            description: description(&plugin.name, &plugin.description),
+            enabled: config.tool_enabled(&plugin.name),
+            name: plugin.name,
+            parameters: plugin.parameters,
+            plugin: true,
+        });
+    }
+    tools
+}
+
+/// Adapt an MCP server's tool like a plugin tool, but counted as a step and
+/// held for approval like the built-in tools
+fn mcp_function_tool(tool: Arc<dyn NexusTool>, require: bool) -> Arc<FunctionTool> {
+    let name = tool.name().to_string();
+    let description = tool.description().to_string();
+    let schema = tool.parameters_schema();
+    Arc::new(
+        FunctionTool::new(name, description, move |args, _ctx| {
+            let tool = tool.clone();
+            Box::pin(async move {
+                if let Err(result) = begin_step(tool.name()).await {
+                    return result;
+                }
+                let args = serde_json::to_value(&args).unwrap_or_default();
+                let sensitive = crate::run::current().is_some_and(|run| {
+                    string_values(&args)
+                        .iter()
+                        .any(|value| url_matches_domains(value, &run.config.sensitive_domains))
+                });
+                if require || sensitive {
+                    emit_event(
+                        "approval",
+                        format!("Waiting for approval to call {}", tool.name()),
+                    );
+                    if let Err(e) = require_approval(tool.name(), args.clone()).await {
+                        emit_event("error", e.clone());
+                        return ToolResult::error(e);
+                    }
+                }
+                crate::trace_info!("nexus::agent::mcp", "MCP tool called", name = tool.name());
+                match tool.call(args).await {
+                    Ok(value) => ToolResult::success(value),
+                    Err(e) => {
+                        crate::trace_error!(
+                            "nexus::agent::mcp",
+                            "MCP tool failed",
+                            name = tool.name(),
+                            error = e
+                        );
+                        ToolResult::error(e)
+                    }
+                }
+            })
+        })
+        .with_parameters_schema(schema),
+    )
+}
+
+/// Every string anywhere in `value`
+fn string_values(value: &serde_json::Value) -> Vec<&str> {
+    match value {
+        serde_json::Value::String(s) => vec![s.as_str()],
+        serde_json::Value::Array(items) => items.iter().flat_map(string_values).collect(),
+        serde_json::Value::Object(map) => map.values().flat_map(string_values).collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Build the Nexus worker with the built-in, plugin and MCP tools
+fn build_worker<T: ReportShape>(llm: MeteredLlm, run: Option<&RunContext>) -> LlmWorker<T> {
     crate::trace_info!("nexus::agent::worker", "Building LlmWorker");
 
     // We use the worker directly as we don't need the full A2A runtime server for this loop
-    let worker = LlmWorker::<NexusReport>::builder(llm)
-        .with_system_instructions("You are Nexus, a premium, autonomous browser agent. Your mission is to provide high-quality, structured reports.")
-        .with_tool(navigate)
-        .with_tool(find_in_page)
-        .with_tool(click)
-        .with_tool(type_input)
-        .with_tool(scroll)
-        .with_tool(upload)
-        .with_tool(memorize)
-        .with_tool(recall)
-        .build();
+    let pack = crate::prompts::load();
+    let mut instructions = match run {
+        Some(run) => pack.instructions(&run.config),
+        None => pack.instructions(&Config::default()),
+    };
+    if let Some(plan) = run.and_then(|r| r.plan()) {
+        instructions.push_str("\n\n");
+        instructions.push_str(&crate::planning::format_plan(&plan));
+    }
+    // Sub-agents always report back in the default shape
+    if let Some(shape) = run
+        .filter(|r| r.parent_run_id.is_none())
+        .and_then(|r| crate::shapes::shape_instructions(&r.config))
+    {
+        instructions.push_str("\n\n");
+        instructions.push_str(&shape);
+    }
+
+    // Tools switched off in the config are never offered to the model
+    let enabled = |name: &str| run.is_none_or(|r| r.config.tool_enabled(name));
+    let mut builder = LlmWorker::<T>::builder(llm).with_system_instructions(instructions);
+    macro_rules! with_enabled_tools {
+        ($($tool:ident),*) => {
+            $(
+                if enabled(stringify!($tool)) {
+                    builder = builder.with_tool($tool);
+                }
+            )*
+        };
+    }
+    with_enabled_tools!(
+        navigate,
+        find_in_page,
+        click,
+        type_input,
+        scroll,
+        upload,
+        memorize,
+        recall,
+        extract_with_recipe,
+        extract_structured,
+        request_human_action,
+        bookmark_position,
+        return_to_bookmark
+    );
+
+    // MCP tools may not shadow a built-in or plugin tool
+    let mut taken: std::collections::HashSet<String> = crate::prompts::BUILTIN_TOOLS
+        .iter()
+        .map(|name| name.to_string())
+        .collect();
+    for plugin in crate::plugins::discover() {
+        taken.insert(plugin.name().to_string());
+        if !enabled(plugin.name()) {
+            continue;
+        }
+        crate::trace_debug!(
+            "nexus::agent::worker",
+            "Registering plugin tool",
+            name = plugin.name()
+        );
+        builder = builder.with_tool(crate::plugins::to_function_tool(plugin));
+    }
+    for client in run.map(|r| r.mcp_clients.as_slice()).unwrap_or_default() {
+        for tool in client.tools() {
+            if !enabled(tool.name()) {
+                continue;
+            }
+            if !taken.insert(tool.name().to_string()) {
+                crate::trace_warn!(
+                    "nexus::agent::worker",
+                    "Skipping MCP tool named like another tool",
+                    name = tool.name(),
+                    server = client.name
+                );
+                continue;
+            }
+            crate::trace_debug!(
+                "nexus::agent::worker",
+                "Registering MCP tool",
+                name = tool.name()
+            );
+            builder = builder.with_tool(mcp_function_tool(tool, client.require_approval));
+        }
+    }
+    if let Some(run) = run {
+        // Leave room for the final answer after the last permitted tool call
+        builder = builder.with_max_iterations(run.config.max_steps + 2);
+    }
+    builder.build()
+}
+
+/// Run `$f::<Shape>(args)` with the output shape the run's config asks for
+macro_rules! with_output_shape {
+    ($config:expr, $f:ident($($arg:expr),*)) => {
+        match (
+            $config.output_schema.is_some(),
+            $config.output_preset.unwrap_or(OutputPreset::Report),
+        ) {
+            (true, _) => $f::<SchemaReport>($($arg),*).await,
+            (false, OutputPreset::Report) => $f::<NexusReport>($($arg),*).await,
+            (false, OutputPreset::ComparisonTable) => $f::<ComparisonReport>($($arg),*).await,
+            (false, OutputPreset::Timeline) => $f::<TimelineReport>($($arg),*).await,
+            (false, OutputPreset::Faq) => $f::<FaqReport>($($arg),*).await,
+            (false, OutputPreset::RawData) => $f::<RawDataReport>($($arg),*).await,
+        }
+    };
+}
+
+async fn execute_nexus_worker(llm: MeteredLlm, thread: Thread) -> Result<String, String> {
+    let config = crate::run::current()
+        .map(|run| run.config.clone())
+        .unwrap_or_default();
+    with_output_shape!(config, execute_worker(llm, thread))
+}
+
+/// Render a finished report, noting any shape problems in it
+fn finish_report<T: ReportShape>(report: &T, config: &Config) -> String {
+    let markdown = report.to_markdown();
+    match report.check(config) {
+        Ok(()) => markdown,
+        Err(problem) => {
+            crate::trace_warn!(
+                "nexus::agent::worker",
+                "Report does not match the requested shape",
+                problem = problem
+            );
+            emit_event("error", problem.clone());
+            format!("{}\n\n> **Warning:** {}", markdown, problem)
+        }
+    }
+}
+
+async fn execute_worker<T: ReportShape>(llm: MeteredLlm, thread: Thread) -> Result<String, String> {
+    let run = crate::run::current();
+    let config = run.as_ref().map(|r| r.config.clone()).unwrap_or_default();
+    let worker = build_worker::<T>(llm.with_checkpoints(), run.as_deref());
 
     crate::trace_info!(
         "nexus::agent::worker",
         "Worker built, starting execution",
-        prompt_len = prompt.len()
+        thread_events = thread.events().len()
     );
 
-    match worker.run(prompt).await {
-        Ok(report) => {
+    let outcome = match &run {
+        Some(ctx) => {
+            tokio::select! {
+                result = worker.run_and_continue(thread) => Some(result),
+                _ = ctx.aborted() => None,
+            }
+        }
+        None => Some(worker.run_and_continue(thread).await),
+    };
+    let Some(outcome) = outcome else {
+        let run = run.as_ref().unwrap();
+        let reason = run.stop_reason().unwrap_or_else(|| "budget".to_string());
+        crate::trace_warn!(
+            "nexus::agent::worker",
+            "Worker aborted by spend budget",
+            reason = reason
+        );
+        let report = partial_report(run, &reason);
+        emit_event(
+            "success",
+            format!("Agent finished (partial): {}", report.markdown_report),
+        );
+        return Ok(report.markdown_report);
+    };
+
+    match outcome {
+        Ok((report, _)) => {
+            let markdown = finish_report(&report, &config);
             crate::trace_info!(
                 "nexus::agent::worker",
                 "Worker completed successfully",
-                report_len = report.markdown_report.len(),
-                discoveries = report.key_discoveries.len(),
-                sources = report.sources.len()
+                report_len = markdown.len()
             );
+            emit_event("success", format!("Agent finished: {}", markdown));
+            Ok(markdown)
+        }
+        Err(e) if run.as_ref().is_some_and(|r| r.budget_exhausted()) => {
+            let run = run.as_ref().unwrap();
+            let reason = run.stop_reason().unwrap_or_else(|| e.to_string());
+            crate::trace_warn!(
+                "nexus::agent::worker",
+                "Worker stopped by run budget",
+                steps = run.steps(),
+                reason = reason,
+                error = e.to_string()
+            );
+            let report = partial_report(run, &reason);
             emit_event(
                 "success",
-                format!("Agent finished: {}", report.markdown_report),
+                format!("Agent finished (partial): {}", report.markdown_report),
             );
             Ok(report.markdown_report)
         }
@@ -440,134 +1591,614 @@ async fn execute_nexus_worker<L: BaseLlm + 'static>(
 }
 
 pub async fn run_agent_loop(prompt: String, config: Config) -> Result<String, String> {
-    crate::trace_info!(
-        "nexus::agent::loop",
-        "Agent loop starting",
-        prompt_len = prompt.len()
-    );
-    emit_event("system", format!("Agent started with prompt: {}", prompt));
+    run_agent_loop_with_id(prompt, config, uuid::Uuid::new_v4().to_string()).await
+}
 
-    let provider = config.provider.to_lowercase();
-    let model_name = config.model.clone();
-    let api_key = config.api_key.clone();
+/// [`run_agent_loop`] under a run id chosen by the caller
+pub async fn run_agent_loop_with_id(
+    prompt: String,
+    config: Config,
+    run_id: String,
+) -> Result<String, String> {
+    let label = prompt.clone();
+    let max_concurrent = config.max_concurrent_runs;
+    let run = run_with_retry(prompt, config, run_id.clone());
+    crate::queue::queue()
+        .run(&run_id, &label, max_concurrent, run)
+        .await
+}
 
-    crate::trace_info!(
-        "nexus::agent::loop",
-        "Configuring LLM",
-        provider = provider,
-        model = model_name
-    );
+/// Queue a run in the background and return its id straight away. Progress
+/// arrives as agent events; the outcome is emitted as `run-finished` and kept
+/// for [`crate::run::get_result`].
+pub fn spawn_agent_run(prompt: String, config: Config) -> String {
+    spawn_agent_run_with_id(prompt, config, uuid::Uuid::new_v4().to_string())
+}
 
-    // Set environment variable for radkit
-    match provider.as_str() {
-        "anthropic" => std::env::set_var("ANTHROPIC_API_KEY", &api_key),
-        "openai" => std::env::set_var("OPENAI_API_KEY", &api_key),
-        "openrouter" => std::env::set_var("OPENROUTER_API_KEY", &api_key),
-        "gemini" => std::env::set_var("GEMINI_API_KEY", &api_key),
-        "grok" => std::env::set_var("XAI_API_KEY", &api_key),
-        "deepseek" => std::env::set_var("DEEPSEEK_API_KEY", &api_key),
-        _ => {}
-    }
-    crate::trace_debug!("nexus::agent::loop", "API key set in environment");
+/// [`spawn_agent_run`] under a run id chosen by the caller
+pub fn spawn_agent_run_with_id(prompt: String, config: Config, run_id: String) -> String {
+    let label = prompt.clone();
+    let max_concurrent = config.max_concurrent_runs;
+    let run = run_with_retry(prompt, config, run_id.clone());
+    spawn_queued(run_id, label, max_concurrent, run)
+}
 
-    crate::trace_info!(
-        "nexus::agent::loop",
-        "Creating LLM instance",
-        provider = provider
-    );
-    match provider.as_str() {
-        "anthropic" => {
-            let llm = AnthropicLlm::from_env(model_name).map_err(|e| {
-                crate::trace_error!(
-                    "nexus::agent::loop",
-                    "Failed to create Anthropic LLM",
-                    error = e.to_string()
-                );
-                e.to_string()
-            })?;
-            crate::trace_debug!("nexus::agent::loop", "Anthropic LLM created");
-            execute_nexus_worker(llm, prompt).await
+/// Continue an interrupted run from its last checkpoint in the background,
+/// reporting its outcome like [`spawn_agent_run`]
+pub fn spawn_resumed_run(run_id: String, config: Config) -> String {
+    let label = format!("Resume run {}", run_id);
+    let max_concurrent = config.max_concurrent_runs;
+    let run = resume_from_checkpoint(run_id.clone(), config);
+    spawn_queued(run_id, label, max_concurrent, run)
+}
+
+/// Queue `run` under `run_id`, tracking its state for
+/// [`crate::run::get_result`] and emitting `run-finished` when it is done
+fn spawn_queued(
+    run_id: String,
+    label: String,
+    max_concurrent: usize,
+    run: impl std::future::Future<Output = Result<String, String>> + Send + 'static,
+) -> String {
+    crate::run::set_result(RunResult::pending(&run_id, RunState::Queued));
+
+    let id = run_id.clone();
+    tauri::async_runtime::spawn(async move {
+        let result = crate::queue::queue()
+            .run(&id, &label, max_concurrent, async {
+                crate::run::set_result(RunResult::pending(&id, RunState::Running));
+                run.await
+            })
+            .await;
+        let mut outcome = RunResult::finished(&id, &result);
+        outcome.retried_as = crate::db::with_db(|conn| crate::run::get_retry(conn, &id))
+            .ok()
+            .flatten()
+            .map(|retry| retry.run_id);
+        crate::run::set_result(outcome.clone());
+        if let Some(app) = GLOBAL_APP.get() {
+            let _ = app.emit("run-finished", outcome);
         }
-        "openai" => {
-            let mut llm = OpenAILlm::from_env(model_name).map_err(|e| {
-                crate::trace_error!(
-                    "nexus::agent::loop",
-                    "Failed to create OpenAI LLM",
-                    error = e.to_string()
-                );
-                e.to_string()
-            })?;
-            if let Some(base_url) = config.base_url {
-                if !base_url.is_empty() {
-                    crate::trace_debug!(
+    });
+    run_id
+}
+
+/// Drive `prompt` under `run_id`, retrying once under a new id if the config
+/// asks for it. Runs the user stopped, e.g. by rejecting the plan, are not
+/// retried.
+async fn run_with_retry(prompt: String, config: Config, run_id: String) -> Result<String, String> {
+    let speak_summaries = config.speak_summaries;
+    let summary_config = config.clone();
+
+    let (ctx, mut result) = execute_run(prompt.clone(), config.clone(), run_id, None).await;
+    if let Err(error) = &result {
+        if config.auto_retry_on_failure && ctx.halted().is_none() {
+            match ctx.retry_config() {
+                Some(mut retry_config) => {
+                    retry_config.system_prompt = format!(
+                        "{}\n\n{}",
+                        retry_config.system_prompt(),
+                        ctx.post_mortem(error)
+                    );
+                    crate::trace_info!(
                         "nexus::agent::loop",
-                        "Using custom base URL",
-                        base_url = base_url
+                        "Retrying failed run",
+                        run_id = ctx.run_id,
+                        error = error
                     );
-                    llm = llm.with_base_url(base_url);
+                    emit_event(
+                        "system",
+                        format!("Run failed ({}). Retrying with an adjusted strategy...", error),
+                    );
+                    result = execute_run(
+                        prompt.clone(),
+                        retry_config,
+                        uuid::Uuid::new_v4().to_string(),
+                        Some(ctx.run_id.clone()),
+                    )
+                    .await
+                    .1;
                 }
+                None => crate::trace_info!(
+                    "nexus::agent::loop",
+                    "Skipping automatic retry, no budget left",
+                    run_id = ctx.run_id
+                ),
             }
-            crate::trace_debug!("nexus::agent::loop", "OpenAI LLM created");
-            execute_nexus_worker(llm, prompt).await
         }
-        "openrouter" => {
-            let llm = OpenRouterLlm::from_env(model_name)
-                .map_err(|e| {
+    }
+
+    crate::notifications::run_finished(&config, &prompt, &result);
+    if speak_summaries {
+        if let Ok(report) = &result {
+            tokio::spawn(speak_summary(report.clone(), summary_config));
+        }
+    }
+    result
+}
+
+/// Record and drive one attempt at `prompt`; `retry_of` links an automatic
+/// retry to the run it retries
+async fn execute_run(
+    prompt: String,
+    config: Config,
+    run_id: String,
+    retry_of: Option<String>,
+) -> (Arc<RunContext>, Result<String, String>) {
+    let ctx = Arc::new(prepare_run(RunContext::with_id(run_id, config.clone())).await);
+    let run_id = ctx.run_id.clone();
+    let series = crate::run::series_key(&prompt);
+    let mut record = RunRecord {
+        run_id: run_id.clone(),
+        prompt: prompt.clone(),
+        provider: config.provider.clone(),
+        model: config.model.clone(),
+        status: "running".to_string(),
+        started_at: chrono::Utc::now().timestamp_millis(),
+        finished_at: None,
+        report: None,
+        error: None,
+        usage: ctx.usage(),
+        series: series.clone(),
+        previous_run_id: None,
+        retry_of,
+        validation: None,
+        sources: Vec::new(),
+        key_discoveries: Vec::new(),
+    };
+    if let Err(e) = crate::db::with_db(|conn| {
+        record.previous_run_id = crate::run::latest_in_series(conn, &series)?;
+        crate::run::insert_record(conn, &record)
+    }) {
+        crate::trace_warn!("nexus::agent::loop", "Failed to store run record", error = e);
+    }
+
+    let plan_first = config.plan_first;
+    let unprepared = ctx.halted();
+    let result = drive_run(ctx.clone(), async move {
+        if let Some(reason) = unprepared {
+            return Err(reason);
+        }
+        if plan_first {
+            plan_run(&prompt, &config).await?;
+        }
+        if config.orchestrate {
+            run_orchestrated(prompt, config).await
+        } else {
+            run_with_provider(Thread::from_user(prompt), config).await
+        }
+    })
+    .await;
+    release_run(&ctx).await;
+    (ctx, result)
+}
+
+/// Give a new or resumed run its own browser context, when runs may overlap,
+/// and connect its MCP servers. A run that can't get its own context is
+/// halted rather than let onto the shared browser next to other runs.
+async fn prepare_run(mut ctx: RunContext) -> RunContext {
+    // Runs that may overlap get their own incognito context, so they can't
+    // navigate each other's pages
+    if ctx.config.max_concurrent_runs > 1 {
+        if let Some(shared) = crate::browser::GLOBAL_BROWSER.get() {
+            match shared.isolated().await {
+                Ok(browser) => ctx = ctx.with_browser(browser),
+                Err(e) => {
                     crate::trace_error!(
                         "nexus::agent::loop",
-                        "Failed to create OpenRouter LLM",
+                        "Could not isolate run browser",
+                        run_id = ctx.run_id,
                         error = e.to_string()
                     );
-                    e.to_string()
-                })?
-                .with_site_url("https://nexus.local")
-                .with_app_name("Nexus Agent");
-            crate::trace_debug!("nexus::agent::loop", "OpenRouter LLM created");
-            execute_nexus_worker(llm, prompt).await
-        }
-        "gemini" => {
-            let llm = GeminiLlm::from_env(model_name).map_err(|e| {
-                crate::trace_error!(
-                    "nexus::agent::loop",
-                    "Failed to create Gemini LLM",
-                    error = e.to_string()
-                );
-                e.to_string()
-            })?;
-            crate::trace_debug!("nexus::agent::loop", "Gemini LLM created");
-            execute_nexus_worker(llm, prompt).await
+                    ctx.halt(&format!(
+                        "Could not open a separate browser context for this run ({}). \
+                         Set max_concurrent_runs to 1 to run on the shared browser.",
+                        e
+                    ));
+                    return ctx;
+                }
+            }
         }
-        "grok" => {
-            let llm = GrokLlm::from_env(model_name).map_err(|e| {
-                crate::trace_error!(
-                    "nexus::agent::loop",
-                    "Failed to create Grok LLM",
-                    error = e.to_string()
-                );
-                e.to_string()
-            })?;
-            crate::trace_debug!("nexus::agent::loop", "Grok LLM created");
-            execute_nexus_worker(llm, prompt).await
+    }
+    if !ctx.config.mcp_servers.is_empty() {
+        let clients = crate::mcp_client::connect_all(&ctx.config.mcp_servers).await;
+        ctx = ctx.with_mcp_clients(clients);
+    }
+    ctx
+}
+
+/// Close what [`prepare_run`] opened for the run
+async fn release_run(ctx: &RunContext) {
+    if let Some(browser) = &ctx.browser {
+        if let Err(e) = browser.close().await {
+            crate::trace_warn!(
+                "nexus::agent::loop",
+                "Failed to close run browser",
+                error = e.to_string()
+            );
         }
-        "deepseek" => {
-            let llm = DeepSeekLlm::from_env(model_name).map_err(|e| {
-                crate::trace_error!(
-                    "nexus::agent::loop",
-                    "Failed to create DeepSeek LLM",
-                    error = e.to_string()
+    }
+    ctx.close_mcp_clients().await;
+}
+
+// --- Orchestration ---
+
+/// Split the task into sub-questions, research them in parallel with one
+/// sub-agent (and browser tab) each, then synthesize a single report
+async fn run_orchestrated(prompt: String, config: Config) -> Result<String, String> {
+    let run = crate::run::current().ok_or_else(|| "No active run to orchestrate".to_string())?;
+    let browser =
+        crate::browser::current().ok_or_else(|| "Browser not initialized".to_string())?;
+
+    emit_event("system", "Splitting the task into sub-questions...".to_string());
+    let splitter = LlmFunction::<ResearchSplit>::new_with_system_instructions(
+        build_llm(&config)?,
+        "You coordinate a team of browser research agents. Split the task into independent \
+         sub-questions that can be researched in parallel without overlap. Return a single \
+         sub-question if the task can't be split.",
+    );
+    // Once the run's over-budget grace call is spent, further LLM calls never
+    // return; the splitter and synthesizer stop with a partial report instead
+    let split = tokio::select! {
+        split = splitter.run(format!(
+            "Task: {}\nUse at most {} sub-questions.",
+            prompt, config.max_sub_agents
+        )) => split.map_err(|e| e.to_string())?,
+        _ = run.aborted() => return Ok(orchestration_aborted(&run, "split")),
+    };
+    let questions: Vec<String> = split
+        .sub_questions
+        .into_iter()
+        .filter(|q| !q.trim().is_empty())
+        .take(config.max_sub_agents.max(1))
+        .collect();
+    if questions.len() <= 1 {
+        return run_with_provider(Thread::from_user(prompt), config).await;
+    }
+    emit_event(
+        "orchestration",
+        format!("Researching {} sub-questions in parallel", questions.len()),
+    );
+
+    let sub_runs = questions.iter().map(|question| {
+        let ctx =
+            Arc::new(RunContext::sub_agent(&run, questions.len()).with_browser(browser.new_tab()));
+        let question = question.clone();
+        let config = config.clone();
+        async move {
+            let result = crate::run::scope(ctx.clone(), async {
+                emit_event("system", format!("Sub-agent started: {}", question));
+                let worker = build_worker::<NexusReport>(build_llm(&config)?, Some(&ctx));
+                // A sub-agent over its budget is aborted mid-call, so race it
+                // against the abort rather than wait on it forever
+                tokio::select! {
+                    result = worker.run(question.clone()) => result.map_err(|e| e.to_string()),
+                    _ = ctx.aborted() => Err(format!(
+                        "Stopped at the {}",
+                        ctx.stop_reason().unwrap_or_else(|| "budget".to_string())
+                    )),
+                }
+            })
+            .await;
+            if let Some(tab) = &ctx.browser {
+                let _ = tab.reset().await;
+            }
+            (question, ctx, result)
+        }
+    });
+    let results = futures::future::join_all(sub_runs).await;
+
+    let mut findings = String::new();
+    for (question, ctx, result) in &results {
+        run.absorb_usage(&ctx.usage());
+        for source in ctx.sources() {
+            run.record_source(&source);
+        }
+        match result {
+            Ok(report) => {
+                for discovery in &report.key_discoveries {
+                    run.record_finding(discovery);
+                }
+                findings.push_str(&format!(
+                    "## Sub-question: {}\n\n{}\n\nSources: {}\n\n",
+                    question,
+                    report.markdown_report,
+                    report.sources.join(", ")
+                ));
+            }
+            Err(e) => {
+                crate::trace_warn!(
+                    "nexus::agent::orchestrator",
+                    "Sub-agent failed",
+                    question = question,
+                    error = e
                 );
-                e.to_string()
-            })?;
-            crate::trace_debug!("nexus::agent::loop", "DeepSeek LLM created");
-            execute_nexus_worker(llm, prompt).await
+                findings.push_str(&format!(
+                    "## Sub-question: {}\n\nNot answered: {}\n\n",
+                    question, e
+                ));
+            }
         }
-        _ => {
-            crate::trace_error!(
+    }
+    if results.iter().all(|(_, _, result)| result.is_err()) {
+        return Err("All sub-agents failed".to_string());
+    }
+
+    emit_event("system", "Synthesizing sub-agent findings...".to_string());
+    let findings = format!("Task: {}\n\n{}", prompt, findings);
+    let report = tokio::select! {
+        report = async { with_output_shape!(config, synthesize(&config, findings)) } => report?,
+        _ = run.aborted() => return Ok(orchestration_aborted(&run, "synthesis")),
+    };
+    emit_event("success", format!("Agent finished: {}", report));
+    Ok(report)
+}
+
+/// The partial report of an orchestrated run aborted for exceeding its
+/// budget during `stage`
+fn orchestration_aborted(run: &RunContext, stage: &str) -> String {
+    let reason = run.stop_reason().unwrap_or_else(|| "budget".to_string());
+    crate::trace_warn!(
+        "nexus::agent::orchestrator",
+        "Orchestrator aborted by spend budget",
+        stage = stage,
+        reason = reason
+    );
+    let report = partial_report(run, &reason);
+    emit_event(
+        "success",
+        format!("Agent finished (partial): {}", report.markdown_report),
+    );
+    report.markdown_report
+}
+
+/// Merge sub-agent findings into one report of the run's output shape
+async fn synthesize<T: ReportShape>(config: &Config, findings: String) -> Result<String, String> {
+    let mut instructions = format!(
+        "{}\n\nMerge the research of your sub-agents into one report. Resolve \
+         contradictions, keep sources, and say which parts could not be answered.",
+        config.system_prompt()
+    );
+    if let Some(shape) = crate::shapes::shape_instructions(config) {
+        instructions.push_str("\n\n");
+        instructions.push_str(&shape);
+    }
+    let synthesizer =
+        LlmFunction::<T>::new_with_system_instructions(build_llm(config)?, instructions);
+    let report = synthesizer
+        .run(findings)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(finish_report(&report, config))
+}
+
+/// Generate a spoken summary of a finished report and read it aloud
+async fn speak_summary(report: String, config: Config) {
+    let outcome = match crate::speech::spoken_summary(&config, &report).await {
+        Ok(script) => {
+            emit_event("spoken_summary", script.clone());
+            crate::speech::speak(&script).await
+        }
+        Err(e) => Err(e),
+    };
+    if let Err(e) = outcome {
+        crate::trace_warn!("nexus::agent::speech", "Spoken summary failed", error = e);
+    }
+}
+
+/// Draft a plan for the current run and wait for the user to approve it
+async fn plan_run(prompt: &str, config: &Config) -> Result<(), String> {
+    let run = crate::run::current().ok_or_else(|| "No active run to plan".to_string())?;
+    emit_event("system", "Drafting a plan...".to_string());
+    let steps = crate::planning::draft(config, prompt).await?;
+    emit_event(
+        "plan",
+        format!("Proposed plan with {} steps, waiting for approval", steps.len()),
+    );
+    let steps = crate::planning::await_decision(crate::planning::PlanProposal {
+        run_id: run.run_id.clone(),
+        prompt: prompt.to_string(),
+        steps,
+    })
+    .await
+    .inspect_err(|e| run.halt(e))?;
+    emit_event("system", format!("Plan approved with {} steps", steps.len()));
+    run.set_plan(steps);
+    Ok(())
+}
+
+async fn resume_from_checkpoint(run_id: String, config: Config) -> Result<String, String> {
+    if crate::run::active(&run_id).is_some() {
+        return Err(format!("Run {} is still in progress", run_id));
+    }
+    let (record, checkpoint) = crate::db::with_db(|conn| {
+        Ok((
+            crate::run::get_record(conn, &run_id)?,
+            crate::checkpoint::load(conn, &run_id)?,
+        ))
+    })?;
+    let record = record.ok_or_else(|| format!("Run {} not found", run_id))?;
+    if record.status == "completed" {
+        return Err(format!("Run {} already completed", run_id));
+    }
+    let checkpoint =
+        checkpoint.ok_or_else(|| format!("Run {} has no checkpoint to resume from", run_id))?;
+    let thread = checkpoint.thread()?;
+
+    let restored = crate::checkpoint::restore_memory(&checkpoint.memory).await;
+    crate::trace_info!(
+        "nexus::agent::loop",
+        "Resuming run from checkpoint",
+        run_id = run_id,
+        steps = checkpoint.steps,
+        memories_restored = restored,
+        current_url = checkpoint.current_url
+    );
+    let ctx = Arc::new(prepare_run(RunContext::resume(config.clone(), &checkpoint)).await);
+    if let Some(reason) = ctx.halted() {
+        return Err(reason);
+    }
+    let browser = ctx
+        .browser
+        .clone()
+        .or_else(|| crate::browser::GLOBAL_BROWSER.get().cloned());
+    if let (Some(url), Some(browser)) = (&checkpoint.current_url, browser) {
+        if let Err(e) = browser.navigate_and_get_content(url).await {
+            crate::trace_warn!(
                 "nexus::agent::loop",
-                "Unsupported provider",
-                provider = provider
+                "Failed to restore page",
+                url = url,
+                error = e.to_string()
+            );
+        }
+    }
+    if let Err(e) = crate::db::with_db(|conn| crate::run::mark_resumed(conn, &run_id)) {
+        crate::trace_warn!("nexus::agent::loop", "Failed to update run record", error = e);
+    }
+
+    let thread = thread.add_event(Event::user(format!(
+        "The session was interrupted and has been restored{}. Continue the original task from where you left off.",
+        checkpoint
+            .current_url
+            .as_deref()
+            .map(|url| format!("; the browser is back on {}", url))
+            .unwrap_or_default()
+    )));
+    let result = drive_run(ctx.clone(), run_with_provider(thread, config.clone())).await;
+    release_run(&ctx).await;
+    crate::notifications::run_finished(&config, &record.prompt, &result);
+    result
+}
+
+/// Execute `fut` as the run described by `ctx`, then persist its outcome
+async fn drive_run(
+    ctx: Arc<RunContext>,
+    fut: impl std::future::Future<Output = Result<String, String>>,
+) -> Result<String, String> {
+    let run_id = ctx.run_id.clone();
+    crate::run::register(ctx.clone());
+
+    let result = crate::run::scope(ctx.clone(), fut).await;
+
+    let usage = ctx.usage();
+    crate::run::unregister(&run_id);
+    crate::trace_info!(
+        "nexus::agent::loop",
+        "Run usage",
+        run_id = run_id,
+        llm_calls = usage.llm_calls,
+        prompt_tokens = usage.prompt_tokens,
+        completion_tokens = usage.completion_tokens,
+        cost_usd = usage.cost_usd
+    );
+    if let Err(e) = crate::db::with_db(|conn| {
+        crate::run::finish_record(conn, &run_id, &result, &usage)?;
+        crate::run::set_provenance(conn, &run_id, &ctx.sources(), &ctx.findings())?;
+        // Completed runs no longer need their checkpoint; failed ones keep it
+        // so they can be resumed
+        if result.is_ok() {
+            crate::checkpoint::delete(conn, &run_id)?;
+        }
+        Ok(())
+    }) {
+        crate::trace_warn!("nexus::agent::loop", "Failed to update run record", error = e);
+    }
+    if let (Ok(report), Some(criteria)) = (&result, &ctx.config.success_criteria) {
+        let validation = crate::criteria::validate(criteria, report, &ctx.sources());
+        crate::trace_info!(
+            "nexus::agent::loop",
+            "Run validated",
+            run_id = run_id,
+            passed = validation.passed,
+            reasons = validation.reasons.join("; ")
+        );
+        if validation.passed {
+            emit_event("validation", "Run met its success criteria".to_string());
+        } else {
+            emit_event(
+                "validation",
+                format!(
+                    "Run did not meet its success criteria: {}",
+                    validation.reasons.join("; ")
+                ),
             );
-            Err(format!("Unsupported LLM_PROVIDER: {}", provider))
         }
+        if let Err(e) =
+            crate::db::with_db(|conn| crate::run::set_validation(conn, &run_id, &validation))
+        {
+            crate::trace_warn!("nexus::agent::loop", "Failed to store validation", error = e);
+        }
+    }
+    crate::workspace::enforce_retention(&ctx.config);
+    result
+}
+
+async fn run_with_provider(thread: Thread, config: Config) -> Result<String, String> {
+    crate::trace_info!(
+        "nexus::agent::loop",
+        "Agent loop starting",
+        thread_events = thread.events().len()
+    );
+    if let Some(prompt) = thread
+        .events()
+        .last()
+        .and_then(|e| e.content().first_text())
+    {
+        emit_event("system", format!("Agent started with prompt: {}", prompt));
     }
+
+    let llm = build_llm(&config)?;
+    execute_nexus_worker(llm, thread).await
+}
+
+/// Run one turn of a chat session, continuing from `thread` when given.
+/// Returns the report and the updated conversation thread.
+pub async fn run_chat_turn(
+    message: String,
+    thread: Option<Thread>,
+    config: Config,
+) -> Result<(String, Thread), String> {
+    let ctx = Arc::new(RunContext::new(config.clone()));
+    crate::run::register(ctx.clone());
+    let run_id = ctx.run_id.clone();
+
+    let result = crate::run::scope(ctx.clone(), async move {
+        crate::trace_info!(
+            "nexus::agent::chat",
+            "Chat turn starting",
+            message_len = message.len(),
+            history_events = thread.as_ref().map(|t| t.events().len()).unwrap_or(0)
+        );
+        emit_event("system", format!("Chat message: {}", message));
+
+        let llm = build_llm(&config)?;
+        let worker = build_worker(llm, Some(&ctx));
+        let thread = match thread {
+            Some(thread) => thread.add_event(Event::user(message)),
+            None => Thread::from_user(message),
+        };
+
+        match worker.run_and_continue(thread).await {
+            Ok((report, thread)) => {
+                emit_event(
+                    "success",
+                    format!("Agent finished: {}", report.markdown_report),
+                );
+                Ok((report.markdown_report, thread))
+            }
+            Err(e) => {
+                crate::trace_error!(
+                    "nexus::agent::chat",
+                    "Chat turn failed",
+                    error = e.to_string()
+                );
+                emit_event("error", format!("Agent execution failed: {}", e));
+                Err(e.to_string())
+            }
+        }
+    })
+    .await;
+
+    crate::run::unregister(&run_id);
+    result
 }
@@ -0,0 +1,255 @@
+//! Local HTTP API
+//!
+//! An optional REST server on 127.0.0.1 so other applications on the machine
+//! can start research runs and read their results, traces and memories.
+//! Enabled with `api_server`; every request must carry `api_server_token` as
+//! `Authorization: Bearer <token>`.
+//!
+//! ```text
+//! POST /runs                  {"prompt": "..."} -> {"run_id": "..."}
+//! GET  /runs/{id}             queued, running, or the finished report
+//! GET  /status                runs in progress
+//! GET  /traces                the current session's events, filtered by
+//!                             TraceFilter query parameters
+//! GET  /sessions/{id}/traces  a recorded session's events
+//! GET  /memories?tag=<tag>    stored memories, optionally by tag
+//! ```
+//!
+//! With `mcp_server` enabled it also serves the MCP endpoints of
+//! [`crate::mcp`].
+
+use crate::config::{Config, ConfigManager};
+use crate::memory::{MemoryEntry, GLOBAL_MEMORY};
+use crate::run::{AgentStatus, RunResult};
+use crate::tracing::{TraceFilter, TracePage, TRACE_STORE};
+use axum::extract::{Path, Query, Request};
+use axum::http::{header, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use serde_json::json;
+use std::net::SocketAddr;
+use std::sync::{Mutex, RwLock};
+use tauri::async_runtime::JoinHandle;
+use tauri::Manager;
+
+/// Port the server is listening on and its task, `None` while disabled
+static SERVER: Mutex<Option<(u16, JoinHandle<()>)>> = Mutex::new(None);
+
+/// Token requests must present; kept apart from the server so changing it
+/// doesn't need a restart
+static TOKEN: RwLock<String> = RwLock::new(String::new());
+
+/// Start, stop or move the server to match `config`
+pub fn configure(config: &Config) {
+    let mut token = config.api_server_token.trim().to_string();
+    // A reference left because the keychain couldn't be read is no secret
+    if crate::secrets::parse_reference(&token).is_some() {
+        token.clear();
+    }
+    if config.api_server && token.is_empty() {
+        crate::trace_warn!(
+            "nexus::api",
+            "Local API not started: api_server_token is empty or unreadable"
+        );
+    }
+    let port = (config.api_server && !token.is_empty()).then_some(config.api_server_port);
+    *TOKEN.write().unwrap() = token;
+
+    let Ok(mut server) = SERVER.lock() else {
+        return;
+    };
+    if server.as_ref().map(|(current, _)| *current) == port {
+        return;
+    }
+    if let Some((current, task)) = server.take() {
+        task.abort();
+        crate::trace_info!("nexus::api", "Local API stopped", port = current);
+    }
+    if let Some(port) = port {
+        *server = Some((port, tauri::async_runtime::spawn(serve(port))));
+    }
+}
+
+async fn serve(port: u16) {
+    let addr = SocketAddr::from(([127, 0, 0, 1], port));
+    match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => {
+            crate::trace_info!("nexus::api", "Local API listening", port = port);
+            if let Err(e) = axum::serve(listener, router()).await {
+                crate::trace_error!("nexus::api", "Local API failed", error = e.to_string());
+            }
+        }
+        Err(e) => crate::trace_error!(
+            "nexus::api",
+            "Local API could not listen",
+            port = port,
+            error = e.to_string()
+        ),
+    }
+    // Free the slot so the next config change can try again
+    if let Ok(mut server) = SERVER.lock() {
+        if server.as_ref().is_some_and(|(current, _)| *current == port) {
+            *server = None;
+        }
+    }
+}
+
+pub fn router() -> Router {
+    Router::new()
+        .route("/runs", post(start_run))
+        .route("/runs/{id}", get(run_result))
+        .route("/status", get(status))
+        .route("/traces", get(traces))
+        .route("/sessions/{id}/traces", get(session_traces))
+        .route("/memories", get(memories))
+        .merge(crate::mcp::routes())
+        .layer(middleware::from_fn(authorize))
+}
+
+/// An error response with a JSON `{"error": ...}` body
+struct ApiError(StatusCode, String);
+
+impl From<String> for ApiError {
+    fn from(message: String) -> Self {
+        ApiError(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, Json(json!({ "error": self.1 }))).into_response()
+    }
+}
+
+async fn authorize(request: Request, next: Next) -> Response {
+    let presented = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok());
+    if !accepts(presented) {
+        return ApiError(
+            StatusCode::UNAUTHORIZED,
+            "Missing or invalid bearer token".to_string(),
+        )
+        .into_response();
+    }
+    next.run(request).await
+}
+
+/// Whether an `Authorization` header value carries the configured token
+pub fn accepts(header: Option<&str>) -> bool {
+    authorized(header, &TOKEN.read().unwrap())
+}
+
+/// Whether an `Authorization` header value carries `token`, compared in
+/// constant time
+pub fn authorized(header: Option<&str>, token: &str) -> bool {
+    let Some(presented) = header.and_then(|value| value.strip_prefix("Bearer ")) else {
+        return false;
+    };
+    !token.is_empty()
+        && presented.len() == token.len()
+        && presented
+            .bytes()
+            .zip(token.bytes())
+            .fold(0, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+#[derive(Deserialize)]
+struct RunRequest {
+    prompt: String,
+    plan_first: Option<bool>,
+    orchestrate: Option<bool>,
+    output_schema: Option<serde_json::Value>,
+}
+
+async fn start_run(
+    Json(request): Json<RunRequest>,
+) -> Result<(StatusCode, Json<serde_json::Value>), ApiError> {
+    if request.prompt.trim().is_empty() {
+        return Err(ApiError(
+            StatusCode::BAD_REQUEST,
+            "The prompt is empty".to_string(),
+        ));
+    }
+    let app = crate::GLOBAL_APP
+        .get()
+        .ok_or_else(|| "App not initialized".to_string())?;
+    let mut config = app.state::<Mutex<ConfigManager>>().lock().unwrap().load();
+    if let Some(plan_first) = request.plan_first {
+        config.plan_first = plan_first;
+    }
+    if let Some(orchestrate) = request.orchestrate {
+        config.orchestrate = orchestrate;
+    }
+    if request.output_schema.is_some() {
+        config.output_schema = request.output_schema;
+    }
+
+    let run_id = crate::agent::spawn_agent_run(request.prompt, config);
+    crate::trace_info!("nexus::api", "Run started over HTTP", run_id = run_id);
+    Ok((StatusCode::ACCEPTED, Json(json!({ "run_id": run_id }))))
+}
+
+async fn run_result(Path(run_id): Path<String>) -> Result<Json<RunResult>, ApiError> {
+    crate::db::with_db(|conn| crate::run::get_result(conn, &run_id))?
+        .map(Json)
+        .ok_or_else(|| ApiError(StatusCode::NOT_FOUND, format!("Run {} not found", run_id)))
+}
+
+async fn status() -> Json<AgentStatus> {
+    Json(crate::run::agent_status())
+}
+
+async fn traces(Query(filter): Query<TraceFilter>) -> Result<Json<TracePage>, ApiError> {
+    let store = TRACE_STORE
+        .get()
+        .ok_or_else(|| "Failed to access trace store".to_string())?;
+    let page = store.lock().await.query(&filter);
+    Ok(Json(page))
+}
+
+async fn session_traces(
+    Path(session_id): Path<String>,
+    Query(filter): Query<TraceFilter>,
+) -> Result<Json<TracePage>, ApiError> {
+    crate::tracing::flush().await;
+    let page = crate::db::with_db(|conn| crate::tracing::load_session(conn, &session_id, &filter))?;
+    Ok(Json(page))
+}
+
+#[derive(Deserialize)]
+struct MemoryQuery {
+    tag: Option<String>,
+}
+
+async fn memories(Query(query): Query<MemoryQuery>) -> Result<Json<Vec<MemoryEntry>>, ApiError> {
+    let memory = GLOBAL_MEMORY
+        .get()
+        .ok_or_else(|| "Failed to access memory".to_string())?;
+    let memory = memory.read().await;
+    Ok(Json(match query.tag {
+        Some(tag) => memory.get_by_tag(&tag),
+        None => memory.get_all(),
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_authorized() {
+        assert!(authorized(Some("Bearer s3cret"), "s3cret"));
+        assert!(!authorized(Some("Bearer s3cre"), "s3cret"));
+        assert!(!authorized(Some("Bearer s3creT"), "s3cret"));
+        assert!(!authorized(Some("s3cret"), "s3cret"));
+        assert!(!authorized(None, "s3cret"));
+        // An unset token never authorizes, even an empty bearer
+        assert!(!authorized(Some("Bearer "), ""));
+    }
+}
@@ -0,0 +1,178 @@
+//! Human-in-the-loop approval gate
+//!
+//! Sensitive tool actions (form submission, file upload, navigation to
+//! configured domains) emit an `approval-request` event and wait until the UI
+//! answers through the `approve_action` command or the request times out.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::Emitter;
+use tokio::sync::oneshot;
+use tokio::time::{timeout, Duration};
+use uuid::Uuid;
+
+/// Global approval manager instance
+pub static GLOBAL_APPROVALS: OnceLock<ApprovalManager> = OnceLock::new();
+
+/// Payload of the `approval-request` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalRequest {
+    pub id: String,
+    pub action: String,
+    pub details: serde_json::Value,
+    pub timeout_secs: u64,
+}
+
+/// Tracks approval requests that are waiting for a user decision
+pub struct ApprovalManager {
+    pending: Mutex<HashMap<String, oneshot::Sender<bool>>>,
+}
+
+impl ApprovalManager {
+    pub fn new() -> Self {
+        Self {
+            pending: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Ask the user to approve `action`, blocking until answered or timed out
+    pub async fn request(
+        &self,
+        action: &str,
+        details: serde_json::Value,
+        timeout_secs: u64,
+    ) -> Result<(), String> {
+        let id = Uuid::new_v4().to_string();
+        let (tx, rx) = oneshot::channel();
+        self.pending
+            .lock()
+            .map_err(|_| "Approval state poisoned".to_string())?
+            .insert(id.clone(), tx);
+
+        let request = ApprovalRequest {
+            id: id.clone(),
+            action: action.to_string(),
+            details,
+            timeout_secs,
+        };
+        crate::trace_info!(
+            "nexus::approval",
+            "Approval requested",
+            id = id,
+            action = action
+        );
+        if let Some(app) = crate::GLOBAL_APP.get() {
+            let _ = app.emit("approval-request", &request);
+        }
+        crate::notifications::approval_needed(action);
+
+        let outcome = timeout(Duration::from_secs(timeout_secs), rx).await;
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.remove(&id);
+        }
+
+        match outcome {
+            Ok(Ok(true)) => {
+                crate::trace_info!("nexus::approval", "Action approved", id = id);
+                Ok(())
+            }
+            Ok(Ok(false)) | Ok(Err(_)) => {
+                crate::trace_warn!("nexus::approval", "Action rejected", id = id);
+                Err(format!("The user rejected the action: {}", action))
+            }
+            Err(_) => {
+                crate::trace_warn!("nexus::approval", "Approval timed out", id = id);
+                Err(format!(
+                    "No approval received for '{}' within {} seconds",
+                    action, timeout_secs
+                ))
+            }
+        }
+    }
+
+    /// Deliver the user's decision for a pending request
+    pub fn resolve(&self, id: &str, approved: bool) -> Result<(), String> {
+        let sender = self
+            .pending
+            .lock()
+            .map_err(|_| "Approval state poisoned".to_string())?
+            .remove(id)
+            .ok_or_else(|| format!("No pending approval with id {}", id))?;
+        sender
+            .send(approved)
+            .map_err(|_| "Approval request is no longer waiting".to_string())
+    }
+}
+
+/// Initialize the approval manager
+pub fn init_approvals() {
+    let _ = GLOBAL_APPROVALS.set(ApprovalManager::new());
+}
+
+/// Gate an action behind user approval using the current run's settings
+pub async fn require_approval(action: &str, details: serde_json::Value) -> Result<(), String> {
+    let timeout_secs = crate::run::current()
+        .map(|run| run.config.approval_timeout_secs)
+        .unwrap_or_else(|| crate::config::Config::default().approval_timeout_secs);
+    match GLOBAL_APPROVALS.get() {
+        Some(manager) => manager.request(action, details, timeout_secs).await,
+        None => Err("Approval manager not initialized".to_string()),
+    }
+}
+
+/// Extract the lowercase host from a URL without pulling in a URL parser
+pub fn host_of(url: &str) -> Option<String> {
+    let rest = url.split_once("://").map(|(_, r)| r).unwrap_or(url);
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = authority.rsplit('@').next()?;
+    let host = host.split(':').next()?.trim_end_matches('.');
+    if host.is_empty() {
+        None
+    } else {
+        Some(host.to_lowercase())
+    }
+}
+
+/// Whether `url` points at one of `domains` or a subdomain of one
+pub fn url_matches_domains(url: &str, domains: &[String]) -> bool {
+    let Some(host) = host_of(url) else {
+        return false;
+    };
+    domains.iter().any(|d| {
+        let d = d.trim().trim_start_matches("*.").to_lowercase();
+        !d.is_empty() && (host == d || host.ends_with(&format!(".{}", d)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_url_matches_domains() {
+        let domains = vec!["bank.com".to_string(), "*.corp.internal".to_string()];
+        assert!(url_matches_domains("https://bank.com/login", &domains));
+        assert!(url_matches_domains("https://www.Bank.com", &domains));
+        assert!(url_matches_domains("http://user@hr.corp.internal:8080/x", &domains));
+        assert!(!url_matches_domains("https://notbank.com", &domains));
+        assert!(!url_matches_domains("https://example.com/?bank.com", &domains));
+    }
+
+    #[tokio::test]
+    async fn test_resolve_unblocks_request() {
+        let manager = std::sync::Arc::new(ApprovalManager::new());
+        let waiter = {
+            let manager = manager.clone();
+            tokio::spawn(async move { manager.request("upload", serde_json::json!({}), 5).await })
+        };
+        let id = loop {
+            if let Some(id) = manager.pending.lock().unwrap().keys().next().cloned() {
+                break id;
+            }
+            tokio::task::yield_now().await;
+        };
+        manager.resolve(&id, false).unwrap();
+        assert!(waiter.await.unwrap().is_err());
+    }
+}
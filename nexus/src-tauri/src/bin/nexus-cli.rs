@@ -0,0 +1,197 @@
+//! Headless command-line entry point
+//!
+//! Runs one research prompt to completion without the desktop UI and prints
+//! the markdown report to stdout. It uses the app's config, database and
+//! workspaces, so CLI runs show up in the app's history.
+//!
+//! ```text
+//! nexus-cli [--config-dir <dir>] [--data-dir <dir>] [<prompt>...]
+//! nexus-cli [--config-dir <dir>] [--data-dir <dir>] --mcp
+//! ```
+//!
+//! The prompt is read from stdin when none is given, and config fields can be
+//! overridden with `NEXUS_<FIELD>` environment variables as in the app. Exits
+//! with 0 on success, 1 if the run fails, 2 on a usage error and 3 when the
+//! run was cut off by its budget and the printed report is partial. Actions
+//! that need approval are rejected, since there is no one to approve them.
+//!
+//! With `--mcp` it runs no prompt and instead serves the browser tools to an
+//! MCP client over stdio until the client disconnects.
+
+use nexus_lib::browser::{BrowserManager, GLOBAL_BROWSER};
+use nexus_lib::config::Config;
+use nexus_lib::{agent, init_services, mcp, tracing};
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+/// The bundle identifier from tauri.conf.json, which names the app's dirs
+const IDENTIFIER: &str = "com.jules.nexus";
+
+const USAGE: &str = "Usage: nexus-cli [--config-dir <dir>] [--data-dir <dir>] [<prompt>...]
+       nexus-cli [--config-dir <dir>] [--data-dir <dir>] --mcp
+
+Runs a research prompt and prints the report. Reads the prompt from stdin
+when none is given.
+
+Options:
+  --config-dir <dir>  Config, database and workspaces (default: the app's)
+  --data-dir <dir>    Trace log files (default: the app's)
+  --mcp               Serve the browser tools to an MCP client over stdio
+  -h, --help          Show this help";
+
+struct Args {
+    config_dir: PathBuf,
+    data_dir: PathBuf,
+    prompt: String,
+    mcp: bool,
+}
+
+/// Parse the command line, or `None` if help was requested
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut config_dir = dirs::config_dir().map(|dir| dir.join(IDENTIFIER));
+    let mut data_dir = dirs::data_dir().map(|dir| dir.join(IDENTIFIER));
+    let mut words = Vec::new();
+    let mut mcp = false;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(None),
+            "--mcp" => mcp = true,
+            "--config-dir" | "--data-dir" => {
+                let value = args
+                    .next()
+                    .map(PathBuf::from)
+                    .ok_or_else(|| format!("{} needs a directory", arg))?;
+                if arg == "--config-dir" {
+                    config_dir = Some(value);
+                } else {
+                    data_dir = Some(value);
+                }
+            }
+            "--" => words.extend(args.by_ref()),
+            flag if flag.starts_with("--") => return Err(format!("Unknown option {}", flag)),
+            _ => words.push(arg),
+        }
+    }
+    Ok(Some(Args {
+        config_dir: config_dir.unwrap_or_else(|| PathBuf::from(".")),
+        data_dir: data_dir.unwrap_or_else(|| PathBuf::from(".")),
+        prompt: words.join(" "),
+        mcp,
+    }))
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(Some(args)) => args,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return ExitCode::from(2);
+        }
+    };
+    if args.mcp {
+        if !args.prompt.is_empty() {
+            eprintln!("--mcp takes no prompt\n\n{}", USAGE);
+            return ExitCode::from(2);
+        }
+        tracing::init_tracing();
+        let (_, config) = init_services(&args.config_dir, &args.data_dir);
+        return match tauri::async_runtime::block_on(serve_mcp(config)) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("MCP server failed: {}", e);
+                ExitCode::from(1)
+            }
+        };
+    }
+
+    let mut prompt = args.prompt;
+    if prompt.trim().is_empty() {
+        if let Err(e) = std::io::stdin().read_to_string(&mut prompt) {
+            eprintln!("Could not read the prompt from stdin: {}", e);
+            return ExitCode::from(2);
+        }
+    }
+    let prompt = prompt.trim().to_string();
+    if prompt.is_empty() {
+        eprintln!("No prompt given\n\n{}", USAGE);
+        return ExitCode::from(2);
+    }
+
+    tracing::init_tracing();
+    let (_, config) = init_services(&args.config_dir, &args.data_dir);
+    match tauri::async_runtime::block_on(run(prompt, config)) {
+        Ok(report) => {
+            println!("{}", report);
+            if agent::is_partial(&report) {
+                ExitCode::from(3)
+            } else {
+                ExitCode::SUCCESS
+            }
+        }
+        Err(e) => {
+            eprintln!("Run failed: {}", e);
+            ExitCode::from(1)
+        }
+    }
+}
+
+async fn run(prompt: String, config: Config) -> Result<String, String> {
+    let browser = launch_browser(&config).await?;
+    let result = agent::run_agent_loop(prompt, config).await;
+
+    let _ = browser.stop().await;
+    // The writer only flushes periodically, so write out the run's tail
+    tracing::flush().await;
+    result
+}
+
+async fn serve_mcp(config: Config) -> Result<(), String> {
+    let browser = launch_browser(&config).await?;
+    let result = mcp::serve_stdio(config).await;
+
+    let _ = browser.stop().await;
+    tracing::flush().await;
+    result
+}
+
+async fn launch_browser(config: &Config) -> Result<BrowserManager, String> {
+    let browser = BrowserManager::new(config)
+        .await
+        .map_err(|e| format!("Browser launch failed: {}", e))?;
+    let _ = GLOBAL_BROWSER.set(browser.clone());
+    tracing::start_trace_writer();
+    Ok(browser)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Option<Args>, String> {
+        parse_args(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = parse(&["--config-dir", "/tmp/nexus", "compare", "pricing"])
+            .unwrap()
+            .unwrap();
+        assert_eq!(args.config_dir, PathBuf::from("/tmp/nexus"));
+        assert_eq!(args.prompt, "compare pricing");
+        assert!(!args.mcp);
+
+        assert!(parse(&["--mcp"]).unwrap().unwrap().mcp);
+
+        let args = parse(&["--", "--not-a-flag"]).unwrap().unwrap();
+        assert_eq!(args.prompt, "--not-a-flag");
+
+        assert!(parse(&["--help"]).unwrap().is_none());
+        assert!(parse(&["--config-dir"]).is_err());
+        assert!(parse(&["--bogus"]).is_err());
+    }
+}
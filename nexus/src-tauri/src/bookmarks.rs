@@ -0,0 +1,137 @@
+//! Named positions within a page
+//!
+//! Agents working through long documents can bookmark where they are and jump
+//! back later without re-reading from the top. A bookmark records the scroll
+//! offset and the nearest heading above the viewport; returning prefers the
+//! heading (robust to lazy-loaded content shifting the page) and falls back
+//! to the raw offset.
+
+use serde::{Deserialize, Serialize};
+
+/// A saved position on a page
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Bookmark {
+    pub name: String,
+    pub url: String,
+    pub scroll_y: f64,
+    /// Text of the nearest heading at or above the top of the viewport
+    pub heading: Option<String>,
+    /// `id` of that heading (or its closest anchor), if it has one
+    pub anchor: Option<String>,
+}
+
+/// Position data returned by [`capture_script`]
+#[derive(Debug, Clone, Deserialize)]
+pub struct CapturedPosition {
+    pub url: String,
+    pub scroll_y: f64,
+    pub heading: Option<String>,
+    pub anchor: Option<String>,
+}
+
+/// Section text returned by [`Bookmark::restore_script`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestoredPosition {
+    /// "anchor", "heading" or "offset", depending on how the position was found
+    pub method: String,
+    pub scroll_y: f64,
+    pub text: String,
+}
+
+/// Characters of section text returned after jumping to a bookmark
+const SECTION_TEXT_CHARS: usize = 3000;
+
+/// JavaScript expression describing the current scroll position
+pub fn capture_script() -> String {
+    r#"(() => {
+        const headings = Array.from(document.querySelectorAll("h1, h2, h3, h4, h5, h6"));
+        let nearest = null;
+        for (const h of headings) {
+            if (h.getBoundingClientRect().top <= 1) nearest = h; else break;
+        }
+        if (!nearest && headings.length) nearest = headings[0];
+        const anchorEl = nearest && (nearest.id ? nearest : nearest.closest("[id]"));
+        return {
+            url: location.href,
+            scroll_y: window.scrollY,
+            heading: nearest ? nearest.textContent.trim().slice(0, 200) : null,
+            anchor: anchorEl ? anchorEl.id : null,
+        };
+    })()"#
+        .to_string()
+}
+
+impl Bookmark {
+    pub fn new(name: &str, position: CapturedPosition) -> Self {
+        Self {
+            name: name.to_string(),
+            url: position.url,
+            scroll_y: position.scroll_y,
+            heading: position.heading.filter(|h| !h.is_empty()),
+            anchor: position.anchor.filter(|a| !a.is_empty()),
+        }
+    }
+
+    /// JavaScript expression scrolling back to the bookmark and returning the
+    /// text of the section found there
+    pub fn restore_script(&self) -> String {
+        let anchor = serde_json::to_string(&self.anchor).unwrap_or_else(|_| "null".to_string());
+        let heading = serde_json::to_string(&self.heading).unwrap_or_else(|_| "null".to_string());
+        format!(
+            r#"(() => {{
+                const anchor = {anchor};
+                const heading = {heading};
+                const scrollY = {scroll_y};
+                let target = anchor ? document.getElementById(anchor) : null;
+                let method = target ? "anchor" : null;
+                if (!target && heading) {{
+                    target = Array.from(document.querySelectorAll("h1, h2, h3, h4, h5, h6"))
+                        .find(h => h.textContent.trim().slice(0, 200) === heading) || null;
+                    if (target) method = "heading";
+                }}
+                if (target) {{
+                    target.scrollIntoView({{ block: "start" }});
+                }} else {{
+                    window.scrollTo(0, scrollY);
+                    method = "offset";
+                }}
+                let text = "";
+                if (target) {{
+                    let node = target;
+                    while (node && text.length < {limit}) {{
+                        text += (node.innerText || node.textContent || "") + "\n";
+                        node = node.nextElementSibling;
+                    }}
+                }} else {{
+                    text = document.body.innerText.slice(0, {limit});
+                }}
+                return {{ method, scroll_y: window.scrollY, text: text.slice(0, {limit}) }};
+            }})()"#,
+            scroll_y = self.scroll_y,
+            limit = SECTION_TEXT_CHARS,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bookmark_from_position() {
+        let bookmark = Bookmark::new(
+            "pricing",
+            CapturedPosition {
+                url: "https://example.com/docs".to_string(),
+                scroll_y: 1200.0,
+                heading: Some("Pricing".to_string()),
+                anchor: Some(String::new()),
+            },
+        );
+        assert_eq!(bookmark.anchor, None);
+        let script = bookmark.restore_script();
+        assert!(script.contains(r#"const heading = "Pricing";"#));
+        assert!(script.contains("const anchor = null;"));
+        assert!(script.contains("const scrollY = 1200;"));
+    }
+}
@@ -1,31 +1,105 @@
+use crate::config::Config;
 use anyhow::Result;
 use chromiumoxide::cdp::browser_protocol::dom::SetFileInputFilesParams;
+use chromiumoxide::cdp::browser_protocol::emulation::SetScriptExecutionDisabledParams;
+use chromiumoxide::cdp::browser_protocol::page::SetDocumentContentParams;
+use chromiumoxide::cdp::browser_protocol::target::{
+    BrowserContextId, CreateBrowserContextParams, CreateTargetParams, DisposeBrowserContextParams,
+};
 use chromiumoxide::{Browser, BrowserConfig, Page};
 use futures::StreamExt;
 use std::sync::Arc;
-use std::sync::OnceLock;
+use std::sync::{OnceLock, RwLock};
 use tokio::sync::Mutex;
 use tokio::time::{sleep, timeout, Duration};
 
 pub static GLOBAL_BROWSER: OnceLock<BrowserManager> = OnceLock::new();
 
+/// Browser for the current task: the run's own context or a sub-agent's own
+/// tab when it has one, otherwise the shared browser
+pub fn current() -> Option<BrowserManager> {
+    crate::run::current()
+        .and_then(|run| run.browser.clone())
+        .or_else(|| GLOBAL_BROWSER.get().cloned())
+}
+
+/// Browser a live run drives, falling back to the shared browser when the
+/// run has no context of its own
+pub fn for_run(run_id: &str) -> Option<BrowserManager> {
+    crate::run::active(run_id)
+        .and_then(|run| run.browser.clone())
+        .or_else(|| GLOBAL_BROWSER.get().cloned())
+}
+
+/// How long browser operations may take before they fail
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BrowserTimeouts {
+    pub navigation: Duration,
+    pub action: Duration,
+    pub selector: Duration,
+}
+
+impl BrowserTimeouts {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            navigation: Duration::from_secs(config.navigation_timeout_secs.max(1)),
+            action: Duration::from_secs(config.action_timeout_secs.max(1)),
+            selector: Duration::from_secs(config.selector_timeout_secs.max(1)),
+        }
+    }
+}
+
+impl Default for BrowserTimeouts {
+    fn default() -> Self {
+        Self::from_config(&Config::default())
+    }
+}
+
 #[derive(Clone)]
 pub struct BrowserManager {
-    browser: Arc<Browser>,
+    /// The Chromium process, `None` while stopped; shared by every handle
+    /// and tab, so a restart rebinds them all
+    browser: Arc<RwLock<Option<Arc<Browser>>>>,
     current_page: Arc<Mutex<Option<Page>>>,
+    /// Incognito context pages are opened in; `None` for the default context
+    context: Option<BrowserContextId>,
+    /// Shared by every handle and tab on this browser
+    timeouts: Arc<RwLock<BrowserTimeouts>>,
 }
 
 impl BrowserManager {
-    pub async fn new() -> Result<Self> {
-        crate::trace_info!("nexus::browser", "Launching headless browser");
+    pub async fn new(config: &Config) -> Result<Self> {
+        let browser = Self::launch(config).await?;
+        crate::trace_info!("nexus::browser", "BrowserManager initialized successfully");
+        Ok(Self {
+            browser: Arc::new(RwLock::new(Some(Arc::new(browser)))),
+            current_page: Arc::new(Mutex::new(None)),
+            context: None,
+            timeouts: Arc::new(RwLock::new(BrowserTimeouts::from_config(config))),
+        })
+    }
+
+    async fn launch(config: &Config) -> Result<Browser> {
+        crate::trace_info!(
+            "nexus::browser",
+            "Launching browser",
+            headless = config.browser_headless,
+            proxy = config.browser_proxy
+        );
 
-        // Launch headless browser
-        let (browser, mut handler) = Browser::launch(
-            BrowserConfig::builder()
-                .build()
-                .map_err(|e| anyhow::anyhow!(e))?,
-        )
-        .await?;
+        let mut builder = BrowserConfig::builder();
+        if !config.browser_headless {
+            builder = builder.with_head();
+        }
+        if let Some(proxy) = config
+            .browser_proxy
+            .as_deref()
+            .filter(|p| !p.trim().is_empty())
+        {
+            builder = builder.arg(format!("--proxy-server={}", proxy.trim()));
+        }
+        let (browser, mut handler) =
+            Browser::launch(builder.build().map_err(|e| anyhow::anyhow!(e))?).await?;
 
         crate::trace_debug!(
             "nexus::browser",
@@ -41,21 +115,121 @@ impl BrowserManager {
             }
         });
 
-        crate::trace_info!("nexus::browser", "BrowserManager initialized successfully");
-        Ok(Self {
-            browser: Arc::new(browser),
+        Ok(browser)
+    }
+
+    /// The running Chromium process
+    fn process(&self) -> Result<Arc<Browser>> {
+        self.browser
+            .read()
+            .ok()
+            .and_then(|browser| browser.clone())
+            .ok_or_else(|| anyhow::anyhow!("The browser is stopped. Restart it first."))
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.browser.read().is_ok_and(|browser| browser.is_some())
+    }
+
+    /// Close the current page and shut down the Chromium process
+    pub async fn stop(&self) -> Result<()> {
+        self.reset().await?;
+        let process = self.browser.write().ok().and_then(|mut b| b.take());
+        if let Some(process) = process {
+            crate::trace_info!("nexus::browser", "Stopping browser");
+            // The process is killed once the last handle on it is dropped
+            // even if it ignores the request
+            let _ = process
+                .execute(chromiumoxide::cdp::browser_protocol::browser::CloseParams::default())
+                .await;
+        }
+        emit_update(None);
+        Ok(())
+    }
+
+    /// Relaunch Chromium with `config`'s launch settings and timeouts
+    pub async fn restart(&self, config: &Config) -> Result<()> {
+        self.stop().await?;
+        let browser = Self::launch(config).await?;
+        if let Ok(mut slot) = self.browser.write() {
+            *slot = Some(Arc::new(browser));
+        }
+        self.set_timeouts(BrowserTimeouts::from_config(config));
+        crate::trace_info!("nexus::browser", "Browser restarted");
+        emit_update(None);
+        Ok(())
+    }
+
+    pub fn timeouts(&self) -> BrowserTimeouts {
+        self.timeouts.read().map(|t| *t).unwrap_or_default()
+    }
+
+    /// Apply new timeouts, e.g. after the config is saved
+    pub fn set_timeouts(&self, timeouts: BrowserTimeouts) {
+        if let Ok(mut current) = self.timeouts.write() {
+            *current = timeouts;
+        }
+    }
+
+    /// A handle on the same browser process with its own current page, so
+    /// concurrent sub-agents don't navigate each other's tabs
+    pub fn new_tab(&self) -> Self {
+        Self {
+            browser: self.browser.clone(),
             current_page: Arc::new(Mutex::new(None)),
+            context: self.context.clone(),
+            timeouts: self.timeouts.clone(),
+        }
+    }
+
+    /// A handle on the same browser process in a fresh incognito context, so
+    /// concurrent runs share neither pages nor cookies and storage
+    pub async fn isolated(&self) -> Result<Self> {
+        let context = self
+            .process()?
+            .execute(CreateBrowserContextParams::default())
+            .await?
+            .result
+            .browser_context_id;
+        crate::trace_debug!(
+            "nexus::browser",
+            "Browser context created",
+            context = context.inner()
+        );
+        Ok(Self {
+            context: Some(context),
+            ..self.new_tab()
         })
     }
 
-    async fn wait_for_selector(page: &Page, selector: &str) -> Result<chromiumoxide::Element> {
+    /// Close the current page and, for an isolated handle, its context
+    pub async fn close(&self) -> Result<()> {
+        self.reset().await?;
+        if let Some(context) = &self.context {
+            self.process()?
+                .execute(DisposeBrowserContextParams::new(context.clone()))
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn open_page(&self, url: &str) -> Result<Page> {
+        let mut params = CreateTargetParams::new(url);
+        params.browser_context_id = self.context.clone();
+        Ok(self.process()?.new_page(params).await?)
+    }
+
+    async fn wait_for_selector(
+        page: &Page,
+        selector: &str,
+        wait_timeout: Duration,
+    ) -> Result<chromiumoxide::Element> {
         crate::trace_debug!(
             "nexus::browser",
             "Waiting for selector",
             selector = selector
         );
         let start = std::time::Instant::now();
-        let wait_timeout = Duration::from_secs(5);
 
         loop {
             match page.find_element(selector).await {
@@ -76,8 +250,9 @@ impl BrowserManager {
                             selector = selector
                         );
                         return Err(anyhow::anyhow!(
-                            "Element '{}' not found after 5 seconds",
-                            selector
+                            "Element '{}' not found after {} seconds",
+                            selector,
+                            wait_timeout.as_secs()
                         ));
                     }
                     sleep(Duration::from_millis(200)).await;
@@ -88,11 +263,11 @@ impl BrowserManager {
 
     pub async fn navigate_and_get_content(&self, url: &str) -> Result<String> {
         crate::trace_info!("nexus::browser", "Starting navigation", url = url);
-        let timeout_duration = Duration::from_secs(30);
+        let timeout_duration = self.timeouts().navigation;
 
         let result = timeout(timeout_duration, async {
             crate::trace_debug!("nexus::browser", "Creating new page");
-            let page = self.browser.new_page(url).await?;
+            let page = self.open_page(url).await?;
             crate::trace_debug!("nexus::browser", "Page created, waiting for navigation");
             // Wait for page to load
             page.wait_for_navigation().await?;
@@ -119,17 +294,7 @@ impl BrowserManager {
                 }
                 *guard = Some(page);
 
-                // Emit event for UI update
-                if let Some(app) = crate::GLOBAL_APP.get() {
-                    use serde_json::json;
-                    use tauri::Emitter;
-                    let _ = app.emit(
-                        "browser-update",
-                        json!({
-                            "url": url,
-                        }),
-                    );
-                }
+                emit_update(Some(url));
 
                 crate::trace_info!(
                     "nexus::browser",
@@ -150,7 +315,10 @@ impl BrowserManager {
             }
             Err(_) => {
                 crate::trace_error!("nexus::browser", "Navigation timeout", url = url);
-                Err(anyhow::anyhow!("Navigation timed out after 30 seconds"))
+                Err(anyhow::anyhow!(
+                    "Navigation timed out after {} seconds",
+                    timeout_duration.as_secs()
+                ))
             }
         }
     }
@@ -169,6 +337,14 @@ impl BrowserManager {
     }
 
     pub async fn take_screenshot(&self) -> Result<String> {
+        let screenshot_data = self.screenshot_png().await?;
+        use base64::{engine::general_purpose, Engine as _};
+        let base64_image = general_purpose::STANDARD.encode(screenshot_data);
+        Ok(format!("data:image/png;base64,{}", base64_image))
+    }
+
+    /// PNG screenshot of the current page
+    pub async fn screenshot_png(&self) -> Result<Vec<u8>> {
         let guard = self.current_page.lock().await;
         if let Some(page) = guard.as_ref() {
             // chromiumoxide's screenshot returns Vec<u8>
@@ -177,15 +353,45 @@ impl BrowserManager {
                     .format(chromiumoxide::cdp::browser_protocol::page::CaptureScreenshotFormat::Png)
                     .build()
             ).await?;
-
-            use base64::{engine::general_purpose, Engine as _};
-            let base64_image = general_purpose::STANDARD.encode(screenshot_data);
-            Ok(format!("data:image/png;base64,{}", base64_image))
+            Ok(screenshot_data)
         } else {
             Err(anyhow::anyhow!("No active page to screenshot"))
         }
     }
 
+    /// Print a standalone HTML document to PDF in a scratch tab of its own
+    /// context, with JavaScript disabled
+    pub async fn render_pdf(&self, html: &str) -> Result<Vec<u8>> {
+        let timeout_duration = self.timeouts().navigation;
+        let scratch = self.isolated().await?;
+        let result = timeout(timeout_duration, async {
+            let page = scratch.open_page("about:blank").await?;
+            page.execute(SetScriptExecutionDisabledParams::new(true))
+                .await?;
+            let frame_id = page
+                .mainframe()
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("The scratch page has no frame"))?;
+            page.execute(SetDocumentContentParams::new(frame_id, html))
+                .await?;
+            let pdf = page
+                .pdf(chromiumoxide::cdp::browser_protocol::page::PrintToPdfParams::default())
+                .await?;
+            let _ = page.close().await;
+            Ok::<_, anyhow::Error>(pdf)
+        })
+        .await;
+        // Disposing the context also closes a page left open by a failure
+        let _ = scratch.close().await;
+        match result {
+            Ok(r) => r,
+            Err(_) => Err(anyhow::anyhow!(
+                "PDF rendering timed out after {} seconds",
+                timeout_duration.as_secs()
+            )),
+        }
+    }
+
     pub async fn click_element(&self, selector: &str) -> Result<String> {
         crate::trace_info!(
             "nexus::browser",
@@ -194,7 +400,8 @@ impl BrowserManager {
         );
         let guard = self.current_page.lock().await;
         if let Some(page) = guard.as_ref() {
-            let timeout_duration = Duration::from_secs(30);
+            let timeouts = self.timeouts();
+            let timeout_duration = timeouts.action;
             let selector_owned = selector.to_string();
             let selector_for_log = selector_owned.clone();
             let page_clone = page.clone();
@@ -202,7 +409,9 @@ impl BrowserManager {
             crate::trace_debug!("nexus::browser", "Starting click operation with timeout");
             let result = timeout(timeout_duration, async move {
                 crate::trace_debug!("nexus::browser", "Waiting for element");
-                let element = Self::wait_for_selector(&page_clone, &selector_owned).await?;
+                let element =
+                    Self::wait_for_selector(&page_clone, &selector_owned, timeouts.selector)
+                        .await?;
                 crate::trace_debug!("nexus::browser", "Element found, clicking");
                 element.click().await?;
                 crate::trace_debug!("nexus::browser", "Click executed, getting page content");
@@ -226,7 +435,10 @@ impl BrowserManager {
                         "Click operation timeout",
                         selector = selector_for_log
                     );
-                    Err(anyhow::anyhow!("Click action timed out after 30 seconds"))
+                    Err(anyhow::anyhow!(
+                        "Click action timed out after {} seconds",
+                        timeout_duration.as_secs()
+                    ))
                 }
             }
         } else {
@@ -235,10 +447,73 @@ impl BrowserManager {
         }
     }
 
+    /// Give keyboard focus to the element matching `selector`
+    pub async fn focus_element(&self, selector: &str) -> Result<()> {
+        let guard = self.current_page.lock().await;
+        if let Some(page) = guard.as_ref() {
+            let timeouts = self.timeouts();
+            let result = timeout(timeouts.action, async {
+                let element = Self::wait_for_selector(page, selector, timeouts.selector).await?;
+                element.focus().await?;
+                Ok::<_, anyhow::Error>(())
+            })
+            .await;
+            match result {
+                Ok(r) => r,
+                Err(_) => Err(anyhow::anyhow!(
+                    "Focus action timed out after {} seconds",
+                    timeouts.action.as_secs()
+                )),
+            }
+        } else {
+            Err(anyhow::anyhow!("No active page. Navigate to a URL first."))
+        }
+    }
+
+    /// Check whether clicking `selector` would submit a form
+    pub async fn is_form_submit(&self, selector: &str) -> Result<bool> {
+        let guard = self.current_page.lock().await;
+        if let Some(page) = guard.as_ref() {
+            let selector_json = serde_json::to_string(selector)?;
+            let script = format!(
+                r#"(() => {{
+                    const el = document.querySelector({});
+                    if (!el) return false;
+                    const tag = el.tagName.toLowerCase();
+                    const type = (el.getAttribute("type") || "").toLowerCase();
+                    if (tag === "input") return type === "submit" || type === "image";
+                    if (tag === "button") return !!el.form && (type === "" || type === "submit");
+                    return false;
+                }})()"#,
+                selector_json
+            );
+            let is_submit: bool = page.evaluate(script).await?.into_value()?;
+            Ok(is_submit)
+        } else {
+            Err(anyhow::anyhow!("No active page. Navigate to a URL first."))
+        }
+    }
+
+    /// Check whether the focused element is a password input
+    pub async fn is_password_focused(&self) -> Result<bool> {
+        let guard = self.current_page.lock().await;
+        if let Some(page) = guard.as_ref() {
+            let script = r#"(() => {
+                const el = document.activeElement;
+                return !!el && el.tagName.toLowerCase() === "input"
+                    && (el.getAttribute("type") || "").toLowerCase() === "password";
+            })()"#;
+            let is_password: bool = page.evaluate(script).await?.into_value()?;
+            Ok(is_password)
+        } else {
+            Err(anyhow::anyhow!("No active page. Navigate to a URL first."))
+        }
+    }
+
     pub async fn type_text(&self, text: &str) -> Result<String> {
         let guard = self.current_page.lock().await;
         if let Some(page) = guard.as_ref() {
-            let timeout_duration = Duration::from_secs(30);
+            let timeout_duration = self.timeouts().action;
             let text = text.to_string();
             let page_clone = page.clone();
 
@@ -262,7 +537,10 @@ impl BrowserManager {
 
             match result {
                 Ok(r) => r,
-                Err(_) => Err(anyhow::anyhow!("Type action timed out after 30 seconds")),
+                Err(_) => Err(anyhow::anyhow!(
+                    "Type action timed out after {} seconds",
+                    timeout_duration.as_secs()
+                )),
             }
         } else {
             Err(anyhow::anyhow!("No active page. Navigate to a URL first."))
@@ -272,13 +550,15 @@ impl BrowserManager {
     pub async fn upload_file(&self, selector: &str, file_path: &str) -> Result<String> {
         let guard = self.current_page.lock().await;
         if let Some(page) = guard.as_ref() {
-            let timeout_duration = Duration::from_secs(30);
+            let timeouts = self.timeouts();
+            let timeout_duration = timeouts.action;
             let selector = selector.to_string();
             let file_path = file_path.to_string();
             let page_clone = page.clone();
 
             let result = timeout(timeout_duration, async move {
-                let element = Self::wait_for_selector(&page_clone, &selector).await?;
+                let element =
+                    Self::wait_for_selector(&page_clone, &selector, timeouts.selector).await?;
                 // We use CDP directly since set_input_files helper is missing
                 page_clone
                     .execute(
@@ -306,7 +586,7 @@ impl BrowserManager {
     pub async fn scroll_page(&self, direction: &str, amount: Option<i32>) -> Result<String> {
         let guard = self.current_page.lock().await;
         if let Some(page) = guard.as_ref() {
-            let timeout_duration = Duration::from_secs(10); // Scroll should be fast
+            let timeout_duration = self.timeouts().action;
             let val = amount.unwrap_or(500);
             let delta = if direction == "up" { -val } else { val };
             let page_clone = page.clone();
@@ -339,6 +619,39 @@ impl BrowserManager {
         }
     }
 
+    /// Outer HTML of every element matching the CSS `selector`
+    pub async fn outer_html(&self, selector: &str) -> Result<Vec<String>> {
+        let script = format!(
+            "Array.from(document.querySelectorAll({})).map(el => el.outerHTML)",
+            serde_json::to_string(selector)?
+        );
+        Ok(serde_json::from_value(self.evaluate_json(script).await?)?)
+    }
+
+    /// Evaluate a JavaScript expression on the current page and return its JSON value
+    pub async fn evaluate_json(&self, script: String) -> Result<serde_json::Value> {
+        let guard = self.current_page.lock().await;
+        if let Some(page) = guard.as_ref() {
+            let timeout_duration = self.timeouts().action;
+            let page_clone = page.clone();
+            let result = timeout(timeout_duration, async move {
+                let value: serde_json::Value = page_clone.evaluate(script).await?.into_value()?;
+                Ok::<_, anyhow::Error>(value)
+            })
+            .await;
+
+            match result {
+                Ok(r) => r,
+                Err(_) => Err(anyhow::anyhow!(
+                    "Script evaluation timed out after {} seconds",
+                    timeout_duration.as_secs()
+                )),
+            }
+        } else {
+            Err(anyhow::anyhow!("No active page. Navigate to a URL first."))
+        }
+    }
+
     pub async fn reset(&self) -> Result<()> {
         let mut guard = self.current_page.lock().await;
         if let Some(page) = guard.take() {
@@ -347,3 +660,19 @@ impl BrowserManager {
         Ok(())
     }
 }
+
+/// Tell the UI the browser's page changed, or that it has none after a stop
+/// or restart
+fn emit_update(url: Option<&str>) {
+    if let Some(app) = crate::GLOBAL_APP.get() {
+        use serde_json::json;
+        use tauri::Emitter;
+        let _ = app.emit(
+            "browser-update",
+            json!({
+                "url": url,
+                "run_id": crate::run::current().map(|run| run.run_id.clone()),
+            }),
+        );
+    }
+}
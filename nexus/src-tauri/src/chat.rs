@@ -0,0 +1,94 @@
+//! Multi-turn chat sessions with the agent
+//!
+//! A chat keeps the worker's conversation thread between messages. The shared
+//! browser is left on whatever page the previous turn ended on, so follow-up
+//! questions can build on it.
+
+use crate::config::Config;
+use radkit::models::Thread;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use uuid::Uuid;
+
+/// Global chat session store
+pub static GLOBAL_CHATS: OnceLock<Mutex<HashMap<String, ChatSession>>> = OnceLock::new();
+
+pub struct ChatSession {
+    pub config: Config,
+    /// Conversation so far; `None` until the first message, and while a turn
+    /// is in flight
+    thread: Option<Thread>,
+    busy: bool,
+}
+
+fn chats() -> &'static Mutex<HashMap<String, ChatSession>> {
+    GLOBAL_CHATS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Open a new chat session, returning its id
+pub fn start(config: Config) -> String {
+    let id = Uuid::new_v4().to_string();
+    if let Ok(mut chats) = chats().lock() {
+        chats.insert(
+            id.clone(),
+            ChatSession {
+                config,
+                thread: None,
+                busy: false,
+            },
+        );
+    }
+    crate::trace_info!("nexus::chat", "Chat started", chat_id = id);
+    id
+}
+
+/// Send a message to a chat and wait for the agent's answer
+pub async fn send(chat_id: &str, message: String) -> Result<String, String> {
+    let (config, thread) = {
+        let mut chats = chats().lock().map_err(|_| "Chat state poisoned".to_string())?;
+        let session = chats
+            .get_mut(chat_id)
+            .ok_or_else(|| format!("Chat {} not found", chat_id))?;
+        if session.busy {
+            return Err("The agent is still answering the previous message".to_string());
+        }
+        session.busy = true;
+        (session.config.clone(), session.thread.take())
+    };
+
+    let previous = thread.clone();
+    let result = crate::agent::run_chat_turn(message, thread, config).await;
+
+    let mut chats = chats().lock().map_err(|_| "Chat state poisoned".to_string())?;
+    let Some(session) = chats.get_mut(chat_id) else {
+        // Ended while the turn was running
+        return result.map(|(report, _)| report);
+    };
+    session.busy = false;
+    match result {
+        Ok((report, thread)) => {
+            session.thread = Some(thread);
+            Ok(report)
+        }
+        Err(e) => {
+            // Keep the history from before the failed turn
+            session.thread = previous;
+            Err(e)
+        }
+    }
+}
+
+/// Close a chat session and drop its history
+pub fn end(chat_id: &str) -> Result<(), String> {
+    let removed = chats()
+        .lock()
+        .map_err(|_| "Chat state poisoned".to_string())?
+        .remove(chat_id);
+    match removed {
+        Some(_) => {
+            crate::trace_info!("nexus::chat", "Chat ended", chat_id = chat_id);
+            Ok(())
+        }
+        None => Err(format!("Chat {} not found", chat_id)),
+    }
+}
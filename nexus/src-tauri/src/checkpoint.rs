@@ -0,0 +1,180 @@
+//! Run checkpoints for crash recovery
+//!
+//! While a run executes, [`crate::usage::MeteredLlm`] periodically persists the
+//! worker's conversation thread together with the memory store, the current
+//! page and the run's progress and spend counters. `resume_run` restores a checkpoint
+//! and continues the conversation under the same run_id, so an app restart or
+//! crash doesn't throw away a long research session.
+
+use crate::memory::{MemoryEntry, GLOBAL_MEMORY};
+use crate::run::RunContext;
+use crate::usage::RunUsage;
+use radkit::models::Thread;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+
+/// Everything needed to continue an interrupted run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Checkpoint {
+    pub run_id: String,
+    pub saved_at: i64,
+    pub steps: usize,
+    pub current_url: Option<String>,
+    /// Serialized worker conversation thread
+    pub thread: serde_json::Value,
+    pub memory: Vec<MemoryEntry>,
+    pub findings: Vec<String>,
+    pub sources: Vec<String>,
+    /// Approved plan in plan-then-execute mode
+    #[serde(default)]
+    pub plan: Option<Vec<String>>,
+    /// Tokens and cost spent so far, so budgets hold across a resume
+    #[serde(default)]
+    pub usage: Option<RunUsage>,
+}
+
+impl Checkpoint {
+    pub fn thread(&self) -> Result<Thread, String> {
+        serde_json::from_value(self.thread.clone())
+            .map_err(|e| format!("Checkpoint thread is unreadable: {}", e))
+    }
+}
+
+/// Snapshot the run's state together with the worker's current thread
+pub async fn capture(run: &RunContext, thread: &Thread) -> Result<Checkpoint, String> {
+    let thread = serde_json::to_value(thread).map_err(|e| e.to_string())?;
+    let current_url = match crate::browser::current() {
+        Some(browser) => browser
+            .get_current_url()
+            .await
+            .ok()
+            .filter(|url| url.starts_with("http")),
+        None => None,
+    };
+    let memory = match GLOBAL_MEMORY.get() {
+        Some(mem) => mem.read().await.get_all(),
+        None => Vec::new(),
+    };
+
+    Ok(Checkpoint {
+        run_id: run.run_id.clone(),
+        saved_at: chrono::Utc::now().timestamp_millis(),
+        steps: run.steps(),
+        current_url,
+        thread,
+        memory,
+        findings: run.findings(),
+        sources: run.sources(),
+        plan: run.plan(),
+        usage: Some(run.usage()),
+    })
+}
+
+/// Capture and persist a checkpoint if the run is due for one
+pub async fn maybe_save(run: &RunContext, thread: &Thread) {
+    if !run.checkpoint_due() {
+        return;
+    }
+    let result = match capture(run, thread).await {
+        Ok(checkpoint) => crate::db::with_db(|conn| save(conn, &checkpoint)),
+        Err(e) => Err(e),
+    };
+    match result {
+        Ok(()) => crate::trace_debug!(
+            "nexus::checkpoint",
+            "Checkpoint saved",
+            run_id = run.run_id,
+            steps = run.steps()
+        ),
+        Err(e) => crate::trace_warn!(
+            "nexus::checkpoint",
+            "Failed to save checkpoint",
+            run_id = run.run_id,
+            error = e
+        ),
+    }
+}
+
+/// Merge checkpointed memories back into the memory store, skipping entries
+/// that are still present
+pub async fn restore_memory(entries: &[MemoryEntry]) -> usize {
+    let Some(mem_lock) = GLOBAL_MEMORY.get() else {
+        return 0;
+    };
+    let mut mem = mem_lock.write().await;
+    let mut restored = 0;
+    for entry in entries {
+        // Entries checkpointed before IDs existed get a new ID on every load,
+        // so match those by content and time as well
+        let known = mem.entries.iter().any(|existing| {
+            existing.id == entry.id
+                || (existing.content == entry.content && existing.timestamp == entry.timestamp)
+        });
+        if !known {
+            mem.entries.push(entry.clone());
+            restored += 1;
+        }
+    }
+    restored
+}
+
+pub fn save(conn: &Connection, checkpoint: &Checkpoint) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT OR REPLACE INTO checkpoints (run_id, saved_at, state) VALUES (?1, ?2, ?3)",
+        params![
+            checkpoint.run_id,
+            checkpoint.saved_at,
+            serde_json::to_string(checkpoint).unwrap_or_default()
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn load(conn: &Connection, run_id: &str) -> rusqlite::Result<Option<Checkpoint>> {
+    let state: Option<String> = conn
+        .query_row(
+            "SELECT state FROM checkpoints WHERE run_id = ?1",
+            params![run_id],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(state.and_then(|s| serde_json::from_str(&s).ok()))
+}
+
+pub fn delete(conn: &Connection, run_id: &str) -> rusqlite::Result<()> {
+    conn.execute("DELETE FROM checkpoints WHERE run_id = ?1", params![run_id])?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_checkpoint_roundtrip() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+
+        let mut checkpoint = Checkpoint {
+            run_id: "run-1".to_string(),
+            saved_at: 1,
+            steps: 4,
+            current_url: Some("https://example.com/".to_string()),
+            thread: serde_json::json!({ "events": [] }),
+            memory: Vec::new(),
+            findings: vec!["widgets cost $10".to_string()],
+            sources: vec!["https://example.com/".to_string()],
+            plan: None,
+            usage: Some(RunUsage::new("gpt-4o")),
+        };
+        save(&conn, &checkpoint).unwrap();
+        checkpoint.steps = 7;
+        save(&conn, &checkpoint).unwrap();
+
+        let loaded = load(&conn, "run-1").unwrap().unwrap();
+        assert_eq!(loaded, checkpoint);
+
+        delete(&conn, "run-1").unwrap();
+        assert!(load(&conn, "run-1").unwrap().is_none());
+    }
+}
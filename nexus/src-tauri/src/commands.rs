@@ -1,23 +1,53 @@
+use crate::agent::{PageContent, ToolInfo};
 use crate::browser::BrowserManager;
-use crate::config::{Config, ConfigManager};
-use crate::memory::{MemoryEntry, GLOBAL_MEMORY};
-use crate::search::search_content;
-use crate::tracing::{TraceEvent, TRACE_STORE};
+use crate::bundle::{BundledRun, SessionBundle};
+use crate::config::{Config, ConfigIssue, ConfigManager, ProfileSummary};
+use crate::criteria::SuccessCriteria;
+use crate::datasets::DatasetRun;
+use crate::export::ExportFormat;
+use crate::frontier::FrontierSnapshot;
+use crate::human_actions::HumanAction;
+use crate::memory::{ArchivedMemory, MemoryEntry, TagCount, GLOBAL_MEMORY};
+use crate::metrics::RunMetrics;
+use crate::models::{ConnectionTest, ModelInfo};
+use crate::prompts::PromptPack;
+use crate::queue::Job;
+use crate::recipes::{LearnResult, Recipe};
+use crate::replay::{ReplayState, ReplayStep};
+use crate::reports::ReportDiff;
+use crate::run::{AgentStatus, RunRecord, RunResult};
+use crate::search::{search_with_variants, SearchHit, SearchMatch, SearchOptions, UnifiedResults};
+use crate::shapes::OutputPreset;
+use crate::tasks::SavedTask;
+use crate::templates::PromptTemplate;
+use crate::tracing::{
+    TraceEvent, TraceExportFormat, TraceFilter, TracePage, TraceSession, TraceStats, TRACE_STORE,
+};
+use crate::usage::RunUsage;
+use crate::workspace::{ArtifactUsage, PurgeReport};
 use html_to_markdown_rs::convert;
+use std::collections::HashMap;
 use std::sync::Mutex;
 use tauri::State;
 
+/// Fetch `url` and search its markdown for `query`. With a CSS `selector`,
+/// the text of each matching element is searched instead, one element per
+/// line; an empty query then returns every element.
 #[tauri::command]
 pub async fn fetch_and_search(
     url: String,
     query: String,
+    selector: Option<String>,
+    options: Option<SearchOptions>,
     state: State<'_, BrowserManager>,
-) -> Result<Vec<String>, String> {
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<Vec<SearchMatch>, String> {
     crate::trace_info!(
         "nexus::commands",
         "fetch_and_search called",
         url = url,
-        query = query
+        query = query,
+        selector = selector
     );
 
     let content_html = state
@@ -25,10 +55,40 @@ pub async fn fetch_and_search(
         .await
         .map_err(|e| e.to_string())?;
 
-    // Convert HTML to Markdown
-    let content_md = convert(&content_html, None).map_err(|e| e.to_string())?;
+    let content = match &selector {
+        Some(selector) => {
+            let texts: Vec<String> = serde_json::from_value(
+                state
+                    .evaluate_json(crate::search::selector_text_script(selector))
+                    .await
+                    .map_err(|e| format!("Selector '{}' failed: {}", selector, e))?,
+            )
+            .map_err(|e| e.to_string())?;
+            texts.join("\n")
+        }
+        // Convert HTML to Markdown
+        None => convert(&content_html, None).map_err(|e| e.to_string())?,
+    };
 
-    let matches = search_content(&content_md, &query).map_err(|e| e.to_string())?;
+    let config = config_manager.lock().unwrap().load();
+    let variants = if query.trim().is_empty() {
+        Vec::new()
+    } else {
+        crate::language::expand_query(&config, &query, &config.query_languages)
+            .await
+            .unwrap_or_else(|e| {
+                crate::trace_warn!("nexus::commands", "Query expansion failed", error = e);
+                Vec::new()
+            })
+    };
+    let mut options = options.unwrap_or_default();
+    options.context_lines = options.context_lines.or(Some(config.search_context_lines));
+    options.max_results = options.max_results.or(Some(config.search_max_results));
+    let matches: Vec<SearchMatch> = search_with_variants(&content, &query, &variants, options)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .filter(|m| crate::language::is_allowed(&m.line, &config.allowed_languages))
+        .collect();
 
     crate::trace_info!(
         "nexus::commands",
@@ -38,15 +98,83 @@ pub async fn fetch_and_search(
     Ok(matches)
 }
 
+/// Search the current page, the memory store and the trace store at once
+#[tauri::command]
+pub async fn search_everything(
+    query: String,
+    options: Option<SearchOptions>,
+    state: State<'_, BrowserManager>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<UnifiedResults, String> {
+    let config = config_manager.lock().unwrap().load();
+    let mut options = options.unwrap_or_default();
+    options.context_lines = options.context_lines.or(Some(config.search_context_lines));
+    options.max_results = options.max_results.or(Some(config.search_max_results));
+
+    // No page is loaded before the first navigation
+    let page = match state.get_content().await {
+        Ok(html) => Some(convert(&html, None).map_err(|e| e.to_string())?),
+        Err(_) => None,
+    };
+    let memories = match GLOBAL_MEMORY.get() {
+        Some(mem_lock) => mem_lock.read().await.get_all(),
+        None => Vec::new(),
+    };
+    let traces = match TRACE_STORE.get() {
+        Some(store) => store.lock().await.get_events(),
+        None => Vec::new(),
+    };
+
+    let results = crate::search::search_sources(&query, options, page.as_deref(), memories, traces)
+        .map_err(|e| e.to_string())?;
+    // Traced only after searching, so the query doesn't match its own event
+    crate::trace_info!(
+        "nexus::commands",
+        "search_everything complete",
+        page_matches = results.page.len(),
+        memories = results.memories.len(),
+        traces = results.traces.len()
+    );
+    Ok(results)
+}
+
+/// Queue an agent run and return its run_id without waiting for it to finish
 #[tauri::command]
 pub async fn run_agent(
     prompt: String,
-    _app_handle: tauri::AppHandle,
+    plan_first: Option<bool>,
+    query_languages: Option<Vec<String>>,
+    orchestrate: Option<bool>,
+    system_prompt: Option<String>,
+    success_criteria: Option<SuccessCriteria>,
+    output_preset: Option<OutputPreset>,
+    output_schema: Option<serde_json::Value>,
     config_manager: State<'_, Mutex<ConfigManager>>,
 ) -> Result<String, String> {
     crate::trace_info!("nexus::commands", "run_agent called", prompt = prompt);
 
-    let config = config_manager.lock().unwrap().load();
+    let mut config = config_manager.lock().unwrap().load();
+    if let Some(plan_first) = plan_first {
+        config.plan_first = plan_first;
+    }
+    if let Some(query_languages) = query_languages {
+        config.query_languages = query_languages;
+    }
+    if let Some(orchestrate) = orchestrate {
+        config.orchestrate = orchestrate;
+    }
+    if let Some(system_prompt) = system_prompt {
+        config.system_prompt = system_prompt;
+    }
+    if success_criteria.is_some() {
+        config.success_criteria = success_criteria;
+    }
+    if output_preset.is_some() {
+        config.output_preset = output_preset;
+    }
+    if output_schema.is_some() {
+        config.output_schema = output_schema;
+    }
     crate::trace_debug!(
         "nexus::commands",
         "Config loaded",
@@ -54,61 +182,398 @@ pub async fn run_agent(
         model = config.model
     );
 
-    let result = crate::agent::run_agent_loop(prompt, config).await;
+    let run_id = crate::agent::spawn_agent_run(prompt, config);
+    crate::trace_info!("nexus::commands", "run_agent queued", run_id = run_id);
+    Ok(run_id)
+}
 
-    match &result {
-        Ok(_) => crate::trace_info!("nexus::commands", "run_agent completed successfully"),
-        Err(e) => crate::trace_error!("nexus::commands", "run_agent failed", error = e),
-    }
+/// Where a run started with `run_agent` is: queued, running, or finished
+/// with its report or error
+#[tauri::command]
+pub fn get_run_result(run_id: String) -> Result<RunResult, String> {
+    crate::db::with_db(|conn| crate::run::get_result(conn, &run_id))?
+        .ok_or_else(|| format!("Run {} not found", run_id))
+}
+
+/// Transcribe a voice prompt. Pass either a path to a WAV file or the WAV
+/// bytes recorded by the frontend.
+#[tauri::command]
+pub async fn transcribe_audio(
+    path: Option<String>,
+    bytes: Option<Vec<u8>>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<String, String> {
+    let config = config_manager.lock().unwrap().load();
+    let model_path = config
+        .whisper_model_path
+        .filter(|p| !p.trim().is_empty())
+        .ok_or_else(|| "No whisper model configured for voice input".to_string())?;
+    let bytes = match (bytes, path) {
+        (Some(bytes), _) => bytes,
+        (None, Some(path)) => std::fs::read(&path).map_err(|e| format!("{}: {}", path, e))?,
+        (None, None) => return Err("Either path or bytes is required".to_string()),
+    };
+
+    let transcript = tokio::task::spawn_blocking(move || {
+        let audio = crate::speech::decode_wav(&bytes)?;
+        crate::speech::transcribe(&model_path, &audio)
+    })
+    .await
+    .map_err(|e| e.to_string())??;
+
+    crate::trace_info!(
+        "nexus::commands",
+        "Audio transcribed",
+        transcript_len = transcript.len()
+    );
+    Ok(transcript)
+}
+
+/// Read text aloud with the platform speech synthesizer
+#[tauri::command]
+pub async fn speak_text(text: String) -> Result<(), String> {
+    crate::trace_info!("nexus::commands", "speak_text called", text_len = text.len());
+    crate::speech::speak(&text).await
+}
+
+/// Queue an interrupted run to continue from its last checkpoint, returning
+/// its run_id straight away; the outcome arrives like `run_agent`'s
+#[tauri::command]
+pub fn resume_run(
+    run_id: String,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<String, String> {
+    crate::trace_info!("nexus::commands", "resume_run called", run_id = run_id);
+    let config = config_manager.lock().unwrap().load();
+    Ok(crate::agent::spawn_resumed_run(run_id, config))
+}
+
+#[tauri::command]
+pub fn start_chat(config_manager: State<'_, Mutex<ConfigManager>>) -> Result<String, String> {
+    let config = config_manager.lock().unwrap().load();
+    Ok(crate::chat::start(config))
+}
+
+#[tauri::command]
+pub async fn send_message(chat_id: String, message: String) -> Result<String, String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "send_message called",
+        chat_id = chat_id,
+        message = message
+    );
+    crate::chat::send(&chat_id, message).await
+}
 
-    result
+#[tauri::command]
+pub fn end_chat(chat_id: String) -> Result<(), String> {
+    crate::chat::end(&chat_id)
 }
 
 #[tauri::command]
-pub fn get_memories() -> Result<Vec<MemoryEntry>, String> {
+pub async fn get_memories() -> Result<Vec<MemoryEntry>, String> {
     crate::trace_debug!("nexus::commands", "get_memories called");
 
     if let Some(mem_lock) = GLOBAL_MEMORY.get() {
-        if let Ok(mem) = mem_lock.lock() {
-            let memories = mem.get_all();
-            crate::trace_debug!(
-                "nexus::commands",
-                "get_memories returning",
-                count = memories.len()
-            );
-            return Ok(memories);
+        let memories = mem_lock.read().await.get_all();
+        crate::trace_debug!(
+            "nexus::commands",
+            "get_memories returning",
+            count = memories.len()
+        );
+        return Ok(memories);
+    }
+    crate::trace_error!("nexus::commands", "Failed to access memory");
+    Err("Failed to access memory".to_string())
+}
+
+/// Memories carrying `tag`, ignoring case
+#[tauri::command]
+pub async fn get_memories_by_tag(tag: String) -> Result<Vec<MemoryEntry>, String> {
+    crate::trace_debug!("nexus::commands", "get_memories_by_tag called", tag = tag);
+
+    if let Some(mem_lock) = GLOBAL_MEMORY.get() {
+        return Ok(mem_lock.read().await.get_by_tag(&tag));
+    }
+    crate::trace_error!("nexus::commands", "Failed to access memory");
+    Err("Failed to access memory".to_string())
+}
+
+/// Tags used in memory with their entry counts, most used first
+#[tauri::command]
+pub async fn list_memory_tags() -> Result<Vec<TagCount>, String> {
+    if let Some(mem_lock) = GLOBAL_MEMORY.get() {
+        return Ok(mem_lock.read().await.list_tags());
+    }
+    crate::trace_error!("nexus::commands", "Failed to access memory");
+    Err("Failed to access memory".to_string())
+}
+
+/// Replace a memory's content and tags; long content is condensed as when
+/// the agent memorizes it
+#[tauri::command]
+pub async fn update_memory(
+    id: String,
+    content: String,
+    tags: Vec<String>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<MemoryEntry, String> {
+    crate::trace_info!("nexus::commands", "update_memory called", id = id);
+
+    let summary = if crate::memory::needs_summary(&content) {
+        let config = config_manager.lock().unwrap().load();
+        match crate::memory::summarize(&config, &content).await {
+            Ok(summary) => Some(summary),
+            Err(e) => {
+                crate::trace_warn!(
+                    "nexus::commands",
+                    "Could not summarize memory, storing it in full",
+                    error = e
+                );
+                None
+            }
         }
+    } else {
+        None
+    };
+
+    let mem_lock = GLOBAL_MEMORY
+        .get()
+        .ok_or_else(|| "Failed to access memory".to_string())?;
+    let mut mem = mem_lock.write().await;
+    mem.update(&id, content, summary, tags)
+        .ok_or_else(|| format!("Memory '{}' not found", id))
+}
+
+/// Memories archived after expiring, most recent first
+#[tauri::command]
+pub fn list_archived_memories() -> Result<Vec<ArchivedMemory>, String> {
+    crate::db::with_db(crate::memory::list_archived)
+}
+
+/// Forget a single memory; false if it no longer exists
+#[tauri::command]
+pub async fn delete_memory(id: String) -> Result<bool, String> {
+    crate::trace_info!("nexus::commands", "delete_memory called", id = id);
+
+    if let Some(mem_lock) = GLOBAL_MEMORY.get() {
+        return Ok(mem_lock.write().await.delete(&id));
     }
     crate::trace_error!("nexus::commands", "Failed to access memory");
     Err("Failed to access memory".to_string())
 }
 
 #[tauri::command]
-pub fn clear_memories() -> Result<(), String> {
+pub async fn clear_memories() -> Result<(), String> {
     crate::trace_info!("nexus::commands", "clear_memories called");
 
     if let Some(mem_lock) = GLOBAL_MEMORY.get() {
-        if let Ok(mut mem) = mem_lock.lock() {
-            mem.clear();
-            crate::trace_info!("nexus::commands", "Memories cleared");
-            return Ok(());
-        }
+        mem_lock.write().await.clear();
+        crate::trace_info!("nexus::commands", "Memories cleared");
+        return Ok(());
     }
     crate::trace_error!("nexus::commands", "Failed to clear memory");
     Err("Failed to access memory".to_string())
 }
 
+/// Screenshot of a live run's page when `run_id` is given, otherwise of the
+/// shared browser
 #[tauri::command]
-pub async fn take_screenshot(state: State<'_, BrowserManager>) -> Result<String, String> {
-    crate::trace_debug!("nexus::commands", "take_screenshot called");
-    state.take_screenshot().await.map_err(|e| e.to_string())
+pub async fn take_screenshot(
+    run_id: Option<String>,
+    state: State<'_, BrowserManager>,
+) -> Result<String, String> {
+    crate::trace_debug!("nexus::commands", "take_screenshot called", run_id = run_id);
+    let browser = run_browser(run_id.as_deref(), &state);
+    browser.take_screenshot().await.map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub async fn get_current_url(state: State<'_, BrowserManager>) -> Result<String, String> {
+pub async fn get_current_url(
+    run_id: Option<String>,
+    state: State<'_, BrowserManager>,
+) -> Result<String, String> {
+    let browser = run_browser(run_id.as_deref(), &state);
+    browser.get_current_url().await.map_err(|e| e.to_string())
+}
+
+/// Drive the shared browser to `url` by hand, e.g. to log in before handing
+/// over to the agent. Returns the URL the page ended up on.
+#[tauri::command]
+pub async fn navigate(url: String, state: State<'_, BrowserManager>) -> Result<String, String> {
+    crate::trace_info!("nexus::commands", "navigate called", url = url);
+    let url = url.trim();
+    if url.is_empty() {
+        return Err("No URL given".to_string());
+    }
+    let url = if url.contains("://") {
+        url.to_string()
+    } else {
+        format!("https://{}", url)
+    };
+    state
+        .navigate_and_get_content(&url)
+        .await
+        .map_err(|e| e.to_string())?;
     state.get_current_url().await.map_err(|e| e.to_string())
 }
 
+/// Click `selector` by hand, in a live run's browser when `run_id` is given.
+/// Returns the URL the page is on afterwards.
+#[tauri::command]
+pub async fn manual_click(
+    selector: String,
+    run_id: Option<String>,
+    state: State<'_, BrowserManager>,
+) -> Result<String, String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "manual_click called",
+        selector = selector,
+        run_id = run_id
+    );
+    let browser = run_browser(run_id.as_deref(), &state);
+    browser
+        .click_element(&selector)
+        .await
+        .map_err(|e| e.to_string())?;
+    browser.get_current_url().await.map_err(|e| e.to_string())
+}
+
+/// Type `text` into `selector` by hand. Text typed into a password field is
+/// masked in all later events.
+#[tauri::command]
+pub async fn manual_type(
+    selector: String,
+    text: String,
+    run_id: Option<String>,
+    state: State<'_, BrowserManager>,
+) -> Result<(), String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "manual_type called",
+        selector = selector,
+        run_id = run_id
+    );
+    let browser = run_browser(run_id.as_deref(), &state);
+    browser
+        .focus_element(&selector)
+        .await
+        .map_err(|e| e.to_string())?;
+    if browser.is_password_focused().await.unwrap_or(false) {
+        crate::redact::register_secret(&text);
+    }
+    browser.type_text(&text).await.map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn manual_scroll(
+    direction: String,
+    amount: Option<i32>,
+    run_id: Option<String>,
+    state: State<'_, BrowserManager>,
+) -> Result<(), String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "manual_scroll called",
+        direction = direction,
+        run_id = run_id
+    );
+    let browser = run_browser(run_id.as_deref(), &state);
+    browser
+        .scroll_page(&direction, amount)
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// The current page converted to Markdown and cut to the content limit, as
+/// the agent's tools would return it. Long pages are truncated rather than
+/// condensed, since this view doesn't count against any run's budget.
+#[tauri::command]
+pub async fn get_page_markdown(
+    max_chars: Option<usize>,
+    run_id: Option<String>,
+    state: State<'_, BrowserManager>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<PageContent, String> {
+    crate::trace_debug!(
+        "nexus::commands",
+        "get_page_markdown called",
+        run_id = run_id
+    );
+    let mut config = match run_id.as_deref().and_then(crate::run::active) {
+        Some(run) => run.config.clone(),
+        None => config_manager.lock().unwrap().load(),
+    };
+    config.condense_long_pages = false;
+    let browser = run_browser(run_id.as_deref(), &state);
+    let html = browser.get_content().await.map_err(|e| e.to_string())?;
+    Ok(crate::agent::page_content(html, max_chars, &config).await)
+}
+
+/// Raw HTML of the current page, or of every element matching the CSS
+/// `selector`, one subtree after another
+#[tauri::command]
+pub async fn get_page_html(
+    selector: Option<String>,
+    run_id: Option<String>,
+    state: State<'_, BrowserManager>,
+) -> Result<String, String> {
+    crate::trace_debug!(
+        "nexus::commands",
+        "get_page_html called",
+        selector = selector,
+        run_id = run_id
+    );
+    let browser = run_browser(run_id.as_deref(), &state);
+    let Some(selector) = selector.filter(|s| !s.trim().is_empty()) else {
+        return browser.get_content().await.map_err(|e| e.to_string());
+    };
+    let subtrees = browser
+        .outer_html(&selector)
+        .await
+        .map_err(|e| format!("Selector '{}' failed: {}", selector, e))?;
+    if subtrees.is_empty() {
+        return Err(format!("No element matches '{}'", selector));
+    }
+    Ok(subtrees.join("\n"))
+}
+
+fn run_browser(run_id: Option<&str>, shared: &BrowserManager) -> BrowserManager {
+    run_id
+        .and_then(crate::browser::for_run)
+        .unwrap_or_else(|| shared.clone())
+}
+
+/// Shut down the Chromium process; agent tools fail until it is restarted
+#[tauri::command]
+pub async fn stop_browser(state: State<'_, BrowserManager>) -> Result<(), String> {
+    crate::trace_info!("nexus::commands", "stop_browser called");
+    ensure_browser_idle()?;
+    state.stop().await.map_err(|e| e.to_string())
+}
+
+/// Relaunch Chromium, applying the saved browser launch settings
+#[tauri::command]
+pub async fn restart_browser(
+    state: State<'_, BrowserManager>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<(), String> {
+    crate::trace_info!("nexus::commands", "restart_browser called");
+    ensure_browser_idle()?;
+    let config = config_manager.lock().unwrap().load();
+    state.restart(&config).await.map_err(|e| e.to_string())
+}
+
+fn ensure_browser_idle() -> Result<(), String> {
+    match crate::run::active_ids().first() {
+        Some(run_id) => Err(format!("Run {} is still using the browser", run_id)),
+        None => Ok(()),
+    }
+}
+
 #[tauri::command]
 pub fn get_config(config_manager: State<'_, Mutex<ConfigManager>>) -> Result<Config, String> {
     Ok(config_manager.lock().unwrap().load())
@@ -125,7 +590,77 @@ pub fn save_config(
         provider = config.provider,
         model = config.model
     );
-    config_manager.lock().unwrap().save(&config)
+    crate::providers::validate_generation_params(&config)?;
+    let manager = config_manager.lock().unwrap();
+    manager.save(&config)?;
+    // Keys and the API token live in the keychain, so changing only those
+    // doesn't touch config.json and the file watcher won't reload
+    crate::config_watch::apply(&manager.load());
+    Ok(())
+}
+
+/// Check `config`, or the saved config when omitted, for settings that would
+/// break a run. The saved config is also checked for fields this version
+/// doesn't recognize.
+#[tauri::command]
+pub fn validate_config(
+    config: Option<Config>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<Vec<ConfigIssue>, String> {
+    if let Some(config) = config {
+        return Ok(crate::config::validate_config(&config));
+    }
+    let manager = config_manager.lock().unwrap();
+    let mut issues = crate::config::validate_config(&manager.load());
+    issues.extend(manager.unknown_fields().into_iter().map(|field| ConfigIssue {
+        message: format!(
+            "Unknown setting '{}' is ignored. It may be misspelled or from another version of Nexus",
+            field
+        ),
+        field,
+    }));
+    Ok(issues)
+}
+
+#[tauri::command]
+pub fn list_profiles(
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<Vec<ProfileSummary>, String> {
+    Ok(config_manager.lock().unwrap().list_profiles())
+}
+
+/// Save `config`, or the current config when omitted, as the profile `name`
+#[tauri::command]
+pub fn save_profile(
+    name: String,
+    config: Option<Config>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<(), String> {
+    crate::trace_info!("nexus::commands", "save_profile called", name = name);
+    let manager = config_manager.lock().unwrap();
+    let config = config.unwrap_or_else(|| manager.load());
+    manager.save_profile(&name, &config)
+}
+
+/// Switch the active config to the profile `name`
+#[tauri::command]
+pub fn activate_profile(
+    name: String,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<Config, String> {
+    crate::trace_info!("nexus::commands", "activate_profile called", name = name);
+    let config = config_manager.lock().unwrap().activate_profile(&name)?;
+    crate::config_watch::apply(&config);
+    Ok(config)
+}
+
+#[tauri::command]
+pub fn delete_profile(
+    name: String,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<bool, String> {
+    crate::trace_info!("nexus::commands", "delete_profile called", name = name);
+    config_manager.lock().unwrap().delete_profile(&name)
 }
 
 #[tauri::command]
@@ -134,10 +669,9 @@ pub async fn reset_session(
 ) -> Result<(), String> {
     crate::trace_info!("nexus::commands", "reset_session called");
 
+    // Long-term memories outlive sessions
     if let Some(mem_lock) = GLOBAL_MEMORY.get() {
-        if let Ok(mut mem) = mem_lock.lock() {
-            mem.clear();
-        }
+        mem_lock.write().await.clear_session();
     }
 
     browser.reset().await.map_err(|e| e.to_string())?;
@@ -157,16 +691,12 @@ pub async fn reset_session(
 // Trace API Commands
 // ============================================================================
 
+/// The live session's buffered events selected by `filter`
 #[tauri::command]
-pub fn get_traces(level: Option<String>) -> Result<Vec<TraceEvent>, String> {
+pub fn get_traces(filter: Option<TraceFilter>) -> Result<TracePage, String> {
     if let Some(store) = TRACE_STORE.get() {
         if let Ok(guard) = store.try_lock() {
-            let events = if let Some(lvl) = level {
-                guard.get_events_by_level(&lvl)
-            } else {
-                guard.get_events()
-            };
-            return Ok(events);
+            return Ok(guard.query(&filter.unwrap_or_default()));
         }
     }
     Err("Failed to access trace store".to_string())
@@ -192,3 +722,725 @@ pub fn get_trace_count() -> Result<usize, String> {
     }
     Err("Failed to access trace store".to_string())
 }
+
+/// Live trace buffer size and evicted/dropped event counters
+#[tauri::command]
+pub async fn get_trace_stats() -> Result<TraceStats, String> {
+    match TRACE_STORE.get() {
+        Some(store) => Ok(store.lock().await.stats()),
+        None => Err("Failed to access trace store".to_string()),
+    }
+}
+
+/// Stream live trace events at `level` and above to the UI in
+/// `trace-events` batches; `None` stops the stream
+#[tauri::command]
+pub fn set_trace_stream_level(level: Option<String>) -> Result<(), String> {
+    crate::tracing::set_stream_level(level.as_deref())
+}
+
+/// Sessions recorded in the trace database, most recent first
+#[tauri::command]
+pub async fn list_trace_sessions() -> Result<Vec<TraceSession>, String> {
+    // Include the live session's latest events
+    crate::tracing::flush().await;
+    crate::db::with_db(crate::tracing::list_sessions)
+}
+
+/// Search the messages and fields of recorded trace events, in every session
+/// or only `session_id`'s, best match first
+#[tauri::command]
+pub async fn search_traces(
+    query: String,
+    session_id: Option<String>,
+    options: Option<SearchOptions>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<Vec<SearchHit<TraceEvent>>, String> {
+    let config = config_manager.lock().unwrap().load();
+    let mut options = options.unwrap_or_default();
+    options.context_lines = options.context_lines.or(Some(config.search_context_lines));
+    options.max_results = options.max_results.or(Some(config.search_max_results));
+
+    // The live session's events are searched from SQLite too, including any
+    // already evicted from the live buffer
+    crate::tracing::flush().await;
+    let events =
+        crate::db::with_db(|conn| crate::tracing::load_events(conn, session_id.as_deref()))?;
+    let hits = crate::search::search_traces(events, &query, options).map_err(|e| e.to_string())?;
+    // Traced only after searching, so the query doesn't match its own event
+    crate::trace_info!(
+        "nexus::commands",
+        "search_traces complete",
+        hits = hits.len()
+    );
+    Ok(hits)
+}
+
+/// Write a recorded session's events to `path` as JSON Lines or CSV
+#[tauri::command]
+pub async fn export_traces(
+    session_id: String,
+    format: TraceExportFormat,
+    path: String,
+) -> Result<String, String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "export_traces called",
+        session_id = session_id,
+        path = path
+    );
+    crate::tracing::flush().await;
+    let events = crate::db::with_db(|conn| {
+        crate::tracing::load_session(conn, &session_id, &TraceFilter::default())
+    })?
+    .events;
+    if events.is_empty() {
+        return Err(format!("Session {} has no recorded traces", session_id));
+    }
+    let path_ref = std::path::Path::new(&path);
+    if let Some(parent) = path_ref.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    std::fs::write(path_ref, crate::tracing::render_events(&events, format))
+        .map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Package a trace session (the current one by default) into a zip with the
+/// reports and screenshots of its runs, the memory store and the config
+#[tauri::command]
+pub async fn export_session(
+    path: String,
+    session_id: Option<String>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<String, String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "export_session called",
+        session_id = session_id,
+        path = path
+    );
+    crate::tracing::flush().await;
+    let session_id = match session_id {
+        Some(session_id) => session_id,
+        None => match TRACE_STORE.get() {
+            Some(store) => store.lock().await.session_id().to_string(),
+            None => return Err("Trace store not initialized".to_string()),
+        },
+    };
+    let (events, records) = crate::db::with_db(|conn| {
+        let events = crate::tracing::load_events(conn, Some(&session_id))?;
+        let run_ids = crate::tracing::list_sessions(conn)?
+            .into_iter()
+            .find(|session| session.session_id == session_id)
+            .map(|session| session.run_ids)
+            .unwrap_or_default();
+        let mut records = Vec::new();
+        for run_id in &run_ids {
+            records.extend(crate::run::get_record(conn, run_id)?);
+        }
+        Ok((events, records))
+    })?;
+    if events.is_empty() {
+        return Err(format!("Session {} has no recorded traces", session_id));
+    }
+    let memories = match GLOBAL_MEMORY.get() {
+        Some(memory) => memory.read().await.get_all(),
+        None => Vec::new(),
+    };
+    let bundle = SessionBundle {
+        session_id,
+        config: config_manager.lock().unwrap().load(),
+        events,
+        memories,
+        runs: records
+            .into_iter()
+            .map(|record| BundledRun {
+                screenshots: crate::export::screenshots(&record.run_id),
+                record,
+            })
+            .collect(),
+    };
+
+    let path_ref = std::path::Path::new(&path);
+    if let Some(parent) = path_ref.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    bundle.write(path_ref)?;
+    Ok(path)
+}
+
+/// A recorded session's events selected by `filter`
+#[tauri::command]
+pub async fn get_session_traces(
+    session_id: String,
+    filter: Option<TraceFilter>,
+) -> Result<TracePage, String> {
+    crate::tracing::flush().await;
+    let filter = filter.unwrap_or_default();
+    crate::db::with_db(|conn| crate::tracing::load_session(conn, &session_id, &filter))
+}
+
+/// Tool-call counts, latency percentiles and error rates of a run, from its
+/// recorded traces
+#[tauri::command]
+pub async fn get_run_metrics(run_id: String) -> Result<RunMetrics, String> {
+    crate::tracing::flush().await;
+    let events = crate::db::with_db(|conn| crate::tracing::load_run_events(conn, &run_id))?;
+    if events.is_empty() {
+        return Err(format!("No traces recorded for run {}", run_id));
+    }
+    Ok(crate::metrics::compute(&run_id, &events))
+}
+
+// ============================================================================
+// Replay Commands
+// ============================================================================
+
+/// Open a step-through replay of a run's recorded browser actions
+#[tauri::command]
+pub async fn start_replay(run_id: String) -> Result<ReplayState, String> {
+    crate::trace_info!("nexus::commands", "start_replay called", run_id = run_id);
+    crate::tracing::flush().await;
+    let actions = crate::db::with_db(|conn| crate::replay::load_actions(conn, &run_id))?;
+    crate::replay::start(&run_id, actions)
+}
+
+#[tauri::command]
+pub fn get_replay_state(replay_id: String) -> Result<ReplayState, String> {
+    crate::replay::state(&replay_id)
+}
+
+/// Replay the next recorded action; `None` once the replay is finished
+#[tauri::command]
+pub async fn replay_step(
+    replay_id: String,
+    browser: State<'_, BrowserManager>,
+) -> Result<Option<ReplayStep>, String> {
+    crate::replay::step(&replay_id, &browser).await
+}
+
+/// Replay the remaining actions, stopping at the first failure
+#[tauri::command]
+pub async fn replay_continue(
+    replay_id: String,
+    browser: State<'_, BrowserManager>,
+) -> Result<Vec<ReplayStep>, String> {
+    crate::replay::run_to_end(&replay_id, &browser).await
+}
+
+#[tauri::command]
+pub fn stop_replay(replay_id: String) -> Result<(), String> {
+    crate::replay::stop(&replay_id)
+}
+
+// ============================================================================
+// Dataset Commands
+// ============================================================================
+
+#[tauri::command]
+pub async fn run_dataset(
+    recipe: String,
+    url: String,
+    key_fields: Option<Vec<String>>,
+    browser: State<'_, BrowserManager>,
+) -> Result<DatasetRun, String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "run_dataset called",
+        recipe = recipe,
+        url = url
+    );
+    crate::datasets::run_dataset(&browser, &recipe, &url, &key_fields.unwrap_or_default()).await
+}
+
+#[tauri::command]
+pub fn get_dataset_history(
+    dataset: String,
+    limit: Option<usize>,
+) -> Result<Vec<DatasetRun>, String> {
+    crate::db::with_db(|conn| crate::datasets::history(conn, &dataset, limit.unwrap_or(20)))
+}
+
+// ============================================================================
+// Run Commands
+// ============================================================================
+
+/// Whether the agent is working, with the progress of each live run
+#[tauri::command]
+pub fn get_agent_status() -> AgentStatus {
+    crate::run::agent_status()
+}
+
+#[tauri::command]
+pub fn get_run_cost(run_id: String) -> Result<RunUsage, String> {
+    if let Some(run) = crate::run::active(&run_id) {
+        return Ok(run.usage());
+    }
+    crate::db::with_db(|conn| crate::run::get_record(conn, &run_id))?
+        .map(|record| record.usage)
+        .ok_or_else(|| format!("Run {} not found", run_id))
+}
+
+#[tauri::command]
+pub fn get_run_versions(run_id: String) -> Result<Vec<RunRecord>, String> {
+    crate::db::with_db(|conn| crate::run::series_history(conn, &run_id))
+}
+
+#[tauri::command]
+pub fn diff_reports(run_a: String, run_b: String) -> Result<ReportDiff, String> {
+    let (a, b) = crate::db::with_db(|conn| {
+        Ok((
+            crate::run::get_record(conn, &run_a)?,
+            crate::run::get_record(conn, &run_b)?,
+        ))
+    })?;
+    let report_of = |record: Option<RunRecord>, id: &str| {
+        record
+            .ok_or_else(|| format!("Run {} not found", id))?
+            .report
+            .ok_or_else(|| format!("Run {} has no report", id))
+    };
+    let report_a = report_of(a, &run_a)?;
+    let report_b = report_of(b, &run_b)?;
+    Ok(crate::reports::diff_reports(
+        &run_a, &report_a, &run_b, &report_b,
+    ))
+}
+
+/// Write a run's report, discoveries, sources and screenshots to `path`
+#[tauri::command]
+pub async fn export_report(
+    run_id: String,
+    format: ExportFormat,
+    path: String,
+    browser: State<'_, BrowserManager>,
+) -> Result<String, String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "export_report called",
+        run_id = run_id,
+        path = path
+    );
+    let record = crate::db::with_db(|conn| crate::run::get_record(conn, &run_id))?
+        .ok_or_else(|| format!("Run {} not found", run_id))?;
+    crate::export::export(&record, format, std::path::Path::new(&path), &browser).await?;
+    Ok(path)
+}
+
+/// Hold a run before its next tool call, so the user can take over the
+/// browser with the manual commands
+#[tauri::command]
+pub fn pause_run(run_id: String) -> Result<(), String> {
+    crate::trace_info!("nexus::commands", "pause_run called", run_id = run_id);
+    let run = crate::run::active(&run_id).ok_or_else(|| format!("Run {} is not active", run_id))?;
+    run.pause();
+    crate::agent::emit_event(
+        "system",
+        "Run paused, waiting for you to hand back control".to_string(),
+    );
+    Ok(())
+}
+
+#[tauri::command]
+pub fn unpause_run(run_id: String) -> Result<(), String> {
+    crate::trace_info!("nexus::commands", "unpause_run called", run_id = run_id);
+    let run = crate::run::active(&run_id).ok_or_else(|| format!("Run {} is not active", run_id))?;
+    run.unpause();
+    crate::agent::emit_event("system", "Run resumed".to_string());
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_frontier(run_id: String) -> Result<FrontierSnapshot, String> {
+    let run = crate::run::active(&run_id).ok_or_else(|| format!("Run {} is not active", run_id))?;
+    let frontier = run
+        .frontier
+        .lock()
+        .map_err(|_| "Frontier lock poisoned".to_string())?;
+    Ok(frontier.snapshot())
+}
+
+// ============================================================================
+// Approval Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn approve_action(request_id: String, approved: bool) -> Result<(), String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "approve_action called",
+        request_id = request_id,
+        approved = approved
+    );
+    match crate::approval::GLOBAL_APPROVALS.get() {
+        Some(manager) => manager.resolve(&request_id, approved),
+        None => Err("Approval manager not initialized".to_string()),
+    }
+}
+
+/// Approve (optionally with edited steps) or reject the plan proposed for a
+/// run in plan-then-execute mode
+#[tauri::command]
+pub fn approve_plan(
+    run_id: String,
+    approved: bool,
+    steps: Option<Vec<String>>,
+) -> Result<(), String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "approve_plan called",
+        run_id = run_id,
+        approved = approved,
+        edited = steps.is_some()
+    );
+    crate::planning::resolve(&run_id, crate::planning::PlanDecision { approved, steps })
+}
+
+// ============================================================================
+// Human Action Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn list_pending_actions() -> Result<Vec<HumanAction>, String> {
+    crate::db::with_db(crate::human_actions::list_pending)
+}
+
+/// Mark a queued task done, or dismiss it
+#[tauri::command]
+pub fn resolve_action(
+    id: String,
+    dismissed: Option<bool>,
+    resolution: Option<String>,
+) -> Result<HumanAction, String> {
+    crate::trace_info!("nexus::commands", "resolve_action called", id = id);
+    crate::db::with_db(|conn| {
+        crate::human_actions::resolve(
+            conn,
+            &id,
+            dismissed.unwrap_or(false),
+            resolution.as_deref(),
+        )
+    })?
+    .ok_or_else(|| format!("Action {} not found", id))
+}
+
+// ============================================================================
+// Artifact Workspace Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn get_artifact_usage() -> Result<ArtifactUsage, String> {
+    let root = crate::workspace::WORKSPACE_ROOT
+        .get()
+        .ok_or_else(|| "Workspaces not initialized".to_string())?;
+    Ok(crate::workspace::usage(root))
+}
+
+/// Delete one run's artifacts, or every finished run's when no run_id is given
+#[tauri::command]
+pub fn purge_artifacts(run_id: Option<String>) -> Result<PurgeReport, String> {
+    crate::trace_info!("nexus::commands", "purge_artifacts called", run_id = run_id);
+    let root = crate::workspace::WORKSPACE_ROOT
+        .get()
+        .ok_or_else(|| "Workspaces not initialized".to_string())?;
+    match run_id {
+        Some(run_id) => {
+            if crate::run::active(&run_id).is_some() {
+                return Err(format!("Run {} is still in progress", run_id));
+            }
+            crate::workspace::purge_run(root, &run_id)
+        }
+        None => {
+            let active = crate::run::active_ids();
+            Ok(crate::workspace::apply_retention(root, Some(0), None, &active))
+        }
+    }
+}
+
+// ============================================================================
+// Extraction Recipe Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn save_recipe(recipe: Recipe) -> Result<(), String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "save_recipe called",
+        name = recipe.name,
+        domain = recipe.domain
+    );
+    crate::db::with_db(|conn| crate::recipes::save(conn, &recipe))
+}
+
+#[tauri::command]
+pub fn list_recipes() -> Result<Vec<Recipe>, String> {
+    crate::db::with_db(crate::recipes::list)
+}
+
+#[tauri::command]
+pub async fn learn_recipe(
+    name: String,
+    url: String,
+    example_record: serde_json::Map<String, serde_json::Value>,
+    validation_url: Option<String>,
+    browser: State<'_, BrowserManager>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<LearnResult, String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "learn_recipe called",
+        name = name,
+        url = url
+    );
+    let config = config_manager.lock().unwrap().load();
+    crate::recipes::learn(
+        &browser,
+        &config,
+        &name,
+        &url,
+        example_record,
+        validation_url.as_deref(),
+    )
+    .await
+}
+
+#[tauri::command]
+pub fn delete_recipe(name: String) -> Result<bool, String> {
+    crate::trace_info!("nexus::commands", "delete_recipe called", name = name);
+    crate::db::with_db(|conn| crate::recipes::delete(conn, &name))
+}
+
+/// Extract JSON matching `schema` from the page open in the browser
+#[tauri::command]
+pub async fn extract_structured(
+    schema: serde_json::Value,
+    instructions: Option<String>,
+    browser: State<'_, BrowserManager>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<serde_json::Value, String> {
+    crate::trace_info!("nexus::commands", "extract_structured called");
+    let config = config_manager.lock().unwrap().load();
+    crate::agent::extract_structured_from(&browser, &config, &schema, instructions.as_deref()).await
+}
+
+// ============================================================================
+// Prompt Pack
+// ============================================================================
+
+/// The prompt pack, listing every built-in and plugin tool (with an empty
+/// description when not overridden)
+#[tauri::command]
+pub fn get_prompt_pack() -> PromptPack {
+    let mut pack = crate::prompts::load();
+    let plugin_tools: Vec<String> = crate::plugins::summaries()
+        .into_iter()
+        .map(|tool| tool.name)
+        .collect();
+    for name in crate::prompts::BUILTIN_TOOLS
+        .iter()
+        .map(|name| name.to_string())
+        .chain(plugin_tools)
+    {
+        pack.tool_descriptions.entry(name).or_default();
+    }
+    pack
+}
+
+/// Built-in and plugin tools with their argument schemas, and whether the
+/// config enables them
+#[tauri::command]
+pub fn list_tools(config_manager: State<'_, Mutex<ConfigManager>>) -> Vec<ToolInfo> {
+    let config = config_manager.lock().unwrap().load();
+    crate::agent::list_tools(&config)
+}
+
+#[tauri::command]
+pub fn save_prompt_pack(mut pack: PromptPack) -> Result<(), String> {
+    crate::trace_info!("nexus::commands", "save_prompt_pack called");
+    pack.tool_descriptions
+        .retain(|_, description| !description.trim().is_empty());
+    if pack
+        .system_instructions
+        .as_ref()
+        .is_some_and(|s| s.trim().is_empty())
+    {
+        pack.system_instructions = None;
+    }
+    crate::prompts::save(&pack)
+}
+
+// ============================================================================
+// Template Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn list_templates() -> Result<Vec<PromptTemplate>, String> {
+    crate::db::with_db(crate::templates::list)
+}
+
+#[tauri::command]
+pub fn save_template(template: PromptTemplate) -> Result<(), String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "save_template called",
+        name = template.name
+    );
+    if template.name.trim().is_empty() || template.prompt.trim().is_empty() {
+        return Err("Templates need a name and a prompt".to_string());
+    }
+    crate::db::with_db(|conn| crate::templates::save(conn, &template))
+}
+
+#[tauri::command]
+pub fn delete_template(name: String) -> Result<bool, String> {
+    crate::trace_info!("nexus::commands", "delete_template called", name = name);
+    crate::db::with_db(|conn| crate::templates::delete(conn, &name))
+}
+
+/// Fill in a template's variables and queue the resulting prompt with the
+/// template's success criteria and output shape, returning the run_id
+#[tauri::command]
+pub fn run_template(
+    name: String,
+    variables: HashMap<String, String>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<String, String> {
+    crate::trace_info!("nexus::commands", "run_template called", name = name);
+    let template = crate::db::with_db(|conn| crate::templates::get(conn, &name))?
+        .ok_or_else(|| format!("Template '{}' not found", name))?;
+    let prompt = template.render(&variables)?;
+
+    let mut config = config_manager.lock().unwrap().load();
+    if template.success_criteria.is_some() {
+        config.success_criteria = template.success_criteria;
+    }
+    if template.output_preset.is_some() {
+        config.output_preset = template.output_preset;
+    }
+    Ok(crate::agent::spawn_agent_run(prompt, config))
+}
+
+// ============================================================================
+// Saved Task Commands
+// ============================================================================
+
+#[tauri::command]
+pub fn list_saved_tasks() -> Result<Vec<SavedTask>, String> {
+    crate::db::with_db(crate::tasks::list)
+}
+
+/// Save a task for re-running; pass `id` to update an existing task
+#[tauri::command]
+pub fn save_task(
+    id: Option<String>,
+    name: String,
+    prompt: String,
+    config_overrides: Option<serde_json::Map<String, serde_json::Value>>,
+    schedule: Option<String>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<SavedTask, String> {
+    crate::trace_info!("nexus::commands", "save_task called", name = name);
+    if name.trim().is_empty() || prompt.trim().is_empty() {
+        return Err("Tasks need a name and a prompt".to_string());
+    }
+    let existing = match &id {
+        Some(id) => Some(
+            crate::db::with_db(|conn| crate::tasks::get(conn, id))?
+                .ok_or_else(|| format!("Task {} not found", id))?,
+        ),
+        None => None,
+    };
+    let task = SavedTask {
+        id: id.unwrap_or_else(|| uuid::Uuid::new_v4().to_string()),
+        name,
+        prompt,
+        config_overrides: config_overrides.unwrap_or_default(),
+        created_at: existing
+            .as_ref()
+            .map(|t| t.created_at)
+            .unwrap_or_else(|| chrono::Utc::now().timestamp_millis()),
+        last_run_id: existing.and_then(|t| t.last_run_id),
+        schedule: schedule.filter(|s| !s.trim().is_empty()),
+    };
+    // Reject overrides and schedules that would break the task's runs up front
+    task.apply_overrides(&config_manager.lock().unwrap().load())?;
+    if let Some(schedule) = &task.schedule {
+        crate::scheduler::parse_schedule(schedule)?;
+    }
+    crate::db::with_db(|conn| crate::tasks::save(conn, &task))?;
+    Ok(task)
+}
+
+#[tauri::command]
+pub fn delete_saved_task(id: String) -> Result<bool, String> {
+    crate::trace_info!("nexus::commands", "delete_saved_task called", id = id);
+    crate::db::with_db(|conn| crate::tasks::delete(conn, &id))
+}
+
+/// Queue a saved task with its config overrides, returning the run_id. The
+/// run joins the task's report series, so `get_run_versions` on the task's
+/// `last_run_id` lists every run of the task for comparison.
+#[tauri::command]
+pub fn run_saved_task(
+    id: String,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<String, String> {
+    crate::trace_info!("nexus::commands", "run_saved_task called", id = id);
+    let task = crate::db::with_db(|conn| crate::tasks::get(conn, &id))?
+        .ok_or_else(|| format!("Task {} not found", id))?;
+    let config = config_manager.lock().unwrap().load();
+    crate::tasks::spawn(&task, &config)
+}
+
+/// When a cron expression next fires, in milliseconds since the epoch
+#[tauri::command]
+pub fn preview_schedule(schedule: String) -> Result<Option<i64>, String> {
+    crate::scheduler::next_run(&schedule)
+}
+
+// ============================================================================
+// Queue Commands
+// ============================================================================
+
+/// Queued, running and recently finished agent runs
+#[tauri::command]
+pub fn get_queue() -> Vec<Job> {
+    crate::queue::queue().snapshot()
+}
+
+// ============================================================================
+// Model Commands
+// ============================================================================
+
+/// Models offered by `provider`, using the stored key and endpoint unless
+/// others are given (e.g. while editing settings before saving)
+#[tauri::command]
+pub async fn list_models(
+    provider: String,
+    api_key: Option<String>,
+    base_url: Option<String>,
+    config_manager: State<'_, Mutex<ConfigManager>>,
+) -> Result<Vec<ModelInfo>, String> {
+    crate::trace_info!("nexus::commands", "list_models called", provider = provider);
+    let mut config = config_manager.lock().unwrap().load();
+    config.provider = provider;
+    if let Some(api_key) = api_key {
+        config.api_key = api_key;
+    }
+    if base_url.is_some() {
+        config.base_url = base_url;
+    }
+    crate::models::list_models(&config).await
+}
+
+/// Check a (possibly unsaved) config's provider, key and model
+#[tauri::command]
+pub async fn test_provider_connection(config: Config) -> Result<ConnectionTest, String> {
+    crate::trace_info!(
+        "nexus::commands",
+        "test_provider_connection called",
+        provider = config.provider
+    );
+    Ok(crate::models::test_connection(&config).await)
+}
@@ -0,0 +1,149 @@
+//! Map-reduce condensing of pages longer than the content limit
+//!
+//! Instead of cutting a long page off at the limit, the Markdown is split into
+//! chunks on paragraph boundaries, each chunk is summarized by the (optionally
+//! cheaper) summary model, a few chunks at a time, and the summaries are
+//! joined. If the joined summaries still exceed the limit they are condensed
+//! once more, so the agent gets a faithful view of the whole page rather than
+//! its first part.
+
+use crate::config::Config;
+use futures::stream::{self, StreamExt, TryStreamExt};
+use radkit::agent::LlmFunction;
+use radkit::macros::LLMOutput;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// Characters of Markdown sent to the summary model per chunk
+pub const CHUNK_CHARS: usize = 12_000;
+
+/// Chunks summarized at once, so a very long page doesn't fire every request
+/// at the provider together
+const CONCURRENT_CHUNKS: usize = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct ChunkSummary {
+    /// Condensed section keeping every name, number, price, date, URL and heading.
+    pub summary: String,
+}
+
+/// Split `text` into chunks of at most `chunk_chars` characters, breaking at
+/// paragraph boundaries where possible
+pub fn split_chunks(text: &str, chunk_chars: usize) -> Vec<String> {
+    let chunk_chars = chunk_chars.max(1);
+    let mut chunks = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+
+    for paragraph in text.split("\n\n") {
+        let len = paragraph.chars().count();
+        if current_len > 0 && current_len + 2 + len > chunk_chars {
+            chunks.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if len > chunk_chars {
+            // A single paragraph longer than a chunk is split mid-text
+            let chars: Vec<char> = paragraph.chars().collect();
+            for piece in chars.chunks(chunk_chars) {
+                chunks.push(piece.iter().collect());
+            }
+            continue;
+        }
+        if current_len > 0 {
+            current.push_str("\n\n");
+            current_len += 2;
+        }
+        current.push_str(paragraph);
+        current_len += len;
+    }
+    if !current.trim().is_empty() {
+        chunks.push(current);
+    }
+    chunks
+}
+
+/// The config used for summarization calls: `summary_model` if set,
+/// otherwise the run's own model
+pub fn summary_config(config: &Config) -> Config {
+    let mut summary = config.clone();
+    if let Some(model) = config.summary_model.as_ref().filter(|m| !m.is_empty()) {
+        summary.model = model.clone();
+    }
+    summary
+}
+
+async fn summarize_chunk(
+    config: &Config,
+    chunk: &str,
+    part: usize,
+    parts: usize,
+    max_words: usize,
+) -> Result<String, String> {
+    let llm = crate::providers::build_llm(config)?;
+    let function = LlmFunction::<ChunkSummary>::new_with_system_instructions(
+        llm,
+        "You condense sections of a web page for a research agent. Keep every concrete fact \
+         (names, numbers, prices, dates, URLs, headings, link texts) and drop navigation, ads \
+         and filler. Never add information that isn't in the text.",
+    );
+    let result = function
+        .run(format!(
+            "Condense part {} of {} of the page in under {} words:\n\n{}",
+            part, parts, max_words, chunk
+        ))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(result.summary)
+}
+
+/// Condense `markdown` to roughly `limit` characters with a map-reduce pass
+/// over its chunks
+pub async fn condense_page(
+    config: &Config,
+    markdown: &str,
+    limit: usize,
+) -> Result<String, String> {
+    let config = summary_config(config);
+    let chunks = split_chunks(markdown, CHUNK_CHARS);
+    let parts = chunks.len();
+    // Roughly six characters per word, shared between the chunks
+    let max_words = (limit / parts.max(1) / 6).max(50);
+
+    crate::trace_info!(
+        "nexus::condense",
+        "Condensing long page",
+        chars = markdown.chars().count(),
+        chunks = parts,
+        model = config.model
+    );
+
+    let summaries = stream::iter(chunks.iter().enumerate())
+        .map(|(i, chunk)| summarize_chunk(&config, chunk, i + 1, parts, max_words))
+        .buffered(CONCURRENT_CHUNKS)
+        .try_collect::<Vec<_>>()
+        .await?;
+
+    let joined = summaries.join("\n\n");
+    if joined.chars().count() <= limit || parts == 1 {
+        return Ok(joined);
+    }
+    // Reduce step: the section summaries are still too long together
+    summarize_chunk(&config, &joined, 1, 1, (limit / 6).max(50)).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_chunks() {
+        let text = "aaaa\n\nbbbb\n\ncccc";
+        assert_eq!(split_chunks(text, 10), vec!["aaaa\n\nbbbb", "cccc"]);
+        assert_eq!(split_chunks(text, 100), vec![text]);
+
+        let long = "x".repeat(25);
+        let chunks = split_chunks(&format!("intro\n\n{}", long), 10);
+        assert_eq!(chunks, vec!["intro", "xxxxxxxxxx", "xxxxxxxxxx", "xxxxx"]);
+        assert!(split_chunks("", 10).is_empty());
+    }
+}
@@ -1,53 +1,1157 @@
+use crate::criteria::SuccessCriteria;
+use crate::mcp_client::McpServerConfig;
+use crate::memory::MemoryEviction;
+use crate::ratelimit::RateLimit;
+use crate::shapes::OutputPreset;
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::path::PathBuf;
-use tauri::AppHandle;
-use tauri::Manager;
+use std::path::{Path, PathBuf};
+
+/// Persona and research style used when no custom system prompt is set
+pub const DEFAULT_SYSTEM_PROMPT: &str = "You are Nexus, a premium, autonomous browser agent. Your mission is to provide high-quality, structured reports.";
+
+/// Ordered upgrades for config files written by older versions; entry `n`
+/// brings a version `n` file to version `n + 1`. Renamed or restructured
+/// fields get a migration here so old values carry over rather than falling
+/// back to their defaults.
+const CONFIG_MIGRATIONS: &[fn(&mut serde_json::Map<String, serde_json::Value>)] = &[
+    // 1: settings remembered per provider
+    remember_legacy_provider,
+];
+
+/// Schema version of configs written by this build
+pub const CONFIG_VERSION: u32 = CONFIG_MIGRATIONS.len() as u32;
+
+/// Credentials and model remembered for one provider
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct ProviderSettings {
+    pub api_key: String,
+    pub model: String,
+    pub base_url: Option<String>,
+}
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default)]
 pub struct Config {
+    /// Schema version the config was written with; see [`CONFIG_MIGRATIONS`]
+    pub version: u32,
+    /// The active provider; `api_key`, `model` and `base_url` are its settings
     pub provider: String,
     pub api_key: String,
     pub model: String,
     pub base_url: Option<String>,
+    /// Settings of every provider used so far, so switching back to one
+    /// restores its key and model
+    pub providers: BTreeMap<String, ProviderSettings>,
+    /// Host of the Ollama server used by the `ollama` provider
+    pub ollama_host: String,
+    /// Port of the Ollama server used by the `ollama` provider
+    pub ollama_port: u16,
+    /// Sampling temperature; the provider's default when unset. Low values
+    /// keep extraction runs deterministic. Anthropic accepts 0-1, the other
+    /// providers 0-2. (`top_p` and reasoning effort are not offered, as the
+    /// radkit clients have no way to set them.)
+    pub temperature: Option<f32>,
+    /// Cap on the tokens generated per model response
+    pub max_output_tokens: Option<u32>,
+    /// Domains whose navigation requires user approval (subdomains included)
+    pub sensitive_domains: Vec<String>,
+    /// Seconds to wait for the user to answer an approval request
+    pub approval_timeout_secs: u64,
+    /// Seconds a paused run waits for the user to hand back control before
+    /// it is told to wrap up
+    pub pause_timeout_secs: u64,
+    /// Maximum number of tool calls before the agent must wrap up
+    pub max_steps: usize,
+    /// Total token budget for a run; the run wraps up once exceeded
+    pub max_tokens: Option<u64>,
+    /// Estimated USD budget for a run; the run wraps up once exceeded
+    pub max_cost_usd: Option<f64>,
+    /// Maximum pages fetched per domain within one run
+    pub max_pages_per_domain: Option<usize>,
+    /// Languages (e.g. "en", "de") pages and search results must be in;
+    /// empty allows all
+    pub allowed_languages: Vec<String>,
+    /// Minimum seconds between run checkpoints
+    pub checkpoint_interval_secs: u64,
+    /// Trace events kept in memory for the live flight recorder view; older
+    /// events are only available from the trace history
+    pub trace_buffer_size: usize,
+    /// Milliseconds between batches of live trace events sent to the UI
+    pub trace_stream_interval_ms: u64,
+    /// OTLP/HTTP collector (e.g. http://localhost:4318) trace events are
+    /// also exported to; unset disables export
+    pub otlp_endpoint: Option<String>,
+    /// Headers sent with every OTLP export, e.g. a hosted collector's API key
+    pub otlp_headers: HashMap<String, String>,
+    /// Also append trace events to rotating NDJSON files in the app data
+    /// directory as they are recorded
+    pub trace_log_files: bool,
+    /// Size at which a session's trace log file is rotated
+    pub trace_log_max_bytes: u64,
+    /// Trace log files kept per session, including the live one
+    pub trace_log_max_files: usize,
+    /// Sessions whose trace logs are kept, including the current one; older
+    /// sessions' files are deleted
+    pub trace_log_max_sessions: usize,
+    /// Regular expressions whose matches are masked in traces and agent
+    /// events, on top of the built-in API key and credential formats
+    pub redact_patterns: Vec<String>,
+    /// Path to a ggml whisper model used to transcribe voice prompts
+    pub whisper_model_path: Option<String>,
+    /// Draft a step plan for the user to approve before executing a run
+    pub plan_first: bool,
+    /// Read a one-minute spoken summary aloud when a run completes
+    pub speak_summaries: bool,
+    /// Languages (e.g. "de", "fr") to search in alongside the query's own,
+    /// using LLM-generated localized query variants; empty disables expansion
+    pub query_languages: Vec<String>,
+    /// Non-blank lines of context returned on each side of a page search match
+    pub search_context_lines: usize,
+    /// Most page search matches returned, best first
+    pub search_max_results: usize,
+    /// Split runs into sub-questions researched by parallel sub-agents
+    pub orchestrate: bool,
+    /// Maximum number of sub-agents an orchestrated run spawns
+    pub max_sub_agents: usize,
+    /// Built-in or plugin tools never offered to the model
+    pub disabled_tools: Vec<String>,
+    /// System instructions defining the agent's persona, tone and research style
+    pub system_prompt: String,
+    /// Requests/tokens per minute allowed per provider, shared by all runs
+    pub rate_limits: HashMap<String, RateLimit>,
+    /// Seconds a page may take to load
+    pub navigation_timeout_secs: u64,
+    /// Seconds a click, type, scroll, upload or script evaluation may take
+    pub action_timeout_secs: u64,
+    /// Seconds to wait for a selector to appear before failing
+    pub selector_timeout_secs: u64,
+    /// Run Chromium without a window; applied at launch or `restart_browser`
+    pub browser_headless: bool,
+    /// Proxy server Chromium connects through, e.g. socks5://127.0.0.1:9050;
+    /// applied at launch or `restart_browser`
+    pub browser_proxy: Option<String>,
+    /// Keep artifact workspaces of only the most recent N runs
+    pub artifact_retention_runs: Option<usize>,
+    /// Delete artifact workspaces older than this many days
+    pub artifact_retention_days: Option<u64>,
+    /// Retries for browser tools failing with transient errors
+    pub tool_retries: u32,
+    /// Delay before the first retry; doubles on each further attempt
+    pub retry_backoff_ms: u64,
+    /// Retry a failed run once, with a post-mortem of the failure and the
+    /// remaining budget
+    pub auto_retry_on_failure: bool,
+    /// Characters of page Markdown returned to the agent per tool call;
+    /// tools may request more for a single call
+    pub max_page_content_chars: usize,
+    /// Condense pages over the content limit with a chunked summarization
+    /// pass instead of truncating them
+    pub condense_long_pages: bool,
+    /// Cheaper model of the same provider used for summarization; the run's
+    /// model is used when unset
+    pub summary_model: Option<String>,
+    /// Criteria the report is validated against when a run completes
+    pub success_criteria: Option<SuccessCriteria>,
+    /// Shape of the run's report; the default prose report when unset
+    pub output_preset: Option<OutputPreset>,
+    /// JSON Schema the report's data must match; takes precedence over
+    /// `output_preset`
+    pub output_schema: Option<serde_json::Value>,
+    /// Save a screenshot of every page the agent visits to the run's
+    /// workspace, for embedding in exported reports
+    pub capture_screenshots: bool,
+    /// Agent runs allowed at once; further runs wait in the queue. Above 1,
+    /// each run browses in its own incognito context
+    pub max_concurrent_runs: usize,
+    /// Show OS notifications when a run finishes or fails, or an action needs
+    /// approval, while the window is in the background
+    pub desktop_notifications: bool,
+    /// Days a memory lasts before it is archived out of recall, unless the
+    /// note sets its own lifetime; unset keeps memories indefinitely
+    pub memory_ttl_days: Option<u64>,
+    /// Most memories kept; beyond it entries are evicted per
+    /// `memory_eviction`
+    pub memory_max_entries: Option<usize>,
+    /// Most bytes of note text kept in memory
+    pub memory_max_bytes: Option<usize>,
+    /// Which memories to evict first when over a cap
+    pub memory_eviction: MemoryEviction,
+    /// Serve the local HTTP API on 127.0.0.1 so other applications can start
+    /// runs; needs `api_server_token`
+    pub api_server: bool,
+    /// Port the local HTTP API listens on
+    pub api_server_port: u16,
+    /// Bearer token every local HTTP API request must present; stored in the
+    /// keychain like API keys
+    pub api_server_token: String,
+    /// Also serve the browser tools to MCP clients over SSE on the local
+    /// HTTP API, at `/mcp/sse`
+    pub mcp_server: bool,
+    /// MCP tool servers launched at the start of each run, whose tools are
+    /// offered to the agent alongside the built-in ones
+    pub mcp_servers: Vec<McpServerConfig>,
+}
+
+impl Config {
+    /// The configured system prompt, falling back to the default when blank
+    pub fn system_prompt(&self) -> &str {
+        if self.system_prompt.trim().is_empty() {
+            DEFAULT_SYSTEM_PROMPT
+        } else {
+            &self.system_prompt
+        }
+    }
+
+    pub fn tool_enabled(&self, name: &str) -> bool {
+        !self.disabled_tools.iter().any(|tool| tool == name)
+    }
+
+    /// Record the active provider's settings in `providers`
+    pub fn remember_provider(&mut self) {
+        if self.provider.trim().is_empty() {
+            return;
+        }
+        self.providers.insert(
+            self.provider.to_lowercase(),
+            ProviderSettings {
+                api_key: self.api_key.clone(),
+                model: self.model.clone(),
+                base_url: self.base_url.clone(),
+            },
+        );
+    }
+
+    /// Make `provider` active, restoring the settings last used with it
+    pub fn switch_provider(&mut self, provider: &str) {
+        self.remember_provider();
+        let settings = self
+            .providers
+            .get(&provider.to_lowercase())
+            .cloned()
+            .unwrap_or_default();
+        self.provider = provider.to_string();
+        self.api_key = settings.api_key;
+        self.model = settings.model;
+        self.base_url = settings.base_url;
+    }
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             provider: "anthropic".to_string(),
             api_key: "".to_string(),
             model: "claude-3-sonnet-20240229".to_string(),
             base_url: None,
+            providers: BTreeMap::new(),
+            ollama_host: "localhost".to_string(),
+            ollama_port: 11434,
+            temperature: None,
+            max_output_tokens: None,
+            sensitive_domains: Vec::new(),
+            approval_timeout_secs: 120,
+            pause_timeout_secs: 1800,
+            max_steps: 25,
+            max_tokens: None,
+            max_cost_usd: None,
+            max_pages_per_domain: None,
+            allowed_languages: Vec::new(),
+            checkpoint_interval_secs: 30,
+            trace_buffer_size: 5000,
+            trace_stream_interval_ms: 250,
+            otlp_endpoint: None,
+            otlp_headers: HashMap::new(),
+            trace_log_files: false,
+            trace_log_max_bytes: 10 * 1024 * 1024,
+            trace_log_max_files: 5,
+            trace_log_max_sessions: 10,
+            redact_patterns: Vec::new(),
+            whisper_model_path: None,
+            plan_first: false,
+            speak_summaries: false,
+            query_languages: Vec::new(),
+            search_context_lines: 1,
+            search_max_results: 20,
+            orchestrate: false,
+            max_sub_agents: 3,
+            disabled_tools: Vec::new(),
+            system_prompt: DEFAULT_SYSTEM_PROMPT.to_string(),
+            rate_limits: HashMap::new(),
+            navigation_timeout_secs: 30,
+            action_timeout_secs: 30,
+            selector_timeout_secs: 5,
+            browser_headless: true,
+            browser_proxy: None,
+            artifact_retention_runs: Some(50),
+            artifact_retention_days: Some(30),
+            tool_retries: 2,
+            retry_backoff_ms: 500,
+            auto_retry_on_failure: false,
+            max_page_content_chars: 15000,
+            condense_long_pages: false,
+            summary_model: None,
+            success_criteria: None,
+            output_preset: None,
+            output_schema: None,
+            capture_screenshots: false,
+            max_concurrent_runs: 1,
+            desktop_notifications: true,
+            memory_ttl_days: None,
+            memory_max_entries: None,
+            memory_max_bytes: None,
+            memory_eviction: MemoryEviction::Lru,
+            api_server: false,
+            api_server_port: 7878,
+            api_server_token: String::new(),
+            mcp_server: false,
+            mcp_servers: Vec::new(),
+        }
+    }
+}
+
+/// Keychain namespace of the active config's API keys
+const CONFIG_NAMESPACE: &str = "config";
+
+/// A saved config profile, as listed for switching between setups
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ProfileSummary {
+    pub name: String,
+    pub provider: String,
+    pub model: String,
+    pub active: bool,
+}
+
+/// Profile names double as file names, so keep them to plain characters
+pub fn validate_profile_name(name: &str) -> Result<(), String> {
+    let valid = !name.trim().is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_' | '.'))
+        && !name.starts_with('.');
+    if valid {
+        Ok(())
+    } else {
+        Err(format!(
+            "Invalid profile name '{}': use letters, digits, spaces, '-', '_' or '.'",
+            name
+        ))
+    }
+}
+
+/// A problem found in a config and how to fix it
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ConfigIssue {
+    /// The field at fault
+    pub field: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(field: &str, message: impl Into<String>) -> Self {
+        Self {
+            field: field.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Problems in `config` that would stop a run from starting or make it
+/// behave other than intended
+pub fn validate_config(config: &Config) -> Vec<ConfigIssue> {
+    let mut issues = Vec::new();
+    let provider = config.provider.trim().to_lowercase();
+
+    if !crate::providers::is_supported(&provider) {
+        let mut known: Vec<&str> = crate::providers::PROVIDERS.to_vec();
+        known.extend(
+            crate::providers::OPENAI_COMPATIBLE
+                .iter()
+                .map(|(name, _, _)| *name),
+        );
+        issues.push(ConfigIssue::new(
+            "provider",
+            format!(
+                "Unknown provider '{}'. Use one of: {}",
+                config.provider,
+                known.join(", ")
+            ),
+        ));
+    } else {
+        if config.model.trim().is_empty() {
+            issues.push(ConfigIssue::new(
+                "model",
+                format!(
+                    "No model is set for {}. Enter a model name, or load the provider's models to pick one",
+                    provider
+                ),
+            ));
+        }
+        if config.api_key.trim().is_empty() && !crate::providers::key_optional(&provider) {
+            issues.push(ConfigIssue::new(
+                "api_key",
+                format!("No API key is set for {}", provider),
+            ));
+        }
+    }
+
+    let base_url = config
+        .base_url
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty());
+    match base_url {
+        Some(url) => {
+            if let Err(problem) = check_base_url(url) {
+                issues.push(ConfigIssue::new(
+                    "base_url",
+                    format!(
+                        "Base URL '{}' {}. Use the full API address, e.g. http://localhost:1234/v1",
+                        url, problem
+                    ),
+                ));
+            }
+        }
+        None if provider == "custom" => issues.push(ConfigIssue::new(
+            "base_url",
+            "The custom provider needs a base URL, e.g. http://localhost:1234/v1",
+        )),
+        None => {}
+    }
+
+    if let Err(e) = crate::providers::validate_generation_params(config) {
+        issues.push(ConfigIssue::new("generation", e));
+    }
+
+    let otlp_endpoint = config
+        .otlp_endpoint
+        .as_deref()
+        .map(str::trim)
+        .filter(|url| !url.is_empty());
+    if let Some(url) = otlp_endpoint {
+        if let Err(problem) = check_base_url(url) {
+            issues.push(ConfigIssue::new(
+                "otlp_endpoint",
+                format!(
+                    "OTLP endpoint '{}' {}. Use the collector's HTTP address, e.g. http://localhost:4318",
+                    url, problem
+                ),
+            ));
+        }
+    }
+
+    if config.api_server && config.api_server_token.trim().is_empty() {
+        issues.push(ConfigIssue::new(
+            "api_server_token",
+            "The local API needs a token for clients to authenticate with",
+        ));
+    }
+    if config.mcp_server && !config.api_server {
+        issues.push(ConfigIssue::new(
+            "mcp_server",
+            "The MCP server is served on the local API; enable api_server too",
+        ));
+    }
+    let mut server_names = std::collections::HashSet::new();
+    for server in &config.mcp_servers {
+        let name = server.name.trim();
+        if name.is_empty() || server.command.trim().is_empty() {
+            issues.push(ConfigIssue::new(
+                "mcp_servers",
+                "Every MCP server needs a name and a command to start it",
+            ));
+        } else if !server_names.insert(crate::mcp_client::tool_name(name, "")) {
+            issues.push(ConfigIssue::new(
+                "mcp_servers",
+                format!("More than one MCP server is named '{}'", name),
+            ));
         }
     }
+
+    for pattern in &config.redact_patterns {
+        if let Err(e) = regex::Regex::new(pattern) {
+            issues.push(ConfigIssue::new(
+                "redact_patterns",
+                format!(
+                    "Redaction pattern '{}' is not a valid regex: {}",
+                    pattern, e
+                ),
+            ));
+        }
+    }
+    issues
 }
 
+/// Why `url` can't serve as an API base URL, if it can't
+fn check_base_url(url: &str) -> Result<(), &'static str> {
+    let parsed = reqwest::Url::parse(url).map_err(|_| "is not a valid URL")?;
+    if !matches!(parsed.scheme(), "http" | "https") {
+        return Err("must start with http:// or https://");
+    }
+    if parsed.host_str().is_none() {
+        return Err("has no host");
+    }
+    Ok(())
+}
+
+/// Loads and saves the config file and named config profiles. API keys are
+/// kept in the OS keychain and only referenced from the files; plaintext keys
+/// from older configs are moved there on first load.
 pub struct ConfigManager {
     config_path: PathBuf,
+    profiles_dir: PathBuf,
 }
 
 impl ConfigManager {
-    pub fn new(app_handle: &AppHandle) -> Self {
-        let mut path = app_handle.path().app_config_dir().unwrap_or_else(|_| PathBuf::from("."));
+    pub fn new(dir: &Path) -> Self {
         // Ensure directory exists
-        let _ = fs::create_dir_all(&path);
-        path.push("config.json");
-        Self { config_path: path }
+        let _ = fs::create_dir_all(dir);
+        Self {
+            config_path: dir.join("config.json"),
+            profiles_dir: dir.join("profiles"),
+        }
+    }
+
+    /// Location of the active config file
+    pub fn config_path(&self) -> &Path {
+        &self.config_path
     }
 
+    /// Fields in the config file this version doesn't know, which are
+    /// ignored on load
+    pub fn unknown_fields(&self) -> Vec<String> {
+        let Some(serde_json::Value::Object(fields)) = fs::read_to_string(&self.config_path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+        else {
+            return Vec::new();
+        };
+        let known = match serde_json::to_value(Config::default()) {
+            Ok(serde_json::Value::Object(known)) => known,
+            _ => return Vec::new(),
+        };
+        fields
+            .keys()
+            .filter(|name| !known.contains_key(*name))
+            .cloned()
+            .collect()
+    }
+
+    /// The config file's settings, with `NEXUS_<FIELD>` environment
+    /// variables taking precedence
     pub fn load(&self) -> Config {
-        if let Ok(content) = fs::read_to_string(&self.config_path) {
-            if let Ok(config) = serde_json::from_str(&content) {
-                return config;
+        let mut config = read_config(&self.config_path, CONFIG_NAMESPACE).unwrap_or_default();
+        apply_env_overrides(&mut config, env_var);
+        config
+    }
+
+    /// Save `config`, keeping the file's own values for fields overridden
+    /// from the environment so those never reach the disk or keychain
+    pub fn save(&self, config: &Config) -> Result<(), String> {
+        let on_disk = read_config(&self.config_path, CONFIG_NAMESPACE).unwrap_or_default();
+        let overridden = apply_env_overrides(&mut on_disk.clone(), env_var);
+        if overridden.is_empty() {
+            return write_config(&self.config_path, config, CONFIG_NAMESPACE);
+        }
+        write_config(
+            &self.config_path,
+            &without_overrides(config, &on_disk, &overridden),
+            CONFIG_NAMESPACE,
+        )
+    }
+
+    fn profile_path(&self, name: &str) -> PathBuf {
+        self.profiles_dir.join(format!("{}.json", name))
+    }
+
+    fn active_profile_path(&self) -> PathBuf {
+        self.profiles_dir.join("active")
+    }
+
+    /// Name of the profile last activated, if it still exists
+    pub fn active_profile(&self) -> Option<String> {
+        let name = fs::read_to_string(self.active_profile_path()).ok()?;
+        let name = name.trim();
+        self.profile_path(name).exists().then(|| name.to_string())
+    }
+
+    /// Saved profiles, sorted by name
+    pub fn list_profiles(&self) -> Vec<ProfileSummary> {
+        let active = self.active_profile();
+        let mut profiles: Vec<ProfileSummary> = fs::read_dir(&self.profiles_dir)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path.extension()? != "json" {
+                    return None;
+                }
+                let name = path.file_stem()?.to_string_lossy().to_string();
+                // Listing needs no secrets, so skip the keychain
+                let config: Config = serde_json::from_str(&fs::read_to_string(&path).ok()?).ok()?;
+                Some(ProfileSummary {
+                    active: active.as_deref() == Some(name.as_str()),
+                    name,
+                    provider: config.provider,
+                    model: config.model,
+                })
+            })
+            .collect();
+        profiles.sort_by(|a, b| a.name.cmp(&b.name));
+        profiles
+    }
+
+    /// Save `config` as the profile `name`, replacing any previous one
+    pub fn save_profile(&self, name: &str, config: &Config) -> Result<(), String> {
+        validate_profile_name(name)?;
+        fs::create_dir_all(&self.profiles_dir).map_err(|e| e.to_string())?;
+        write_config(&self.profile_path(name), config, &profile_namespace(name))
+    }
+
+    /// Make the profile `name` the active config
+    pub fn activate_profile(&self, name: &str) -> Result<Config, String> {
+        validate_profile_name(name)?;
+        let config = read_config(&self.profile_path(name), &profile_namespace(name))
+            .ok_or_else(|| format!("Profile '{}' not found", name))?;
+        self.save(&config)?;
+        fs::write(self.active_profile_path(), name).map_err(|e| e.to_string())?;
+        Ok(config)
+    }
+
+    pub fn delete_profile(&self, name: &str) -> Result<bool, String> {
+        validate_profile_name(name)?;
+        let path = self.profile_path(name);
+        if !path.exists() {
+            return Ok(false);
+        }
+        let providers: Vec<String> = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Config>(&content).ok())
+            .map(|config| config.providers.into_keys().collect())
+            .unwrap_or_default();
+        fs::remove_file(path).map_err(|e| e.to_string())?;
+        let namespace = profile_namespace(name);
+        let accounts = providers
+            .iter()
+            .map(|provider| format!("{}:{}", namespace, provider))
+            .chain(std::iter::once(token_account(&namespace)));
+        for account in accounts {
+            if let Err(e) = crate::secrets::delete(&account) {
+                crate::trace_warn!(
+                    "nexus::config",
+                    "Could not remove profile API key from the keychain",
+                    error = e
+                );
+            }
+        }
+        Ok(true)
+    }
+}
+
+/// Keychain namespace of a profile's API keys
+fn profile_namespace(name: &str) -> String {
+    format!("profile:{}", name)
+}
+
+/// Keychain account of the local API token in `namespace`
+fn token_account(namespace: &str) -> String {
+    format!("{}:api_server_token", namespace)
+}
+
+/// Replace a keychain reference with its secret; true if `key` was stored in
+/// plaintext. The reference is kept when the keychain can't be read, so a
+/// later save doesn't mistake the key for cleared and delete it.
+fn resolve_key(key: &mut String) -> bool {
+    let Some(account) = crate::secrets::parse_reference(key) else {
+        return !key.is_empty();
+    };
+    match crate::secrets::load(account) {
+        Ok(secret) => *key = secret.unwrap_or_default(),
+        Err(e) => {
+            crate::trace_warn!(
+                "nexus::config",
+                "Could not read API key from the keychain",
+                error = e
+            );
+        }
+    }
+    false
+}
+
+/// Move a plaintext key into the keychain under `account`, leaving a
+/// reference; an empty key, which only a user clearing it produces, removes
+/// the stored secret
+fn stash_key(key: &mut String, account: &str) {
+    if key.is_empty() {
+        if let Err(e) = crate::secrets::delete(account) {
+            crate::trace_warn!(
+                "nexus::config",
+                "Could not remove API key from the keychain",
+                error = e
+            );
+        }
+    } else if crate::secrets::parse_reference(key).is_none() {
+        match crate::secrets::store(account, key) {
+            Ok(()) => *key = crate::secrets::reference(account),
+            Err(e) => crate::trace_warn!(
+                "nexus::config",
+                "Keychain unavailable, keeping API key in the config file",
+                error = e
+            ),
+        }
+    }
+}
+
+/// Read the config at `path`, resolving its API keys from the keychain
+fn read_config(path: &Path, namespace: &str) -> Option<Config> {
+    let content = fs::read_to_string(path).ok()?;
+    let mut value: serde_json::Value = serde_json::from_str(&content).ok()?;
+    let migrated = migrate_config(&mut value);
+    let mut config: Config = serde_json::from_value(value).ok()?;
+    let mut plaintext = resolve_key(&mut config.api_key);
+    for settings in config.providers.values_mut() {
+        plaintext |= resolve_key(&mut settings.api_key);
+    }
+    plaintext |= resolve_key(&mut config.api_server_token);
+
+    // Persist upgrades, moving keys from older plaintext configs into the
+    // keychain
+    if migrated || plaintext {
+        if let Err(e) = write_config(path, &config, namespace) {
+            crate::trace_warn!(
+                "nexus::config",
+                "Could not save the upgraded config",
+                error = e
+            );
+        }
+    }
+    Some(config)
+}
+
+/// Bring a config file's JSON up to [`CONFIG_VERSION`]; true if any
+/// migration ran. Files without a version predate versioning and count as 0.
+fn migrate_config(value: &mut serde_json::Value) -> bool {
+    let Some(fields) = value.as_object_mut() else {
+        return false;
+    };
+    let current = fields
+        .get("version")
+        .and_then(|version| version.as_u64())
+        .unwrap_or(0) as usize;
+    if current > CONFIG_MIGRATIONS.len() {
+        crate::trace_warn!(
+            "nexus::config",
+            "Config was written by a newer version of Nexus",
+            version = current
+        );
+        return false;
+    }
+    for migration in &CONFIG_MIGRATIONS[current..] {
+        migration(fields);
+    }
+    fields.insert("version".to_string(), CONFIG_VERSION.into());
+    current < CONFIG_MIGRATIONS.len()
+}
+
+/// Record the active provider's key, model and base URL under `providers`,
+/// which configs before version 1 lacked
+fn remember_legacy_provider(fields: &mut serde_json::Map<String, serde_json::Value>) {
+    let Some(provider) = fields
+        .get("provider")
+        .and_then(|provider| provider.as_str())
+        .filter(|provider| !provider.trim().is_empty())
+        .map(str::to_lowercase)
+    else {
+        return;
+    };
+    let settings: serde_json::Map<String, serde_json::Value> = ["api_key", "model", "base_url"]
+        .into_iter()
+        .filter_map(|key| Some((key.to_string(), fields.get(key)?.clone())))
+        .collect();
+    let providers = fields
+        .entry("providers")
+        .or_insert_with(|| serde_json::json!({}));
+    if let Some(providers) = providers.as_object_mut() {
+        providers.entry(provider).or_insert(settings.into());
+    }
+}
+
+/// Write `config` to `path`, storing each provider's API key in the keychain
+/// under `namespace`
+fn write_config(path: &Path, config: &Config, namespace: &str) -> Result<(), String> {
+    let mut stored = config.clone();
+    stored.version = CONFIG_VERSION;
+    stored.remember_provider();
+    for (provider, settings) in stored.providers.iter_mut() {
+        stash_key(
+            &mut settings.api_key,
+            &format!("{}:{}", namespace, provider),
+        );
+    }
+    stored.api_key = stored
+        .providers
+        .get(&stored.provider.to_lowercase())
+        .map(|settings| settings.api_key.clone())
+        .unwrap_or_default();
+    stash_key(&mut stored.api_server_token, &token_account(namespace));
+    let content = serde_json::to_string_pretty(&stored).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+/// Prefix of the environment variables overriding config fields, e.g.
+/// `NEXUS_MODEL` for `model`
+const ENV_PREFIX: &str = "NEXUS_";
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok()
+}
+
+/// Override config fields from `NEXUS_<FIELD>` variables looked up through
+/// `var`, returning the fields overridden. Values are read as JSON where they
+/// parse as such (numbers, booleans, lists) and as plain strings otherwise;
+/// values that don't fit their field are ignored.
+fn apply_env_overrides(config: &mut Config, var: impl Fn(&str) -> Option<String>) -> Vec<String> {
+    let mut overridden = Vec::new();
+    // Switch provider first so its remembered key and model apply unless
+    // overridden as well
+    if let Some(provider) = var(&format!("{}PROVIDER", ENV_PREFIX)) {
+        if !provider.trim().is_empty() {
+            config.switch_provider(provider.trim());
+            overridden.push("provider".to_string());
+        }
+    }
+
+    let Ok(serde_json::Value::Object(mut fields)) = serde_json::to_value(&*config) else {
+        return overridden;
+    };
+    let names: Vec<String> = fields
+        .keys()
+        .filter(|name| *name != "provider")
+        .cloned()
+        .collect();
+    for name in names {
+        let Some(raw) = var(&format!("{}{}", ENV_PREFIX, name.to_uppercase())) else {
+            continue;
+        };
+        let previous = fields.get(&name).cloned().unwrap_or_default();
+        let candidates = [
+            serde_json::from_str(&raw).ok(),
+            Some(serde_json::Value::String(raw.clone())),
+        ];
+        let fits = candidates.into_iter().flatten().any(|value| {
+            fields.insert(name.clone(), value);
+            serde_json::from_value::<Config>(serde_json::Value::Object(fields.clone())).is_ok()
+        });
+        if fits {
+            overridden.push(name);
+        } else {
+            fields.insert(name.clone(), previous);
+            crate::trace_warn!(
+                "nexus::config",
+                "Ignoring invalid config override from the environment",
+                field = name
+            );
+        }
+    }
+
+    if let Ok(updated) = serde_json::from_value(serde_json::Value::Object(fields)) {
+        *config = updated;
+    }
+    overridden
+}
+
+/// `config` with the `overridden` fields put back to their values in
+/// `on_disk`
+fn without_overrides(config: &Config, on_disk: &Config, overridden: &[String]) -> Config {
+    let is_overridden = |field: &str| overridden.iter().any(|name| name == field);
+    let mut config = config.clone();
+    let provider = config.provider.to_lowercase();
+
+    if is_overridden("provider") {
+        // Keep changes made to the overridden provider's settings
+        config.remember_provider();
+        config.provider = on_disk.provider.clone();
+        config.api_key = on_disk.api_key.clone();
+        config.model = on_disk.model.clone();
+        config.base_url = on_disk.base_url.clone();
+    }
+
+    if let (Ok(serde_json::Value::Object(mut fields)), Ok(serde_json::Value::Object(disk))) =
+        (serde_json::to_value(&config), serde_json::to_value(on_disk))
+    {
+        for name in overridden.iter().filter(|name| *name != "provider") {
+            if let Some(value) = disk.get(name) {
+                fields.insert(name.clone(), value.clone());
             }
         }
-        Config::default()
+        if let Ok(restored) = serde_json::from_value(serde_json::Value::Object(fields)) {
+            config = restored;
+        }
     }
 
-    pub fn save(&self, config: &Config) -> Result<(), String> {
-        let content = serde_json::to_string_pretty(config).map_err(|e| e.to_string())?;
-        fs::write(&self.config_path, content).map_err(|e| e.to_string())?;
-        Ok(())
+    // The overridden provider's remembered settings must not keep the
+    // environment's values either
+    let remembered = on_disk
+        .providers
+        .get(&provider)
+        .cloned()
+        .unwrap_or_default();
+    if let Some(settings) = config.providers.get_mut(&provider) {
+        if is_overridden("api_key") {
+            settings.api_key = remembered.api_key;
+        }
+        if is_overridden("model") {
+            settings.model = remembered.model;
+        }
+        if is_overridden("base_url") {
+            settings.base_url = remembered.base_url;
+        }
+    }
+    config
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_provider() {
+        let mut config = Config {
+            provider: "openai".to_string(),
+            api_key: "sk-openai".to_string(),
+            model: "gpt-4o".to_string(),
+            ..Config::default()
+        };
+        config.switch_provider("ollama");
+        assert_eq!(config.provider, "ollama");
+        assert!(config.api_key.is_empty());
+        assert!(config.model.is_empty());
+
+        config.model = "llama3.1".to_string();
+        config.switch_provider("openai");
+        assert_eq!(config.api_key, "sk-openai");
+        assert_eq!(config.model, "gpt-4o");
+        assert_eq!(config.providers["ollama"].model, "llama3.1");
+
+        // Older configs without per-provider settings deserialize as before
+        let legacy: Config =
+            serde_json::from_str(r#"{"provider": "gemini", "model": "gemini-2.5-pro"}"#).unwrap();
+        assert_eq!(legacy.model, "gemini-2.5-pro");
+        assert!(legacy.providers.is_empty());
+    }
+
+    #[test]
+    fn test_validate_profile_name() {
+        assert!(validate_profile_name("cheap local").is_ok());
+        assert!(validate_profile_name("deep-research_v2").is_ok());
+        assert!(validate_profile_name("").is_err());
+        assert!(validate_profile_name("../config").is_err());
+        assert!(validate_profile_name(".hidden").is_err());
+        assert!(validate_profile_name("a/b").is_err());
+    }
+
+    #[test]
+    fn test_migrate_config() {
+        let mut legacy = serde_json::json!({
+            "provider": "OpenAI",
+            "api_key": "keyring:config:openai",
+            "model": "gpt-4o"
+        });
+        assert!(migrate_config(&mut legacy));
+        let config: Config = serde_json::from_value(legacy).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.providers["openai"].model, "gpt-4o");
+        assert_eq!(config.providers["openai"].api_key, "keyring:config:openai");
+
+        let mut current = serde_json::to_value(Config::default()).unwrap();
+        assert!(!migrate_config(&mut current));
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let config = Config {
+            api_key: "sk-ant".to_string(),
+            ..Config::default()
+        };
+        assert!(validate_config(&config).is_empty());
+
+        let config = Config {
+            provider: "opeanai".to_string(),
+            ..Config::default()
+        };
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "provider");
+        assert!(issues[0].message.contains("openai"));
+
+        let config = Config {
+            provider: "custom".to_string(),
+            model: " ".to_string(),
+            ..Config::default()
+        };
+        let fields: Vec<String> = validate_config(&config)
+            .into_iter()
+            .map(|issue| issue.field)
+            .collect();
+        assert_eq!(fields, ["model", "base_url"]);
+
+        let config = Config {
+            provider: "ollama".to_string(),
+            model: "llama3.1".to_string(),
+            base_url: Some("localhost:11434".to_string()),
+            ..Config::default()
+        };
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "base_url");
+
+        let config = Config {
+            api_key: "sk-ant".to_string(),
+            otlp_endpoint: Some("tempo:4318".to_string()),
+            ..Config::default()
+        };
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "otlp_endpoint");
+
+        let config = Config {
+            api_key: "sk-ant".to_string(),
+            redact_patterns: vec![r"acct-\d{6}".to_string(), "(unclosed".to_string()],
+            ..Config::default()
+        };
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "redact_patterns");
+
+        let config = Config {
+            api_key: "sk-ant".to_string(),
+            api_server: true,
+            ..Config::default()
+        };
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "api_server_token");
+
+        let config = Config {
+            api_key: "sk-ant".to_string(),
+            mcp_server: true,
+            ..Config::default()
+        };
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "mcp_server");
+
+        let server = McpServerConfig {
+            name: "github".to_string(),
+            command: "npx".to_string(),
+            ..McpServerConfig::default()
+        };
+        let config = Config {
+            api_key: "sk-ant".to_string(),
+            mcp_servers: vec![server.clone(), server],
+            ..Config::default()
+        };
+        let issues = validate_config(&config);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].field, "mcp_servers");
+
+        // Names that give their tools the same prefix clash too
+        let named = |name: &str| McpServerConfig {
+            name: name.to_string(),
+            command: "npx".to_string(),
+            ..McpServerConfig::default()
+        };
+        let config = Config {
+            api_key: "sk-ant".to_string(),
+            mcp_servers: vec![named("my files"), named("my.files"), named("files")],
+            ..Config::default()
+        };
+        assert_eq!(validate_config(&config).len(), 1);
+    }
+
+    fn vars(pairs: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let pairs: HashMap<String, String> = pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| pairs.get(name).cloned()
+    }
+
+    #[test]
+    fn test_apply_env_overrides() {
+        let mut config = Config {
+            provider: "anthropic".to_string(),
+            api_key: "sk-ant".to_string(),
+            model: "claude-3-5-sonnet".to_string(),
+            ..Config::default()
+        };
+        config.providers.insert(
+            "openai".to_string(),
+            ProviderSettings {
+                api_key: "sk-openai".to_string(),
+                model: "gpt-4o".to_string(),
+                base_url: None,
+            },
+        );
+
+        let overridden = apply_env_overrides(
+            &mut config,
+            vars(&[
+                ("NEXUS_PROVIDER", "openai"),
+                ("NEXUS_API_KEY", "sk-ci"),
+                ("NEXUS_MAX_STEPS", "10"),
+                ("NEXUS_TEMPERATURE", "0.1"),
+                ("NEXUS_PLAN_FIRST", "true"),
+                ("NEXUS_MAX_SUB_AGENTS", "lots"),
+            ]),
+        );
+        assert_eq!(config.provider, "openai");
+        assert_eq!(config.api_key, "sk-ci");
+        assert_eq!(config.model, "gpt-4o");
+        assert_eq!(config.max_steps, 10);
+        assert_eq!(config.temperature, Some(0.1));
+        assert!(config.plan_first);
+        assert_eq!(config.max_sub_agents, 3);
+        assert!(overridden.contains(&"provider".to_string()));
+        assert!(overridden.contains(&"api_key".to_string()));
+        assert!(!overridden.contains(&"max_sub_agents".to_string()));
+
+        // Values that parse as JSON of the wrong type fall back to a string
+        let mut config = Config::default();
+        apply_env_overrides(&mut config, vars(&[("NEXUS_MODEL", "4")]));
+        assert_eq!(config.model, "4");
+    }
+
+    #[test]
+    fn test_without_overrides() {
+        let mut on_disk = Config {
+            provider: "anthropic".to_string(),
+            api_key: "sk-ant".to_string(),
+            model: "claude-3-5-sonnet".to_string(),
+            ..Config::default()
+        };
+        on_disk.remember_provider();
+
+        let mut config = on_disk.clone();
+        let overridden = apply_env_overrides(
+            &mut config,
+            vars(&[("NEXUS_PROVIDER", "openai"), ("NEXUS_API_KEY", "sk-ci")]),
+        );
+        config.model = "gpt-4o-mini".to_string();
+        config.max_steps = 40;
+
+        let saved = without_overrides(&config, &on_disk, &overridden);
+        assert_eq!(saved.provider, "anthropic");
+        assert_eq!(saved.api_key, "sk-ant");
+        assert_eq!(saved.max_steps, 40);
+        let openai = &saved.providers["openai"];
+        assert_eq!(openai.api_key, "");
+        assert_eq!(openai.model, "gpt-4o-mini");
     }
 }
@@ -0,0 +1,170 @@
+//! Reload the config when config.json changes on disk
+//!
+//! [`ConfigManager`] reads the file on every load, so runs pick up edits made
+//! by hand or by a sync tool on their own. The watcher re-applies the
+//! settings held elsewhere with [`apply`] and emits a `config-changed`
+//! event carrying the new config so open views can refresh. The burst of file
+//! events a single save produces is coalesced over [`DEBOUNCE`], and events
+//! that leave the file's content unchanged are ignored. Saves from the app
+//! call [`apply`] themselves, since a change to a key or token held in the
+//! keychain leaves the file as it was.
+
+use crate::config::{Config, ConfigManager};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock};
+use tauri::{AppHandle, Emitter, Manager};
+use tokio::sync::mpsc;
+use tokio::time::{sleep, Duration};
+
+/// How long to wait for a save's file events to settle before reloading
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// The active watcher; dropping it would stop the notifications
+static WATCHER: OnceLock<Mutex<RecommendedWatcher>> = OnceLock::new();
+
+/// Whether `event` creates, changes or removes the file at `path`
+fn is_config_change(event: &Event, path: &Path) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_) | EventKind::Modify(_) | EventKind::Remove(_)
+    ) && event
+        .paths
+        .iter()
+        .any(|changed| changed.file_name() == path.file_name())
+}
+
+/// Start watching the config file for changes
+pub fn start_config_watcher(app: &AppHandle) {
+    let path = app
+        .state::<Mutex<ConfigManager>>()
+        .lock()
+        .unwrap()
+        .config_path()
+        .to_path_buf();
+    // Editors often save by replacing the file, which drops a watch on the
+    // file itself, so watch its directory instead
+    let Some(dir) = path.parent().map(Path::to_path_buf) else {
+        return;
+    };
+
+    let (tx, rx) = mpsc::unbounded_channel();
+    let watched = path.clone();
+    let watcher = notify::recommended_watcher(move |event: notify::Result<Event>| {
+        if let Ok(event) = event {
+            if is_config_change(&event, &watched) {
+                let _ = tx.send(());
+            }
+        }
+    });
+    let mut watcher = match watcher {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            crate::trace_warn!(
+                "nexus::config",
+                "Could not create config file watcher",
+                error = e.to_string()
+            );
+            return;
+        }
+    };
+    if let Err(e) = watcher.watch(&dir, RecursiveMode::NonRecursive) {
+        crate::trace_warn!(
+            "nexus::config",
+            "Could not watch the config directory",
+            error = e.to_string()
+        );
+        return;
+    }
+    let _ = WATCHER.set(Mutex::new(watcher));
+
+    tauri::async_runtime::spawn(watch_loop(app.clone(), path, rx));
+}
+
+async fn watch_loop(app: AppHandle, path: PathBuf, mut rx: mpsc::UnboundedReceiver<()>) {
+    let mut last = fs::read(&path).ok();
+    while rx.recv().await.is_some() {
+        sleep(DEBOUNCE).await;
+        while rx.try_recv().is_ok() {}
+
+        if fs::read(&path).ok() == last {
+            continue;
+        }
+        reload(&app);
+        // Loading may rewrite the file, moving plaintext keys to the keychain
+        last = fs::read(&path).ok();
+    }
+}
+
+fn reload(app: &AppHandle) {
+    let config = app.state::<Mutex<ConfigManager>>().lock().unwrap().load();
+    apply(&config);
+    crate::trace_info!(
+        "nexus::config",
+        "Config reloaded from disk",
+        provider = config.provider,
+        model = config.model
+    );
+    if let Err(e) = app.emit("config-changed", &config) {
+        crate::trace_warn!(
+            "nexus::config",
+            "Failed to emit config-changed event",
+            error = e.to_string()
+        );
+    }
+}
+
+/// Re-apply the settings held outside [`ConfigManager`]: browser timeouts,
+/// tracing, redaction, and the local API and MCP servers
+pub fn apply(config: &Config) {
+    if let Some(browser) = crate::browser::GLOBAL_BROWSER.get() {
+        browser.set_timeouts(crate::browser::BrowserTimeouts::from_config(config));
+    }
+    crate::tracing::set_buffer_size(config.trace_buffer_size);
+    crate::tracing::set_stream_interval(config.trace_stream_interval_ms);
+    crate::tracelog::configure(config);
+    crate::otlp::configure(config);
+    crate::redact::configure(config);
+    crate::api::configure(config);
+    crate::mcp::configure(config);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use notify::event::{CreateKind, ModifyKind};
+
+    #[test]
+    fn test_is_config_change() {
+        let path = Path::new("/app/config.json");
+        let event = Event::new(EventKind::Modify(ModifyKind::Any))
+            .add_path(PathBuf::from("/app/config.json"));
+        assert!(is_config_change(&event, path));
+
+        let other_file = Event::new(EventKind::Create(CreateKind::File))
+            .add_path(PathBuf::from("/app/profiles"));
+        assert!(!is_config_change(&other_file, path));
+
+        let access = Event::new(EventKind::Access(notify::event::AccessKind::Any))
+            .add_path(PathBuf::from("/app/config.json"));
+        assert!(!is_config_change(&access, path));
+    }
+
+    #[test]
+    fn test_apply_token_change() {
+        let mut config = Config {
+            api_server_token: "first-token-1234".to_string(),
+            ..Config::default()
+        };
+        apply(&config);
+        assert!(crate::api::accepts(Some("Bearer first-token-1234")));
+
+        // Only the token changes, as when it is edited in the keychain
+        config.api_server_token = "second-token-5678".to_string();
+        apply(&config);
+        assert!(!crate::api::accepts(Some("Bearer first-token-1234")));
+        assert!(crate::api::accepts(Some("Bearer second-token-5678")));
+        assert_eq!(crate::redact::redact("second-token-5678"), "[REDACTED]");
+    }
+}
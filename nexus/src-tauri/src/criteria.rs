@@ -0,0 +1,113 @@
+//! Machine-checkable success criteria for runs
+//!
+//! A task template can declare what a good report looks like (how many
+//! sources, which fields must be present, which sites must have been
+//! visited). After a run completes, its report is validated against the
+//! criteria and the verdict is stored with the run record, so history shows
+//! at a glance which runs actually did the job and why the others fell short.
+
+use crate::approval::url_matches_domains;
+use serde::{Deserialize, Serialize};
+
+/// Criteria a run's report must meet to pass
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct SuccessCriteria {
+    /// Minimum number of distinct pages the run must have read
+    pub min_sources: Option<usize>,
+    /// Fields the report must contain, as a heading or a `Field:` label
+    pub required_fields: Vec<String>,
+    /// Domains (subdomains included) at least one source must come from, each
+    pub required_domains: Vec<String>,
+}
+
+/// Outcome of validating a report
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Validation {
+    pub passed: bool,
+    /// Why the run failed; empty when it passed
+    pub reasons: Vec<String>,
+}
+
+/// Whether `report` has `field` as a heading or a `Field:` label
+fn has_field(report: &str, field: &str) -> bool {
+    let field = field.trim().to_lowercase();
+    if field.is_empty() {
+        return true;
+    }
+    report.lines().any(|line| {
+        let line = line
+            .trim()
+            .trim_start_matches(['#', '-', '*', '|', ' '])
+            .to_lowercase();
+        match line.strip_prefix(&field) {
+            Some(rest) => {
+                let rest = rest.trim_start_matches(['*', '_']).trim_start();
+                rest.is_empty() || rest.starts_with(':') || rest.starts_with('|')
+            }
+            None => false,
+        }
+    })
+}
+
+/// Validate a report and the pages the run read against `criteria`
+pub fn validate(criteria: &SuccessCriteria, report: &str, sources: &[String]) -> Validation {
+    let mut reasons = Vec::new();
+
+    if let Some(min_sources) = criteria.min_sources {
+        let mut distinct: Vec<&String> = sources.iter().collect();
+        distinct.sort();
+        distinct.dedup();
+        if distinct.len() < min_sources {
+            reasons.push(format!(
+                "Read {} source(s), at least {} required",
+                distinct.len(),
+                min_sources
+            ));
+        }
+    }
+    for field in &criteria.required_fields {
+        if !has_field(report, field) {
+            reasons.push(format!("Report is missing the field \"{}\"", field));
+        }
+    }
+    for domain in &criteria.required_domains {
+        let visited = sources
+            .iter()
+            .any(|url| url_matches_domains(url, std::slice::from_ref(domain)));
+        if !visited {
+            reasons.push(format!("No page from {} was visited", domain));
+        }
+    }
+
+    Validation {
+        passed: reasons.is_empty(),
+        reasons,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let criteria = SuccessCriteria {
+            min_sources: Some(2),
+            required_fields: vec!["Price".to_string(), "Release date".to_string()],
+            required_domains: vec!["example.com".to_string()],
+        };
+        let report = "# Widget\n\n## Price\n$10\n\n**Release date**: 2024-01-01";
+        let sources = vec![
+            "https://shop.example.com/widget".to_string(),
+            "https://reviews.test/widget".to_string(),
+        ];
+        let validation = validate(&criteria, report, &sources);
+        assert!(validation.passed);
+        assert!(validation.reasons.is_empty());
+
+        let validation = validate(&criteria, "Prices vary", &sources[1..]);
+        assert!(!validation.passed);
+        assert_eq!(validation.reasons.len(), 4);
+    }
+}
@@ -0,0 +1,263 @@
+//! Change-data-capture for recurring dataset extractions
+//!
+//! A dataset is the output of applying an extraction recipe to a URL. Each run
+//! stores a snapshot and the delta against the previous snapshot
+//! (added / removed / changed records), so recurring extractions report what
+//! changed instead of the full export.
+
+use crate::browser::BrowserManager;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use tauri::Emitter;
+
+/// A record whose key is unchanged but whose other fields differ
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChangedRecord {
+    pub key: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+/// Difference between two snapshots of a dataset
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct DatasetDelta {
+    pub added: Vec<Value>,
+    pub removed: Vec<Value>,
+    pub changed: Vec<ChangedRecord>,
+}
+
+impl DatasetDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        format!(
+            "{} new, {} removed, {} changed",
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )
+    }
+}
+
+/// Result of one dataset run
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DatasetRun {
+    pub dataset: String,
+    pub taken_at: i64,
+    pub record_count: usize,
+    /// `None` on the first run, when there is nothing to compare against
+    pub delta: Option<DatasetDelta>,
+}
+
+/// Normalize recipe output (single record or list) into a list of records
+pub fn as_records(output: &Value) -> Vec<Value> {
+    match output {
+        Value::Array(items) => items.clone(),
+        Value::Null => Vec::new(),
+        other => vec![other.clone()],
+    }
+}
+
+/// Identity of a record: the key fields if given, else the whole record
+fn record_key(record: &Value, key_fields: &[String]) -> String {
+    if key_fields.is_empty() {
+        return record.to_string();
+    }
+    key_fields
+        .iter()
+        .map(|f| record.get(f).map(|v| v.to_string()).unwrap_or_default())
+        .collect::<Vec<_>>()
+        .join("\u{1f}")
+}
+
+/// Diff two snapshots, matching records by `key_fields`
+pub fn diff_records(previous: &[Value], current: &[Value], key_fields: &[String]) -> DatasetDelta {
+    let index = |records: &[Value]| -> BTreeMap<String, Value> {
+        records
+            .iter()
+            .map(|r| (record_key(r, key_fields), r.clone()))
+            .collect()
+    };
+    let before = index(previous);
+    let after = index(current);
+
+    let mut delta = DatasetDelta::default();
+    for (key, record) in &after {
+        match before.get(key) {
+            None => delta.added.push(record.clone()),
+            Some(old) if old != record => delta.changed.push(ChangedRecord {
+                key: key.clone(),
+                before: old.clone(),
+                after: record.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+    for (key, record) in &before {
+        if !after.contains_key(key) {
+            delta.removed.push(record.clone());
+        }
+    }
+    delta
+}
+
+pub fn latest_snapshot(conn: &Connection, dataset: &str) -> rusqlite::Result<Option<Vec<Value>>> {
+    let records: Option<String> = conn
+        .query_row(
+            "SELECT records FROM dataset_snapshots WHERE dataset = ?1
+             ORDER BY taken_at DESC, id DESC LIMIT 1",
+            params![dataset],
+            |row| row.get(0),
+        )
+        .optional()?;
+    Ok(records.map(|r| serde_json::from_str(&r).unwrap_or_default()))
+}
+
+pub fn store_snapshot(
+    conn: &Connection,
+    dataset: &str,
+    taken_at: i64,
+    records: &[Value],
+    delta: Option<&DatasetDelta>,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO dataset_snapshots (dataset, taken_at, records, delta) VALUES (?1, ?2, ?3, ?4)",
+        params![
+            dataset,
+            taken_at,
+            serde_json::to_string(records).unwrap_or_default(),
+            delta.map(|d| serde_json::to_string(d).unwrap_or_default())
+        ],
+    )?;
+    Ok(())
+}
+
+/// Most recent deltas recorded for a dataset, newest first
+pub fn history(conn: &Connection, dataset: &str, limit: usize) -> rusqlite::Result<Vec<DatasetRun>> {
+    let mut stmt = conn.prepare(
+        "SELECT taken_at, records, delta FROM dataset_snapshots WHERE dataset = ?1
+         ORDER BY taken_at DESC, id DESC LIMIT ?2",
+    )?;
+    let rows = stmt.query_map(params![dataset, limit as i64], |row| {
+        let records: String = row.get(1)?;
+        let delta: Option<String> = row.get(2)?;
+        Ok(DatasetRun {
+            dataset: dataset.to_string(),
+            taken_at: row.get(0)?,
+            record_count: serde_json::from_str::<Vec<Value>>(&records)
+                .map(|r| r.len())
+                .unwrap_or(0),
+            delta: delta.and_then(|d| serde_json::from_str(&d).ok()),
+        })
+    })?;
+    rows.collect()
+}
+
+/// Extract `url` with `recipe`, diff against the previous snapshot and store
+/// the new snapshot. Emits a `dataset-delta` event when anything changed.
+pub async fn run_dataset(
+    browser: &BrowserManager,
+    recipe_name: &str,
+    url: &str,
+    key_fields: &[String],
+) -> Result<DatasetRun, String> {
+    let recipe = crate::db::with_db(|conn| crate::recipes::find(conn, recipe_name))?
+        .ok_or_else(|| format!("No extraction recipe found for '{}'", recipe_name))?;
+
+    browser
+        .navigate_and_get_content(url)
+        .await
+        .map_err(|e| e.to_string())?;
+    let output = browser
+        .evaluate_json(recipe.to_script())
+        .await
+        .map_err(|e| e.to_string())?;
+    let records = as_records(&output);
+
+    let dataset = format!("{}|{}", recipe.name, url);
+    let taken_at = chrono::Utc::now().timestamp_millis();
+    let delta = crate::db::with_db(|conn| {
+        let delta = latest_snapshot(conn, &dataset)?
+            .map(|previous| diff_records(&previous, &records, key_fields));
+        store_snapshot(conn, &dataset, taken_at, &records, delta.as_ref())?;
+        Ok(delta)
+    })?;
+
+    crate::trace_info!(
+        "nexus::datasets",
+        "Dataset snapshot stored",
+        dataset = dataset,
+        records = records.len(),
+        delta = delta.as_ref().map(|d| d.summary())
+    );
+
+    let run = DatasetRun {
+        dataset,
+        taken_at,
+        record_count: records.len(),
+        delta,
+    };
+    if run.delta.as_ref().is_some_and(|d| !d.is_empty()) {
+        if let Some(app) = crate::GLOBAL_APP.get() {
+            let _ = app.emit("dataset-delta", &run);
+        }
+    }
+    Ok(run)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_diff_records_by_key() {
+        let previous = vec![
+            json!({ "sku": "a", "price": "10" }),
+            json!({ "sku": "b", "price": "20" }),
+        ];
+        let current = vec![
+            json!({ "sku": "b", "price": "25" }),
+            json!({ "sku": "c", "price": "30" }),
+        ];
+        let delta = diff_records(&previous, &current, &["sku".to_string()]);
+        assert_eq!(delta.added, vec![json!({ "sku": "c", "price": "30" })]);
+        assert_eq!(delta.removed, vec![json!({ "sku": "a", "price": "10" })]);
+        assert_eq!(delta.changed.len(), 1);
+        assert_eq!(delta.changed[0].after["price"], "25");
+        assert_eq!(delta.summary(), "1 new, 1 removed, 1 changed");
+    }
+
+    #[test]
+    fn test_diff_without_key_treats_edits_as_add_and_remove() {
+        let delta = diff_records(&[json!({ "x": 1 })], &[json!({ "x": 2 })], &[]);
+        assert_eq!(delta.added.len(), 1);
+        assert_eq!(delta.removed.len(), 1);
+        assert!(delta.changed.is_empty());
+    }
+
+    #[test]
+    fn test_snapshots_roundtrip() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+        assert!(latest_snapshot(&conn, "ds").unwrap().is_none());
+
+        store_snapshot(&conn, "ds", 1, &[json!({ "a": 1 })], None).unwrap();
+        let delta = DatasetDelta {
+            added: vec![json!({ "a": 2 })],
+            ..DatasetDelta::default()
+        };
+        store_snapshot(&conn, "ds", 2, &[json!({ "a": 1 }), json!({ "a": 2 })], Some(&delta))
+            .unwrap();
+
+        assert_eq!(latest_snapshot(&conn, "ds").unwrap().unwrap().len(), 2);
+        let runs = history(&conn, "ds", 10).unwrap();
+        assert_eq!(runs.len(), 2);
+        assert_eq!(runs[0].delta, Some(delta));
+        assert_eq!(runs[1].delta, None);
+    }
+}
@@ -0,0 +1,219 @@
+//! Rust-side SQLite storage
+//!
+//! The frontend reads `traces.db` through `tauri-plugin-sql`; backend features
+//! that need durable structured storage share a single `nexus.db` connection
+//! opened here. Schema changes are appended to [`MIGRATIONS`] and applied in
+//! order, tracked through `PRAGMA user_version`.
+
+use rusqlite::Connection;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+/// Global database connection
+pub static GLOBAL_DB: OnceLock<Mutex<Connection>> = OnceLock::new();
+
+/// Ordered schema migrations; the index + 1 is the schema version
+const MIGRATIONS: &[&str] = &[
+    // 1: extraction recipes
+    r#"
+    CREATE TABLE IF NOT EXISTS recipes (
+        name TEXT PRIMARY KEY,
+        domain TEXT NOT NULL,
+        item_selector TEXT,
+        fields TEXT NOT NULL,
+        updated_at INTEGER NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_recipes_domain ON recipes(domain);
+    "#,
+    // 2: run records
+    r#"
+    CREATE TABLE IF NOT EXISTS runs (
+        run_id TEXT PRIMARY KEY,
+        prompt TEXT NOT NULL,
+        provider TEXT NOT NULL,
+        model TEXT NOT NULL,
+        status TEXT NOT NULL,
+        started_at INTEGER NOT NULL,
+        finished_at INTEGER,
+        report TEXT,
+        error TEXT,
+        usage TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_runs_started ON runs(started_at);
+    "#,
+    // 3: dataset snapshots for change-data-capture
+    r#"
+    CREATE TABLE IF NOT EXISTS dataset_snapshots (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        dataset TEXT NOT NULL,
+        taken_at INTEGER NOT NULL,
+        records TEXT NOT NULL,
+        delta TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_dataset_snapshots ON dataset_snapshots(dataset, taken_at);
+    "#,
+    // 4: report versioning
+    r#"
+    ALTER TABLE runs ADD COLUMN series TEXT NOT NULL DEFAULT '';
+    ALTER TABLE runs ADD COLUMN previous_run_id TEXT;
+    CREATE INDEX IF NOT EXISTS idx_runs_series ON runs(series, started_at);
+    "#,
+    // 5: run checkpoints
+    r#"
+    CREATE TABLE IF NOT EXISTS checkpoints (
+        run_id TEXT PRIMARY KEY,
+        saved_at INTEGER NOT NULL,
+        state TEXT NOT NULL
+    );
+    "#,
+    // 6: actions queued for the user
+    r#"
+    CREATE TABLE IF NOT EXISTS human_actions (
+        id TEXT PRIMARY KEY,
+        run_id TEXT,
+        description TEXT NOT NULL,
+        url TEXT,
+        due TEXT,
+        status TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        resolved_at INTEGER,
+        resolution TEXT
+    );
+    CREATE INDEX IF NOT EXISTS idx_human_actions_status ON human_actions(status, created_at);
+    "#,
+    // 7: automatic retries of failed runs
+    r#"
+    ALTER TABLE runs ADD COLUMN retry_of TEXT;
+    "#,
+    // 8: success criteria verdicts
+    r#"
+    ALTER TABLE runs ADD COLUMN validation TEXT;
+    "#,
+    // 9: sources and discoveries for report exports
+    r#"
+    ALTER TABLE runs ADD COLUMN sources TEXT;
+    ALTER TABLE runs ADD COLUMN key_discoveries TEXT;
+    "#,
+    // 10: prompt templates
+    r#"
+    CREATE TABLE IF NOT EXISTS templates (
+        name TEXT PRIMARY KEY,
+        description TEXT,
+        prompt TEXT NOT NULL,
+        success_criteria TEXT,
+        output_preset TEXT,
+        updated_at INTEGER NOT NULL
+    );
+    "#,
+    // 11: saved tasks
+    r#"
+    CREATE TABLE IF NOT EXISTS saved_tasks (
+        id TEXT PRIMARY KEY,
+        name TEXT NOT NULL,
+        prompt TEXT NOT NULL,
+        config_overrides TEXT NOT NULL,
+        created_at INTEGER NOT NULL,
+        last_run_id TEXT
+    );
+    "#,
+    // 12: cron schedules for saved tasks
+    r#"
+    ALTER TABLE saved_tasks ADD COLUMN schedule TEXT;
+    "#,
+    // 13: expired memories
+    r#"
+    CREATE TABLE IF NOT EXISTS memory_archive (
+        id TEXT PRIMARY KEY,
+        content TEXT NOT NULL,
+        summary TEXT,
+        tags TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        expires_at INTEGER,
+        archived_at INTEGER NOT NULL
+    );
+    "#,
+    // 14: where archived memories came from
+    r#"
+    ALTER TABLE memory_archive ADD COLUMN source_url TEXT;
+    ALTER TABLE memory_archive ADD COLUMN run_id TEXT;
+    "#,
+    // 15: flight recorder events
+    r#"
+    CREATE TABLE IF NOT EXISTS traces (
+        id TEXT PRIMARY KEY,
+        session_id TEXT NOT NULL,
+        timestamp INTEGER NOT NULL,
+        level TEXT NOT NULL,
+        target TEXT NOT NULL,
+        span_name TEXT,
+        message TEXT NOT NULL,
+        fields TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_traces_session ON traces(session_id, timestamp);
+    CREATE INDEX IF NOT EXISTS idx_traces_level ON traces(level);
+    "#,
+    // 16: browser actions recorded for replay
+    r#"
+    CREATE TABLE IF NOT EXISTS browser_actions (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        run_id TEXT NOT NULL,
+        recorded_at INTEGER NOT NULL,
+        action TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS idx_browser_actions_run ON browser_actions(run_id, id);
+    "#,
+    // 17: looking up the automatic retry of a run
+    r#"
+    CREATE INDEX IF NOT EXISTS idx_runs_retry_of ON runs(retry_of);
+    "#,
+    // 18: how archived memories were weighted and scoped
+    r#"
+    ALTER TABLE memory_archive ADD COLUMN importance INTEGER;
+    ALTER TABLE memory_archive ADD COLUMN last_accessed INTEGER;
+    ALTER TABLE memory_archive ADD COLUMN scope TEXT;
+    "#,
+];
+
+/// Apply any migrations newer than the database's schema version
+pub fn migrate(conn: &Connection) -> rusqlite::Result<()> {
+    let current: usize = conn.query_row("PRAGMA user_version", [], |row| row.get(0))?;
+    for (idx, sql) in MIGRATIONS.iter().enumerate().skip(current) {
+        conn.execute_batch(sql)?;
+        conn.pragma_update(None, "user_version", idx + 1)?;
+    }
+    Ok(())
+}
+
+/// Open (and migrate) `nexus.db` in the app config dir
+pub fn init_db(config_dir: &Path) -> anyhow::Result<()> {
+    let _ = std::fs::create_dir_all(config_dir);
+    let conn = Connection::open(config_dir.join("nexus.db"))?;
+    migrate(&conn)?;
+    let _ = GLOBAL_DB.set(Mutex::new(conn));
+    Ok(())
+}
+
+/// Run `f` against the global connection
+pub fn with_db<T>(f: impl FnOnce(&Connection) -> rusqlite::Result<T>) -> Result<T, String> {
+    let db = GLOBAL_DB
+        .get()
+        .ok_or_else(|| "Database not initialized".to_string())?;
+    let conn = db.lock().map_err(|_| "Database lock poisoned".to_string())?;
+    f(&conn).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_is_idempotent() {
+        let conn = Connection::open_in_memory().unwrap();
+        migrate(&conn).unwrap();
+        migrate(&conn).unwrap();
+        let version: usize = conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, MIGRATIONS.len());
+    }
+}
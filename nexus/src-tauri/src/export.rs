@@ -0,0 +1,208 @@
+//! Report export to standalone documents
+//!
+//! Renders a stored run's report together with its key discoveries, sources
+//! and the screenshots saved to its workspace into a single Markdown, HTML or
+//! PDF file. Screenshots are embedded as data URIs so the exported file stands
+//! on its own; PDFs are printed from the HTML rendering by the headless browser.
+
+use crate::browser::BrowserManager;
+use crate::run::RunRecord;
+use base64::{engine::general_purpose, Engine as _};
+use pulldown_cmark::{html, Event, Options, Parser};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    Markdown,
+    Html,
+    Pdf,
+}
+
+/// A screenshot from the run's workspace
+pub struct Screenshot {
+    pub name: String,
+    pub png: Vec<u8>,
+}
+
+/// Screenshots saved to the run's workspace, in the order they were taken
+pub fn screenshots(run_id: &str) -> Vec<Screenshot> {
+    let Ok(dir) = crate::workspace::run_dir(run_id) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = fs::read_dir(dir.join("screenshots"))
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "png"))
+        .collect();
+    paths.sort();
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            Some(Screenshot {
+                name: path.file_stem()?.to_string_lossy().to_string(),
+                png: fs::read(&path).ok()?,
+            })
+        })
+        .collect()
+}
+
+/// The full report document as Markdown
+pub fn render_markdown(record: &RunRecord, screenshots: &[Screenshot]) -> Result<String, String> {
+    let report = record
+        .report
+        .as_deref()
+        .ok_or_else(|| format!("Run {} has no report", record.run_id))?;
+    let started = chrono::DateTime::from_timestamp_millis(record.started_at)
+        .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+        .unwrap_or_default();
+
+    let mut doc = format!(
+        "# {}\n\n_Run {} · {} · {} {}_\n\n{}\n",
+        record.prompt.trim(),
+        record.run_id,
+        started,
+        record.provider,
+        record.model,
+        report.trim()
+    );
+    if !record.key_discoveries.is_empty() {
+        doc.push_str("\n## Key Discoveries\n\n");
+        for discovery in &record.key_discoveries {
+            doc.push_str(&format!("- {}\n", discovery));
+        }
+    }
+    if !record.sources.is_empty() {
+        doc.push_str("\n## Sources\n\n");
+        for source in &record.sources {
+            doc.push_str(&format!("- <{}>\n", source));
+        }
+    }
+    if !screenshots.is_empty() {
+        doc.push_str("\n## Screenshots\n\n");
+        for shot in screenshots {
+            doc.push_str(&format!(
+                "![Screenshot {}](data:image/png;base64,{})\n\n",
+                shot.name,
+                general_purpose::STANDARD.encode(&shot.png)
+            ));
+        }
+    }
+    Ok(doc)
+}
+
+/// A standalone HTML page for a Markdown document. Raw HTML in the Markdown,
+/// which comes from crawled pages, is shown as text rather than rendered.
+pub fn render_html(markdown: &str, title: &str) -> String {
+    let mut body = String::new();
+    let parser = Parser::new_ext(
+        markdown,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH | Options::ENABLE_TASKLISTS,
+    )
+    .map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        event => event,
+    });
+    html::push_html(&mut body, parser);
+    let title = title
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta http-equiv="Content-Security-Policy" content="default-src 'none'; img-src data:; style-src 'unsafe-inline'">
+<title>{title}</title>
+<style>
+body {{ font-family: -apple-system, "Segoe UI", Roboto, sans-serif; max-width: 820px; margin: 2rem auto; padding: 0 1rem; line-height: 1.55; color: #1f2328; }}
+table {{ border-collapse: collapse; }}
+th, td {{ border: 1px solid #d0d7de; padding: 4px 8px; }}
+img {{ max-width: 100%; border: 1px solid #d0d7de; }}
+pre {{ background: #f6f8fa; padding: 0.75rem; overflow-x: auto; }}
+</style>
+</head>
+<body>
+{body}</body>
+</html>
+"#
+    )
+}
+
+/// Export a run's report to `path` in `format`
+pub async fn export(
+    record: &RunRecord,
+    format: ExportFormat,
+    path: &Path,
+    browser: &BrowserManager,
+) -> Result<(), String> {
+    let markdown = render_markdown(record, &screenshots(&record.run_id))?;
+    let bytes = match format {
+        ExportFormat::Markdown => markdown.into_bytes(),
+        ExportFormat::Html => render_html(&markdown, &record.prompt).into_bytes(),
+        ExportFormat::Pdf => browser
+            .render_pdf(&render_html(&markdown, &record.prompt))
+            .await
+            .map_err(|e| e.to_string())?,
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, bytes).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::usage::RunUsage;
+
+    #[test]
+    fn test_render_documents() {
+        let record = RunRecord {
+            run_id: "run-1".to_string(),
+            prompt: "Compare <widgets>".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            status: "completed".to_string(),
+            started_at: 0,
+            finished_at: Some(1),
+            report: Some("Widgets cost **$10**.".to_string()),
+            error: None,
+            usage: RunUsage::new("gpt-4o"),
+            series: "compare <widgets>".to_string(),
+            previous_run_id: None,
+            retry_of: None,
+            validation: None,
+            sources: vec!["https://example.com/widgets".to_string()],
+            key_discoveries: vec!["Widgets are cheap".to_string()],
+        };
+        let shots = vec![Screenshot {
+            name: "001".to_string(),
+            png: vec![1, 2, 3],
+        }];
+        let markdown = render_markdown(&record, &shots).unwrap();
+        assert!(markdown.starts_with("# Compare <widgets>\n\n_Run run-1 · 1970-01-01 00:00 UTC"));
+        assert!(markdown.contains("## Key Discoveries\n\n- Widgets are cheap\n"));
+        assert!(markdown.contains("- <https://example.com/widgets>\n"));
+        assert!(markdown.contains("(data:image/png;base64,AQID)"));
+
+        let html = render_html(&markdown, &record.prompt);
+        assert!(html.contains("<title>Compare &lt;widgets&gt;</title>"));
+        assert!(html.contains("<strong>$10</strong>"));
+
+        let html = render_html("Hi <script>alert(1)</script>\n\n<iframe src=x>", "t");
+        assert!(!html.contains("<script>") && !html.contains("<iframe"));
+        assert!(html.contains("&lt;script&gt;alert(1)&lt;/script&gt;"));
+
+        let unfinished = RunRecord {
+            report: None,
+            ..record
+        };
+        assert!(render_markdown(&unfinished, &[]).is_err());
+    }
+}
@@ -0,0 +1,148 @@
+//! Run-scoped URL frontier
+//!
+//! Tracks which URLs a run (and its sub-agents) has visited and how many
+//! distinct pages were fetched per domain, so per-domain page caps are
+//! respected. Going back to a page already visited is allowed and doesn't
+//! count against the cap again.
+
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Outcome of asking the frontier whether a URL may be fetched
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Admission {
+    /// First visit; the URL is now marked visited
+    Admitted,
+    /// The URL was visited before; fetching it again is free
+    Revisit,
+    DomainCapReached { domain: String, cap: usize },
+}
+
+/// Serializable view of the frontier for the UI
+#[derive(Debug, Clone, Serialize)]
+pub struct FrontierSnapshot {
+    pub visited: Vec<String>,
+    pub per_domain: HashMap<String, usize>,
+}
+
+#[derive(Debug, Default)]
+pub struct UrlFrontier {
+    max_pages_per_domain: Option<usize>,
+    visited: HashSet<String>,
+    visit_order: Vec<String>,
+    per_domain: HashMap<String, usize>,
+}
+
+/// Canonical form used for deduplication: lowercase scheme and host, no
+/// fragment, no trailing slash on the path
+pub fn normalize_url(url: &str) -> String {
+    let url = url.trim();
+    let url = url.split('#').next().unwrap_or(url);
+    let (scheme, rest) = match url.split_once("://") {
+        Some((scheme, rest)) => (scheme.to_lowercase(), rest),
+        None => ("https".to_string(), url),
+    };
+    let (authority, path) = match rest.find(['/', '?']) {
+        Some(idx) => rest.split_at(idx),
+        None => (rest, ""),
+    };
+    let authority = authority.to_lowercase();
+    let authority = authority
+        .strip_suffix(":443")
+        .filter(|_| scheme == "https")
+        .or_else(|| authority.strip_suffix(":80").filter(|_| scheme == "http"))
+        .unwrap_or(&authority);
+    let path = match path.split_once('?') {
+        Some((p, q)) => format!("{}?{}", p.trim_end_matches('/'), q),
+        None => path.trim_end_matches('/').to_string(),
+    };
+    format!("{}://{}{}", scheme, authority, path)
+}
+
+impl UrlFrontier {
+    pub fn new(max_pages_per_domain: Option<usize>) -> Self {
+        Self {
+            max_pages_per_domain,
+            ..Self::default()
+        }
+    }
+
+    pub fn is_visited(&self, url: &str) -> bool {
+        self.visited.contains(&normalize_url(url))
+    }
+
+    /// Check a URL before fetching it, marking it visited when admitted
+    pub fn admit(&mut self, url: &str) -> Admission {
+        let key = normalize_url(url);
+        if self.visited.contains(&key) {
+            return Admission::Revisit;
+        }
+        let domain = crate::approval::host_of(&key).unwrap_or_default();
+        let count = self.per_domain.get(&domain).copied().unwrap_or(0);
+        if let Some(cap) = self.max_pages_per_domain {
+            if count >= cap {
+                return Admission::DomainCapReached { domain, cap };
+            }
+        }
+        self.per_domain.insert(domain, count + 1);
+        self.visited.insert(key.clone());
+        self.visit_order.push(key);
+        Admission::Admitted
+    }
+
+    /// Undo an admission whose fetch failed, so the URL can be retried
+    pub fn release(&mut self, url: &str) {
+        let key = normalize_url(url);
+        if self.visited.remove(&key) {
+            self.visit_order.retain(|u| u != &key);
+            let domain = crate::approval::host_of(&key).unwrap_or_default();
+            if let Some(count) = self.per_domain.get_mut(&domain) {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+
+    pub fn snapshot(&self) -> FrontierSnapshot {
+        FrontierSnapshot {
+            visited: self.visit_order.clone(),
+            per_domain: self.per_domain.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_url() {
+        assert_eq!(
+            normalize_url("HTTPS://Example.com:443/a/#frag"),
+            "https://example.com/a"
+        );
+        assert_eq!(normalize_url("example.com"), "https://example.com");
+        assert_eq!(
+            normalize_url("http://x.org/p/?q=1"),
+            "http://x.org/p?q=1"
+        );
+    }
+
+    #[test]
+    fn test_admit_dedupes_and_caps_domains() {
+        let mut frontier = UrlFrontier::new(Some(2));
+        assert_eq!(frontier.admit("https://a.com/1"), Admission::Admitted);
+        assert_eq!(frontier.admit("https://a.com/1/"), Admission::Revisit);
+        assert_eq!(frontier.admit("https://a.com/2"), Admission::Admitted);
+        assert_eq!(
+            frontier.admit("https://a.com/3"),
+            Admission::DomainCapReached {
+                domain: "a.com".to_string(),
+                cap: 2
+            }
+        );
+        // Going back to a visited page is allowed even at the cap
+        assert_eq!(frontier.admit("https://a.com/2"), Admission::Revisit);
+        frontier.release("https://a.com/2");
+        assert_eq!(frontier.admit("https://a.com/3"), Admission::Admitted);
+    }
+}
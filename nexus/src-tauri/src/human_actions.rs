@@ -0,0 +1,160 @@
+//! To-do list of actions the agent needs a human for
+//!
+//! When the agent hits something it can't do itself (solve a captcha, call a
+//! vendor, log in with 2FA) it records a task through the
+//! `request_human_action` tool instead of silently giving up. Tasks are stored
+//! in the `human_actions` table and reviewed with the `list_pending_actions`
+//! and `resolve_action` commands.
+
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A task queued for the user
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct HumanAction {
+    pub id: String,
+    /// Run that queued the task, if any
+    pub run_id: Option<String>,
+    pub description: String,
+    pub url: Option<String>,
+    /// Free-form due date as given by the agent (e.g. "2024-06-01", "before Friday")
+    pub due: Option<String>,
+    /// "pending", "done" or "dismissed"
+    pub status: String,
+    pub created_at: i64,
+    pub resolved_at: Option<i64>,
+    /// What the user did or found out
+    pub resolution: Option<String>,
+}
+
+impl HumanAction {
+    pub fn new(
+        run_id: Option<String>,
+        description: String,
+        url: Option<String>,
+        due: Option<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            run_id,
+            description,
+            url,
+            due,
+            status: "pending".to_string(),
+            created_at: chrono::Utc::now().timestamp_millis(),
+            resolved_at: None,
+            resolution: None,
+        }
+    }
+}
+
+const ACTION_COLUMNS: &str =
+    "id, run_id, description, url, due, status, created_at, resolved_at, resolution";
+
+fn action_from_row(row: &Row<'_>) -> rusqlite::Result<HumanAction> {
+    Ok(HumanAction {
+        id: row.get(0)?,
+        run_id: row.get(1)?,
+        description: row.get(2)?,
+        url: row.get(3)?,
+        due: row.get(4)?,
+        status: row.get(5)?,
+        created_at: row.get(6)?,
+        resolved_at: row.get(7)?,
+        resolution: row.get(8)?,
+    })
+}
+
+pub fn insert(conn: &Connection, action: &HumanAction) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO human_actions ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+            ACTION_COLUMNS
+        ),
+        params![
+            action.id,
+            action.run_id,
+            action.description,
+            action.url,
+            action.due,
+            action.status,
+            action.created_at,
+            action.resolved_at,
+            action.resolution
+        ],
+    )?;
+    Ok(())
+}
+
+/// Pending tasks, oldest first
+pub fn list_pending(conn: &Connection) -> rusqlite::Result<Vec<HumanAction>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM human_actions WHERE status = 'pending' ORDER BY created_at ASC",
+        ACTION_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], action_from_row)?;
+    rows.collect()
+}
+
+/// Mark a pending task done (or dismissed), returning the updated task
+pub fn resolve(
+    conn: &Connection,
+    id: &str,
+    dismissed: bool,
+    resolution: Option<&str>,
+) -> rusqlite::Result<Option<HumanAction>> {
+    let status = if dismissed { "dismissed" } else { "done" };
+    conn.execute(
+        "UPDATE human_actions SET status = ?2, resolved_at = ?3, resolution = ?4
+         WHERE id = ?1 AND status = 'pending'",
+        params![
+            id,
+            status,
+            chrono::Utc::now().timestamp_millis(),
+            resolution
+        ],
+    )?;
+    conn.query_row(
+        &format!("SELECT {} FROM human_actions WHERE id = ?1", ACTION_COLUMNS),
+        params![id],
+        action_from_row,
+    )
+    .optional()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_action_lifecycle() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+
+        let captcha = HumanAction::new(
+            Some("run-1".to_string()),
+            "Solve the captcha".to_string(),
+            Some("https://example.com/login".to_string()),
+            None,
+        );
+        let call = HumanAction::new(
+            None,
+            "Call the vendor".to_string(),
+            None,
+            Some("Friday".to_string()),
+        );
+        insert(&conn, &captcha).unwrap();
+        insert(&conn, &call).unwrap();
+        assert_eq!(list_pending(&conn).unwrap().len(), 2);
+
+        let resolved = resolve(&conn, &captcha.id, false, Some("Solved"))
+            .unwrap()
+            .unwrap();
+        assert_eq!(resolved.status, "done");
+        assert_eq!(resolved.resolution.as_deref(), Some("Solved"));
+        assert_eq!(list_pending(&conn).unwrap(), vec![call]);
+
+        assert!(resolve(&conn, "missing", true, None).unwrap().is_none());
+    }
+}
@@ -0,0 +1,187 @@
+//! Content language detection and filtering
+//!
+//! Uses `whatlang` trigram detection, which is fast enough to run on every
+//! fetched page and search snippet. Text is only rejected when detection is
+//! reliable, so short or mixed snippets are never dropped by mistake.
+//!
+//! Search queries can also be expanded into localized variants (e.g. German
+//! and French queries for EU-market research) so in-page searches match
+//! content written in other languages.
+
+use crate::config::Config;
+use radkit::agent::LlmFunction;
+use radkit::macros::LLMOutput;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use whatlang::{detect, Lang};
+
+/// Localized variants already generated, keyed by languages and query
+static VARIANT_CACHE: OnceLock<Mutex<HashMap<String, Vec<String>>>> = OnceLock::new();
+
+/// ISO 639-1 codes for the languages users are most likely to configure
+const ISO_639_1: &[(&str, Lang)] = &[
+    ("en", Lang::Eng),
+    ("de", Lang::Deu),
+    ("fr", Lang::Fra),
+    ("es", Lang::Spa),
+    ("it", Lang::Ita),
+    ("pt", Lang::Por),
+    ("nl", Lang::Nld),
+    ("sv", Lang::Swe),
+    ("da", Lang::Dan),
+    ("fi", Lang::Fin),
+    ("pl", Lang::Pol),
+    ("cs", Lang::Ces),
+    ("ru", Lang::Rus),
+    ("uk", Lang::Ukr),
+    ("tr", Lang::Tur),
+    ("ar", Lang::Ara),
+    ("he", Lang::Heb),
+    ("hi", Lang::Hin),
+    ("ja", Lang::Jpn),
+    ("ko", Lang::Kor),
+    ("zh", Lang::Cmn),
+];
+
+/// Resolve a configured language (ISO 639-1, ISO 639-3 or English name)
+pub fn parse_lang(code: &str) -> Option<Lang> {
+    let code = code.trim().to_lowercase();
+    ISO_639_1
+        .iter()
+        .find(|(iso, _)| *iso == code)
+        .map(|(_, lang)| *lang)
+        .or_else(|| Lang::from_code(&code))
+        .or_else(|| Lang::all().iter().copied().find(|l| l.eng_name().to_lowercase() == code))
+}
+
+/// Detected language of `text`, only when detection is reliable
+pub fn detect_reliable(text: &str) -> Option<Lang> {
+    detect(text).filter(|info| info.is_reliable()).map(|info| info.lang())
+}
+
+/// Whether `text` may be kept given the allowed languages. An empty allow-list
+/// or an unreliable detection always keeps the text.
+pub fn is_allowed(text: &str, allowed: &[String]) -> bool {
+    if allowed.is_empty() {
+        return true;
+    }
+    match detect_reliable(text) {
+        Some(lang) => allowed.iter().filter_map(|c| parse_lang(c)).any(|l| l == lang),
+        None => true,
+    }
+}
+
+// ============================================================================
+// Query Expansion
+// ============================================================================
+
+/// Translations of a search query produced by the LLM
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct LocalizedQueries {
+    /// One entry per requested language, in the same order.
+    pub variants: Vec<LocalizedQuery>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LocalizedQuery {
+    /// ISO 639-1 code of the language.
+    pub language: String,
+    /// The query as a native speaker would phrase it (plain text, not a regex).
+    pub query: String,
+}
+
+/// Localized variants of `query` for `languages`, excluding the original.
+/// Results are cached so repeated searches don't cost extra LLM calls.
+pub async fn expand_query(
+    config: &Config,
+    query: &str,
+    languages: &[String],
+) -> Result<Vec<String>, String> {
+    if languages.is_empty() {
+        return Ok(Vec::new());
+    }
+    let key = format!("{}\u{0}{}", languages.join(","), query);
+    let cache = VARIANT_CACHE.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Some(variants) = cache.lock().ok().and_then(|c| c.get(&key).cloned()) {
+        return Ok(variants);
+    }
+
+    let llm = crate::providers::build_llm(config)?;
+    let function = LlmFunction::<LocalizedQueries>::new_with_system_instructions(
+        llm,
+        "You translate search queries for market research. Use the terms people in each \
+         locale actually search for, including local product names and spellings, rather \
+         than word-for-word translations.",
+    );
+    let localized = function
+        .run(format!(
+            "Query: {}\nLanguages: {}",
+            query,
+            languages.join(", ")
+        ))
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let variants = merge_variants(query, localized.variants.into_iter().map(|v| v.query));
+    crate::trace_debug!(
+        "nexus::language",
+        "Query expanded",
+        query = query,
+        variants = variants.clone()
+    );
+    if let Ok(mut cache) = cache.lock() {
+        cache.insert(key, variants.clone());
+    }
+    Ok(variants)
+}
+
+/// Deduplicate variants (case-insensitively) and drop the original query
+fn merge_variants(query: &str, variants: impl Iterator<Item = String>) -> Vec<String> {
+    let mut seen = vec![query.trim().to_lowercase()];
+    let mut merged = Vec::new();
+    for variant in variants {
+        let variant = variant.trim().to_string();
+        let lower = variant.to_lowercase();
+        if variant.is_empty() || seen.contains(&lower) {
+            continue;
+        }
+        seen.push(lower);
+        merged.push(variant);
+    }
+    merged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lang() {
+        assert_eq!(parse_lang("de"), Some(Lang::Deu));
+        assert_eq!(parse_lang("fra"), Some(Lang::Fra));
+        assert_eq!(parse_lang("English"), Some(Lang::Eng));
+        assert_eq!(parse_lang("klingon"), None);
+    }
+
+    #[test]
+    fn test_is_allowed() {
+        let english = "The quick brown fox jumps over the lazy dog and keeps running through the forest until night falls.";
+        let german = "Der schnelle braune Fuchs springt über den faulen Hund und läuft weiter durch den Wald, bis die Nacht hereinbricht.";
+        let allowed = vec!["en".to_string()];
+        assert!(is_allowed(english, &allowed));
+        assert!(!is_allowed(german, &allowed));
+        assert!(is_allowed(german, &[]));
+        assert!(is_allowed("ok", &allowed));
+    }
+
+    #[test]
+    fn test_merge_variants() {
+        let variants = ["Preise", "prix", "PRICES", "preise", " "].map(String::from);
+        assert_eq!(
+            merge_variants("prices", variants.into_iter()),
+            vec!["Preise", "prix"]
+        );
+    }
+}
@@ -1,18 +1,98 @@
 pub mod agent;
+pub mod api;
+pub mod approval;
+pub mod bookmarks;
 pub mod browser;
+pub mod bundle;
+pub mod chat;
+pub mod checkpoint;
 pub mod commands;
+pub mod condense;
 pub mod config;
+pub mod config_watch;
+pub mod criteria;
+pub mod datasets;
+pub mod db;
+pub mod export;
+pub mod frontier;
+pub mod human_actions;
+pub mod language;
+pub mod mcp;
+pub mod mcp_client;
 pub mod memory;
+pub mod metrics;
+pub mod models;
+pub mod notifications;
+pub mod otlp;
+pub mod pagediff;
+pub mod planning;
+pub mod plugins;
+pub mod prompts;
+pub mod providers;
+pub mod queue;
+pub mod ratelimit;
+pub mod recipes;
+pub mod redact;
+pub mod replay;
+pub mod reports;
+pub mod retry;
+pub mod run;
+pub mod sandbox;
+pub mod scheduler;
 pub mod search;
+pub mod secrets;
+pub mod shapes;
+pub mod speech;
+pub mod structured;
+pub mod tasks;
+pub mod templates;
+pub mod tracelog;
 pub mod tracing;
+pub mod usage;
+pub mod workspace;
 
 use browser::BrowserManager;
-use config::ConfigManager;
+use config::{Config, ConfigManager};
+use std::path::{Path, PathBuf};
 use std::sync::{Mutex, OnceLock};
 use tauri::Manager;
 
 pub static GLOBAL_APP: OnceLock<tauri::AppHandle> = OnceLock::new();
 
+/// Set up the stores shared by the desktop app and the CLI, rooted at the
+/// app's config and data directories, and load the config
+pub fn init_services(config_dir: &Path, data_dir: &Path) -> (ConfigManager, Config) {
+    memory::init_memory();
+    crate::trace_debug!("nexus::init", "Memory system initialized");
+
+    plugins::init_plugins(config_dir);
+    crate::trace_debug!("nexus::init", "Plugin registry initialized");
+
+    if let Err(e) = db::init_db(config_dir) {
+        crate::trace_error!(
+            "nexus::init",
+            "Database initialization failed",
+            error = e.to_string()
+        );
+    }
+
+    let config_manager = ConfigManager::new(config_dir);
+    let config = config_manager.load();
+
+    workspace::init_workspaces(config_dir);
+    prompts::init_prompt_pack(config_dir);
+    workspace::enforce_retention(&config);
+    tracing::set_buffer_size(config.trace_buffer_size);
+    tracing::set_stream_interval(config.trace_stream_interval_ms);
+    tracelog::init_trace_logs(data_dir);
+    tracelog::configure(&config);
+    otlp::configure(&config);
+    redact::configure(&config);
+    crate::trace_debug!("nexus::init", "Config manager initialized");
+
+    (config_manager, config)
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     // Initialize tracing first - before anything else
@@ -20,6 +100,7 @@ pub fn run() {
 
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
+        .plugin(tauri_plugin_notification::init())
         .plugin(
             tauri_plugin_log::Builder::new()
                 .level(log::LevelFilter::Debug)
@@ -35,50 +116,146 @@ pub fn run() {
 
             crate::trace_info!("nexus::init", "Nexus application starting");
 
-            memory::init_memory();
-            crate::trace_debug!("nexus::init", "Memory system initialized");
+            approval::init_approvals();
 
-            let config_manager = ConfigManager::new(app.handle());
+            let path = app.path();
+            let config_dir = path.app_config_dir().unwrap_or_else(|_| PathBuf::from("."));
+            let data_dir = path.app_data_dir().unwrap_or_else(|_| PathBuf::from("."));
+            let (config_manager, config) = init_services(&config_dir, &data_dir);
             app.manage(Mutex::new(config_manager));
-            crate::trace_debug!("nexus::init", "Config manager initialized");
-
-            let browser =
-                match tauri::async_runtime::block_on(async { BrowserManager::new().await }) {
-                    Ok(b) => {
-                        crate::trace_info!("nexus::init", "Browser launched successfully");
-                        b
-                    }
-                    Err(e) => {
-                        crate::trace_error!(
-                            "nexus::init",
-                            "Browser launch failed",
-                            error = e.to_string()
-                        );
-                        return Err(e.into());
-                    }
-                };
+            // Only the app does this: a CLI run starting alongside it would
+            // otherwise flag the app's live runs
+            if let Ok(interrupted) = db::with_db(crate::run::mark_interrupted) {
+                if interrupted > 0 {
+                    crate::trace_info!(
+                        "nexus::init",
+                        "Found interrupted runs",
+                        count = interrupted
+                    );
+                }
+            }
+
+            let browser = match tauri::async_runtime::block_on(async {
+                BrowserManager::new(&config).await
+            }) {
+                Ok(b) => {
+                    crate::trace_info!("nexus::init", "Browser launched successfully");
+                    b
+                }
+                Err(e) => {
+                    crate::trace_error!(
+                        "nexus::init",
+                        "Browser launch failed",
+                        error = e.to_string()
+                    );
+                    return Err(e.into());
+                }
+            };
 
             // Set global instance for agent tools
             let _ = browser::GLOBAL_BROWSER.set(browser.clone());
 
             app.manage(browser);
 
+            scheduler::start_scheduler();
+            memory::start_expiry_sweep();
+            tracing::start_trace_writer();
+            tracing::start_trace_stream();
+            config_watch::start_config_watcher(app.handle());
+            api::configure(&config);
+            mcp::configure(&config);
+
             crate::trace_info!("nexus::init", "Nexus initialization complete");
             Ok(())
         })
         .invoke_handler(tauri::generate_handler![
             commands::fetch_and_search,
+            commands::search_everything,
             commands::run_agent,
+            commands::resume_run,
+            commands::transcribe_audio,
+            commands::speak_text,
+            commands::start_chat,
+            commands::send_message,
+            commands::end_chat,
             commands::get_memories,
+            commands::get_memories_by_tag,
+            commands::list_memory_tags,
+            commands::update_memory,
+            commands::list_archived_memories,
+            commands::delete_memory,
             commands::clear_memories,
             commands::take_screenshot,
             commands::get_current_url,
+            commands::navigate,
+            commands::manual_click,
+            commands::manual_type,
+            commands::manual_scroll,
+            commands::get_page_markdown,
+            commands::get_page_html,
+            commands::stop_browser,
+            commands::restart_browser,
             commands::get_config,
             commands::save_config,
+            commands::validate_config,
+            commands::list_profiles,
+            commands::save_profile,
+            commands::activate_profile,
+            commands::delete_profile,
             commands::reset_session,
             commands::get_traces,
             commands::clear_traces,
-            commands::get_trace_count
+            commands::get_trace_count,
+            commands::get_trace_stats,
+            commands::set_trace_stream_level,
+            commands::list_trace_sessions,
+            commands::get_session_traces,
+            commands::export_traces,
+            commands::export_session,
+            commands::search_traces,
+            commands::get_run_metrics,
+            commands::start_replay,
+            commands::get_replay_state,
+            commands::replay_step,
+            commands::replay_continue,
+            commands::stop_replay,
+            commands::run_dataset,
+            commands::get_dataset_history,
+            commands::get_agent_status,
+            commands::get_run_result,
+            commands::get_run_cost,
+            commands::pause_run,
+            commands::unpause_run,
+            commands::get_frontier,
+            commands::get_run_versions,
+            commands::diff_reports,
+            commands::export_report,
+            commands::approve_action,
+            commands::approve_plan,
+            commands::list_pending_actions,
+            commands::resolve_action,
+            commands::get_artifact_usage,
+            commands::purge_artifacts,
+            commands::get_prompt_pack,
+            commands::list_tools,
+            commands::save_prompt_pack,
+            commands::save_recipe,
+            commands::list_recipes,
+            commands::learn_recipe,
+            commands::delete_recipe,
+            commands::extract_structured,
+            commands::list_templates,
+            commands::save_template,
+            commands::delete_template,
+            commands::run_template,
+            commands::list_saved_tasks,
+            commands::save_task,
+            commands::delete_saved_task,
+            commands::run_saved_task,
+            commands::preview_schedule,
+            commands::get_queue,
+            commands::list_models,
+            commands::test_provider_connection
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
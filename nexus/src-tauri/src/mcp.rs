@@ -0,0 +1,433 @@
+//! MCP server
+//!
+//! Publishes the managed browser to Model Context Protocol clients such as
+//! Claude Desktop as `navigate`, `click`, `type` and `extract` tools. The
+//! JSON-RPC messages are served over stdio by `nexus-cli --mcp`, or over SSE
+//! on the local HTTP API when `mcp_server` is enabled: clients open
+//! `GET /mcp/sse`, which announces the `POST /mcp/messages?session_id=<id>`
+//! endpoint to send requests to, and receive the responses as SSE messages.
+//!
+//! Each client drives its own incognito browser context, one tool call at a
+//! time, so it can't disturb running research or other clients. Tool calls
+//! aren't metered against any run, so long pages are truncated rather than
+//! condensed by the LLM.
+
+use crate::browser::BrowserManager;
+use crate::config::{Config, ConfigManager};
+use axum::extract::Query;
+use axum::http::StatusCode;
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use futures::StreamExt;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tauri::Manager;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::sync::mpsc;
+
+/// MCP revision implemented, offered to clients during `initialize`
+pub const PROTOCOL_VERSION: &str = "2024-11-05";
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+
+/// Whether the SSE endpoints answer; they are always routed on the local API
+static SSE_ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Open SSE sessions, by id
+static SESSIONS: Mutex<BTreeMap<String, McpSession>> = Mutex::new(BTreeMap::new());
+
+/// An SSE client's channel for responses and the browser context its tool
+/// calls take turns on
+struct McpSession {
+    replies: mpsc::UnboundedSender<Value>,
+    browser: Arc<tokio::sync::Mutex<BrowserManager>>,
+}
+
+// --- Tool Arguments ---
+
+#[derive(Deserialize, JsonSchema)]
+struct NavigateArgs {
+    /// The URL to open.
+    url: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ClickArgs {
+    /// CSS selector of the element to click.
+    selector: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct TypeArgs {
+    /// CSS selector of the input to type into.
+    selector: String,
+    /// The text to type.
+    text: String,
+}
+
+#[derive(Deserialize, JsonSchema)]
+struct ExtractArgs {
+    /// CSS selector to extract; the whole page when omitted.
+    selector: Option<String>,
+    /// Maximum characters of content to return (defaults to the configured limit).
+    max_chars: Option<usize>,
+}
+
+/// Serve the SSE endpoints per `config`
+pub fn configure(config: &Config) {
+    SSE_ENABLED.store(config.mcp_server, Ordering::Relaxed);
+}
+
+/// The tools as `tools/list` describes them
+pub fn tools() -> Vec<Value> {
+    vec![
+        tool(
+            "navigate",
+            "Open a URL in the browser and return the page as Markdown.",
+            schemars::schema_for!(NavigateArgs),
+        ),
+        tool(
+            "click",
+            "Click an element on the current page and return the resulting page as Markdown.",
+            schemars::schema_for!(ClickArgs),
+        ),
+        tool(
+            "type",
+            "Type text into an input on the current page.",
+            schemars::schema_for!(TypeArgs),
+        ),
+        tool(
+            "extract",
+            "Return the current page, or the elements matching a CSS selector, as Markdown.",
+            schemars::schema_for!(ExtractArgs),
+        ),
+    ]
+}
+
+fn tool(name: &str, description: &str, schema: schemars::Schema) -> Value {
+    json!({ "name": name, "description": description, "inputSchema": schema })
+}
+
+/// Answer one JSON-RPC message, driving `browser` for tool calls; `None` for
+/// notifications and responses, which get no reply
+pub async fn handle(
+    message: Value,
+    config: &Config,
+    browser: Option<&BrowserManager>,
+) -> Option<Value> {
+    let id = message.get("id")?.clone();
+    let method = message.get("method")?.as_str().unwrap_or_default();
+    let params = message.get("params").cloned().unwrap_or(Value::Null);
+    let result = match method {
+        "initialize" => json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": { "tools": {} },
+            "serverInfo": { "name": "nexus", "version": env!("CARGO_PKG_VERSION") },
+        }),
+        "ping" => json!({}),
+        "tools/list" => json!({ "tools": tools() }),
+        "tools/call" => call_tool(&params, config, browser).await,
+        _ => {
+            return Some(error_response(
+                id,
+                METHOD_NOT_FOUND,
+                format!("Method not found: {}", method),
+            ))
+        }
+    };
+    Some(json!({ "jsonrpc": "2.0", "id": id, "result": result }))
+}
+
+fn error_response(id: Value, code: i64, message: String) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// Run a `tools/call` request; tool failures are results flagged `isError`
+/// so the client's model can see them
+async fn call_tool(params: &Value, config: &Config, browser: Option<&BrowserManager>) -> Value {
+    let name = params
+        .get("name")
+        .and_then(Value::as_str)
+        .unwrap_or_default();
+    let args = params
+        .get("arguments")
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    crate::trace_info!("nexus::mcp", "Tool called", tool = name);
+    let config = Config {
+        condense_long_pages: false,
+        ..config.clone()
+    };
+    let result = match browser {
+        Some(browser) => run_tool(name, args, &config, browser).await,
+        None => Err("Browser not initialized".to_string()),
+    };
+    let (text, is_error) = match result {
+        Ok(text) => (text, false),
+        Err(e) => {
+            crate::trace_warn!("nexus::mcp", "Tool failed", tool = name, error = e);
+            (e, true)
+        }
+    };
+    json!({ "content": [{ "type": "text", "text": text }], "isError": is_error })
+}
+
+async fn run_tool(
+    name: &str,
+    args: Value,
+    config: &Config,
+    browser: &BrowserManager,
+) -> Result<String, String> {
+    match name {
+        "navigate" => {
+            let args: NavigateArgs = parse(args)?;
+            if crate::approval::url_matches_domains(&args.url, &config.sensitive_domains) {
+                crate::approval::require_approval("navigate", json!({ "url": args.url })).await?;
+            }
+            let html = browser
+                .navigate_and_get_content(&args.url)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(crate::agent::page_content(html, None, config).await.content)
+        }
+        "click" => {
+            let args: ClickArgs = parse(args)?;
+            browser
+                .click_element(&args.selector)
+                .await
+                .map_err(|e| e.to_string())?;
+            let html = browser.get_content().await.map_err(|e| e.to_string())?;
+            Ok(crate::agent::page_content(html, None, config).await.content)
+        }
+        "type" => {
+            let args: TypeArgs = parse(args)?;
+            browser
+                .focus_element(&args.selector)
+                .await
+                .map_err(|e| e.to_string())?;
+            if browser.is_password_focused().await.unwrap_or(false) {
+                crate::redact::register_secret(&args.text);
+            }
+            browser
+                .type_text(&args.text)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(format!("Typed into {}", args.selector))
+        }
+        "extract" => {
+            let args: ExtractArgs = parse(args)?;
+            let html = match args.selector.filter(|s| !s.trim().is_empty()) {
+                Some(selector) => {
+                    let subtrees = browser
+                        .outer_html(&selector)
+                        .await
+                        .map_err(|e| format!("Selector '{}' failed: {}", selector, e))?;
+                    if subtrees.is_empty() {
+                        return Err(format!("No element matches '{}'", selector));
+                    }
+                    subtrees.join("\n")
+                }
+                None => browser.get_content().await.map_err(|e| e.to_string())?,
+            };
+            Ok(crate::agent::page_content(html, args.max_chars, config)
+                .await
+                .content)
+        }
+        _ => Err(format!("Unknown tool: {}", name)),
+    }
+}
+
+fn parse<T: DeserializeOwned>(args: Value) -> Result<T, String> {
+    serde_json::from_value(args).map_err(|e| format!("Invalid arguments: {}", e))
+}
+
+/// An incognito context on the shared browser for one client
+async fn client_browser() -> Result<BrowserManager, String> {
+    crate::browser::GLOBAL_BROWSER
+        .get()
+        .ok_or_else(|| "Browser not initialized".to_string())?
+        .isolated()
+        .await
+        .map_err(|e| format!("Could not open a browser context: {}", e))
+}
+
+/// Serve newline-delimited JSON-RPC on stdin and stdout until stdin closes
+pub async fn serve_stdio(config: Config) -> Result<(), String> {
+    let browser = client_browser().await?;
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    let mut stdout = tokio::io::stdout();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let reply = match serde_json::from_str::<Value>(&line) {
+            Ok(message) => handle(message, &config, Some(&browser)).await,
+            Err(e) => Some(error_response(Value::Null, PARSE_ERROR, e.to_string())),
+        };
+        if let Some(reply) = reply {
+            let mut out = reply.to_string();
+            out.push('\n');
+            if stdout.write_all(out.as_bytes()).await.is_err() || stdout.flush().await.is_err() {
+                break;
+            }
+        }
+    }
+    let _ = browser.close().await;
+    Ok(())
+}
+
+/// The SSE endpoints, mounted on the local HTTP API
+pub fn routes() -> Router {
+    Router::new()
+        .route("/mcp/sse", get(open_session))
+        .route("/mcp/messages", post(post_message))
+}
+
+/// Removes its session, and closes its browser context, once the client's
+/// event stream is dropped
+struct Session(String);
+
+impl Drop for Session {
+    fn drop(&mut self) {
+        let Some(session) = SESSIONS
+            .lock()
+            .ok()
+            .and_then(|mut sessions| sessions.remove(&self.0))
+        else {
+            return;
+        };
+        tauri::async_runtime::spawn(async move {
+            let _ = session.browser.lock().await.close().await;
+        });
+    }
+}
+
+async fn open_session() -> Response {
+    if !SSE_ENABLED.load(Ordering::Relaxed) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let browser = match client_browser().await {
+        Ok(browser) => browser,
+        Err(e) => return (StatusCode::SERVICE_UNAVAILABLE, e).into_response(),
+    };
+    let id = uuid::Uuid::new_v4().to_string();
+    let (tx, rx) = mpsc::unbounded_channel();
+    SESSIONS.lock().unwrap().insert(
+        id.clone(),
+        McpSession {
+            replies: tx,
+            browser: Arc::new(tokio::sync::Mutex::new(browser)),
+        },
+    );
+    crate::trace_info!("nexus::mcp", "MCP client connected", session_id = id);
+
+    let endpoint = Event::default()
+        .event("endpoint")
+        .data(format!("/mcp/messages?session_id={}", id));
+    let replies = futures::stream::unfold((rx, Session(id)), |(mut rx, session)| async move {
+        let reply = rx.recv().await?;
+        let event = Event::default().event("message").data(reply.to_string());
+        Some((Ok::<_, Infallible>(event), (rx, session)))
+    });
+    let events = futures::stream::once(async { Ok(endpoint) }).chain(replies);
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+#[derive(Deserialize)]
+struct SessionQuery {
+    session_id: String,
+}
+
+async fn post_message(Query(query): Query<SessionQuery>, Json(message): Json<Value>) -> Response {
+    if !SSE_ENABLED.load(Ordering::Relaxed) {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    let Some((sender, browser)) = SESSIONS
+        .lock()
+        .unwrap()
+        .get(&query.session_id)
+        .map(|session| (session.replies.clone(), session.browser.clone()))
+    else {
+        return (StatusCode::NOT_FOUND, "No such MCP session").into_response();
+    };
+    let Some(app) = crate::GLOBAL_APP.get() else {
+        return (StatusCode::SERVICE_UNAVAILABLE, "App not initialized").into_response();
+    };
+    let config = app.state::<Mutex<ConfigManager>>().lock().unwrap().load();
+    // Replies go out on the session's event stream, so tool calls don't hold
+    // the request open
+    tauri::async_runtime::spawn(async move {
+        let browser = browser.lock().await;
+        if let Some(reply) = handle(message, &config, Some(&browser)).await {
+            let _ = sender.send(reply);
+        }
+    });
+    StatusCode::ACCEPTED.into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_handle() {
+        let config = Config::default();
+        let reply = handle(
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} }),
+            &config,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply["result"]["protocolVersion"], PROTOCOL_VERSION);
+
+        let reply = handle(
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" }),
+            &config,
+            None,
+        )
+        .await
+        .unwrap();
+        let names: Vec<&str> = reply["result"]["tools"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|tool| tool["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(names, ["navigate", "click", "type", "extract"]);
+
+        let reply = handle(
+            json!({ "jsonrpc": "2.0", "id": 3, "method": "bogus" }),
+            &config,
+            None,
+        )
+        .await
+        .unwrap();
+        assert_eq!(reply["error"]["code"], METHOD_NOT_FOUND);
+
+        // Tool failures are results the client's model can read
+        let call = json!({
+            "jsonrpc": "2.0",
+            "id": 4,
+            "method": "tools/call",
+            "params": { "name": "extract", "arguments": {} },
+        });
+        let reply = handle(call, &config, None).await.unwrap();
+        assert_eq!(reply["result"]["isError"], true);
+
+        // Notifications get no reply
+        let notification = json!({ "jsonrpc": "2.0", "method": "notifications/initialized" });
+        assert!(handle(notification, &config, None).await.is_none());
+    }
+}
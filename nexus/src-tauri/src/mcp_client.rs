@@ -0,0 +1,400 @@
+//! MCP client
+//!
+//! Connects the tool servers listed in `mcp_servers` when a run starts, so
+//! their tools (filesystem, GitHub, databases, ...) are offered to the agent
+//! next to the built-in ones. Each server is launched as a child process and
+//! spoken to over stdio; every run gets its own, shut down when the run ends.
+//! Tools are named `<server>_<tool>`, which is also the name to list in
+//! `disabled_tools`. Each call counts as a step of the run; calls to a server
+//! marked `require_approval`, or with a URL on a sensitive domain among their
+//! arguments, wait for the user's approval first.
+
+use crate::plugins::NexusTool;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command};
+use tokio::sync::oneshot;
+use tokio::time::timeout;
+
+/// Longest wait for a server's reply, including slow tool calls
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A tool server launched for each run
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct McpServerConfig {
+    /// Prefix of the server's tool names
+    pub name: String,
+    /// Program that starts the server, e.g. "npx"
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Extra environment variables for the server, e.g. an access token.
+    /// Kept in config.json as given, and masked in traces and logs.
+    #[serde(default)]
+    pub env: HashMap<String, String>,
+    /// Ask the user before each call to the server's tools
+    #[serde(default)]
+    pub require_approval: bool,
+}
+
+/// The JSON-RPC channel to one server process
+struct Connection {
+    stdin: tokio::sync::Mutex<ChildStdin>,
+    /// Requests awaiting a reply, by id
+    pending: Mutex<HashMap<u64, oneshot::Sender<Value>>>,
+    next_id: AtomicU64,
+}
+
+impl Connection {
+    async fn send(&self, message: Value) -> Result<(), String> {
+        let mut line = message.to_string();
+        line.push('\n');
+        let mut stdin = self.stdin.lock().await;
+        stdin
+            .write_all(line.as_bytes())
+            .await
+            .map_err(|e| e.to_string())?;
+        stdin.flush().await.map_err(|e| e.to_string())
+    }
+
+    async fn request(&self, method: &str, params: Value) -> Result<Value, String> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let (tx, rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(id, tx);
+        let message = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+        let reply = match self.send(message).await {
+            Ok(()) => timeout(REQUEST_TIMEOUT, rx).await,
+            Err(e) => {
+                self.pending.lock().unwrap().remove(&id);
+                return Err(e);
+            }
+        };
+        self.pending.lock().unwrap().remove(&id);
+
+        let reply = match reply {
+            Ok(Ok(reply)) => reply,
+            Ok(Err(_)) => return Err("The server exited".to_string()),
+            Err(_) => {
+                return Err(format!(
+                    "No reply to {} within {} seconds",
+                    method,
+                    REQUEST_TIMEOUT.as_secs()
+                ))
+            }
+        };
+        if let Some(error) = reply.get("error") {
+            return Err(error
+                .get("message")
+                .and_then(Value::as_str)
+                .map(String::from)
+                .unwrap_or_else(|| error.to_string()));
+        }
+        Ok(reply.get("result").cloned().unwrap_or(Value::Null))
+    }
+
+    async fn notify(&self, method: &str) -> Result<(), String> {
+        self.send(json!({ "jsonrpc": "2.0", "method": method }))
+            .await
+    }
+
+    /// Hand a message from the server to the request waiting on it
+    async fn dispatch(&self, message: Value) {
+        match (message.get("id"), message.get("method")) {
+            // Requests from the server, e.g. for sampling, aren't supported
+            (Some(id), Some(_)) => {
+                let _ = self
+                    .send(json!({
+                        "jsonrpc": "2.0",
+                        "id": id,
+                        "error": { "code": -32601, "message": "Not supported by this client" },
+                    }))
+                    .await;
+            }
+            (Some(id), None) => {
+                let waiting = id
+                    .as_u64()
+                    .and_then(|id| self.pending.lock().unwrap().remove(&id));
+                if let Some(tx) = waiting {
+                    let _ = tx.send(message);
+                }
+            }
+            // Notifications, e.g. log messages
+            _ => {}
+        }
+    }
+}
+
+/// A connected server process and the tools it offers
+pub struct McpClient {
+    pub name: String,
+    pub require_approval: bool,
+    child: tokio::sync::Mutex<Child>,
+    tools: Vec<Arc<dyn NexusTool>>,
+}
+
+impl McpClient {
+    /// Launch `server`, complete the MCP handshake and list its tools
+    pub async fn connect(server: &McpServerConfig) -> Result<Self, String> {
+        let mut child = Command::new(&server.command)
+            .args(&server.args)
+            .envs(&server.env)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Could not start '{}': {}", server.command, e))?;
+        let (Some(stdin), Some(stdout)) = (child.stdin.take(), child.stdout.take()) else {
+            return Err("Could not open the server's stdio".to_string());
+        };
+        let conn = Arc::new(Connection {
+            stdin: tokio::sync::Mutex::new(stdin),
+            pending: Mutex::new(HashMap::new()),
+            next_id: AtomicU64::new(1),
+        });
+
+        let reader = conn.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut lines = BufReader::new(stdout).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                if let Ok(message) = serde_json::from_str(&line) {
+                    reader.dispatch(message).await;
+                }
+            }
+            // Fail whatever is still waiting on the exited server
+            reader.pending.lock().unwrap().clear();
+        });
+
+        conn.request(
+            "initialize",
+            json!({
+                "protocolVersion": crate::mcp::PROTOCOL_VERSION,
+                "capabilities": {},
+                "clientInfo": { "name": "nexus", "version": env!("CARGO_PKG_VERSION") },
+            }),
+        )
+        .await?;
+        conn.notify("notifications/initialized").await?;
+
+        let mut tools: Vec<Arc<dyn NexusTool>> = Vec::new();
+        let mut cursor: Option<Value> = None;
+        loop {
+            let params = match &cursor {
+                Some(cursor) => json!({ "cursor": cursor }),
+                None => json!({}),
+            };
+            let page = conn.request("tools/list", params).await?;
+            for tool in page
+                .get("tools")
+                .and_then(Value::as_array)
+                .into_iter()
+                .flatten()
+            {
+                let Some(remote) = tool.get("name").and_then(Value::as_str) else {
+                    continue;
+                };
+                let name = tool_name(&server.name, remote);
+                // Names that only differ in characters replaced or cut off
+                // would be ambiguous to the model
+                if tools.iter().any(|tool| tool.name() == name) {
+                    crate::trace_warn!(
+                        "nexus::mcp",
+                        "Skipping MCP tool with a duplicate name",
+                        server = server.name,
+                        tool = remote
+                    );
+                    continue;
+                }
+                tools.push(Arc::new(McpTool {
+                    name,
+                    remote: remote.to_string(),
+                    description: tool
+                        .get("description")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string(),
+                    schema: tool
+                        .get("inputSchema")
+                        .cloned()
+                        .unwrap_or_else(|| json!({ "type": "object" })),
+                    conn: conn.clone(),
+                }));
+            }
+            cursor = page.get("nextCursor").filter(|c| !c.is_null()).cloned();
+            if cursor.is_none() {
+                break;
+            }
+        }
+
+        Ok(Self {
+            name: server.name.clone(),
+            require_approval: server.require_approval,
+            child: tokio::sync::Mutex::new(child),
+            tools,
+        })
+    }
+
+    pub fn tools(&self) -> Vec<Arc<dyn NexusTool>> {
+        self.tools.clone()
+    }
+
+    /// Shut down the server process
+    pub async fn close(&self) {
+        if let Err(e) = self.child.lock().await.kill().await {
+            crate::trace_warn!(
+                "nexus::mcp",
+                "Could not stop MCP server",
+                server = self.name,
+                error = e.to_string()
+            );
+        }
+    }
+}
+
+/// Connect every server in `servers`. Servers that fail to start are left
+/// out so the run can go ahead with the rest.
+pub async fn connect_all(servers: &[McpServerConfig]) -> Vec<Arc<McpClient>> {
+    let attempts = futures::future::join_all(
+        servers
+            .iter()
+            .map(|server| async move { (server, McpClient::connect(server).await) }),
+    )
+    .await;
+    attempts
+        .into_iter()
+        .filter_map(|(server, result)| match result {
+            Ok(client) => {
+                crate::trace_info!(
+                    "nexus::mcp",
+                    "MCP server connected",
+                    server = server.name,
+                    tools = client.tools.len()
+                );
+                Some(Arc::new(client))
+            }
+            Err(e) => {
+                crate::trace_warn!(
+                    "nexus::mcp",
+                    "MCP server unavailable",
+                    server = server.name,
+                    error = e
+                );
+                crate::agent::emit_event(
+                    "error",
+                    format!("MCP server '{}' unavailable: {}", server.name, e),
+                );
+                None
+            }
+        })
+        .collect()
+}
+
+/// A server's tool as offered to the agent
+struct McpTool {
+    name: String,
+    /// The tool's name on its server
+    remote: String,
+    description: String,
+    schema: Value,
+    conn: Arc<Connection>,
+}
+
+#[async_trait]
+impl NexusTool for McpTool {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn description(&self) -> &str {
+        &self.description
+    }
+
+    fn parameters_schema(&self) -> Value {
+        self.schema.clone()
+    }
+
+    async fn call(&self, args: Value) -> Result<Value, String> {
+        let result = self
+            .conn
+            .request(
+                "tools/call",
+                json!({ "name": self.remote, "arguments": args }),
+            )
+            .await?;
+        let text = result_text(&result);
+        if result
+            .get("isError")
+            .and_then(Value::as_bool)
+            .unwrap_or(false)
+        {
+            return Err(text);
+        }
+        Ok(Value::String(text))
+    }
+}
+
+/// `<server>_<tool>`, restricted to the characters and length model
+/// providers accept in tool names
+pub fn tool_name(server: &str, tool: &str) -> String {
+    format!("{}_{}", server, tool)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .take(64)
+        .collect()
+}
+
+/// The text of a `tools/call` result; other content, such as images, is
+/// only noted
+pub fn result_text(result: &Value) -> String {
+    result
+        .get("content")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|item| match item.get("type").and_then(Value::as_str)? {
+            "text" => item.get("text").and_then(Value::as_str).map(String::from),
+            kind => Some(format!("[{} content omitted]", kind)),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tool_name() {
+        assert_eq!(tool_name("github", "create_issue"), "github_create_issue");
+        assert_eq!(tool_name("my files", "read.file"), "my_files_read_file");
+        assert_eq!(tool_name("s", &"x".repeat(100)).len(), 64);
+    }
+
+    #[test]
+    fn test_result_text() {
+        let result = json!({
+            "content": [
+                { "type": "text", "text": "3 open issues" },
+                { "type": "image", "data": "", "mimeType": "image/png" },
+            ],
+        });
+        assert_eq!(
+            result_text(&result),
+            "3 open issues\n[image content omitted]"
+        );
+        assert_eq!(result_text(&json!({})), "");
+    }
+}
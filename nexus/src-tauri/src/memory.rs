@@ -1,12 +1,252 @@
-use std::sync::{Arc, Mutex, OnceLock};
+use crate::config::Config;
+use radkit::agent::LlmFunction;
+use radkit::macros::LLMOutput;
+use rusqlite::{params, Connection};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::sync::{Arc, OnceLock};
 use std::time::{SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use tokio::time::{sleep, Duration};
+
+/// Notes longer than this are stored alongside an LLM-condensed version
+pub const SUMMARIZE_THRESHOLD_CHARS: usize = 2000;
+
+/// How often expired memories are moved to the archive
+const EXPIRY_SWEEP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Importance of notes stored without one, on the 1-5 scale
+pub const DEFAULT_IMPORTANCE: u8 = 3;
+
+/// Word overlap (Jaccard) from which two notes count as the same fact
+const DUPLICATE_SIMILARITY: f64 = 0.85;
+
+/// Notes `recall` returns when the agent doesn't ask for a number
+pub const DEFAULT_RECALL_LIMIT: usize = 10;
+
+/// Days after which a note's recency score has halved
+const RECENCY_HALF_LIFE_DAYS: f64 = 30.0;
+
+/// Weights of relevance, importance and recency in a note's recall score
+const RELEVANCE_WEIGHT: f64 = 0.5;
+const IMPORTANCE_WEIGHT: f64 = 0.3;
+const RECENCY_WEIGHT: f64 = 0.2;
+
+/// How long a note is meant to last
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, JsonSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryScope {
+    /// Working note for the current session, cleared by a session reset
+    Session,
+    /// Lasting knowledge, kept across sessions
+    #[default]
+    LongTerm,
+}
+
+/// Which entries go first when memory is over its size cap
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryEviction {
+    /// Least recently recalled (or stored) first
+    #[default]
+    Lru,
+    /// Least important first, least recently used among equals
+    LowestImportance,
+}
+
+/// Size cap on memory; unset limits don't apply
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct MemoryLimits {
+    pub max_entries: Option<usize>,
+    pub max_bytes: Option<usize>,
+    pub eviction: MemoryEviction,
+}
+
+impl MemoryLimits {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            max_entries: config.memory_max_entries,
+            max_bytes: config.memory_max_bytes,
+            eviction: config.memory_eviction,
+        }
+    }
+}
 
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
 pub struct MemoryEntry {
+    /// Stable identifier for editing and deleting the entry; entries saved
+    /// before IDs existed get a fresh one when loaded
+    #[serde(default = "new_id")]
+    pub id: String,
     pub content: String,
     pub tags: Vec<String>,
     pub timestamp: u64,
+    /// Condensed version of long notes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// When the entry leaves recall for the archive, in seconds since the
+    /// epoch; kept indefinitely when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<u64>,
+    /// How much the note matters, from 1 to 5; decides eviction under the
+    /// lowest-importance policy
+    #[serde(default = "default_importance")]
+    pub importance: u8,
+    /// When the note was last recalled, in seconds since the epoch
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_accessed: Option<u64>,
+    /// Page the note was taken from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_url: Option<String>,
+    /// Run that stored the note
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+    #[serde(default)]
+    pub scope: MemoryScope,
+}
+
+fn default_importance() -> u8 {
+    DEFAULT_IMPORTANCE
+}
+
+/// A memory moved out of recall after expiring, kept for auditing
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct ArchivedMemory {
+    #[serde(flatten)]
+    pub entry: MemoryEntry,
+    pub archived_at: u64,
+}
+
+/// Current time in seconds since the epoch, as memory timestamps use
+pub fn now_secs() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs()
+}
+
+/// Expiry time of a note stored now that lives `ttl_days`
+pub fn expiry_after_days(ttl_days: Option<u64>) -> Option<u64> {
+    ttl_days.map(|days| now_secs() + days.saturating_mul(24 * 60 * 60))
+}
+
+/// Lowercased words of `text` without surrounding punctuation
+fn words(text: &str) -> Vec<String> {
+    text.split_whitespace()
+        .map(|word| {
+            word.trim_matches(|c: char| !c.is_alphanumeric() && c != '$' && c != '%')
+                .to_lowercase()
+        })
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// Hash of a note's normalized text, equal for notes differing only in case,
+/// spacing or punctuation
+fn content_hash(text: &str) -> u64 {
+    use std::hash::{Hash, Hasher};
+    let mut hasher = std::collections::hash_map::DefaultHasher::new();
+    words(text).hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Whether two notes state the same fact: identical after normalizing, or
+/// sharing nearly all words. Notes whose numbers differ are never merged, as
+/// they usually record a changed price, date or count.
+pub fn is_duplicate(a: &str, b: &str) -> bool {
+    if content_hash(a) == content_hash(b) {
+        return true;
+    }
+    let a: std::collections::HashSet<String> = words(a).into_iter().collect();
+    let b: std::collections::HashSet<String> = words(b).into_iter().collect();
+    let numbers = |set: &std::collections::HashSet<String>| {
+        set.iter()
+            .filter(|word| word.chars().any(|c| c.is_ascii_digit()))
+            .cloned()
+            .collect::<std::collections::HashSet<_>>()
+    };
+    if a.is_empty() || b.is_empty() || numbers(&a) != numbers(&b) {
+        return false;
+    }
+    let shared = a.intersection(&b).count() as f64;
+    let union = a.union(&b).count() as f64;
+    shared / union >= DUPLICATE_SIMILARITY
+}
+
+fn new_id() -> String {
+    uuid::Uuid::new_v4().to_string()
+}
+
+/// A tag and how many entries carry it
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+impl MemoryEntry {
+    /// Bytes the entry takes up toward the size cap
+    pub fn size(&self) -> usize {
+        self.content.len() + self.summary.as_ref().map_or(0, String::len)
+    }
+
+    fn last_used(&self) -> u64 {
+        self.last_accessed.unwrap_or(self.timestamp)
+    }
+
+    /// Share of the query's words found in the note's text or tags, from 0
+    /// to 1
+    fn relevance(&self, terms: &[String]) -> f64 {
+        if terms.is_empty() {
+            return 1.0;
+        }
+        let text = format!(
+            "{} {} {}",
+            self.content,
+            self.summary.as_deref().unwrap_or_default(),
+            self.tags.join(" ")
+        )
+        .to_lowercase();
+        let found = terms
+            .iter()
+            .filter(|term| text.contains(term.as_str()))
+            .count();
+        found as f64 / terms.len() as f64
+    }
+
+    /// Recall score blending relevance, importance and recency
+    fn score(&self, terms: &[String], now: u64) -> f64 {
+        let age_days = now.saturating_sub(self.last_used()) as f64 / 86_400.0;
+        let recency = 0.5f64.powf(age_days / RECENCY_HALF_LIFE_DAYS);
+        let importance = f64::from(self.importance.clamp(1, 5)) / 5.0;
+        RELEVANCE_WEIGHT * self.relevance(terms)
+            + IMPORTANCE_WEIGHT * importance
+            + RECENCY_WEIGHT * recency
+    }
+
+    /// Whether the entry carries `tag`, ignoring case
+    pub fn has_tag(&self, tag: &str) -> bool {
+        self.tags.iter().any(|t| t.eq_ignore_ascii_case(tag.trim()))
+    }
+
+    /// The entry as recall returns it by default: long notes are replaced by
+    /// their summary, with `expandable` telling the agent the full text exists
+    pub fn condensed(&self) -> serde_json::Value {
+        match &self.summary {
+            Some(summary) => serde_json::json!({
+                "content": summary,
+                "tags": self.tags,
+                "timestamp": self.timestamp,
+                "condensed": true,
+                "full_length": self.content.chars().count(),
+            }),
+            None => serde_json::json!({
+                "content": self.content,
+                "tags": self.tags,
+                "timestamp": self.timestamp,
+            }),
+        }
+    }
 }
 
 #[derive(Clone, Serialize, Deserialize)]
@@ -20,22 +260,154 @@ impl Memory {
     }
 
     pub fn add(&mut self, content: String, tags: Vec<String>) {
-        let timestamp = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
+        self.add_with_summary(content, None, tags);
+    }
+
+    pub fn add_with_summary(
+        &mut self,
+        content: String,
+        summary: Option<String>,
+        tags: Vec<String>,
+    ) {
+        self.add_expiring(content, summary, tags, None);
+    }
+
+    /// Add a note that is archived at `expires_at` (seconds since the epoch),
+    /// returning it for further details to be filled in. A note restating an
+    /// existing one is merged into it instead: tags are combined, the
+    /// timestamp and expiry refreshed and the note marked as just used, so
+    /// eviction doesn't pick it straight away. The flag is true when that
+    /// happened.
+    pub fn add_expiring(
+        &mut self,
+        content: String,
+        summary: Option<String>,
+        tags: Vec<String>,
+        expires_at: Option<u64>,
+    ) -> (&mut MemoryEntry, bool) {
+        if let Some(idx) = self
+            .entries
+            .iter()
+            .position(|entry| is_duplicate(&entry.content, &content))
+        {
+            let entry = &mut self.entries[idx];
+            for tag in tags {
+                if !entry.has_tag(&tag) {
+                    entry.tags.push(tag);
+                }
+            }
+            entry.timestamp = now_secs();
+            entry.last_accessed = Some(entry.timestamp);
+            // Restating a fact never makes it expire sooner
+            entry.expires_at = match (entry.expires_at, expires_at) {
+                (Some(current), Some(new)) => Some(current.max(new)),
+                _ => None,
+            };
+            return (entry, true);
+        }
 
         self.entries.push(MemoryEntry {
+            id: new_id(),
             content,
             tags,
-            timestamp,
+            timestamp: now_secs(),
+            summary,
+            expires_at,
+            importance: DEFAULT_IMPORTANCE,
+            last_accessed: None,
+            source_url: None,
+            run_id: None,
+            scope: MemoryScope::LongTerm,
         });
+        let last = self.entries.len() - 1;
+        (&mut self.entries[last], false)
+    }
+
+    /// Mark the entries with `ids` as recalled at `now`
+    pub fn touch(&mut self, ids: &[String], now: u64) {
+        for entry in self.entries.iter_mut().filter(|e| ids.contains(&e.id)) {
+            entry.last_accessed = Some(now);
+        }
+    }
+
+    /// Evict entries until memory is within `limits`, returning the evicted
+    /// ones. The newest entry is never evicted.
+    pub fn enforce_limits(&mut self, limits: &MemoryLimits) -> Vec<MemoryEntry> {
+        let over = |entries: &[MemoryEntry]| {
+            limits.max_entries.is_some_and(|max| entries.len() > max)
+                || limits
+                    .max_bytes
+                    .is_some_and(|max| entries.iter().map(MemoryEntry::size).sum::<usize>() > max)
+        };
+        let mut evicted = Vec::new();
+        while self.entries.len() > 1 && over(&self.entries) {
+            let candidates = &self.entries[..self.entries.len() - 1];
+            let victim = match limits.eviction {
+                MemoryEviction::Lru => candidates
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, e)| e.last_used()),
+                MemoryEviction::LowestImportance => candidates
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, e)| (e.importance, e.last_used())),
+            };
+            let Some((idx, _)) = victim else {
+                break;
+            };
+            evicted.push(self.entries.remove(idx));
+        }
+        evicted
+    }
+
+    /// Remove and return the entries expired as of `now`
+    pub fn take_expired(&mut self, now: u64) -> Vec<MemoryEntry> {
+        let (expired, kept) = std::mem::take(&mut self.entries)
+            .into_iter()
+            .partition(|entry| entry.expires_at.is_some_and(|at| at <= now));
+        self.entries = kept;
+        expired
     }
 
     pub fn get_all(&self) -> Vec<MemoryEntry> {
         self.entries.clone()
     }
 
+    /// The `limit` best notes for `query` carrying any of `tags` (all notes
+    /// when either is empty), ranked by relevance, importance and recency,
+    /// together with the number of matches before the limit. Returned notes
+    /// count as recalled at `now`.
+    pub fn recall(
+        &mut self,
+        query: Option<&str>,
+        tags: &[String],
+        limit: usize,
+        now: u64,
+    ) -> (Vec<MemoryEntry>, usize) {
+        let terms: Vec<String> = query
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+        let mut matches: Vec<(f64, &MemoryEntry)> = self
+            .entries
+            .iter()
+            .filter(|entry| tags.is_empty() || tags.iter().any(|tag| entry.has_tag(tag)))
+            .filter(|entry| entry.relevance(&terms) > 0.0)
+            .map(|entry| (entry.score(&terms, now), entry))
+            .collect();
+        let total = matches.len();
+        matches.sort_by(|a, b| b.0.total_cmp(&a.0));
+        let notes: Vec<MemoryEntry> = matches
+            .into_iter()
+            .take(limit)
+            .map(|(_, entry)| entry.clone())
+            .collect();
+        let ids: Vec<String> = notes.iter().map(|note| note.id.clone()).collect();
+        self.touch(&ids, now);
+        (notes, total)
+    }
+
     pub fn search(&self, query: &str) -> Vec<MemoryEntry> {
         let query_lower = query.to_lowercase();
         self.entries
@@ -48,15 +420,214 @@ impl Memory {
             .collect()
     }
 
+    /// Entries carrying `tag`, ignoring case
+    pub fn get_by_tag(&self, tag: &str) -> Vec<MemoryEntry> {
+        self.entries
+            .iter()
+            .filter(|entry| entry.has_tag(tag))
+            .cloned()
+            .collect()
+    }
+
+    /// Every tag in use with its entry count, most used first. Tags differing
+    /// only in case are counted together under their first spelling.
+    pub fn list_tags(&self) -> Vec<TagCount> {
+        let mut counts: Vec<TagCount> = Vec::new();
+        for entry in &self.entries {
+            let mut seen: Vec<String> = Vec::new();
+            for tag in &entry.tags {
+                let key = tag.trim().to_lowercase();
+                if key.is_empty() || seen.contains(&key) {
+                    continue;
+                }
+                seen.push(key);
+                match counts
+                    .iter_mut()
+                    .find(|c| c.tag.eq_ignore_ascii_case(tag.trim()))
+                {
+                    Some(existing) => existing.count += 1,
+                    None => counts.push(TagCount {
+                        tag: tag.trim().to_string(),
+                        count: 1,
+                    }),
+                }
+            }
+        }
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        counts
+    }
+
+    /// Replace an entry's content and tags, returning the updated entry. The
+    /// summary of the old content is replaced by `summary`.
+    pub fn update(
+        &mut self,
+        id: &str,
+        content: String,
+        summary: Option<String>,
+        tags: Vec<String>,
+    ) -> Option<MemoryEntry> {
+        let entry = self.entries.iter_mut().find(|entry| entry.id == id)?;
+        entry.content = content;
+        entry.summary = summary;
+        entry.tags = tags;
+        Some(entry.clone())
+    }
+
+    /// Remove an entry; false if there was none with `id`
+    pub fn delete(&mut self, id: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|entry| entry.id != id);
+        self.entries.len() < before
+    }
+
+    /// Forget session-scoped notes, keeping long-term ones
+    pub fn clear_session(&mut self) {
+        self.entries
+            .retain(|entry| entry.scope == MemoryScope::LongTerm);
+    }
+
     pub fn clear(&mut self) {
         self.entries.clear();
     }
 }
 
-pub static GLOBAL_MEMORY: OnceLock<Arc<Mutex<Memory>>> = OnceLock::new();
+/// Condensed form of a long note
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct NoteSummary {
+    /// Dense summary keeping every name, number, URL and date from the note.
+    pub summary: String,
+}
+
+/// Whether a note is long enough to be stored with a summary
+pub fn needs_summary(note: &str) -> bool {
+    note.chars().count() > SUMMARIZE_THRESHOLD_CHARS
+}
+
+/// Ask the configured LLM to condense a long note
+pub async fn summarize(config: &Config, note: &str) -> Result<String, String> {
+    let llm = crate::providers::build_llm(config)?;
+    let function = LlmFunction::<NoteSummary>::new_with_system_instructions(
+        llm,
+        "You condense notes for an agent's long-term memory. Keep every concrete fact \
+         (names, numbers, prices, URLs, dates) and drop filler. Aim for under 150 words.",
+    );
+    let result = function
+        .run(format!("Condense this note:\n\n{}", note))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(result.summary)
+}
+
+/// The shared memory store. Tools, commands and the expiry sweep all reach it
+/// from async code, so it sits behind an async lock: waiting for it yields to
+/// the runtime instead of parking a worker thread, and a panic while it is
+/// held can't poison it for the rest of the session.
+pub static GLOBAL_MEMORY: OnceLock<Arc<RwLock<Memory>>> = OnceLock::new();
 
 pub fn init_memory() {
-    let _ = GLOBAL_MEMORY.set(Arc::new(Mutex::new(Memory::new())));
+    let _ = GLOBAL_MEMORY.set(Arc::new(RwLock::new(Memory::new())));
+}
+
+/// Record expired entries in the archive table
+pub fn archive(
+    conn: &Connection,
+    entries: &[MemoryEntry],
+    archived_at: u64,
+) -> rusqlite::Result<()> {
+    for entry in entries {
+        conn.execute(
+            "INSERT OR REPLACE INTO memory_archive
+                (id, content, summary, tags, timestamp, expires_at, archived_at,
+                 source_url, run_id, importance, last_accessed, scope)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12)",
+            params![
+                entry.id,
+                entry.content,
+                entry.summary,
+                serde_json::to_string(&entry.tags).unwrap_or_default(),
+                entry.timestamp as i64,
+                entry.expires_at.map(|at| at as i64),
+                archived_at as i64,
+                entry.source_url,
+                entry.run_id,
+                entry.importance,
+                entry.last_accessed.map(|at| at as i64),
+                serde_json::to_string(&entry.scope).unwrap_or_default()
+            ],
+        )?;
+    }
+    Ok(())
+}
+
+/// Archived memories, most recently archived first
+pub fn list_archived(conn: &Connection) -> rusqlite::Result<Vec<ArchivedMemory>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, content, summary, tags, timestamp, expires_at, archived_at,
+                source_url, run_id, importance, last_accessed, scope
+         FROM memory_archive ORDER BY archived_at DESC, timestamp DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        let tags: String = row.get(3)?;
+        // Entries archived before these were stored get the defaults
+        let scope: Option<String> = row.get(11)?;
+        Ok(ArchivedMemory {
+            entry: MemoryEntry {
+                id: row.get(0)?,
+                content: row.get(1)?,
+                summary: row.get(2)?,
+                tags: serde_json::from_str(&tags).unwrap_or_default(),
+                timestamp: row.get::<_, i64>(4)? as u64,
+                expires_at: row.get::<_, Option<i64>>(5)?.map(|at| at as u64),
+                importance: row.get::<_, Option<u8>>(9)?.unwrap_or(DEFAULT_IMPORTANCE),
+                last_accessed: row.get::<_, Option<i64>>(10)?.map(|at| at as u64),
+                source_url: row.get(7)?,
+                run_id: row.get(8)?,
+                scope: scope
+                    .and_then(|s| serde_json::from_str(&s).ok())
+                    .unwrap_or_default(),
+            },
+            archived_at: row.get::<_, i64>(6)? as u64,
+        })
+    })?;
+    rows.collect()
+}
+
+/// Move expired memories to the archive; returns how many were moved
+pub async fn sweep_expired() -> usize {
+    let Some(mem_lock) = GLOBAL_MEMORY.get() else {
+        return 0;
+    };
+    let mut mem = mem_lock.write().await;
+    let now = now_secs();
+    let expired = mem.take_expired(now);
+    if expired.is_empty() {
+        return 0;
+    }
+    if let Err(e) = crate::db::with_db(|conn| archive(conn, &expired, now)) {
+        crate::trace_warn!(
+            "nexus::memory",
+            "Could not archive expired memories, keeping them",
+            error = e
+        );
+        mem.entries.extend(expired);
+        return 0;
+    }
+    crate::trace_info!(
+        "nexus::memory",
+        "Archived expired memories",
+        count = expired.len()
+    );
+    expired.len()
+}
+
+/// Archive expired memories periodically in the background
+pub fn start_expiry_sweep() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            sweep_expired().await;
+            sleep(EXPIRY_SWEEP_INTERVAL).await;
+        }
+    });
 }
 
 #[cfg(test)]
@@ -86,4 +657,260 @@ mod tests {
         mem.clear();
         assert!(mem.get_all().is_empty());
     }
+
+    #[test]
+    fn test_duplicates_merge() {
+        assert!(is_duplicate(
+            "Acme CRM costs $30 per seat.",
+            "acme crm costs $30 per seat"
+        ));
+        assert!(!is_duplicate(
+            "Acme CRM costs $30 per seat",
+            "Acme CRM costs $35 per seat"
+        ));
+        assert!(is_duplicate(
+            "The Acme CRM Pro plan includes unlimited contacts, email sequences, a shared inbox and phone support",
+            "The Acme CRM Pro plan includes unlimited contacts, email sequences, a shared inbox, and phone support too"
+        ));
+        assert!(!is_duplicate(
+            "Acme is based in Berlin",
+            "Globex is based in Paris"
+        ));
+
+        let mut mem = Memory::new();
+        mem.add(
+            "Acme CRM costs $30 per seat".to_string(),
+            vec!["pricing".to_string()],
+        );
+        let (merged, was_duplicate) = mem.add_expiring(
+            "acme CRM costs $30 per seat.".to_string(),
+            None,
+            vec!["Pricing".to_string(), "acme".to_string()],
+            Some(1_000),
+        );
+        assert!(was_duplicate);
+        assert_eq!(merged.tags, vec!["pricing".to_string(), "acme".to_string()]);
+        // The permanent original stays permanent
+        assert_eq!(merged.expires_at, None);
+        assert_eq!(mem.get_all().len(), 1);
+        assert_eq!(mem.get_all()[0].content, "Acme CRM costs $30 per seat");
+
+        let (expiring, _) =
+            mem.add_expiring("Globex is in Paris".to_string(), None, vec![], Some(2_000));
+        expiring.run_id = Some("run-1".to_string());
+        let (merged, _) =
+            mem.add_expiring("Globex is in Paris".to_string(), None, vec![], Some(1_000));
+        assert_eq!(merged.expires_at, Some(2_000));
+        let (merged, _) =
+            mem.add_expiring("Globex is in Paris".to_string(), None, vec![], Some(3_000));
+        assert_eq!(merged.expires_at, Some(3_000));
+        assert_eq!(merged.run_id, Some("run-1".to_string()));
+    }
+
+    #[test]
+    fn test_clear_session() {
+        let mut mem = Memory::new();
+        mem.add("lasting fact".to_string(), vec![]);
+        let (scratch, _) = mem.add_expiring("scratch".to_string(), None, vec![], None);
+        scratch.scope = MemoryScope::Session;
+        mem.clear_session();
+
+        let all = mem.get_all();
+        assert_eq!(all.len(), 1);
+        assert_eq!(all[0].content, "lasting fact");
+        assert_eq!(all[0].scope, MemoryScope::LongTerm);
+    }
+
+    #[test]
+    fn test_ranked_recall() {
+        let mut mem = Memory::new();
+        let now = now_secs();
+        mem.add(
+            "Acme CRM costs $30 per seat".to_string(),
+            vec!["pricing".to_string()],
+        );
+        mem.add("Acme CRM was founded in 2010".to_string(), vec![]);
+        mem.add(
+            "Globex CRM costs $25 per seat".to_string(),
+            vec!["pricing".to_string()],
+        );
+        mem.entries[2].importance = 5;
+        mem.entries[0].timestamp = now - 90 * 86_400;
+
+        let (notes, total) = mem.recall(Some("crm costs"), &[], 10, now);
+        assert_eq!(total, 3);
+        assert_eq!(notes[0].content, "Globex CRM costs $25 per seat");
+        assert_eq!(notes[2].content, "Acme CRM was founded in 2010");
+        assert_eq!(notes[0].last_accessed, None);
+        assert_eq!(mem.entries[2].last_accessed, Some(now));
+
+        let (notes, total) = mem.recall(Some("acme"), &["pricing".to_string()], 10, now);
+        assert_eq!(total, 1);
+        assert_eq!(notes[0].content, "Acme CRM costs $30 per seat");
+
+        let (notes, total) = mem.recall(None, &[], 2, now);
+        assert_eq!((notes.len(), total), (2, 3));
+        assert!(mem.recall(Some("weather"), &[], 10, now).0.is_empty());
+    }
+
+    #[test]
+    fn test_eviction() {
+        let mut mem = Memory::new();
+        for note in ["a", "b", "c"] {
+            mem.add(note.to_string(), vec![]);
+        }
+        mem.entries[0].importance = 5;
+        let a = mem.entries[0].id.clone();
+        mem.touch(&[a], mem.entries[0].timestamp + 10);
+
+        let limits = MemoryLimits {
+            max_entries: Some(2),
+            ..MemoryLimits::default()
+        };
+        let evicted = mem.enforce_limits(&limits);
+        assert_eq!(evicted.len(), 1);
+        assert_eq!(evicted[0].content, "b");
+
+        mem.add("dddd".to_string(), vec![]);
+        let limits = MemoryLimits {
+            max_bytes: Some(5),
+            eviction: MemoryEviction::LowestImportance,
+            ..MemoryLimits::default()
+        };
+        let evicted: Vec<String> = mem
+            .enforce_limits(&limits)
+            .into_iter()
+            .map(|e| e.content)
+            .collect();
+        assert_eq!(evicted, ["c"]);
+        assert_eq!(mem.get_all().len(), 2);
+
+        // The newest note stays even when it alone is over the cap
+        mem.add("x".repeat(10), vec![]);
+        mem.enforce_limits(&limits);
+        assert_eq!(mem.get_all().len(), 1);
+
+        // Restating an old note counts as using it
+        let mut mem = Memory::new();
+        for note in ["old", "b", "c"] {
+            mem.add(note.to_string(), vec![]);
+        }
+        let old = mem.entries[0].id.clone();
+        mem.touch(&[old], 1);
+        mem.entries[1].timestamp -= 10;
+        mem.add("old".to_string(), vec![]);
+        let limits = MemoryLimits {
+            max_entries: Some(2),
+            ..MemoryLimits::default()
+        };
+        let evicted = mem.enforce_limits(&limits);
+        assert_eq!(evicted[0].content, "b");
+    }
+
+    #[test]
+    fn test_expiry_and_archive() {
+        let mut mem = Memory::new();
+        let (stale, _) = mem.add_expiring(
+            "stale price".to_string(),
+            None,
+            vec!["crm".to_string()],
+            Some(100),
+        );
+        stale.source_url = Some("https://vendor.example/pricing".to_string());
+        stale.run_id = Some("run-1".to_string());
+        stale.importance = 5;
+        stale.last_accessed = Some(50);
+        stale.scope = MemoryScope::Session;
+        mem.add_expiring("fresh".to_string(), None, vec![], Some(1_000));
+        mem.add("forever".to_string(), vec![]);
+
+        let expired = mem.take_expired(500);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].content, "stale price");
+        assert_eq!(mem.get_all().len(), 2);
+
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+        archive(&conn, &expired, 500).unwrap();
+        let archived = list_archived(&conn).unwrap();
+        assert_eq!(archived.len(), 1);
+        assert_eq!(archived[0].entry, expired[0]);
+        assert_eq!(archived[0].archived_at, 500);
+    }
+
+    #[test]
+    fn test_tags() {
+        let mut mem = Memory::new();
+        mem.add(
+            "a".to_string(),
+            vec!["Pricing".to_string(), "crm".to_string()],
+        );
+        mem.add("b".to_string(), vec!["pricing".to_string()]);
+        mem.add("c".to_string(), vec!["crm".to_string(), "CRM".to_string()]);
+        mem.add("d".to_string(), vec![]);
+
+        let pricing = mem.get_by_tag("PRICING");
+        assert_eq!(pricing.len(), 2);
+        assert_eq!(pricing[0].content, "a");
+
+        assert_eq!(
+            mem.list_tags(),
+            vec![
+                TagCount {
+                    tag: "Pricing".to_string(),
+                    count: 2
+                },
+                TagCount {
+                    tag: "crm".to_string(),
+                    count: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_update_and_delete() {
+        let mut mem = Memory::new();
+        mem.add("old note".to_string(), vec!["a".to_string()]);
+        mem.add("keep".to_string(), vec![]);
+        let id = mem.get_all()[0].id.clone();
+        assert_ne!(id, mem.get_all()[1].id);
+
+        let updated = mem
+            .update(&id, "new note".to_string(), None, vec!["b".to_string()])
+            .unwrap();
+        assert_eq!(updated.content, "new note");
+        assert_eq!(updated.tags, vec!["b".to_string()]);
+        assert_eq!(mem.get_all()[0], updated);
+        assert!(mem.update("missing", String::new(), None, vec![]).is_none());
+
+        assert!(mem.delete(&id));
+        assert!(!mem.delete(&id));
+        assert_eq!(mem.get_all().len(), 1);
+        assert_eq!(mem.get_all()[0].content, "keep");
+
+        // Entries from before IDs existed still load
+        let legacy: MemoryEntry =
+            serde_json::from_str(r#"{"content": "note", "tags": [], "timestamp": 1}"#).unwrap();
+        assert!(!legacy.id.is_empty());
+    }
+
+    #[test]
+    fn test_condensed_recall() {
+        let long = "x".repeat(SUMMARIZE_THRESHOLD_CHARS + 1);
+        assert!(needs_summary(&long));
+        assert!(!needs_summary("short"));
+
+        let mut mem = Memory::new();
+        mem.add_with_summary(long.clone(), Some("summary".to_string()), vec![]);
+        mem.add("short".to_string(), vec![]);
+
+        let all = mem.get_all();
+        let condensed = all[0].condensed();
+        assert_eq!(condensed["content"], "summary");
+        assert_eq!(condensed["condensed"], true);
+        assert_eq!(all[0].content, long);
+        assert_eq!(all[1].condensed()["content"], "short");
+        assert!(all[1].condensed().get("condensed").is_none());
+    }
 }
@@ -0,0 +1,168 @@
+//! Run metrics aggregated from the flight recorder
+//!
+//! Everything recorded within a run carries its `run_id`, so a run's metrics
+//! can be computed after the fact from its persisted trace events: tool calls
+//! from step events, latencies and failures from finished spans, and
+//! navigations from recorded browser actions.
+
+use crate::tracing::TraceEvent;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+/// Where a run spent its time
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RunMetrics {
+    pub run_id: String,
+    /// Tool calls admitted by the step budget, by tool name
+    pub tool_calls: BTreeMap<String, usize>,
+    /// Timed operations (tools, LLM calls, the run itself), slowest total first
+    pub spans: Vec<SpanMetrics>,
+    /// Page loads the agent attempted
+    pub navigations: usize,
+    /// ERROR-level events recorded during the run
+    pub errors: usize,
+    pub events: usize,
+}
+
+/// Latency and failures of one kind of span
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct SpanMetrics {
+    pub name: String,
+    pub count: usize,
+    pub failures: usize,
+    pub error_rate: f64,
+    pub total_ms: u64,
+    pub p50_ms: u64,
+    pub p90_ms: u64,
+    pub p99_ms: u64,
+    pub max_ms: u64,
+}
+
+/// Aggregate `events`, all recorded during `run_id`
+pub fn compute(run_id: &str, events: &[TraceEvent]) -> RunMetrics {
+    let mut metrics = RunMetrics {
+        run_id: run_id.to_string(),
+        events: events.len(),
+        ..RunMetrics::default()
+    };
+    // Durations and failure flags, by span name
+    let mut spans: BTreeMap<String, Vec<(u64, bool)>> = BTreeMap::new();
+    for event in events {
+        let fields: Value = serde_json::from_str(&event.fields).unwrap_or(Value::Null);
+        if event.level == "ERROR" {
+            metrics.errors += 1;
+        }
+        if event.message == "Step started" {
+            if let Some(tool) = fields["tool"].as_str() {
+                *metrics.tool_calls.entry(tool.to_string()).or_default() += 1;
+            }
+        }
+        if event.target == crate::replay::ACTION_TARGET
+            && fields["browser_action"]["action"] == "navigate"
+        {
+            metrics.navigations += 1;
+        }
+        if let (Some(name), Some(duration_ms)) = (&event.span_name, fields["duration_ms"].as_u64())
+        {
+            let failed = fields.get("error").is_some();
+            spans
+                .entry(name.clone())
+                .or_default()
+                .push((duration_ms, failed));
+        }
+    }
+
+    metrics.spans = spans
+        .into_iter()
+        .map(|(name, mut samples)| {
+            samples.sort_unstable();
+            let durations: Vec<u64> = samples.iter().map(|&(ms, _)| ms).collect();
+            let failures = samples.iter().filter(|&&(_, failed)| failed).count();
+            SpanMetrics {
+                name,
+                count: samples.len(),
+                failures,
+                error_rate: failures as f64 / samples.len() as f64,
+                total_ms: durations.iter().sum(),
+                p50_ms: percentile(&durations, 50.0),
+                p90_ms: percentile(&durations, 90.0),
+                p99_ms: percentile(&durations, 99.0),
+                max_ms: durations.last().copied().unwrap_or(0),
+            }
+        })
+        .collect();
+    metrics.spans.sort_by(|a, b| b.total_ms.cmp(&a.total_ms));
+    metrics
+}
+
+/// Nearest-rank percentile of sorted, non-empty `values`
+fn percentile(values: &[u64], p: f64) -> u64 {
+    let rank = (p / 100.0 * values.len() as f64).ceil() as usize;
+    values[rank.clamp(1, values.len()) - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn event(level: &str, target: &str, span_name: Option<&str>, fields: Value) -> TraceEvent {
+        TraceEvent {
+            id: String::new(),
+            session_id: "s".to_string(),
+            timestamp: 0,
+            level: level.to_string(),
+            target: target.to_string(),
+            span_name: span_name.map(str::to_string),
+            message: match span_name {
+                Some(name) => format!("{} finished", name),
+                None => "Step started".to_string(),
+            },
+            fields: fields.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_compute() {
+        let mut events = Vec::new();
+        for ms in [100, 200, 300, 400] {
+            events.push(event(
+                "DEBUG",
+                "nexus::agent::steps",
+                None,
+                json!({ "tool": "navigate" }),
+            ));
+            events.push(event(
+                "INFO",
+                crate::replay::ACTION_TARGET,
+                None,
+                json!({ "browser_action": { "action": "navigate", "url": "https://example.com" } }),
+            ));
+            events.push(event(
+                "INFO",
+                "nexus::agent::navigate",
+                Some("navigate"),
+                json!({ "duration_ms": ms }),
+            ));
+        }
+        events.push(event(
+            "ERROR",
+            "nexus::agent::click",
+            Some("click"),
+            json!({ "duration_ms": 50, "error": "Element not found" }),
+        ));
+
+        let metrics = compute("run-a", &events);
+        assert_eq!(metrics.tool_calls["navigate"], 4);
+        assert_eq!(metrics.navigations, 4);
+        assert_eq!(metrics.errors, 1);
+        assert_eq!(metrics.spans[0].name, "navigate");
+        assert_eq!(metrics.spans[0].total_ms, 1000);
+        assert_eq!(metrics.spans[0].p50_ms, 200);
+        assert_eq!(metrics.spans[0].p90_ms, 400);
+        assert_eq!(metrics.spans[0].max_ms, 400);
+        assert_eq!(metrics.spans[1].name, "click");
+        assert_eq!(metrics.spans[1].error_rate, 1.0);
+    }
+}
@@ -0,0 +1,452 @@
+//! Model discovery
+//!
+//! Queries a provider's models endpoint so the settings UI can offer the
+//! models a key actually has access to instead of free-text names. Most
+//! providers answer in the OpenAI `{"data": [...]}` shape; Gemini lists
+//! `models/...` resources instead. Context lengths are reported where the
+//! provider includes them.
+//!
+//! The same request doubles as a cheap connection test: it checks that the
+//! provider is reachable, accepts the key and offers the configured model
+//! before a long run finds out the hard way.
+
+use crate::config::Config;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+
+/// Time allowed for a models request
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(15);
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ModelInfo {
+    /// Name to use as the config's `model`
+    pub id: String,
+    /// Human-readable name, if the provider has one
+    pub name: Option<String>,
+    /// Maximum context window in tokens, if the provider reports it
+    pub context_length: Option<u64>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Auth {
+    None,
+    Bearer(String),
+    Anthropic(String),
+    /// Key passed as the `key` query parameter
+    Query(String),
+}
+
+/// URL and credentials of the provider's models endpoint
+fn models_request(config: &Config) -> Result<(String, Auth), String> {
+    let provider = config.provider.to_lowercase();
+    let key = config.api_key.trim().to_string();
+    let bearer = || {
+        if key.is_empty() {
+            Auth::None
+        } else {
+            Auth::Bearer(key.clone())
+        }
+    };
+    let custom_base = config
+        .base_url
+        .as_deref()
+        .map(|url| url.trim().trim_end_matches('/'))
+        .filter(|url| !url.is_empty());
+
+    let request = match provider.as_str() {
+        "anthropic" => (
+            "https://api.anthropic.com/v1/models?limit=1000".to_string(),
+            Auth::Anthropic(key.clone()),
+        ),
+        "openai" => (
+            format!(
+                "{}/models",
+                custom_base.unwrap_or("https://api.openai.com/v1")
+            ),
+            bearer(),
+        ),
+        "openrouter" => ("https://openrouter.ai/api/v1/models".to_string(), bearer()),
+        "gemini" => (
+            "https://generativelanguage.googleapis.com/v1beta/models?pageSize=1000".to_string(),
+            Auth::Query(key.clone()),
+        ),
+        "grok" => ("https://api.x.ai/v1/models".to_string(), bearer()),
+        "deepseek" => ("https://api.deepseek.com/models".to_string(), bearer()),
+        "ollama" => (
+            format!("{}/models", crate::providers::ollama_base_url(config)),
+            Auth::None,
+        ),
+        "custom" => (
+            format!(
+                "{}/models",
+                custom_base.ok_or("The custom provider needs a base URL")?
+            ),
+            bearer(),
+        ),
+        other => {
+            let &(_, _, endpoint) = crate::providers::OPENAI_COMPATIBLE
+                .iter()
+                .find(|(name, _, _)| *name == other)
+                .ok_or_else(|| format!("Unsupported LLM_PROVIDER: {}", other))?;
+            (
+                format!("{}/models", custom_base.unwrap_or(endpoint)),
+                bearer(),
+            )
+        }
+    };
+    Ok(request)
+}
+
+fn first_u64(entry: &Value, keys: &[&str]) -> Option<u64> {
+    keys.iter()
+        .find_map(|key| entry.get(*key).and_then(Value::as_u64))
+}
+
+/// Models in a models endpoint response, sorted by id
+fn parse_models(body: &Value) -> Vec<ModelInfo> {
+    let mut models: Vec<ModelInfo> = if let Some(data) = body["data"].as_array() {
+        data.iter()
+            .filter_map(|entry| {
+                Some(ModelInfo {
+                    id: entry["id"].as_str()?.to_string(),
+                    name: entry
+                        .get("display_name")
+                        .or_else(|| entry.get("name"))
+                        .and_then(Value::as_str)
+                        .map(str::to_string),
+                    context_length: first_u64(
+                        entry,
+                        &["context_length", "context_window", "max_context_length"],
+                    ),
+                })
+            })
+            .collect()
+    } else if let Some(resources) = body["models"].as_array() {
+        // Gemini: only models that can generate content are usable
+        resources
+            .iter()
+            .filter(|entry| {
+                entry["supportedGenerationMethods"]
+                    .as_array()
+                    .is_none_or(|methods| methods.iter().any(|m| m == "generateContent"))
+            })
+            .filter_map(|entry| {
+                let name = entry["name"].as_str()?;
+                Some(ModelInfo {
+                    id: name.strip_prefix("models/").unwrap_or(name).to_string(),
+                    name: entry["displayName"].as_str().map(str::to_string),
+                    context_length: first_u64(entry, &["inputTokenLimit"]),
+                })
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    models
+}
+
+/// Why a provider request failed
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The config is incomplete, e.g. a custom provider without a base URL
+    Config,
+    /// The provider could not be reached
+    Unreachable,
+    /// The provider rejected the API key
+    InvalidKey,
+    RateLimited,
+    /// The endpoint does not exist, usually a wrong base URL
+    NotFound,
+    /// The configured model is not offered by the provider
+    UnknownModel,
+    Other,
+}
+
+#[derive(Debug)]
+struct RequestError {
+    kind: ErrorKind,
+    message: String,
+}
+
+impl RequestError {
+    fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+        }
+    }
+}
+
+/// Outcome of a provider connection test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionTest {
+    pub ok: bool,
+    pub latency_ms: u64,
+    pub model_count: Option<usize>,
+    /// Whether the configured model is among the provider's models
+    pub model_available: Option<bool>,
+    pub error_kind: Option<ErrorKind>,
+    pub error: Option<String>,
+}
+
+fn error_kind(status: reqwest::StatusCode) -> ErrorKind {
+    match status.as_u16() {
+        401 | 403 => ErrorKind::InvalidKey,
+        404 => ErrorKind::NotFound,
+        429 => ErrorKind::RateLimited,
+        _ => ErrorKind::Other,
+    }
+}
+
+/// Provider error message from a failed response body
+fn error_message(status: reqwest::StatusCode, body: &str) -> String {
+    let detail = serde_json::from_str::<Value>(body).ok().and_then(|v| {
+        v["error"]["message"]
+            .as_str()
+            .or_else(|| v["error"].as_str())
+            .or_else(|| v["message"].as_str())
+            .map(str::to_string)
+    });
+    match detail {
+        Some(detail) => format!("{} ({})", detail, status),
+        None => format!("Request failed with status {}", status),
+    }
+}
+
+async fn get_json(url: &str, auth: Auth) -> Result<Value, RequestError> {
+    let client = reqwest::Client::builder()
+        .timeout(REQUEST_TIMEOUT)
+        .build()
+        .map_err(|e| RequestError::new(ErrorKind::Other, e.to_string()))?;
+    let request = match auth {
+        Auth::None => client.get(url),
+        Auth::Bearer(key) => client.get(url).bearer_auth(key),
+        Auth::Anthropic(key) => client
+            .get(url)
+            .header("x-api-key", key)
+            .header("anthropic-version", "2023-06-01"),
+        Auth::Query(key) => client.get(url).query(&[("key", key)]),
+    };
+    let response = request.send().await.map_err(|e| {
+        RequestError::new(
+            ErrorKind::Unreachable,
+            format!("Could not reach the provider: {}", e),
+        )
+    })?;
+    let status = response.status();
+    let body = response
+        .text()
+        .await
+        .map_err(|e| RequestError::new(ErrorKind::Unreachable, e.to_string()))?;
+    if !status.is_success() {
+        return Err(RequestError::new(
+            error_kind(status),
+            error_message(status, &body),
+        ));
+    }
+    serde_json::from_str(&body).map_err(|e| {
+        RequestError::new(
+            ErrorKind::Other,
+            format!("Unexpected provider response: {}", e),
+        )
+    })
+}
+
+async fn fetch_models(config: &Config) -> Result<Vec<ModelInfo>, RequestError> {
+    let (url, auth) =
+        models_request(config).map_err(|e| RequestError::new(ErrorKind::Config, e))?;
+    crate::trace_info!(
+        "nexus::models",
+        "Listing models",
+        provider = config.provider,
+        url = url
+    );
+    let body = get_json(&url, auth).await.inspect_err(|e| {
+        crate::trace_warn!(
+            "nexus::models",
+            "Listing models failed",
+            provider = config.provider,
+            error = e.message
+        );
+    })?;
+    Ok(parse_models(&body))
+}
+
+/// The models available to the provider and key in `config`
+pub async fn list_models(config: &Config) -> Result<Vec<ModelInfo>, String> {
+    fetch_models(config).await.map_err(|e| e.message)
+}
+
+/// Whether `model` names one of `models`; Ollama tags default to `latest`
+fn offers_model(models: &[ModelInfo], model: &str) -> bool {
+    let model = model.trim();
+    models
+        .iter()
+        .any(|m| m.id == model || m.id.strip_suffix(":latest") == Some(model))
+}
+
+/// Check that `config`'s provider is reachable, accepts its key and offers
+/// its model, without starting a run
+pub async fn test_connection(config: &Config) -> ConnectionTest {
+    let started = std::time::Instant::now();
+    let mut result = fetch_models(config).await;
+
+    // OpenRouter lists models without authentication, so check the key itself
+    if result.is_ok() && config.provider.eq_ignore_ascii_case("openrouter") {
+        if let Err(e) = get_json(
+            "https://openrouter.ai/api/v1/key",
+            Auth::Bearer(config.api_key.trim().to_string()),
+        )
+        .await
+        {
+            result = Err(e);
+        }
+    }
+
+    let latency_ms = started.elapsed().as_millis() as u64;
+    let test = match result {
+        Ok(models) => {
+            let model_available = if models.is_empty() {
+                None
+            } else {
+                Some(offers_model(&models, &config.model))
+            };
+            let unknown_model = model_available == Some(false);
+            ConnectionTest {
+                ok: !unknown_model,
+                latency_ms,
+                model_count: Some(models.len()),
+                model_available,
+                error_kind: unknown_model.then_some(ErrorKind::UnknownModel),
+                error: unknown_model.then(|| {
+                    format!(
+                        "Model '{}' is not offered by {}",
+                        config.model, config.provider
+                    )
+                }),
+            }
+        }
+        Err(e) => ConnectionTest {
+            ok: false,
+            latency_ms,
+            model_count: None,
+            model_available: None,
+            error_kind: Some(e.kind),
+            error: Some(e.message),
+        },
+    };
+    crate::trace_info!(
+        "nexus::models",
+        "Connection tested",
+        provider = config.provider,
+        ok = test.ok,
+        latency_ms = test.latency_ms
+    );
+    test
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_models_request() {
+        let config = Config {
+            provider: "groq".to_string(),
+            api_key: "gsk".to_string(),
+            ..Config::default()
+        };
+        assert_eq!(
+            models_request(&config).unwrap(),
+            (
+                "https://api.groq.com/openai/v1/models".to_string(),
+                Auth::Bearer("gsk".to_string())
+            )
+        );
+
+        let custom = Config {
+            provider: "custom".to_string(),
+            ..Config::default()
+        };
+        assert!(models_request(&custom).is_err());
+        let custom = Config {
+            base_url: Some("http://localhost:1234/v1/".to_string()),
+            ..custom
+        };
+        assert_eq!(
+            models_request(&custom).unwrap(),
+            ("http://localhost:1234/v1/models".to_string(), Auth::None)
+        );
+    }
+
+    #[test]
+    fn test_parse_models() {
+        let openai_style = json!({
+            "data": [
+                { "id": "llama-3.3-70b-versatile", "context_window": 131072 },
+                { "id": "claude-sonnet-4", "display_name": "Claude Sonnet 4" }
+            ]
+        });
+        assert_eq!(
+            parse_models(&openai_style),
+            vec![
+                ModelInfo {
+                    id: "claude-sonnet-4".to_string(),
+                    name: Some("Claude Sonnet 4".to_string()),
+                    context_length: None,
+                },
+                ModelInfo {
+                    id: "llama-3.3-70b-versatile".to_string(),
+                    name: None,
+                    context_length: Some(131072),
+                },
+            ]
+        );
+
+        let gemini = json!({
+            "models": [
+                {
+                    "name": "models/gemini-2.5-flash",
+                    "displayName": "Gemini 2.5 Flash",
+                    "inputTokenLimit": 1048576,
+                    "supportedGenerationMethods": ["generateContent"]
+                },
+                {
+                    "name": "models/text-embedding-004",
+                    "supportedGenerationMethods": ["embedContent"]
+                }
+            ]
+        });
+        let models = parse_models(&gemini);
+        assert_eq!(models.len(), 1);
+        assert_eq!(models[0].id, "gemini-2.5-flash");
+        assert_eq!(models[0].context_length, Some(1048576));
+    }
+
+    #[test]
+    fn test_offers_model() {
+        let models = vec![
+            ModelInfo {
+                id: "llama3.1:latest".to_string(),
+                name: None,
+                context_length: None,
+            },
+            ModelInfo {
+                id: "gpt-4o".to_string(),
+                name: None,
+                context_length: None,
+            },
+        ];
+        assert!(offers_model(&models, "gpt-4o"));
+        assert!(offers_model(&models, "llama3.1"));
+        assert!(!offers_model(&models, "gpt-4o-mni"));
+        assert_eq!(
+            error_kind(reqwest::StatusCode::UNAUTHORIZED),
+            ErrorKind::InvalidKey
+        );
+    }
+}
@@ -0,0 +1,111 @@
+//! Native desktop notifications
+//!
+//! Runs often take minutes, so the user is told through an OS notification
+//! when one finishes or fails, or when an action waits on their approval.
+//! Notifications are only shown while the main window is not focused; the
+//! in-app events cover the rest.
+
+use crate::config::{Config, ConfigManager};
+use std::sync::Mutex;
+use tauri::Manager;
+use tauri_plugin_notification::NotificationExt;
+
+/// Characters of the prompt or error shown in a notification body
+const MAX_BODY_CHARS: usize = 120;
+
+fn shorten(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= MAX_BODY_CHARS {
+        return text;
+    }
+    let mut short: String = text.chars().take(MAX_BODY_CHARS - 1).collect();
+    short.push('…');
+    short
+}
+
+/// Title and body announcing a run's outcome
+fn run_message(prompt: &str, result: &Result<String, String>) -> (String, String) {
+    match result {
+        Ok(_) => ("Research complete".to_string(), shorten(prompt)),
+        Err(e) => (
+            "Run failed".to_string(),
+            format!("{}\n{}", shorten(prompt), shorten(e)),
+        ),
+    }
+}
+
+/// Whether notifications are on for the current run, or in the saved config
+/// outside of one
+fn enabled() -> bool {
+    if let Some(run) = crate::run::current() {
+        return run.config.desktop_notifications;
+    }
+    crate::GLOBAL_APP
+        .get()
+        .map(|app| {
+            app.state::<Mutex<ConfigManager>>()
+                .lock()
+                .unwrap()
+                .load()
+                .desktop_notifications
+        })
+        .unwrap_or(false)
+}
+
+/// Show a notification unless the user is looking at the app
+pub fn notify(title: &str, body: &str) {
+    let Some(app) = crate::GLOBAL_APP.get() else {
+        return;
+    };
+    let focused = app
+        .get_webview_window("main")
+        .and_then(|window| window.is_focused().ok())
+        .unwrap_or(false);
+    if focused {
+        return;
+    }
+    if let Err(e) = app.notification().builder().title(title).body(body).show() {
+        crate::trace_warn!(
+            "nexus::notifications",
+            "Failed to show notification",
+            error = e.to_string()
+        );
+    }
+}
+
+/// Announce that a top-level run finished or failed
+pub fn run_finished(config: &Config, prompt: &str, result: &Result<String, String>) {
+    if config.desktop_notifications {
+        let (title, body) = run_message(prompt, result);
+        notify(&title, &body);
+    }
+}
+
+/// Announce that an action is waiting for the user's approval
+pub fn approval_needed(action: &str) {
+    if enabled() {
+        notify(
+            "Approval needed",
+            &format!("The agent is waiting for approval to {}", action),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_message() {
+        let (title, body) = run_message("Compare  CRM\npricing", &Ok("report".to_string()));
+        assert_eq!(title, "Research complete");
+        assert_eq!(body, "Compare CRM pricing");
+
+        let long_error = "x".repeat(300);
+        let (title, body) = run_message("Compare CRM pricing", &Err(long_error));
+        assert_eq!(title, "Run failed");
+        let error_line = body.lines().nth(1).unwrap();
+        assert_eq!(error_line.chars().count(), MAX_BODY_CHARS);
+        assert!(error_line.ends_with('…'));
+    }
+}
@@ -0,0 +1,292 @@
+//! OpenTelemetry export of flight recorder events
+//!
+//! When `otlp_endpoint` is configured, every batch the trace writer persists
+//! is also sent to an OTLP/HTTP collector (an OpenTelemetry Collector, Jaeger
+//! or Grafana Tempo) as JSON. Finished spans become OTLP spans, other events
+//! become log records, and each recorder session maps to one trace.
+
+use crate::config::Config;
+use crate::tracing::TraceEvent;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+/// Collector settings from the active config; `None` disables export
+static EXPORTER: RwLock<Option<OtlpExporter>> = RwLock::new(None);
+
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Resource name the collector files Nexus telemetry under
+const SERVICE_NAME: &str = "nexus";
+
+/// Span bookkeeping fields carried by the OTLP span itself
+const SPAN_FIELDS: &[&str] = &["span_id", "parent_span_id", "duration_ms"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct OtlpExporter {
+    /// Collector base URL, e.g. http://localhost:4318
+    pub endpoint: String,
+    pub headers: HashMap<String, String>,
+}
+
+/// Apply the config's collector settings; takes effect on the next batch
+pub fn configure(config: &Config) {
+    let exporter = config
+        .otlp_endpoint
+        .as_deref()
+        .map(|endpoint| endpoint.trim().trim_end_matches('/'))
+        .filter(|endpoint| !endpoint.is_empty())
+        .map(|endpoint| OtlpExporter {
+            endpoint: endpoint.to_string(),
+            headers: config.otlp_headers.clone(),
+        });
+    *EXPORTER.write().unwrap() = exporter;
+}
+
+/// Send `events` to the configured collector, if any
+pub async fn export(events: &[TraceEvent]) -> Result<(), String> {
+    let Some(exporter) = EXPORTER.read().unwrap().clone() else {
+        return Ok(());
+    };
+    let (spans, logs): (Vec<_>, Vec<_>) = events
+        .iter()
+        .filter(|event| !is_span_start(event))
+        .partition(|event| is_span_end(event));
+    if !spans.is_empty() {
+        exporter.post("v1/traces", &traces_body(&spans)).await?;
+    }
+    if !logs.is_empty() {
+        exporter.post("v1/logs", &logs_body(&logs)).await?;
+    }
+    Ok(())
+}
+
+impl OtlpExporter {
+    async fn post(&self, path: &str, body: &Value) -> Result<(), String> {
+        let client = CLIENT.get_or_init(|| {
+            reqwest::Client::builder()
+                .timeout(EXPORT_TIMEOUT)
+                .build()
+                .unwrap_or_default()
+        });
+        let url = format!("{}/{}", self.endpoint, path);
+        let mut request = client.post(&url).json(body);
+        for (name, value) in &self.headers {
+            request = request.header(name, value);
+        }
+        let response = request
+            .send()
+            .await
+            .map_err(|e| format!("{}: {}", url, e))?;
+        if !response.status().is_success() {
+            return Err(format!("{} returned {}", url, response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// OTLP/JSON `ExportTraceServiceRequest` for finished-span events
+pub fn traces_body(events: &[&TraceEvent]) -> Value {
+    let spans: Vec<Value> = events
+        .iter()
+        .map(|event| {
+            let fields = fields(event);
+            let span_id = fields["span_id"].as_str().unwrap_or(&event.id);
+            let duration_ms = fields["duration_ms"].as_i64().unwrap_or(0);
+            let mut span = json!({
+                "traceId": hex_id(&event.session_id, 32),
+                "spanId": hex_id(span_id, 16),
+                "name": event.span_name.as_deref().unwrap_or(&event.message),
+                "kind": 1,
+                "startTimeUnixNano": nanos(event.timestamp - duration_ms),
+                "endTimeUnixNano": nanos(event.timestamp),
+                "attributes": attributes(event, &fields),
+            });
+            if let Some(parent) = fields["parent_span_id"].as_str() {
+                span["parentSpanId"] = hex_id(parent, 16).into();
+            }
+            if let Some(error) = fields["error"].as_str() {
+                span["status"] = json!({ "code": 2, "message": error });
+            }
+            span
+        })
+        .collect();
+    json!({
+        "resourceSpans": [{
+            "resource": resource(),
+            "scopeSpans": [{ "scope": { "name": SERVICE_NAME }, "spans": spans }],
+        }]
+    })
+}
+
+/// OTLP/JSON `ExportLogsServiceRequest` for plain events
+pub fn logs_body(events: &[&TraceEvent]) -> Value {
+    let records: Vec<Value> = events
+        .iter()
+        .map(|event| {
+            let fields = fields(event);
+            let mut record = json!({
+                "timeUnixNano": nanos(event.timestamp),
+                "severityNumber": severity(&event.level),
+                "severityText": event.level,
+                "body": { "stringValue": event.message },
+                "attributes": attributes(event, &fields),
+                "traceId": hex_id(&event.session_id, 32),
+            });
+            if let Some(span_id) = fields["span_id"].as_str() {
+                record["spanId"] = hex_id(span_id, 16).into();
+            }
+            record
+        })
+        .collect();
+    json!({
+        "resourceLogs": [{
+            "resource": resource(),
+            "scopeLogs": [{ "scope": { "name": SERVICE_NAME }, "logRecords": records }],
+        }]
+    })
+}
+
+fn resource() -> Value {
+    json!({ "attributes": [{ "key": "service.name", "value": { "stringValue": SERVICE_NAME } }] })
+}
+
+fn fields(event: &TraceEvent) -> Value {
+    serde_json::from_str(&event.fields).unwrap_or(Value::Null)
+}
+
+fn is_span_start(event: &TraceEvent) -> bool {
+    is_span_event(event, "started")
+}
+
+fn is_span_end(event: &TraceEvent) -> bool {
+    is_span_event(event, "finished")
+}
+
+/// Whether `event` is the start or end record written by a `TraceSpan`
+fn is_span_event(event: &TraceEvent, phase: &str) -> bool {
+    let Some(name) = &event.span_name else {
+        return false;
+    };
+    event.message == format!("{} {}", name, phase) && fields(event).get("span_id").is_some()
+}
+
+/// Event fields as OTLP key-values, plus the recording module
+fn attributes(event: &TraceEvent, fields: &Value) -> Vec<Value> {
+    let mut attributes = vec![json!({
+        "key": "nexus.target",
+        "value": { "stringValue": event.target },
+    })];
+    if let Some(map) = fields.as_object() {
+        for (key, value) in map {
+            if SPAN_FIELDS.contains(&key.as_str()) || value.is_null() {
+                continue;
+            }
+            attributes.push(json!({ "key": key, "value": any_value(value) }));
+        }
+    }
+    attributes
+}
+
+fn any_value(value: &Value) -> Value {
+    match value {
+        Value::String(s) => json!({ "stringValue": s }),
+        Value::Bool(b) => json!({ "boolValue": b }),
+        // int64 values are strings in OTLP/JSON
+        Value::Number(n) if n.is_i64() || n.is_u64() => json!({ "intValue": n.to_string() }),
+        Value::Number(n) => json!({ "doubleValue": n.as_f64() }),
+        other => json!({ "stringValue": other.to_string() }),
+    }
+}
+
+/// OTLP severity number for a recorder level
+fn severity(level: &str) -> u8 {
+    match level {
+        "TRACE" => 1,
+        "DEBUG" => 5,
+        "WARN" => 13,
+        "ERROR" => 17,
+        _ => 9,
+    }
+}
+
+/// Millisecond timestamp as the string nanosecond count OTLP/JSON expects
+fn nanos(millis: i64) -> String {
+    (millis.max(0) as u64 * 1_000_000).to_string()
+}
+
+/// First `len` hex digits of a UUID, the width of an OTLP trace or span ID
+fn hex_id(uuid: &str, len: usize) -> String {
+    let hex: String = uuid.chars().filter(char::is_ascii_hexdigit).collect();
+    format!("{:0>width$}", &hex[..hex.len().min(len)], width = len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(message: &str, span_name: Option<&str>, fields: Value) -> TraceEvent {
+        TraceEvent {
+            id: "e1".to_string(),
+            session_id: "6f1c2a4e-0b7d-4c55-9e2a-1d3f5b7a9c0e".to_string(),
+            timestamp: 5_000,
+            level: "INFO".to_string(),
+            target: "nexus::test".to_string(),
+            span_name: span_name.map(str::to_string),
+            message: message.to_string(),
+            fields: fields.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_traces_body() {
+        let end = event(
+            "navigate finished",
+            Some("navigate"),
+            json!({
+                "span_id": "a1b2c3d4-e5f6-4a7b-8c9d-0e1f2a3b4c5d",
+                "parent_span_id": "11111111-2222-4333-8444-555555555555",
+                "duration_ms": 1500,
+                "url": "https://example.com",
+            }),
+        );
+        assert!(is_span_end(&end));
+        assert!(!is_span_start(&end));
+
+        let body = traces_body(&[&end]);
+        let span = &body["resourceSpans"][0]["scopeSpans"][0]["spans"][0];
+        assert_eq!(span["traceId"], "6f1c2a4e0b7d4c559e2a1d3f5b7a9c0e");
+        assert_eq!(span["spanId"], "a1b2c3d4e5f64a7b");
+        assert_eq!(span["parentSpanId"], "1111111122224333");
+        assert_eq!(span["name"], "navigate");
+        assert_eq!(span["startTimeUnixNano"], "3500000000");
+        assert_eq!(span["endTimeUnixNano"], "5000000000");
+        let attributes = span["attributes"].as_array().unwrap();
+        assert_eq!(attributes.len(), 2);
+        assert_eq!(attributes[1]["key"], "url");
+    }
+
+    #[test]
+    fn test_logs_body() {
+        let warning = TraceEvent {
+            level: "WARN".to_string(),
+            ..event(
+                "Provider throttled",
+                None,
+                json!({ "attempt": 2, "wait_ms": 1.5 }),
+            )
+        };
+        assert!(!is_span_end(&warning));
+
+        let body = logs_body(&[&warning]);
+        let record = &body["resourceLogs"][0]["scopeLogs"][0]["logRecords"][0];
+        assert_eq!(record["severityNumber"], 13);
+        assert_eq!(record["body"]["stringValue"], "Provider throttled");
+        assert_eq!(record["attributes"][1]["value"]["intValue"], "2");
+        assert_eq!(record["attributes"][2]["value"]["doubleValue"], 1.5);
+        assert!(record.get("spanId").is_none());
+    }
+}
@@ -0,0 +1,95 @@
+//! Structured diffs of page content between tool steps
+//!
+//! After a click, type or scroll the agent usually sees the same page again
+//! with a few sections changed. Returning just the added and removed sections
+//! saves tokens and makes it obvious what the action did.
+
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+/// Sections added and removed between two versions of a page
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PageDiff {
+    /// Runs of consecutive added lines
+    pub added: Vec<String>,
+    /// Runs of consecutive removed lines
+    pub removed: Vec<String>,
+    pub unchanged_lines: usize,
+}
+
+impl PageDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Characters of changed content the diff carries
+    pub fn changed_chars(&self) -> usize {
+        self.added
+            .iter()
+            .chain(&self.removed)
+            .map(|section| section.chars().count())
+            .sum()
+    }
+
+    /// Whether sending the diff is cheaper than resending the page: it must
+    /// be under half the page, otherwise the page changed too much to be
+    /// worth reading as a diff
+    pub fn worth_sending(&self, page_chars: usize) -> bool {
+        self.changed_chars() * 2 < page_chars
+    }
+}
+
+/// Close the current run of changed lines as a section
+fn flush(tag: ChangeTag, section: &mut String, result: &mut PageDiff) {
+    let text = section.trim_end().to_string();
+    section.clear();
+    if text.trim().is_empty() {
+        return;
+    }
+    match tag {
+        ChangeTag::Insert => result.added.push(text),
+        ChangeTag::Delete => result.removed.push(text),
+        ChangeTag::Equal => {}
+    }
+}
+
+/// Diff `before` and `after` line by line, grouping changes into sections
+pub fn diff_pages(before: &str, after: &str) -> PageDiff {
+    let diff = TextDiff::from_lines(before, after);
+    let mut result = PageDiff::default();
+    let mut section = String::new();
+    let mut section_tag = ChangeTag::Equal;
+
+    for change in diff.iter_all_changes() {
+        let tag = change.tag();
+        if tag != section_tag {
+            flush(section_tag, &mut section, &mut result);
+            section_tag = tag;
+        }
+        match tag {
+            ChangeTag::Equal => result.unchanged_lines += 1,
+            _ => section.push_str(change.value()),
+        }
+    }
+    flush(section_tag, &mut section, &mut result);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_pages() {
+        let before = "# Shop\nWidget $10\nGadget $20\nFooter\n";
+        let after = "# Shop\nWidget $10\nGadget $18\nIn stock\nFooter\n";
+        let diff = diff_pages(before, after);
+        assert_eq!(diff.added, vec!["Gadget $18\nIn stock"]);
+        assert_eq!(diff.removed, vec!["Gadget $20"]);
+        assert_eq!(diff.unchanged_lines, 3);
+        assert!(diff.worth_sending(after.len() * 2));
+        assert!(!diff.worth_sending(after.len() / 2));
+
+        assert!(diff_pages(before, before).is_empty());
+    }
+}
@@ -0,0 +1,172 @@
+//! Plan-then-execute mode
+//!
+//! When enabled, the LLM first drafts a step plan for the prompt. The plan is
+//! emitted as a `plan` event and the run waits until the user approves it,
+//! possibly after editing, through the `approve_plan` command. The approved
+//! plan is then injected into the worker's system instructions.
+
+use crate::config::Config;
+use radkit::agent::LlmFunction;
+use radkit::macros::LLMOutput;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use tauri::Emitter;
+use tokio::sync::oneshot;
+
+/// Plans waiting for the user's decision, keyed by run_id
+static PENDING_PLANS: OnceLock<Mutex<HashMap<String, oneshot::Sender<PlanDecision>>>> =
+    OnceLock::new();
+
+/// Step plan drafted by the LLM
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct AgentPlan {
+    /// Ordered, concrete steps (which sites to visit, what to extract, how to report).
+    pub steps: Vec<String>,
+}
+
+/// Payload of the `plan` event
+#[derive(Debug, Clone, Serialize)]
+pub struct PlanProposal {
+    pub run_id: String,
+    pub prompt: String,
+    pub steps: Vec<String>,
+}
+
+/// The user's answer to a plan proposal
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlanDecision {
+    pub approved: bool,
+    /// Edited steps replacing the proposal; `None` keeps it as drafted
+    pub steps: Option<Vec<String>>,
+}
+
+/// Ask the LLM for a step plan for `prompt`
+pub async fn draft(config: &Config, prompt: &str) -> Result<Vec<String>, String> {
+    let llm = crate::providers::build_llm(config)?;
+    let function = LlmFunction::<AgentPlan>::new_with_system_instructions(
+        llm,
+        "You plan research tasks for an autonomous browser agent with tools to navigate, \
+         search pages, click, type, scroll, upload files and keep notes. Break the task into \
+         a short list of concrete steps. Do not execute anything.",
+    );
+    let plan = function
+        .run(format!("Task: {}", prompt))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(plan.steps)
+}
+
+/// Emit the proposal and wait for the user to approve or reject it.
+/// Returns the steps to execute.
+pub async fn await_decision(proposal: PlanProposal) -> Result<Vec<String>, String> {
+    let (tx, rx) = oneshot::channel();
+    PENDING_PLANS
+        .get_or_init(|| Mutex::new(HashMap::new()))
+        .lock()
+        .map_err(|_| "Plan state poisoned".to_string())?
+        .insert(proposal.run_id.clone(), tx);
+
+    crate::trace_info!(
+        "nexus::planning",
+        "Plan proposed",
+        run_id = proposal.run_id,
+        steps = proposal.steps.len()
+    );
+    if let Some(app) = crate::GLOBAL_APP.get() {
+        let _ = app.emit("plan", &proposal);
+    }
+
+    let decision = rx
+        .await
+        .map_err(|_| "Plan approval was abandoned".to_string())?;
+    if !decision.approved {
+        crate::trace_warn!("nexus::planning", "Plan rejected", run_id = proposal.run_id);
+        return Err("The user rejected the plan".to_string());
+    }
+    let steps = decision
+        .steps
+        .map(|steps| {
+            steps
+                .into_iter()
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or(proposal.steps);
+    crate::trace_info!(
+        "nexus::planning",
+        "Plan approved",
+        run_id = proposal.run_id,
+        steps = steps.len()
+    );
+    Ok(steps)
+}
+
+/// Deliver the user's decision for a run waiting on its plan
+pub fn resolve(run_id: &str, decision: PlanDecision) -> Result<(), String> {
+    let sender = PENDING_PLANS
+        .get()
+        .and_then(|plans| plans.lock().ok()?.remove(run_id))
+        .ok_or_else(|| format!("No plan awaiting approval for run {}", run_id))?;
+    sender
+        .send(decision)
+        .map_err(|_| "Run is no longer waiting for its plan".to_string())
+}
+
+/// Render an approved plan for the worker's system instructions
+pub fn format_plan(steps: &[String]) -> String {
+    let mut text = String::from(
+        "Follow this plan, approved by the user, step by step. Adapt only if a step turns out to be impossible:\n",
+    );
+    for (i, step) in steps.iter().enumerate() {
+        text.push_str(&format!("{}. {}\n", i + 1, step));
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_plan() {
+        let text = format_plan(&["Search for X".to_string(), "Summarize".to_string()]);
+        assert!(text.contains("1. Search for X\n2. Summarize\n"));
+    }
+
+    #[tokio::test]
+    async fn test_edited_plan_replaces_proposal() {
+        let proposal = PlanProposal {
+            run_id: "plan-run".to_string(),
+            prompt: "task".to_string(),
+            steps: vec!["draft step".to_string()],
+        };
+        let waiting = tokio::spawn(await_decision(proposal));
+        while !PENDING_PLANS
+            .get()
+            .is_some_and(|plans| plans.lock().unwrap().contains_key("plan-run"))
+        {
+            tokio::task::yield_now().await;
+        }
+        resolve(
+            "plan-run",
+            PlanDecision {
+                approved: true,
+                steps: Some(vec!["edited step".to_string(), "  ".to_string()]),
+            },
+        )
+        .unwrap();
+        assert_eq!(waiting.await.unwrap().unwrap(), vec!["edited step"]);
+
+        assert!(resolve(
+            "missing",
+            PlanDecision {
+                approved: true,
+                steps: None
+            }
+        )
+        .is_err());
+    }
+}
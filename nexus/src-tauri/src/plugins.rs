@@ -0,0 +1,448 @@
+//! Plugin system for custom agent tools
+//!
+//! Third-party tools implement [`NexusTool`]. They can be registered in-process
+//! with [`register_tool`] or dropped into the `plugins` directory of the app
+//! config dir as a JSON manifest pointing at a dynamic library or a WASM
+//! module. Everything is collected by [`discover`] at the start of each agent
+//! run.
+//!
+//! WASM plugins are untrusted and run inside the sandbox described in
+//! [`crate::sandbox`]. A dylib plugin runs with full process privileges and
+//! must export the following C ABI functions:
+//!
+//! ```c
+//! char* nexus_tool_call(const char* args_json); // returns {"ok": ...} or {"error": "..."}
+//! void nexus_free_string(char* s);
+//! ```
+
+use crate::sandbox::{Capabilities, ResourceLimits, WasmSandbox};
+use async_trait::async_trait;
+use radkit::tools::{FunctionTool, ToolResult};
+use serde::{Deserialize, Serialize};
+use std::ffi::{c_char, CStr, CString};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+/// Global plugin registry instance
+pub static GLOBAL_PLUGINS: OnceLock<Mutex<PluginRegistry>> = OnceLock::new();
+
+/// A tool that can be exposed to the agent without modifying the crate
+#[async_trait]
+pub trait NexusTool: Send + Sync {
+    /// Unique tool name presented to the LLM
+    fn name(&self) -> &str;
+    /// Description presented to the LLM
+    fn description(&self) -> &str;
+    /// JSON schema of the tool arguments
+    fn parameters_schema(&self) -> serde_json::Value;
+    /// Execute the tool with JSON arguments
+    async fn call(&self, args: serde_json::Value) -> Result<serde_json::Value, String>;
+}
+
+/// On-disk description of a plugin tool
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub description: String,
+    #[serde(default = "default_parameters")]
+    pub parameters: serde_json::Value,
+    pub kind: PluginKind,
+    /// Library or module path, relative to the manifest unless absolute
+    pub path: PathBuf,
+    /// Sandbox grants (WASM plugins only)
+    #[serde(default)]
+    pub capabilities: Capabilities,
+    /// Sandbox resource limits (WASM plugins only)
+    #[serde(default)]
+    pub limits: ResourceLimits,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PluginKind {
+    Dylib,
+    Wasm,
+}
+
+/// Name, description and argument schema of a tool, known without loading it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ToolSummary {
+    pub name: String,
+    pub description: String,
+    pub parameters: serde_json::Value,
+}
+
+fn default_parameters() -> serde_json::Value {
+    serde_json::json!({ "type": "object", "properties": {} })
+}
+
+/// Holds in-process tools and the directory scanned for plugin manifests
+pub struct PluginRegistry {
+    plugins_dir: PathBuf,
+    registered: Vec<Arc<dyn NexusTool>>,
+}
+
+impl PluginRegistry {
+    pub fn new(plugins_dir: PathBuf) -> Self {
+        Self {
+            plugins_dir,
+            registered: Vec::new(),
+        }
+    }
+
+    pub fn plugins_dir(&self) -> &Path {
+        &self.plugins_dir
+    }
+
+    pub fn register(&mut self, tool: Arc<dyn NexusTool>) {
+        self.registered.retain(|t| t.name() != tool.name());
+        self.registered.push(tool);
+    }
+
+    /// Describe the registered tools and every plugin manifest in the
+    /// directory, reading the manifests only. Cheap enough for listings,
+    /// unlike [`PluginRegistry::discover`], which loads every plugin.
+    pub fn summaries(&self) -> Vec<ToolSummary> {
+        let mut tools: Vec<ToolSummary> = self
+            .registered
+            .iter()
+            .map(|tool| ToolSummary {
+                name: tool.name().to_string(),
+                description: tool.description().to_string(),
+                parameters: tool.parameters_schema(),
+            })
+            .collect();
+        let Ok(entries) = fs::read_dir(&self.plugins_dir) else {
+            return tools;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let Some(manifest) = fs::read_to_string(&path)
+                .ok()
+                .and_then(|text| serde_json::from_str::<PluginManifest>(&text).ok())
+            else {
+                continue;
+            };
+            tools.retain(|t| t.name != manifest.name);
+            tools.push(ToolSummary {
+                name: manifest.name,
+                description: manifest.description,
+                parameters: manifest.parameters,
+            });
+        }
+        tools
+    }
+
+    /// Collect registered tools plus every loadable plugin in the directory
+    pub fn discover(&self) -> Vec<Arc<dyn NexusTool>> {
+        let mut tools = self.registered.clone();
+        let Ok(entries) = fs::read_dir(&self.plugins_dir) else {
+            return tools;
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match load_plugin(&path) {
+                Ok(tool) => {
+                    crate::trace_info!(
+                        "nexus::plugins",
+                        "Plugin loaded",
+                        name = tool.name(),
+                        path = path.display().to_string()
+                    );
+                    tools.retain(|t| t.name() != tool.name());
+                    tools.push(tool);
+                }
+                Err(e) => {
+                    crate::trace_error!(
+                        "nexus::plugins",
+                        "Failed to load plugin",
+                        path = path.display().to_string(),
+                        error = e.to_string()
+                    );
+                }
+            }
+        }
+        tools
+    }
+}
+
+/// Load a single plugin from its manifest
+pub fn load_plugin(manifest_path: &Path) -> anyhow::Result<Arc<dyn NexusTool>> {
+    let manifest: PluginManifest = serde_json::from_str(&fs::read_to_string(manifest_path)?)?;
+    let lib_path = match manifest_path.parent() {
+        Some(dir) if manifest.path.is_relative() => dir.join(&manifest.path),
+        _ => manifest.path.clone(),
+    };
+    match manifest.kind {
+        PluginKind::Dylib => Ok(Arc::new(DylibTool::load(manifest, &lib_path)?)),
+        PluginKind::Wasm => Ok(Arc::new(WasmTool::load(manifest, &lib_path)?)),
+    }
+}
+
+/// Tool backed by a sandboxed WASI module
+pub struct WasmTool {
+    manifest: PluginManifest,
+    sandbox: Arc<WasmSandbox>,
+}
+
+impl WasmTool {
+    pub fn load(manifest: PluginManifest, path: &Path) -> anyhow::Result<Self> {
+        let sandbox = WasmSandbox::load(
+            &manifest.name,
+            path,
+            manifest.capabilities.clone(),
+            manifest.limits.clone(),
+        )?;
+        Ok(Self {
+            manifest,
+            sandbox: Arc::new(sandbox),
+        })
+    }
+}
+
+#[async_trait]
+impl NexusTool for WasmTool {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn description(&self) -> &str {
+        &self.manifest.description
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.manifest.parameters.clone()
+    }
+
+    async fn call(&self, args: serde_json::Value) -> Result<serde_json::Value, String> {
+        let sandbox = self.sandbox.clone();
+        let args = args.to_string();
+        let output = tokio::task::spawn_blocking(move || sandbox.call(&args))
+            .await
+            .map_err(|e| format!("Plugin panicked: {}", e))?
+            .map_err(|e| e.to_string())?;
+        parse_plugin_output(&output)
+    }
+}
+
+/// Tool backed by a dynamic library exporting the Nexus C ABI
+pub struct DylibTool {
+    manifest: PluginManifest,
+    library: Arc<libloading::Library>,
+}
+
+type CallFn = unsafe extern "C" fn(*const c_char) -> *mut c_char;
+type FreeFn = unsafe extern "C" fn(*mut c_char);
+
+impl DylibTool {
+    pub fn load(manifest: PluginManifest, path: &Path) -> anyhow::Result<Self> {
+        // SAFETY: loading a library runs its initializers; plugins are trusted
+        // by virtue of the user placing them in the plugins directory.
+        let library = unsafe { libloading::Library::new(path)? };
+        // Fail early if the expected symbols are missing.
+        unsafe {
+            library.get::<CallFn>(b"nexus_tool_call\0")?;
+            library.get::<FreeFn>(b"nexus_free_string\0")?;
+        }
+        Ok(Self {
+            manifest,
+            library: Arc::new(library),
+        })
+    }
+
+    fn call_blocking(library: &libloading::Library, args: &str) -> Result<serde_json::Value, String> {
+        let input = CString::new(args).map_err(|e| e.to_string())?;
+        let output = unsafe {
+            let call = library
+                .get::<CallFn>(b"nexus_tool_call\0")
+                .map_err(|e| e.to_string())?;
+            let free = library
+                .get::<FreeFn>(b"nexus_free_string\0")
+                .map_err(|e| e.to_string())?;
+            let raw = call(input.as_ptr());
+            if raw.is_null() {
+                return Err("Plugin returned no result".to_string());
+            }
+            let text = CStr::from_ptr(raw).to_string_lossy().into_owned();
+            free(raw);
+            text
+        };
+        parse_plugin_output(&output)
+    }
+}
+
+#[async_trait]
+impl NexusTool for DylibTool {
+    fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    fn description(&self) -> &str {
+        &self.manifest.description
+    }
+
+    fn parameters_schema(&self) -> serde_json::Value {
+        self.manifest.parameters.clone()
+    }
+
+    async fn call(&self, args: serde_json::Value) -> Result<serde_json::Value, String> {
+        let library = self.library.clone();
+        let args = args.to_string();
+        tokio::task::spawn_blocking(move || Self::call_blocking(&library, &args))
+            .await
+            .map_err(|e| format!("Plugin panicked: {}", e))?
+    }
+}
+
+/// Interpret a plugin's `{"ok": ...}` / `{"error": "..."}` response
+pub fn parse_plugin_output(output: &str) -> Result<serde_json::Value, String> {
+    let value: serde_json::Value =
+        serde_json::from_str(output).map_err(|e| format!("Invalid plugin output: {}", e))?;
+    if let Some(err) = value.get("error") {
+        return Err(err.as_str().map(String::from).unwrap_or_else(|| err.to_string()));
+    }
+    Ok(value.get("ok").cloned().unwrap_or(value))
+}
+
+/// Adapt a [`NexusTool`] into a radkit tool for the `LlmWorker`
+pub fn to_function_tool(tool: Arc<dyn NexusTool>) -> Arc<FunctionTool> {
+    let name = tool.name().to_string();
+    let description = tool.description().to_string();
+    let schema = tool.parameters_schema();
+    Arc::new(
+        FunctionTool::new(name, description, move |args, _ctx| {
+            let tool = tool.clone();
+            Box::pin(async move {
+                let args = serde_json::to_value(&args).unwrap_or_default();
+                crate::trace_info!("nexus::plugins", "Plugin tool called", name = tool.name());
+                match tool.call(args).await {
+                    Ok(value) => ToolResult::success(value),
+                    Err(e) => {
+                        crate::trace_error!(
+                            "nexus::plugins",
+                            "Plugin tool failed",
+                            name = tool.name(),
+                            error = e
+                        );
+                        ToolResult::error(e)
+                    }
+                }
+            })
+        })
+        .with_parameters_schema(schema),
+    )
+}
+
+/// Initialize the plugin registry under the app config dir
+pub fn init_plugins(config_dir: &Path) {
+    let dir = config_dir.join("plugins");
+    let _ = fs::create_dir_all(&dir);
+    let _ = GLOBAL_PLUGINS.set(Mutex::new(PluginRegistry::new(dir)));
+}
+
+/// Register an in-process tool with the global registry
+pub fn register_tool(tool: Arc<dyn NexusTool>) {
+    if let Some(registry) = GLOBAL_PLUGINS.get() {
+        if let Ok(mut guard) = registry.lock() {
+            guard.register(tool);
+        }
+    }
+}
+
+/// Describe all plugin tools without loading them
+pub fn summaries() -> Vec<ToolSummary> {
+    GLOBAL_PLUGINS
+        .get()
+        .and_then(|registry| registry.lock().ok().map(|guard| guard.summaries()))
+        .unwrap_or_default()
+}
+
+/// Discover all plugin tools available for a run
+pub fn discover() -> Vec<Arc<dyn NexusTool>> {
+    GLOBAL_PLUGINS
+        .get()
+        .and_then(|registry| registry.lock().ok().map(|guard| guard.discover()))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Echo;
+
+    #[async_trait]
+    impl NexusTool for Echo {
+        fn name(&self) -> &str {
+            "echo"
+        }
+        fn description(&self) -> &str {
+            "Echo the arguments"
+        }
+        fn parameters_schema(&self) -> serde_json::Value {
+            default_parameters()
+        }
+        async fn call(&self, args: serde_json::Value) -> Result<serde_json::Value, String> {
+            Ok(args)
+        }
+    }
+
+    #[test]
+    fn test_parse_plugin_output() {
+        assert_eq!(
+            parse_plugin_output(r#"{"ok": {"a": 1}}"#).unwrap(),
+            serde_json::json!({"a": 1})
+        );
+        assert_eq!(
+            parse_plugin_output(r#"{"error": "boom"}"#).unwrap_err(),
+            "boom"
+        );
+        assert!(parse_plugin_output("not json").is_err());
+    }
+
+    #[test]
+    fn test_registry_discovers_registered_and_skips_bad_manifests() {
+        let dir = std::env::temp_dir().join(format!("nexus-plugins-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let mut registry = PluginRegistry::new(dir.clone());
+        registry.register(Arc::new(Echo));
+        registry.register(Arc::new(Echo));
+
+        let tools = registry.discover();
+        assert_eq!(tools.len(), 1);
+        assert_eq!(tools[0].name(), "echo");
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_summaries_read_manifests_without_loading() {
+        let dir = std::env::temp_dir().join(format!("nexus-plugins-{}", uuid::Uuid::new_v4()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("broken.json"), "{").unwrap();
+        fs::write(
+            dir.join("weather.json"),
+            r#"{"name": "weather", "description": "Look up the weather", "kind": "wasm", "path": "missing.wasm"}"#,
+        )
+        .unwrap();
+
+        let mut registry = PluginRegistry::new(dir.clone());
+        registry.register(Arc::new(Echo));
+
+        let names: Vec<String> = registry.summaries().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["echo", "weather"]);
+        // The module doesn't exist, so only a listing can include the tool
+        assert_eq!(registry.discover().len(), 1);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
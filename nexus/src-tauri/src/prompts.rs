@@ -0,0 +1,126 @@
+//! Editable prompt pack
+//!
+//! The worker's system instructions and per-tool guidance can be tuned in a
+//! `prompt_pack.json` next to the config, without recompiling. Tool
+//! declarations are compiled into the worker, so description overrides are
+//! presented to the model as a "how to use your tools" section of the system
+//! instructions, which takes precedence over the built-in wording.
+
+use crate::config::{Config, DEFAULT_SYSTEM_PROMPT};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+static PROMPT_PACK_PATH: OnceLock<PathBuf> = OnceLock::new();
+
+/// Names of the built-in worker tools a pack can describe
+pub const BUILTIN_TOOLS: &[&str] = &[
+    "navigate",
+    "find_in_page",
+    "click",
+    "type_input",
+    "scroll",
+    "upload",
+    "memorize",
+    "recall",
+    "extract_with_recipe",
+    "extract_structured",
+    "request_human_action",
+    "bookmark_position",
+    "return_to_bookmark",
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct PromptPack {
+    /// Replaces the default system prompt for the agent worker
+    pub system_instructions: Option<String>,
+    /// Tool name to the description the model should go by
+    pub tool_descriptions: BTreeMap<String, String>,
+}
+
+impl PromptPack {
+    /// Worker instructions: a system prompt customized in the config (or for
+    /// the run) wins, then the pack's, then the default
+    pub fn instructions(&self, config: &Config) -> String {
+        let configured = config.system_prompt();
+        let base = match &self.system_instructions {
+            Some(pack) if configured == DEFAULT_SYSTEM_PROMPT && !pack.trim().is_empty() => {
+                pack.as_str()
+            }
+            _ => configured,
+        };
+        match self.tool_guidance() {
+            Some(guidance) => format!("{}\n\n{}", base, guidance),
+            None => base.to_string(),
+        }
+    }
+
+    /// The tool description overrides as a section of the instructions
+    pub fn tool_guidance(&self) -> Option<String> {
+        let lines: Vec<String> = self
+            .tool_descriptions
+            .iter()
+            .filter(|(_, description)| !description.trim().is_empty())
+            .map(|(name, description)| format!("- {}: {}", name, description.trim()))
+            .collect();
+        if lines.is_empty() {
+            return None;
+        }
+        Some(format!(
+            "How to use your tools (follow these over the tools' own descriptions):\n{}",
+            lines.join("\n")
+        ))
+    }
+}
+
+pub fn init_prompt_pack(config_dir: &Path) {
+    let _ = PROMPT_PACK_PATH.set(config_dir.join("prompt_pack.json"));
+}
+
+/// The saved prompt pack, or an empty one if none exists
+pub fn load() -> PromptPack {
+    PROMPT_PACK_PATH
+        .get()
+        .and_then(|path| fs::read_to_string(path).ok())
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+pub fn save(pack: &PromptPack) -> Result<(), String> {
+    let path = PROMPT_PACK_PATH
+        .get()
+        .ok_or_else(|| "Prompt pack not initialized".to_string())?;
+    let content = serde_json::to_string_pretty(pack).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instructions_precedence() {
+        let mut pack = PromptPack::default();
+        let config = Config::default();
+        assert_eq!(pack.instructions(&config), DEFAULT_SYSTEM_PROMPT);
+
+        pack.system_instructions = Some("Be terse.".to_string());
+        pack.tool_descriptions
+            .insert("navigate".to_string(), "Open a URL.".to_string());
+        assert_eq!(
+            pack.instructions(&config),
+            "Be terse.\n\nHow to use your tools (follow these over the tools' own descriptions):\n- navigate: Open a URL."
+        );
+
+        let custom = Config {
+            system_prompt: "You are a shopping assistant.".to_string(),
+            ..Config::default()
+        };
+        assert!(pack
+            .instructions(&custom)
+            .starts_with("You are a shopping assistant.\n\n"));
+    }
+}
@@ -0,0 +1,298 @@
+//! LLM provider construction
+//!
+//! Maps the configured provider name to a radkit client. Every client is
+//! wrapped in [`MeteredLlm`] so usage tracking applies regardless of which
+//! feature issues the LLM call.
+//!
+//! Local Ollama models are reached through Ollama's OpenAI-compatible
+//! endpoint, which translates tool calls to and from the models' native
+//! tool-calling format, so they drive the agent like any hosted model.
+//! The `custom` provider covers any other server speaking the OpenAI
+//! chat-completions protocol (LM Studio, vLLM, llama.cpp server, proxies):
+//! the base URL, key and model are passed through verbatim.
+//!
+//! Hosted providers whose APIs are OpenAI-compatible, function calling
+//! included, are listed in [`OPENAI_COMPATIBLE`] and share the OpenAI client;
+//! a configured `base_url` replaces their default endpoint.
+//!
+//! Temperature and the output token cap are applied to every client.
+
+use crate::config::Config;
+use crate::usage::MeteredLlm;
+use radkit::models::providers::{
+    AnthropicLlm, DeepSeekLlm, GeminiLlm, GrokLlm, OpenAILlm, OpenRouterLlm,
+};
+use radkit::models::BaseLlm;
+
+/// Provider name, display name and default endpoint of hosted providers
+/// reached through the OpenAI client
+pub const OPENAI_COMPATIBLE: &[(&str, &str, &str)] = &[
+    ("mistral", "Mistral", "https://api.mistral.ai/v1"),
+    ("groq", "Groq", "https://api.groq.com/openai/v1"),
+];
+
+/// Providers with a dedicated client or setup; see also [`OPENAI_COMPATIBLE`]
+pub const PROVIDERS: &[&str] = &[
+    "anthropic",
+    "openai",
+    "openrouter",
+    "gemini",
+    "grok",
+    "deepseek",
+    "ollama",
+    "custom",
+];
+
+/// Whether `provider` names a supported provider
+pub fn is_supported(provider: &str) -> bool {
+    let provider = provider.to_lowercase();
+    PROVIDERS.contains(&provider.as_str()) || openai_compatible(&provider).is_some()
+}
+
+/// Whether `provider` can be used without an API key
+pub fn key_optional(provider: &str) -> bool {
+    matches!(provider.to_lowercase().as_str(), "ollama" | "custom")
+}
+
+fn openai_compatible(provider: &str) -> Option<(&'static str, &'static str)> {
+    OPENAI_COMPATIBLE
+        .iter()
+        .find(|(name, _, _)| *name == provider)
+        .map(|&(_, display, endpoint)| (display, endpoint))
+}
+
+/// Apply the configured generation parameters to a radkit client
+macro_rules! with_generation_params {
+    ($llm:expr, $config:expr) => {{
+        let mut llm = $llm;
+        if let Some(temperature) = $config.temperature {
+            llm = llm.with_temperature(temperature);
+        }
+        if let Some(max_tokens) = $config.max_output_tokens {
+            llm = llm.with_max_tokens(max_tokens);
+        }
+        llm
+    }};
+}
+
+/// Highest sampling temperature `provider` accepts
+fn max_temperature(provider: &str) -> f32 {
+    match provider.to_lowercase().as_str() {
+        "anthropic" => 1.0,
+        _ => 2.0,
+    }
+}
+
+/// Check the generation parameters are within the ranges the configured
+/// provider accepts
+pub fn validate_generation_params(config: &Config) -> Result<(), String> {
+    if let Some(temperature) = config.temperature {
+        let max = max_temperature(&config.provider);
+        if !(0.0..=max).contains(&temperature) {
+            return Err(format!(
+                "Temperature for {} must be between 0 and {}, got {}",
+                config.provider, max, temperature
+            ));
+        }
+    }
+    if config.max_output_tokens == Some(0) {
+        return Err("Max output tokens must be at least 1".to_string());
+    }
+    Ok(())
+}
+
+/// Build the LLM client described by `config`
+pub fn build_llm(config: &Config) -> Result<MeteredLlm, String> {
+    let provider = config.provider.to_lowercase();
+    let model_name = config.model.clone();
+    let api_key = config.api_key.clone();
+
+    validate_generation_params(config)?;
+
+    crate::trace_info!(
+        "nexus::providers",
+        "Configuring LLM",
+        provider = provider,
+        model = model_name
+    );
+
+    // Keys go straight to the client rather than through the process
+    // environment, so concurrent runs with different credentials can't see
+    // each other's keys
+    let api_key = match provider.as_str() {
+        // Ollama ignores the key, but the OpenAI client requires one
+        "ollama" if api_key.is_empty() => "ollama".to_string(),
+        // Many self-hosted servers run without a key
+        "custom" if api_key.is_empty() => "none".to_string(),
+        p if api_key.is_empty() && is_supported(p) => {
+            return Err(creation_failed(
+                p,
+                format!("No API key is configured for {}", p),
+            ));
+        }
+        _ => api_key,
+    };
+
+    let llm: Box<dyn BaseLlm> = match provider.as_str() {
+        "anthropic" => Box::new(with_generation_params!(
+            AnthropicLlm::new(model_name, api_key),
+            config
+        )),
+        "openai" => {
+            let mut llm = with_generation_params!(OpenAILlm::new(model_name, api_key), config);
+            if let Some(base_url) = &config.base_url {
+                if !base_url.is_empty() {
+                    crate::trace_debug!(
+                        "nexus::providers",
+                        "Using custom base URL",
+                        base_url = base_url
+                    );
+                    llm = llm.with_base_url(base_url.clone());
+                }
+            }
+            Box::new(llm)
+        }
+        "openrouter" => Box::new(with_generation_params!(
+            OpenRouterLlm::new(model_name, api_key)
+                .with_site_url("https://nexus.local")
+                .with_app_name("Nexus Agent"),
+            config
+        )),
+        "gemini" => Box::new(with_generation_params!(
+            GeminiLlm::new(model_name, api_key),
+            config
+        )),
+        "grok" => Box::new(with_generation_params!(
+            GrokLlm::new(model_name, api_key),
+            config
+        )),
+        "deepseek" => Box::new(with_generation_params!(
+            DeepSeekLlm::new(model_name, api_key),
+            config
+        )),
+        "ollama" => {
+            let base_url = ollama_base_url(config);
+            crate::trace_debug!(
+                "nexus::providers",
+                "Using Ollama server",
+                base_url = base_url
+            );
+            Box::new(with_generation_params!(
+                OpenAILlm::new(model_name, api_key).with_base_url(base_url),
+                config
+            ))
+        }
+        "custom" => {
+            let base_url = config
+                .base_url
+                .as_deref()
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .ok_or_else(|| {
+                    creation_failed("Custom", "The custom provider needs a base URL".to_string())
+                })?;
+            crate::trace_debug!(
+                "nexus::providers",
+                "Using custom OpenAI-compatible server",
+                base_url = base_url
+            );
+            Box::new(with_generation_params!(
+                OpenAILlm::new(model_name, api_key).with_base_url(base_url.to_string()),
+                config
+            ))
+        }
+        p if openai_compatible(p).is_some() => {
+            let (_, endpoint) = openai_compatible(p).unwrap_or_default();
+            let base_url = config
+                .base_url
+                .as_deref()
+                .map(str::trim)
+                .filter(|url| !url.is_empty())
+                .unwrap_or(endpoint);
+            Box::new(with_generation_params!(
+                OpenAILlm::new(model_name, api_key).with_base_url(base_url.to_string()),
+                config
+            ))
+        }
+        _ => {
+            crate::trace_error!(
+                "nexus::providers",
+                "Unsupported provider",
+                provider = provider
+            );
+            return Err(format!("Unsupported LLM_PROVIDER: {}", provider));
+        }
+    };
+
+    crate::trace_debug!("nexus::providers", "LLM created", provider = provider);
+    // Every provider gets its shared limiter, even without configured limits,
+    // so a throttled call makes concurrent callers back off too
+    let limit = config
+        .rate_limits
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(&provider))
+        .map(|(_, limit)| limit.clone())
+        .unwrap_or_default();
+    let limiter = crate::ratelimit::limiter_for(&provider, limit);
+    Ok(MeteredLlm::new(llm).with_rate_limiter(limiter))
+}
+
+/// OpenAI-compatible endpoint of the configured Ollama server
+pub fn ollama_base_url(config: &Config) -> String {
+    let host = config.ollama_host.trim().trim_end_matches('/');
+    let host = if host.is_empty() { "localhost" } else { host };
+    if host.contains("://") {
+        format!("{}:{}/v1", host, config.ollama_port)
+    } else {
+        format!("http://{}:{}/v1", host, config.ollama_port)
+    }
+}
+
+fn creation_failed(provider: &str, error: String) -> String {
+    crate::trace_error!(
+        "nexus::providers",
+        "Failed to create LLM",
+        provider = provider,
+        error = error
+    );
+    error
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ollama_base_url() {
+        let mut config = Config::default();
+        assert_eq!(ollama_base_url(&config), "http://localhost:11434/v1");
+
+        config.ollama_host = "https://gpu-box.lan/".to_string();
+        config.ollama_port = 8080;
+        assert_eq!(ollama_base_url(&config), "https://gpu-box.lan:8080/v1");
+    }
+
+    #[test]
+    fn test_validate_generation_params() {
+        let mut config = Config::default();
+        assert!(validate_generation_params(&config).is_ok());
+
+        config.temperature = Some(0.0);
+        config.max_output_tokens = Some(4096);
+        assert!(validate_generation_params(&config).is_ok());
+
+        config.temperature = Some(2.5);
+        assert!(validate_generation_params(&config).is_err());
+
+        // Anthropic caps temperature at 1, OpenAI at 2
+        config.temperature = Some(1.5);
+        config.provider = "anthropic".to_string();
+        assert!(validate_generation_params(&config).is_err());
+        config.provider = "openai".to_string();
+        assert!(validate_generation_params(&config).is_ok());
+        config.temperature = Some(0.2);
+
+        config.max_output_tokens = Some(0);
+        assert!(validate_generation_params(&config).is_err());
+    }
+}
@@ -0,0 +1,259 @@
+//! Agent run queue
+//!
+//! Agent runs started from the UI, saved tasks and the scheduler are queued
+//! here instead of competing for the browser and the provider's rate limits.
+//! At most `max_concurrent_runs` jobs run at once; the rest wait in
+//! submission order. Queue changes are emitted as `queue-changed` events.
+
+use serde::{Deserialize, Serialize};
+use std::future::Future;
+use std::sync::{Mutex, OnceLock};
+use tauri::Emitter;
+use tokio::sync::Notify;
+
+/// Finished jobs kept for `get_queue`
+const MAX_FINISHED: usize = 50;
+
+static GLOBAL_QUEUE: OnceLock<JobQueue> = OnceLock::new();
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Pending,
+    Running,
+    Done,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Job {
+    /// The run_id of the run the job drives
+    pub id: String,
+    /// What the job runs, e.g. the run's prompt
+    pub label: String,
+    pub status: JobStatus,
+    pub queued_at: i64,
+    pub started_at: Option<i64>,
+    pub finished_at: Option<i64>,
+    pub error: Option<String>,
+}
+
+pub struct JobQueue {
+    jobs: Mutex<Vec<Job>>,
+    changed: Notify,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JobQueue {
+    pub fn new() -> Self {
+        Self {
+            jobs: Mutex::new(Vec::new()),
+            changed: Notify::new(),
+        }
+    }
+
+    /// All queued, running and recently finished jobs, oldest first
+    pub fn snapshot(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    /// Queue `job` under `id` and run it once fewer than `max_concurrent` jobs
+    /// are running and every job queued before it has started
+    pub async fn run<F, T>(
+        &self,
+        id: &str,
+        label: &str,
+        max_concurrent: usize,
+        job: F,
+    ) -> Result<T, String>
+    where
+        F: Future<Output = Result<T, String>>,
+    {
+        let id = id.to_string();
+        self.update(|jobs| {
+            // A resumed run is queued again under its id
+            jobs.retain(|j| j.id != id || !is_finished(j));
+            jobs.push(Job {
+                id: id.clone(),
+                label: label.to_string(),
+                status: JobStatus::Pending,
+                queued_at: chrono::Utc::now().timestamp_millis(),
+                started_at: None,
+                finished_at: None,
+                error: None,
+            })
+        });
+
+        // Drop the job from the queue if this future is dropped before it
+        // finishes, so it cannot hold up the jobs behind it
+        let mut guard = Abandoned {
+            queue: self,
+            id: &id,
+            armed: true,
+        };
+
+        loop {
+            // Register for wakeups before checking, so a job finishing in
+            // between is not missed
+            let changed = self.changed.notified();
+            if self.try_start(&id, max_concurrent.max(1)) {
+                break;
+            }
+            crate::trace_debug!("nexus::queue", "Job waiting for a free slot", id = id);
+            changed.await;
+        }
+
+        let result = job.await;
+        guard.armed = false;
+        let error = result.as_ref().err().cloned();
+        self.update(|jobs| {
+            if let Some(job) = jobs.iter_mut().find(|j| j.id == id) {
+                job.status = if error.is_some() {
+                    JobStatus::Failed
+                } else {
+                    JobStatus::Done
+                };
+                job.finished_at = Some(chrono::Utc::now().timestamp_millis());
+                job.error = error;
+            }
+            prune_finished(jobs);
+        });
+        result
+    }
+
+    fn try_start(&self, id: &str, max_concurrent: usize) -> bool {
+        let mut jobs = self.jobs.lock().unwrap();
+        let running = jobs
+            .iter()
+            .filter(|j| j.status == JobStatus::Running)
+            .count();
+        let next = jobs.iter().position(|j| j.status == JobStatus::Pending);
+        let Some(index) = next.filter(|&i| jobs[i].id == id && running < max_concurrent) else {
+            return false;
+        };
+        jobs[index].status = JobStatus::Running;
+        jobs[index].started_at = Some(chrono::Utc::now().timestamp_millis());
+        drop(jobs);
+        self.changed.notify_waiters();
+        emit_changed(self);
+        true
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Vec<Job>)) {
+        f(&mut self.jobs.lock().unwrap());
+        self.changed.notify_waiters();
+        emit_changed(self);
+    }
+}
+
+struct Abandoned<'a> {
+    queue: &'a JobQueue,
+    id: &'a str,
+    armed: bool,
+}
+
+impl Drop for Abandoned<'_> {
+    fn drop(&mut self) {
+        if self.armed {
+            self.queue.update(|jobs| jobs.retain(|j| j.id != self.id));
+        }
+    }
+}
+
+fn is_finished(job: &Job) -> bool {
+    matches!(job.status, JobStatus::Done | JobStatus::Failed)
+}
+
+fn prune_finished(jobs: &mut Vec<Job>) {
+    let finished = jobs.iter().filter(|j| is_finished(j)).count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED);
+    jobs.retain(|j| {
+        if excess > 0 && is_finished(j) {
+            excess -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+fn emit_changed(queue: &JobQueue) {
+    if let Some(app) = crate::GLOBAL_APP.get() {
+        let _ = app.emit("queue-changed", queue.snapshot());
+    }
+}
+
+/// The queue shared by all agent runs
+pub fn queue() -> &'static JobQueue {
+    GLOBAL_QUEUE.get_or_init(JobQueue::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use tokio::sync::oneshot;
+
+    #[tokio::test]
+    async fn test_queue_limits_concurrency() {
+        let queue = Arc::new(JobQueue::new());
+        let (release_first, first_gate) = oneshot::channel::<()>();
+
+        let q = queue.clone();
+        let first = tokio::spawn(async move {
+            q.run("run-1", "first", 1, async {
+                first_gate.await.unwrap();
+                Ok::<_, String>(1)
+            })
+            .await
+        });
+        let q = queue.clone();
+        let second = tokio::spawn(async move {
+            q.run("run-2", "second", 1, async {
+                Err::<i32, _>("boom".to_string())
+            })
+            .await
+        });
+
+        while queue.snapshot().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        let statuses: Vec<JobStatus> = queue.snapshot().iter().map(|j| j.status).collect();
+        assert_eq!(statuses, vec![JobStatus::Running, JobStatus::Pending]);
+
+        release_first.send(()).unwrap();
+        assert_eq!(first.await.unwrap(), Ok(1));
+        assert_eq!(second.await.unwrap(), Err("boom".to_string()));
+
+        let jobs = queue.snapshot();
+        assert_eq!(jobs[0].id, "run-1");
+        assert_eq!(jobs[0].status, JobStatus::Done);
+        assert_eq!(jobs[1].status, JobStatus::Failed);
+        assert_eq!(jobs[1].error.as_deref(), Some("boom"));
+    }
+
+    #[test]
+    fn test_prune_finished() {
+        let job = |status| Job {
+            id: String::new(),
+            label: String::new(),
+            status,
+            queued_at: 0,
+            started_at: None,
+            finished_at: None,
+            error: None,
+        };
+        let mut jobs: Vec<Job> = (0..MAX_FINISHED + 2)
+            .map(|_| job(JobStatus::Done))
+            .collect();
+        jobs.push(job(JobStatus::Pending));
+        prune_finished(&mut jobs);
+        assert_eq!(jobs.len(), MAX_FINISHED + 1);
+        assert_eq!(jobs.last().unwrap().status, JobStatus::Pending);
+    }
+}
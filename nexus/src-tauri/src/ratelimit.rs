@@ -0,0 +1,353 @@
+//! Provider rate limiting shared across runs
+//!
+//! Every LLM call goes through [`crate::usage::MeteredLlm`], which waits on the
+//! limiter for its provider before sending the request. Limiters are global
+//! per provider, so concurrent runs, chat sessions and orchestrator sub-agents
+//! all draw from the same requests-per-minute and tokens-per-minute budget
+//! instead of each tripping the provider's 429s mid-run.
+//!
+//! When a provider throttles anyway (429, 503, "overloaded"), the call is
+//! retried after the delay the provider asked for, or with exponential
+//! backoff when it gave none, and the provider's limiter holds back every
+//! other caller for the same time. See [`parse_throttle`].
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+/// Length of the sliding window limits apply to
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Retries of an LLM call the provider keeps throttling before giving up
+pub const MAX_THROTTLE_RETRIES: u32 = 5;
+
+/// Wait before the first retry when the provider gives no hint; doubles with
+/// each further attempt
+const THROTTLE_BASE_DELAY: Duration = Duration::from_secs(2);
+
+/// Longest single wait, whatever the provider asks for
+const MAX_THROTTLE_DELAY: Duration = Duration::from_secs(120);
+
+/// Substrings of provider errors that mean "slow down and retry"
+const THROTTLE_MARKERS: &[&str] = &[
+    "too many requests",
+    "rate limit",
+    "rate_limit",
+    "ratelimit",
+    "service unavailable",
+    "overloaded",
+    "resource_exhausted",
+];
+
+/// Status codes that mean "slow down and retry"
+const THROTTLE_STATUSES: &[&str] = &["429", "503", "529"];
+
+/// Phrases providers put in front of the delay they want, e.g. OpenAI's
+/// "Please try again in 1.5s" or Gemini's `"retryDelay": "30s"`
+const RETRY_AFTER_KEYS: &[&str] = &[
+    "retry-after",
+    "retry_after",
+    "retry after",
+    "retrydelay",
+    "try again in",
+];
+
+/// Limiters keyed by provider name
+static LIMITERS: OnceLock<Mutex<HashMap<String, Arc<RateLimiter>>>> = OnceLock::new();
+
+/// Per-provider limits from the config; unset fields are unlimited
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RateLimit {
+    pub requests_per_minute: Option<u32>,
+    pub tokens_per_minute: Option<u64>,
+}
+
+/// Sliding-window accounting of recent requests and tokens
+#[derive(Debug, Default)]
+struct Window {
+    requests: VecDeque<Instant>,
+    tokens: VecDeque<(Instant, u64)>,
+}
+
+impl Window {
+    fn prune(&mut self, now: Instant) {
+        while self
+            .requests
+            .front()
+            .is_some_and(|&at| now.duration_since(at) >= WINDOW)
+        {
+            self.requests.pop_front();
+        }
+        while self
+            .tokens
+            .front()
+            .is_some_and(|&(at, _)| now.duration_since(at) >= WINDOW)
+        {
+            self.tokens.pop_front();
+        }
+    }
+
+    /// Admit a request at `now`, or return how long to wait before retrying
+    fn try_admit(&mut self, limit: &RateLimit, now: Instant) -> Result<(), Duration> {
+        self.prune(now);
+        let until_expiry = |at: Instant| WINDOW.saturating_sub(now.duration_since(at));
+
+        if let Some(rpm) = limit.requests_per_minute {
+            if self.requests.len() >= rpm.max(1) as usize {
+                return Err(until_expiry(self.requests[0]));
+            }
+        }
+        if let Some(tpm) = limit.tokens_per_minute {
+            let used: u64 = self.tokens.iter().map(|&(_, n)| n).sum();
+            if used >= tpm {
+                // Wait until enough of the oldest usage has aged out
+                let mut excess = used - tpm;
+                for &(at, n) in &self.tokens {
+                    if n > excess {
+                        return Err(until_expiry(at));
+                    }
+                    excess -= n;
+                }
+            }
+        }
+        self.requests.push_back(now);
+        Ok(())
+    }
+
+    fn record_tokens(&mut self, tokens: u64, now: Instant) {
+        self.tokens.push_back((now, tokens));
+    }
+}
+
+/// A provider's request to slow down, parsed from an error message
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Throttle {
+    /// Delay the provider asked for, if it gave one
+    pub retry_after: Option<Duration>,
+}
+
+impl Throttle {
+    /// How long to wait before retry number `attempt` (1-based)
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let delay = self.retry_after.unwrap_or_else(|| {
+            THROTTLE_BASE_DELAY * 2u32.saturating_pow(attempt.saturating_sub(1).min(10))
+        });
+        delay.min(MAX_THROTTLE_DELAY)
+    }
+}
+
+/// Whether `code` appears in `text` as a number of its own
+fn contains_status(text: &str, code: &str) -> bool {
+    text.match_indices(code).any(|(at, _)| {
+        let before = text[..at].chars().next_back();
+        let after = text[at + code.len()..].chars().next();
+        !before.is_some_and(|c| c.is_ascii_alphanumeric())
+            && !after.is_some_and(|c| c.is_ascii_alphanumeric())
+    })
+}
+
+/// The delay following one of [`RETRY_AFTER_KEYS`] in `text`
+fn parse_retry_after(text: &str) -> Option<Duration> {
+    RETRY_AFTER_KEYS.iter().find_map(|key| {
+        let rest = &text[text.find(key)? + key.len()..];
+        let rest = rest.trim_start_matches(|c: char| matches!(c, ' ' | ':' | '"' | '=' | '\''));
+        let number_len = rest
+            .find(|c: char| !c.is_ascii_digit() && c != '.')
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..number_len].parse().ok()?;
+        let unit = rest[number_len..].trim_start();
+        let seconds = if unit.starts_with("ms") {
+            value / 1000.0
+        } else if unit.starts_with("min") || (unit.starts_with('m') && !unit.starts_with("mo")) {
+            value * 60.0
+        } else {
+            value
+        };
+        Some(Duration::from_millis(
+            (seconds.max(0.0) * 1000.0).round() as u64
+        ))
+    })
+}
+
+/// Recognize a provider error asking the caller to back off and retry.
+/// Exhausted quotas and billing errors won't clear by waiting and are not
+/// treated as throttling.
+pub fn parse_throttle(error: &str) -> Option<Throttle> {
+    let text = error.to_lowercase();
+    if text.contains("insufficient_quota") || text.contains("billing") {
+        return None;
+    }
+    let throttled = THROTTLE_MARKERS.iter().any(|m| text.contains(m))
+        || THROTTLE_STATUSES
+            .iter()
+            .any(|code| contains_status(&text, code));
+    throttled.then(|| Throttle {
+        retry_after: parse_retry_after(&text),
+    })
+}
+
+/// Rate limiter for one provider
+#[derive(Debug)]
+pub struct RateLimiter {
+    limit: Mutex<RateLimit>,
+    window: Mutex<Window>,
+    /// Set while the provider has asked callers to back off
+    paused_until: Mutex<Option<Instant>>,
+}
+
+impl RateLimiter {
+    pub fn new(limit: RateLimit) -> Self {
+        Self {
+            limit: Mutex::new(limit),
+            window: Mutex::new(Window::default()),
+            paused_until: Mutex::new(None),
+        }
+    }
+
+    /// Hold back every request for `wait`, after the provider throttled one
+    pub fn pause(&self, wait: Duration) {
+        if let Ok(mut paused_until) = self.paused_until.lock() {
+            let until = Instant::now() + wait;
+            if !paused_until.is_some_and(|current| current >= until) {
+                *paused_until = Some(until);
+            }
+        }
+    }
+
+    /// Wait until a request may be sent under the provider's limits
+    pub async fn acquire(&self) {
+        let paused_until = self.paused_until.lock().ok().and_then(|p| *p);
+        if let Some(until) = paused_until {
+            tokio::time::sleep_until(until.into()).await;
+        }
+        loop {
+            let limit = self.limit.lock().map(|l| l.clone()).unwrap_or_default();
+            let outcome = match self.window.lock() {
+                Ok(mut window) => window.try_admit(&limit, Instant::now()),
+                Err(_) => Ok(()),
+            };
+            match outcome {
+                Ok(()) => return,
+                Err(wait) => {
+                    crate::trace_debug!(
+                        "nexus::ratelimit",
+                        "Rate limit reached, waiting",
+                        wait_ms = wait.as_millis() as u64
+                    );
+                    tokio::time::sleep(wait.max(Duration::from_millis(50))).await;
+                }
+            }
+        }
+    }
+
+    /// Count the tokens a completed request used
+    pub fn record_tokens(&self, tokens: u64) {
+        if let Ok(mut window) = self.window.lock() {
+            window.record_tokens(tokens, Instant::now());
+        }
+    }
+}
+
+/// The shared limiter for `provider`, updated to the latest configured limits
+pub fn limiter_for(provider: &str, limit: RateLimit) -> Arc<RateLimiter> {
+    let limiters = LIMITERS.get_or_init(|| Mutex::new(HashMap::new()));
+    let mut limiters = match limiters.lock() {
+        Ok(limiters) => limiters,
+        Err(_) => return Arc::new(RateLimiter::new(limit)),
+    };
+    let limiter = limiters
+        .entry(provider.to_lowercase())
+        .or_insert_with(|| Arc::new(RateLimiter::new(limit.clone())))
+        .clone();
+    if let Ok(mut current) = limiter.limit.lock() {
+        *current = limit;
+    }
+    limiter
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_limit() {
+        let limit = RateLimit {
+            requests_per_minute: Some(2),
+            tokens_per_minute: None,
+        };
+        let mut window = Window::default();
+        let start = Instant::now();
+        assert!(window.try_admit(&limit, start).is_ok());
+        assert!(window
+            .try_admit(&limit, start + Duration::from_secs(10))
+            .is_ok());
+        let wait = window
+            .try_admit(&limit, start + Duration::from_secs(20))
+            .unwrap_err();
+        assert_eq!(wait, Duration::from_secs(40));
+        assert!(window
+            .try_admit(&limit, start + Duration::from_secs(60))
+            .is_ok());
+    }
+
+    #[test]
+    fn test_parse_throttle() {
+        let openai =
+            "429 Too Many Requests: Rate limit reached for gpt-4o. Please try again in 1.5s.";
+        assert_eq!(
+            parse_throttle(openai).unwrap().retry_after,
+            Some(Duration::from_millis(1500))
+        );
+
+        let gemini = r#"RESOURCE_EXHAUSTED {"retryDelay": "31s"}"#;
+        assert_eq!(
+            parse_throttle(gemini).unwrap().retry_after,
+            Some(Duration::from_secs(31))
+        );
+
+        let anthropic = "HTTP 529: overloaded_error";
+        assert_eq!(parse_throttle(anthropic).unwrap().retry_after, None);
+        assert!(parse_throttle("try again in 20ms, rate limited")
+            .unwrap()
+            .retry_after
+            .is_some_and(|d| d == Duration::from_millis(20)));
+
+        assert!(parse_throttle("insufficient_quota: check your plan (429)").is_none());
+        assert!(parse_throttle("invalid model gpt-4295").is_none());
+        assert!(parse_throttle("401 Unauthorized").is_none());
+    }
+
+    #[test]
+    fn test_throttle_delay() {
+        let unhinted = Throttle { retry_after: None };
+        assert_eq!(unhinted.delay(1), Duration::from_secs(2));
+        assert_eq!(unhinted.delay(3), Duration::from_secs(8));
+        assert_eq!(unhinted.delay(10), MAX_THROTTLE_DELAY);
+
+        let hinted = Throttle {
+            retry_after: Some(Duration::from_secs(600)),
+        };
+        assert_eq!(hinted.delay(1), MAX_THROTTLE_DELAY);
+    }
+
+    #[test]
+    fn test_token_limit() {
+        let limit = RateLimit {
+            requests_per_minute: None,
+            tokens_per_minute: Some(1000),
+        };
+        let mut window = Window::default();
+        let start = Instant::now();
+        assert!(window.try_admit(&limit, start).is_ok());
+        window.record_tokens(700, start);
+        window.record_tokens(500, start + Duration::from_secs(30));
+        let wait = window
+            .try_admit(&limit, start + Duration::from_secs(45))
+            .unwrap_err();
+        assert_eq!(wait, Duration::from_secs(15));
+        assert!(window
+            .try_admit(&limit, start + Duration::from_secs(60))
+            .is_ok());
+    }
+}
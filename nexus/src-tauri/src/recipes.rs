@@ -0,0 +1,422 @@
+//! Declarative extraction recipes
+//!
+//! A recipe maps CSS selectors to named fields for a given domain. Applying a
+//! recipe is deterministic (no LLM involved), which gives stable structured
+//! output for sites that are scraped repeatedly. Recipes can be written by
+//! hand or bootstrapped from a single example record with [`learn`].
+
+use crate::browser::BrowserManager;
+use crate::config::Config;
+use radkit::agent::LlmFunction;
+use radkit::macros::LLMOutput;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+/// A single field extracted by a recipe
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RecipeField {
+    pub name: String,
+    pub selector: String,
+    /// Attribute to read instead of the element text (e.g. `href`)
+    #[serde(default)]
+    pub attribute: Option<String>,
+    /// Collect every match instead of the first one
+    #[serde(default)]
+    pub multiple: bool,
+}
+
+/// Per-domain extraction spec
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct Recipe {
+    pub name: String,
+    pub domain: String,
+    /// When set, fields are extracted relative to each matching item,
+    /// producing one record per item
+    #[serde(default)]
+    pub item_selector: Option<String>,
+    pub fields: Vec<RecipeField>,
+}
+
+impl Recipe {
+    /// JavaScript expression that applies the recipe to the current document
+    pub fn to_script(&self) -> String {
+        let fields = serde_json::to_string(&self.fields).unwrap_or_else(|_| "[]".to_string());
+        let item_selector =
+            serde_json::to_string(&self.item_selector).unwrap_or_else(|_| "null".to_string());
+        format!(
+            r#"(() => {{
+                const fields = {fields};
+                const itemSelector = {item_selector};
+                const read = (el, f) => {{
+                    if (!el) return null;
+                    const v = f.attribute ? el.getAttribute(f.attribute) : el.textContent;
+                    return v === null ? null : v.trim();
+                }};
+                const record = (root) => {{
+                    const out = {{}};
+                    for (const f of fields) {{
+                        out[f.name] = f.multiple
+                            ? Array.from(root.querySelectorAll(f.selector)).map(el => read(el, f))
+                            : read(root.querySelector(f.selector), f);
+                    }}
+                    return out;
+                }};
+                return itemSelector
+                    ? Array.from(document.querySelectorAll(itemSelector)).map(record)
+                    : record(document);
+            }})()"#
+        )
+    }
+}
+
+fn recipe_from_row(row: &Row<'_>) -> rusqlite::Result<Recipe> {
+    let fields: String = row.get(3)?;
+    Ok(Recipe {
+        name: row.get(0)?,
+        domain: row.get(1)?,
+        item_selector: row.get(2)?,
+        fields: serde_json::from_str(&fields).unwrap_or_default(),
+    })
+}
+
+pub fn save(conn: &Connection, recipe: &Recipe) -> rusqlite::Result<()> {
+    let fields = serde_json::to_string(&recipe.fields).unwrap_or_else(|_| "[]".to_string());
+    conn.execute(
+        "INSERT INTO recipes (name, domain, item_selector, fields, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5)
+         ON CONFLICT(name) DO UPDATE SET
+            domain = excluded.domain,
+            item_selector = excluded.item_selector,
+            fields = excluded.fields,
+            updated_at = excluded.updated_at",
+        params![
+            recipe.name,
+            recipe.domain.to_lowercase(),
+            recipe.item_selector,
+            fields,
+            chrono::Utc::now().timestamp_millis()
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn list(conn: &Connection) -> rusqlite::Result<Vec<Recipe>> {
+    let mut stmt =
+        conn.prepare("SELECT name, domain, item_selector, fields FROM recipes ORDER BY name")?;
+    let rows = stmt.query_map([], recipe_from_row)?;
+    rows.collect()
+}
+
+pub fn delete(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM recipes WHERE name = ?1", params![name])? > 0)
+}
+
+/// Find a recipe by exact name, falling back to the most recent one for a domain
+pub fn find(conn: &Connection, name_or_domain: &str) -> rusqlite::Result<Option<Recipe>> {
+    let by_name = conn
+        .query_row(
+            "SELECT name, domain, item_selector, fields FROM recipes WHERE name = ?1",
+            params![name_or_domain],
+            recipe_from_row,
+        )
+        .optional()?;
+    if by_name.is_some() {
+        return Ok(by_name);
+    }
+
+    let domain = name_or_domain.to_lowercase();
+    let domain = domain.trim_start_matches("www.");
+    conn.query_row(
+        "SELECT name, domain, item_selector, fields FROM recipes
+         WHERE domain = ?1 OR domain = 'www.' || ?1
+         ORDER BY updated_at DESC LIMIT 1",
+        params![domain],
+        recipe_from_row,
+    )
+    .optional()
+}
+
+// ============================================================================
+// Recipe Learning
+// ============================================================================
+
+/// Selectors proposed by the LLM for an example record
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct LearnedRecipe {
+    /// Selector matching each repeated item, or null for single-record pages.
+    pub item_selector: Option<String>,
+    /// One entry per field of the example record.
+    pub fields: Vec<LearnedField>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct LearnedField {
+    /// Field name, exactly as in the example record.
+    pub name: String,
+    /// CSS selector, relative to the item when item_selector is set.
+    pub selector: String,
+    /// Attribute to read instead of text (e.g. "href"), or null.
+    pub attribute: Option<String>,
+}
+
+/// Outcome of learning a recipe
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LearnResult {
+    pub recipe: Recipe,
+    /// Data the recipe extracted from the example page
+    pub example_output: serde_json::Value,
+    /// Data the recipe extracted from the validation page, if one was given
+    pub validation_output: Option<serde_json::Value>,
+}
+
+/// JavaScript that lists candidate elements whose text or link matches each
+/// example value, along with a structural selector for each
+fn candidates_script(example: &serde_json::Map<String, serde_json::Value>) -> String {
+    let example = serde_json::to_string(example).unwrap_or_else(|_| "{}".to_string());
+    format!(
+        r#"(() => {{
+            const example = {example};
+            const norm = (s) => (s || "").replace(/\s+/g, " ").trim();
+            const step = (el) => {{
+                let s = el.tagName.toLowerCase();
+                const classes = Array.from(el.classList).filter(c => !/\d{{3,}}/.test(c)).slice(0, 2);
+                if (classes.length) s += "." + classes.map(c => CSS.escape(c)).join(".");
+                return s;
+            }};
+            const path = (el) => {{
+                const parts = [];
+                for (let cur = el; cur && cur !== document.body && parts.length < 4; cur = cur.parentElement) {{
+                    parts.unshift(step(cur));
+                }}
+                return parts.join(" > ");
+            }};
+            const all = Array.from(document.body.querySelectorAll("*"));
+            const out = {{}};
+            for (const [field, raw] of Object.entries(example)) {{
+                const value = norm(String(raw));
+                const found = [];
+                for (const el of all) {{
+                    if (found.length >= 5) break;
+                    for (const attr of ["href", "src"]) {{
+                        const v = el.getAttribute(attr);
+                        if (v && (v === value || el[attr] === value)) {{
+                            found.push({{ selector: path(el), attribute: attr, text: v }});
+                        }}
+                    }}
+                    if (el.children.length === 0 || norm(el.textContent).length <= value.length + 20) {{
+                        const text = norm(el.textContent);
+                        if (text === value || (value.length > 3 && text.includes(value))) {{
+                            found.push({{ selector: path(el), attribute: null, text: text.slice(0, 200) }});
+                        }}
+                    }}
+                }}
+                out[field] = found;
+            }}
+            return out;
+        }})()"#
+    )
+}
+
+fn values_match(actual: &serde_json::Value, expected: &serde_json::Value) -> bool {
+    let norm = |v: &serde_json::Value| match v {
+        serde_json::Value::String(s) => s.split_whitespace().collect::<Vec<_>>().join(" "),
+        other => other.to_string(),
+    };
+    let (a, e) = (norm(actual), norm(expected));
+    !e.is_empty() && (a == e || a.contains(&e))
+}
+
+/// Whether `output` (a record or list of records) contains the example record
+pub fn output_contains_example(
+    output: &serde_json::Value,
+    example: &serde_json::Map<String, serde_json::Value>,
+) -> bool {
+    let matches = |record: &serde_json::Value| {
+        example.iter().all(|(field, expected)| {
+            record
+                .get(field)
+                .map(|actual| values_match(actual, expected))
+                .unwrap_or(false)
+        })
+    };
+    match output {
+        serde_json::Value::Array(records) => records.iter().any(matches),
+        record => matches(record),
+    }
+}
+
+/// Whether `output` has at least one record with most fields populated
+pub fn output_is_populated(output: &serde_json::Value, fields: &[RecipeField]) -> bool {
+    let populated = |record: &serde_json::Value| {
+        let filled = fields
+            .iter()
+            .filter(|f| match record.get(&f.name) {
+                Some(serde_json::Value::String(s)) => !s.is_empty(),
+                Some(serde_json::Value::Array(a)) => !a.is_empty(),
+                Some(serde_json::Value::Null) | None => false,
+                Some(_) => true,
+            })
+            .count();
+        filled * 2 >= fields.len().max(1)
+    };
+    match output {
+        serde_json::Value::Array(records) => records.iter().any(populated),
+        record => populated(record),
+    }
+}
+
+/// Infer a recipe from one example record on `url`, validate it (optionally
+/// on a second page) and save it
+pub async fn learn(
+    browser: &BrowserManager,
+    config: &Config,
+    name: &str,
+    url: &str,
+    example: serde_json::Map<String, serde_json::Value>,
+    validation_url: Option<&str>,
+) -> Result<LearnResult, String> {
+    crate::trace_info!("nexus::recipes", "Learning recipe", name = name, url = url);
+    let domain = crate::approval::host_of(url).ok_or_else(|| format!("Invalid URL: {}", url))?;
+
+    browser
+        .navigate_and_get_content(url)
+        .await
+        .map_err(|e| e.to_string())?;
+    let candidates = browser
+        .evaluate_json(candidates_script(&example))
+        .await
+        .map_err(|e| e.to_string())?;
+    crate::trace_debug!(
+        "nexus::recipes",
+        "DOM candidates collected",
+        candidates = candidates.clone()
+    );
+
+    let llm = crate::providers::build_llm(config)?;
+    let function = LlmFunction::<LearnedRecipe>::new_with_system_instructions(
+        llm,
+        "You write robust CSS selectors for web scraping. Prefer stable class names over positional selectors. \
+         If the page lists many similar records, choose an item_selector matching each record and make field selectors relative to it.",
+    );
+    let prompt = format!(
+        "Page: {}\n\nExample record to extract:\n{}\n\nElements whose content matches each field (selector paths from the DOM):\n{}\n\nReturn selectors that reproduce the example record.",
+        url,
+        serde_json::to_string_pretty(&example).unwrap_or_default(),
+        serde_json::to_string_pretty(&candidates).unwrap_or_default()
+    );
+    let learned = function.run(prompt).await.map_err(|e| e.to_string())?;
+
+    let recipe = Recipe {
+        name: name.to_string(),
+        domain,
+        item_selector: learned.item_selector.filter(|s| !s.trim().is_empty()),
+        fields: learned
+            .fields
+            .into_iter()
+            .map(|f| RecipeField {
+                name: f.name,
+                selector: f.selector,
+                attribute: f.attribute.filter(|a| !a.trim().is_empty()),
+                multiple: false,
+            })
+            .collect(),
+    };
+
+    let example_output = browser
+        .evaluate_json(recipe.to_script())
+        .await
+        .map_err(|e| e.to_string())?;
+    if !output_contains_example(&example_output, &example) {
+        crate::trace_warn!(
+            "nexus::recipes",
+            "Learned recipe does not reproduce example",
+            name = name,
+            output = example_output.clone()
+        );
+        return Err(format!(
+            "Learned selectors did not reproduce the example record. Extracted: {}",
+            example_output
+        ));
+    }
+
+    let validation_output = match validation_url {
+        Some(validation_url) => {
+            browser
+                .navigate_and_get_content(validation_url)
+                .await
+                .map_err(|e| e.to_string())?;
+            let output = browser
+                .evaluate_json(recipe.to_script())
+                .await
+                .map_err(|e| e.to_string())?;
+            if !output_is_populated(&output, &recipe.fields) {
+                return Err(format!(
+                    "Recipe extracted no usable data from validation page {}: {}",
+                    validation_url, output
+                ));
+            }
+            Some(output)
+        }
+        None => None,
+    };
+
+    crate::db::with_db(|conn| save(conn, &recipe))?;
+    crate::trace_info!("nexus::recipes", "Recipe learned and saved", name = name);
+    Ok(LearnResult {
+        recipe,
+        example_output,
+        validation_output,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Recipe {
+        Recipe {
+            name: "hn-front".to_string(),
+            domain: "news.ycombinator.com".to_string(),
+            item_selector: Some("tr.athing".to_string()),
+            fields: vec![RecipeField {
+                name: "title".to_string(),
+                selector: ".titleline > a".to_string(),
+                attribute: None,
+                multiple: false,
+            }],
+        }
+    }
+
+    #[test]
+    fn test_recipe_crud() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+
+        save(&conn, &sample()).unwrap();
+        assert_eq!(list(&conn).unwrap().len(), 1);
+        assert_eq!(find(&conn, "hn-front").unwrap(), Some(sample()));
+        assert_eq!(find(&conn, "News.YCombinator.com").unwrap(), Some(sample()));
+        assert_eq!(find(&conn, "example.com").unwrap(), None);
+
+        assert!(delete(&conn, "hn-front").unwrap());
+        assert!(list(&conn).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_output_validation() {
+        let example = serde_json::json!({ "title": "Rust 2.0  released" });
+        let example = example.as_object().unwrap();
+
+        let output = serde_json::json!([
+            { "title": "Something else" },
+            { "title": "Rust 2.0 released" }
+        ]);
+        assert!(output_contains_example(&output, example));
+        assert!(!output_contains_example(&serde_json::json!({ "title": null }), example));
+
+        let fields = sample().fields;
+        assert!(output_is_populated(&output, &fields));
+        assert!(!output_is_populated(&serde_json::json!([{ "title": "" }]), &fields));
+    }
+}
@@ -0,0 +1,378 @@
+//! Deterministic replay of a run's browser actions
+//!
+//! Agent tools record every browser action they perform (navigate, click,
+//! type, scroll) in the `browser_actions` table, and as a trace event for the
+//! live view. A replay loads that sequence and re-executes it on the browser
+//! one step at a time, without any LLM calls, so a failing run can be
+//! reproduced and inspected between steps. Runs recorded before the table
+//! existed are replayed from their persisted traces.
+//!
+//! Secrets are redacted before actions are recorded, so steps carrying a
+//! redacted value (e.g. a typed password) can't be replayed and are skipped.
+
+use crate::browser::BrowserManager;
+use rusqlite::{params, Connection};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use uuid::Uuid;
+
+/// Trace target of recorded browser actions
+pub const ACTION_TARGET: &str = "nexus::replay::action";
+
+static REPLAYS: OnceLock<Mutex<HashMap<String, Replay>>> = OnceLock::new();
+
+/// A browser action as recorded by the agent's tools
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum BrowserAction {
+    Navigate {
+        url: String,
+    },
+    Click {
+        selector: String,
+    },
+    Type {
+        text: String,
+    },
+    Scroll {
+        direction: String,
+        amount: Option<i32>,
+    },
+}
+
+impl BrowserAction {
+    /// Whether redaction masked part of the action when it was recorded
+    pub fn is_redacted(&self) -> bool {
+        let value = match self {
+            BrowserAction::Navigate { url } => url,
+            BrowserAction::Click { selector } => selector,
+            BrowserAction::Type { text } => text,
+            BrowserAction::Scroll { direction, .. } => direction,
+        };
+        value.contains(crate::redact::MASK)
+    }
+
+    async fn perform(&self, browser: &BrowserManager) -> anyhow::Result<String> {
+        match self {
+            BrowserAction::Navigate { url } => browser.navigate_and_get_content(url).await,
+            BrowserAction::Click { selector } => browser.click_element(selector).await,
+            BrowserAction::Type { text } => browser.type_text(text).await,
+            BrowserAction::Scroll { direction, amount } => {
+                browser.scroll_page(direction, *amount).await
+            }
+        }
+    }
+}
+
+/// Record `action` for the current run's replay and on its trace
+pub fn record(action: BrowserAction) {
+    let run_id = crate::run::current().map(|run| run.run_id.clone());
+    if let Some(run_id) = &run_id {
+        if let Err(e) = crate::db::with_db(|conn| save_action(conn, run_id, &action)) {
+            crate::trace_warn!(
+                "nexus::replay",
+                "Failed to record browser action",
+                run_id = run_id,
+                error = e
+            );
+        }
+    }
+    crate::tracing::record_trace(
+        "INFO",
+        ACTION_TARGET,
+        None,
+        "Browser action",
+        json!({ "run_id": run_id, "browser_action": action }),
+    );
+}
+
+/// Store `action` as the next step of `run_id`, with secrets redacted
+pub fn save_action(
+    conn: &Connection,
+    run_id: &str,
+    action: &BrowserAction,
+) -> rusqlite::Result<()> {
+    let mut value = serde_json::to_value(action).unwrap_or_default();
+    crate::redact::redact_fields(&mut value);
+    conn.execute(
+        "INSERT INTO browser_actions (run_id, recorded_at, action) VALUES (?1, ?2, ?3)",
+        params![
+            run_id,
+            chrono::Utc::now().timestamp_millis(),
+            value.to_string()
+        ],
+    )?;
+    Ok(())
+}
+
+/// A run's recorded browser actions, in the order they were performed
+pub fn load_actions(conn: &Connection, run_id: &str) -> rusqlite::Result<Vec<BrowserAction>> {
+    let mut stmt =
+        conn.prepare("SELECT action FROM browser_actions WHERE run_id = ?1 ORDER BY id")?;
+    let rows = stmt.query_map(params![run_id], |row| row.get::<_, String>(0))?;
+    let mut actions = Vec::new();
+    for row in rows {
+        if let Ok(action) = serde_json::from_str(&row?) {
+            actions.push(action);
+        }
+    }
+    if actions.is_empty() {
+        return load_traced_actions(conn, run_id);
+    }
+    Ok(actions)
+}
+
+/// Actions of a run recorded only on its trace
+fn load_traced_actions(conn: &Connection, run_id: &str) -> rusqlite::Result<Vec<BrowserAction>> {
+    let mut stmt = conn.prepare(
+        "SELECT json_extract(fields, '$.browser_action') FROM traces
+         WHERE target = ?1 AND json_extract(fields, '$.run_id') = ?2
+         ORDER BY timestamp, rowid",
+    )?;
+    let rows = stmt.query_map(params![ACTION_TARGET, run_id], |row| {
+        row.get::<_, Option<String>>(0)
+    })?;
+    let mut actions = Vec::new();
+    for row in rows {
+        if let Some(action) = row?.and_then(|json| serde_json::from_str(&json).ok()) {
+            actions.push(action);
+        }
+    }
+    Ok(actions)
+}
+
+struct Replay {
+    run_id: String,
+    actions: Vec<BrowserAction>,
+    position: usize,
+    busy: bool,
+}
+
+impl Replay {
+    fn state(&self, replay_id: &str) -> ReplayState {
+        ReplayState {
+            replay_id: replay_id.to_string(),
+            run_id: self.run_id.clone(),
+            position: self.position,
+            total: self.actions.len(),
+            next: self.actions.get(self.position).cloned(),
+        }
+    }
+}
+
+/// Where a replay is in its run's action sequence
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayState {
+    pub replay_id: String,
+    pub run_id: String,
+    /// Steps performed so far
+    pub position: usize,
+    pub total: usize,
+    /// Action the next step performs; `None` once the replay is finished
+    pub next: Option<BrowserAction>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    Ok,
+    Failed,
+    /// Not performed because a recorded value was redacted
+    Skipped,
+}
+
+/// Outcome of one replayed action
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ReplayStep {
+    /// Zero-based position of the action in the run
+    pub index: usize,
+    pub action: BrowserAction,
+    pub status: StepStatus,
+    pub error: Option<String>,
+    /// Page URL after the step
+    pub url: Option<String>,
+}
+
+fn replays() -> &'static Mutex<HashMap<String, Replay>> {
+    REPLAYS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+/// Open a replay of `run_id`'s recorded actions
+pub fn start(run_id: &str, actions: Vec<BrowserAction>) -> Result<ReplayState, String> {
+    if actions.is_empty() {
+        return Err(format!("Run {} has no recorded browser actions", run_id));
+    }
+    let replay_id = Uuid::new_v4().to_string();
+    let replay = Replay {
+        run_id: run_id.to_string(),
+        actions,
+        position: 0,
+        busy: false,
+    };
+    let state = replay.state(&replay_id);
+    replays()
+        .lock()
+        .map_err(|_| "Replay state poisoned".to_string())?
+        .insert(replay_id.clone(), replay);
+    crate::trace_info!(
+        "nexus::replay",
+        "Replay started",
+        replay_id = replay_id,
+        run_id = run_id,
+        actions = state.total
+    );
+    Ok(state)
+}
+
+pub fn state(replay_id: &str) -> Result<ReplayState, String> {
+    let replays = replays()
+        .lock()
+        .map_err(|_| "Replay state poisoned".to_string())?;
+    replays
+        .get(replay_id)
+        .map(|replay| replay.state(replay_id))
+        .ok_or_else(|| format!("Replay {} not found", replay_id))
+}
+
+/// Perform the next recorded action; `None` once every action has been
+/// replayed
+pub async fn step(replay_id: &str, browser: &BrowserManager) -> Result<Option<ReplayStep>, String> {
+    let (index, action) = {
+        let mut replays = replays()
+            .lock()
+            .map_err(|_| "Replay state poisoned".to_string())?;
+        let replay = replays
+            .get_mut(replay_id)
+            .ok_or_else(|| format!("Replay {} not found", replay_id))?;
+        if replay.busy {
+            return Err("The previous replay step is still running".to_string());
+        }
+        let Some(action) = replay.actions.get(replay.position).cloned() else {
+            return Ok(None);
+        };
+        replay.busy = true;
+        (replay.position, action)
+    };
+
+    let _span = crate::trace_span!(
+        "nexus::replay",
+        "replay_step",
+        replay_id = replay_id,
+        index = index
+    );
+    let (status, error) = if action.is_redacted() {
+        (StepStatus::Skipped, None)
+    } else {
+        match action.perform(browser).await {
+            Ok(_) => (StepStatus::Ok, None),
+            Err(e) => (StepStatus::Failed, Some(e.to_string())),
+        }
+    };
+    let step = ReplayStep {
+        index,
+        action,
+        status,
+        error,
+        url: browser.get_current_url().await.ok(),
+    };
+
+    if let Ok(mut replays) = replays().lock() {
+        if let Some(replay) = replays.get_mut(replay_id) {
+            replay.position = index + 1;
+            replay.busy = false;
+        }
+    }
+    Ok(Some(step))
+}
+
+/// Replay the remaining actions, stopping after the first one that fails
+pub async fn run_to_end(
+    replay_id: &str,
+    browser: &BrowserManager,
+) -> Result<Vec<ReplayStep>, String> {
+    let mut steps = Vec::new();
+    while let Some(step) = self::step(replay_id, browser).await? {
+        let failed = step.status == StepStatus::Failed;
+        steps.push(step);
+        if failed {
+            break;
+        }
+    }
+    Ok(steps)
+}
+
+pub fn stop(replay_id: &str) -> Result<(), String> {
+    replays()
+        .lock()
+        .map_err(|_| "Replay state poisoned".to_string())?
+        .remove(replay_id)
+        .map(|_| ())
+        .ok_or_else(|| format!("Replay {} not found", replay_id))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tracing::{save_events, TraceEvent};
+
+    fn recorded(id: &str, run_id: &str, timestamp: i64, action: &BrowserAction) -> TraceEvent {
+        TraceEvent {
+            id: id.to_string(),
+            session_id: "s".to_string(),
+            timestamp,
+            level: "INFO".to_string(),
+            target: ACTION_TARGET.to_string(),
+            span_name: None,
+            message: "Browser action".to_string(),
+            fields: json!({ "run_id": run_id, "browser_action": action }).to_string(),
+        }
+    }
+
+    #[test]
+    fn test_load_actions() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+        let navigate = BrowserAction::Navigate {
+            url: "https://example.com".to_string(),
+        };
+        let click = BrowserAction::Click {
+            selector: "a.pricing".to_string(),
+        };
+        save_events(
+            &conn,
+            &[
+                recorded("2", "run-a", 200, &click),
+                recorded("1", "run-a", 100, &navigate),
+                recorded("3", "run-b", 150, &click),
+            ],
+        )
+        .unwrap();
+
+        let actions = load_actions(&conn, "run-a").unwrap();
+        assert_eq!(actions, vec![navigate.clone(), click.clone()]);
+        assert!(load_actions(&conn, "run-c").unwrap().is_empty());
+
+        // Recorded actions take precedence over the trace
+        save_action(&conn, "run-b", &navigate).unwrap();
+        save_action(&conn, "run-b", &click).unwrap();
+        assert_eq!(load_actions(&conn, "run-b").unwrap(), vec![navigate, click]);
+    }
+
+    #[test]
+    fn test_replay_state() {
+        let typed = BrowserAction::Type {
+            text: crate::redact::MASK.to_string(),
+        };
+        assert!(typed.is_redacted());
+        assert!(start("run-x", Vec::new()).is_err());
+
+        let state = start("run-x", vec![typed.clone()]).unwrap();
+        assert_eq!(state.position, 0);
+        assert_eq!(state.total, 1);
+        assert_eq!(state.next, Some(typed));
+        stop(&state.replay_id).unwrap();
+        assert!(self::state(&state.replay_id).is_err());
+    }
+}
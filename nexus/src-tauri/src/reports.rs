@@ -0,0 +1,73 @@
+//! Report comparison across runs
+//!
+//! Re-running the same prompt links the new run to the previous one (see
+//! [`crate::run::RunRecord::previous_run_id`]); this module renders a
+//! line-level, change-highlighted comparison of two stored reports.
+
+use serde::{Deserialize, Serialize};
+use similar::{ChangeTag, TextDiff};
+
+/// Change-highlighted comparison of two reports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReportDiff {
+    pub run_a: String,
+    pub run_b: String,
+    pub added_lines: usize,
+    pub removed_lines: usize,
+    /// Unified diff wrapped in a ```diff fence for Markdown rendering
+    pub markdown: String,
+}
+
+/// Diff two report texts line by line
+pub fn diff_reports(run_a: &str, report_a: &str, run_b: &str, report_b: &str) -> ReportDiff {
+    let diff = TextDiff::from_lines(report_a, report_b);
+    let (mut added_lines, mut removed_lines) = (0, 0);
+    for change in diff.iter_all_changes() {
+        match change.tag() {
+            ChangeTag::Insert => added_lines += 1,
+            ChangeTag::Delete => removed_lines += 1,
+            ChangeTag::Equal => {}
+        }
+    }
+
+    let unified = diff
+        .unified_diff()
+        .context_radius(3)
+        .header(run_a, run_b)
+        .to_string();
+    let markdown = if added_lines + removed_lines == 0 {
+        "_No changes between the two reports._".to_string()
+    } else {
+        format!(
+            "**{} lines added, {} lines removed**\n\n```diff\n{}```\n",
+            added_lines, removed_lines, unified
+        )
+    };
+
+    ReportDiff {
+        run_a: run_a.to_string(),
+        run_b: run_b.to_string(),
+        added_lines,
+        removed_lines,
+        markdown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_reports() {
+        let a = "# Prices\n- Widget: $10\n- Gadget: $20\n";
+        let b = "# Prices\n- Widget: $12\n- Gadget: $20\n";
+        let diff = diff_reports("run-a", a, "run-b", b);
+        assert_eq!(diff.added_lines, 1);
+        assert_eq!(diff.removed_lines, 1);
+        assert!(diff.markdown.contains("-- Widget: $10"));
+        assert!(diff.markdown.contains("+- Widget: $12"));
+
+        let same = diff_reports("run-a", a, "run-b", a);
+        assert_eq!(same.added_lines + same.removed_lines, 0);
+    }
+}
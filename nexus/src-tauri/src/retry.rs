@@ -0,0 +1,134 @@
+//! Retry policy for flaky browser operations
+//!
+//! Timeouts, detached DOM nodes and `net::ERR_*` failures are usually gone a
+//! moment later. Browser tools run their operation through [`with_retry`],
+//! which retries such transient errors with exponential backoff before the
+//! error reaches the LLM and costs it a turn. Non-idempotent actions (clicks,
+//! typing, uploads) are not retried.
+
+use std::future::Future;
+use tokio::time::{sleep, Duration};
+
+/// Substrings identifying errors worth retrying
+const TRANSIENT_MARKERS: &[&str] = &[
+    "timed out",
+    "timeout",
+    "net::err_",
+    "detached",
+    "no node with given id",
+    "cannot find context with specified id",
+    "execution context was destroyed",
+    "target closed",
+    "websocket",
+    "connection reset",
+];
+
+/// How often and how patiently to retry
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RetryPolicy {
+    pub max_retries: u32,
+    pub base_delay: Duration,
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &crate::config::Config) -> Self {
+        Self {
+            max_retries: config.tool_retries,
+            base_delay: Duration::from_millis(config.retry_backoff_ms),
+        }
+    }
+
+    /// Policy of the current run, or the defaults outside of one
+    pub fn current() -> Self {
+        match crate::run::current() {
+            Some(run) => Self::from_config(&run.config),
+            None => Self::from_config(&crate::config::Config::default()),
+        }
+    }
+
+    /// Delay before retry number `attempt` (1-based): base, 2x, 4x, ...
+    pub fn delay(&self, attempt: u32) -> Duration {
+        self.base_delay * 2u32.saturating_pow(attempt.saturating_sub(1).min(10))
+    }
+}
+
+pub fn is_transient(error: &str) -> bool {
+    let error = error.to_lowercase();
+    TRANSIENT_MARKERS.iter().any(|m| error.contains(m))
+}
+
+/// Run `op`, retrying transient failures according to `policy`
+pub async fn with_retry<T, F, Fut>(
+    operation: &str,
+    policy: RetryPolicy,
+    mut op: F,
+) -> anyhow::Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = anyhow::Result<T>>,
+{
+    let mut attempt = 0;
+    loop {
+        match op().await {
+            Ok(value) => return Ok(value),
+            Err(e) if attempt < policy.max_retries && is_transient(&e.to_string()) => {
+                attempt += 1;
+                let delay = policy.delay(attempt);
+                crate::trace_warn!(
+                    "nexus::retry",
+                    "Transient failure, retrying",
+                    operation = operation,
+                    attempt = attempt,
+                    delay_ms = delay.as_millis() as u64,
+                    error = e.to_string()
+                );
+                sleep(delay).await;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    #[test]
+    fn test_is_transient() {
+        assert!(is_transient("Navigation timed out after 30 seconds"));
+        assert!(is_transient("net::ERR_CONNECTION_RESET"));
+        assert!(is_transient("Node is detached from document"));
+        assert!(!is_transient("Element '#buy' not found"));
+    }
+
+    #[tokio::test]
+    async fn test_with_retry() {
+        let policy = RetryPolicy {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+        };
+        assert_eq!(policy.delay(3), Duration::from_millis(4));
+
+        let calls = AtomicU32::new(0);
+        let result = with_retry("flaky", policy, || async {
+            if calls.fetch_add(1, Ordering::SeqCst) < 2 {
+                Err(anyhow::anyhow!("net::ERR_TIMED_OUT"))
+            } else {
+                Ok("loaded")
+            }
+        })
+        .await;
+        assert_eq!(result.unwrap(), "loaded");
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+
+        calls.store(0, Ordering::SeqCst);
+        let result: anyhow::Result<()> = with_retry("broken", policy, || async {
+            calls.fetch_add(1, Ordering::SeqCst);
+            Err(anyhow::anyhow!("Element '#missing' not found"))
+        })
+        .await;
+        assert!(result.is_err());
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
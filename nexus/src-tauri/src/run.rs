@@ -0,0 +1,1205 @@
+//! Per-run context shared with agent tools.
+//!
+//! Tools are plain functions invoked by the `LlmWorker`, so they have no direct
+//! handle on the run that triggered them. The context is installed as a
+//! task-local for the duration of a run and looked up with [`current`].
+//! Commands reach live runs through the [`ACTIVE_RUNS`] registry, and finished
+//! runs are persisted as [`RunRecord`]s in the `runs` table. Runs started in
+//! the background report their outcome through [`RunResult`]s.
+
+use crate::bookmarks::Bookmark;
+use crate::browser::BrowserManager;
+use crate::checkpoint::Checkpoint;
+use crate::config::Config;
+use crate::criteria::Validation;
+use crate::frontier::UrlFrontier;
+use crate::mcp_client::McpClient;
+use crate::usage::RunUsage;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::sync::Notify;
+use uuid::Uuid;
+
+tokio::task_local! {
+    static CURRENT_RUN: Arc<RunContext>;
+}
+
+/// Runs currently executing, keyed by run_id
+pub static ACTIVE_RUNS: OnceLock<Mutex<HashMap<String, Arc<RunContext>>>> = OnceLock::new();
+
+/// Finished background runs whose results are kept in memory
+const MAX_RESULTS: usize = 50;
+
+/// Outcomes of background runs, oldest first
+static RESULTS: Mutex<Vec<RunResult>> = Mutex::new(Vec::new());
+
+/// State describing the agent run currently executing
+pub struct RunContext {
+    pub run_id: String,
+    pub config: Config,
+    steps: AtomicUsize,
+    sources: Mutex<Vec<String>>,
+    findings: Mutex<Vec<String>>,
+    usage: Mutex<RunUsage>,
+    /// LLM calls started after the spend budget was exceeded
+    calls_over_budget: AtomicUsize,
+    abort: Notify,
+    /// Set while the user has taken over; tool calls wait until resumed
+    paused: AtomicBool,
+    resumed: Notify,
+    /// Why the run was stopped outside its budget, e.g. a pause that ran out
+    /// or a rejected plan. Halted runs are not retried.
+    halted: Mutex<Option<String>>,
+    /// URLs visited and pages fetched per domain during this run, shared with
+    /// its sub-agents
+    pub frontier: Arc<Mutex<UrlFrontier>>,
+    last_checkpoint: Mutex<Option<Instant>>,
+    /// Step plan approved by the user in plan-then-execute mode
+    plan: Mutex<Option<Vec<String>>>,
+    /// Orchestrating run, when this run is a sub-agent
+    pub parent_run_id: Option<String>,
+    /// Sub-agents and concurrent runs drive their own tab or browser context
+    /// instead of the shared browser
+    pub browser: Option<BrowserManager>,
+    /// MCP servers connected for this run; sub-agents share their parent's
+    pub mcp_clients: Vec<Arc<McpClient>>,
+    /// Named page positions saved with `bookmark_position`
+    bookmarks: Mutex<HashMap<String, Bookmark>>,
+    /// Tool failures worth mentioning in a post-mortem
+    failures: Mutex<Vec<String>>,
+    /// Page content last returned to the agent, for diffing the next step
+    last_page: Mutex<Option<String>>,
+    /// Ids of the trace spans open in this run, innermost last
+    spans: Mutex<Vec<String>>,
+    started: Instant,
+    /// Tool most recently called in this run
+    last_tool: Mutex<Option<String>>,
+}
+
+impl RunContext {
+    pub fn new(config: Config) -> Self {
+        Self::with_id(Uuid::new_v4().to_string(), config)
+    }
+
+    /// Context for continuing a checkpointed run under its original id
+    pub fn resume(config: Config, checkpoint: &Checkpoint) -> Self {
+        let ctx = Self::with_id(checkpoint.run_id.clone(), config);
+        ctx.steps.store(checkpoint.steps, Ordering::SeqCst);
+        for url in &checkpoint.sources {
+            ctx.record_source(url);
+        }
+        for finding in &checkpoint.findings {
+            ctx.record_finding(finding);
+        }
+        if let Some(plan) = &checkpoint.plan {
+            ctx.set_plan(plan.clone());
+        }
+        if let (Some(usage), Ok(mut current)) = (&checkpoint.usage, ctx.usage.lock()) {
+            *current = usage.clone();
+        }
+        ctx
+    }
+
+    /// Context for a sub-agent researching part of `parent`'s task, one of
+    /// `share` sub-agents splitting what is left of its budget. The sub-agent
+    /// shares the parent's frontier, so per-domain caps cover the whole run;
+    /// give it its own tab with [`RunContext::with_browser`].
+    pub fn sub_agent(parent: &RunContext, share: usize) -> Self {
+        Self {
+            parent_run_id: Some(parent.run_id.clone()),
+            frontier: parent.frontier.clone(),
+            mcp_clients: parent.mcp_clients.clone(),
+            ..Self::new(parent.budget_share(share))
+        }
+    }
+
+    /// The run's config with an equal `share` of its remaining step, token
+    /// and spend budget, so sub-agents together can't outspend the run
+    pub fn budget_share(&self, share: usize) -> Config {
+        let share = share.max(1);
+        let usage = self.usage();
+        let mut config = self.config.clone();
+        config.max_steps = (config.max_steps.saturating_sub(self.steps()) / share).max(1);
+        config.max_tokens = config
+            .max_tokens
+            .map(|max| max.saturating_sub(usage.total_tokens()) / share as u64);
+        config.max_cost_usd = config
+            .max_cost_usd
+            .map(|max| (max - usage.cost_usd.unwrap_or(0.0)).max(0.0) / share as f64);
+        config
+    }
+
+    /// Drive `browser` instead of the shared browser
+    pub fn with_browser(self, browser: BrowserManager) -> Self {
+        Self {
+            browser: Some(browser),
+            ..self
+        }
+    }
+
+    /// Offer the tools of `clients` to the agent
+    pub fn with_mcp_clients(self, clients: Vec<Arc<McpClient>>) -> Self {
+        Self {
+            mcp_clients: clients,
+            ..self
+        }
+    }
+
+    /// Shut down the run's MCP servers
+    pub async fn close_mcp_clients(&self) {
+        for client in &self.mcp_clients {
+            client.close().await;
+        }
+    }
+
+    /// Context for a new run under an id handed out before it started
+    pub fn with_id(run_id: String, config: Config) -> Self {
+        Self {
+            run_id,
+            steps: AtomicUsize::new(0),
+            sources: Mutex::new(Vec::new()),
+            findings: Mutex::new(Vec::new()),
+            usage: Mutex::new(RunUsage::new(&config.model)),
+            calls_over_budget: AtomicUsize::new(0),
+            abort: Notify::new(),
+            paused: AtomicBool::new(false),
+            resumed: Notify::new(),
+            halted: Mutex::new(None),
+            frontier: Arc::new(Mutex::new(UrlFrontier::new(config.max_pages_per_domain))),
+            last_checkpoint: Mutex::new(None),
+            plan: Mutex::new(None),
+            parent_run_id: None,
+            browser: None,
+            mcp_clients: Vec::new(),
+            bookmarks: Mutex::new(HashMap::new()),
+            failures: Mutex::new(Vec::new()),
+            last_page: Mutex::new(None),
+            spans: Mutex::new(Vec::new()),
+            started: Instant::now(),
+            last_tool: Mutex::new(None),
+            config,
+        }
+    }
+
+    /// Count a tool call, returning the new step number or the reason the
+    /// run's budget no longer allows tool calls
+    pub fn next_step(&self) -> Result<usize, String> {
+        if let Some(reason) = self.halted().or_else(|| self.spend_exceeded()) {
+            return Err(reason);
+        }
+        let step = self.steps.fetch_add(1, Ordering::SeqCst) + 1;
+        if step > self.config.max_steps {
+            Err(format!("step limit of {} tool calls", self.config.max_steps))
+        } else {
+            Ok(step)
+        }
+    }
+
+    /// Number of tool calls made so far (including rejected ones)
+    pub fn steps(&self) -> usize {
+        self.steps.load(Ordering::SeqCst)
+    }
+
+    pub fn record_tool(&self, tool: &str) {
+        if let Ok(mut last_tool) = self.last_tool.lock() {
+            *last_tool = Some(tool.to_string());
+        }
+    }
+
+    /// Progress snapshot for status displays
+    pub fn status(&self) -> RunStatus {
+        RunStatus {
+            run_id: self.run_id.clone(),
+            parent_run_id: self.parent_run_id.clone(),
+            step: self.steps().min(self.config.max_steps),
+            max_steps: self.config.max_steps,
+            last_tool: self.last_tool.lock().ok().and_then(|tool| tool.clone()),
+            paused: self.is_paused(),
+            elapsed_ms: self.started.elapsed().as_millis() as u64,
+            usage: self.usage(),
+        }
+    }
+
+    /// Which token or cost limit has been reached, if any
+    pub fn spend_exceeded(&self) -> Option<String> {
+        let usage = self.usage();
+        if let Some(max_tokens) = self.config.max_tokens {
+            if usage.total_tokens() >= max_tokens {
+                return Some(format!("token limit of {}", max_tokens));
+            }
+        }
+        if let (Some(max_cost), Some(cost)) = (self.config.max_cost_usd, usage.cost_usd) {
+            if cost >= max_cost {
+                return Some(format!("spend limit of ${:.2}", max_cost));
+            }
+        }
+        None
+    }
+
+    /// Why the run has to stop, if any budget is exhausted
+    pub fn stop_reason(&self) -> Option<String> {
+        self.halted().or_else(|| self.spend_exceeded()).or_else(|| {
+            (self.steps() >= self.config.max_steps)
+                .then(|| format!("step limit of {} tool calls", self.config.max_steps))
+        })
+    }
+
+    /// Refuse any further tool calls, so the agent writes its report
+    pub fn halt(&self, reason: &str) {
+        if let Ok(mut halted) = self.halted.lock() {
+            halted.get_or_insert_with(|| reason.to_string());
+        }
+    }
+
+    pub fn halted(&self) -> Option<String> {
+        self.halted.lock().ok().and_then(|halted| halted.clone())
+    }
+
+    pub fn budget_exhausted(&self) -> bool {
+        self.stop_reason().is_some()
+    }
+
+    /// Called before each LLM call. Once over the spend budget, one more call
+    /// is allowed so the model can write its report; after that the run is
+    /// aborted and `false` is returned.
+    pub fn admit_llm_call(&self) -> bool {
+        if self.spend_exceeded().is_none() {
+            return true;
+        }
+        if self.calls_over_budget.fetch_add(1, Ordering::SeqCst) == 0 {
+            return true;
+        }
+        self.abort.notify_one();
+        false
+    }
+
+    /// Resolves once the run has been aborted for exceeding its budget
+    pub async fn aborted(&self) {
+        self.abort.notified().await
+    }
+
+    /// Hold the agent's next tool call until [`RunContext::unpause`]
+    pub fn pause(&self) {
+        self.paused.store(true, Ordering::SeqCst);
+    }
+
+    pub fn unpause(&self) {
+        self.paused.store(false, Ordering::SeqCst);
+        self.resumed.notify_waiters();
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::SeqCst)
+    }
+
+    /// Resolves at once unless the run is paused, then once it is unpaused.
+    /// A pause longer than `pause_timeout_secs` halts the run instead of
+    /// holding its browser and MCP servers indefinitely.
+    pub async fn wait_while_paused(&self) {
+        let limit = Duration::from_secs(self.config.pause_timeout_secs);
+        let unpaused = tokio::time::timeout(limit, async {
+            loop {
+                // Register before checking, so an unpause in between is not missed
+                let resumed = self.resumed.notified();
+                if !self.is_paused() {
+                    return;
+                }
+                resumed.await;
+            }
+        });
+        if unpaused.await.is_err() {
+            self.halt(&format!(
+                "pause limit of {} seconds",
+                self.config.pause_timeout_secs
+            ));
+            self.unpause();
+        }
+    }
+
+    /// Remember a finding worth keeping in a partial report
+    pub fn record_finding(&self, finding: &str) {
+        if let Ok(mut findings) = self.findings.lock() {
+            findings.push(finding.to_string());
+        }
+    }
+
+    pub fn findings(&self) -> Vec<String> {
+        self.findings.lock().map(|f| f.clone()).unwrap_or_default()
+    }
+
+    /// Remember a URL the agent has read content from
+    pub fn record_source(&self, url: &str) {
+        if let Ok(mut sources) = self.sources.lock() {
+            if !sources.iter().any(|s| s == url) {
+                sources.push(url.to_string());
+            }
+        }
+    }
+
+    pub fn sources(&self) -> Vec<String> {
+        self.sources.lock().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Add one LLM call's token counts, returning the updated totals
+    pub fn record_usage(&self, prompt_tokens: u64, completion_tokens: u64) -> RunUsage {
+        match self.usage.lock() {
+            Ok(mut usage) => {
+                usage.add(prompt_tokens, completion_tokens);
+                usage.clone()
+            }
+            Err(_) => RunUsage::default(),
+        }
+    }
+
+    pub fn usage(&self) -> RunUsage {
+        self.usage.lock().map(|u| u.clone()).unwrap_or_default()
+    }
+
+    /// Remember a failed action (e.g. a site that wouldn't load)
+    pub fn record_failure(&self, failure: &str) {
+        if let Ok(mut failures) = self.failures.lock() {
+            failures.push(failure.to_string());
+        }
+    }
+
+    pub fn failures(&self) -> Vec<String> {
+        self.failures.lock().map(|f| f.clone()).unwrap_or_default()
+    }
+
+    /// Remember the page content just returned, handing back the previous one
+    pub fn replace_page(&self, content: String) -> Option<String> {
+        self.last_page
+            .lock()
+            .ok()
+            .and_then(|mut page| page.replace(content))
+    }
+
+    /// Config for an automatic retry of this failed run: budgets reduced by
+    /// what was already spent, further retries disabled. `None` when no
+    /// budget is left to retry with.
+    pub fn retry_config(&self) -> Option<Config> {
+        let usage = self.usage();
+        let mut config = self.config.clone();
+        config.auto_retry_on_failure = false;
+        if let Some(max_tokens) = config.max_tokens {
+            config.max_tokens = Some(
+                max_tokens
+                    .checked_sub(usage.total_tokens())
+                    .filter(|t| *t > 0)?,
+            );
+        }
+        if let (Some(max_cost), Some(cost)) = (config.max_cost_usd, usage.cost_usd) {
+            let remaining = max_cost - cost;
+            if remaining <= 0.0 {
+                return None;
+            }
+            config.max_cost_usd = Some(remaining);
+        }
+        Some(config)
+    }
+
+    /// Instructions telling a retry what went wrong the first time
+    pub fn post_mortem(&self, error: &str) -> String {
+        let mut text = format!("A previous attempt at this task failed: {}\n", error);
+        let failures = self.failures();
+        if !failures.is_empty() {
+            text.push_str("These actions failed and should be avoided:\n");
+            for failure in failures.iter().take(10) {
+                text.push_str(&format!("- {}\n", failure));
+            }
+        }
+        let sources = self.sources();
+        if !sources.is_empty() {
+            text.push_str(&format!(
+                "Pages that were read successfully: {}\n",
+                sources.join(", ")
+            ));
+        }
+        text.push_str(
+            "Adjust your strategy: avoid the failing sites, start from a search engine to find \
+             alternative sources, and keep the number of steps small.",
+        );
+        text
+    }
+
+    pub fn save_bookmark(&self, bookmark: Bookmark) {
+        if let Ok(mut bookmarks) = self.bookmarks.lock() {
+            bookmarks.insert(bookmark.name.clone(), bookmark);
+        }
+    }
+
+    pub fn bookmark(&self, name: &str) -> Option<Bookmark> {
+        self.bookmarks.lock().ok()?.get(name).cloned()
+    }
+
+    pub fn bookmark_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .bookmarks
+            .lock()
+            .map(|b| b.keys().cloned().collect())
+            .unwrap_or_default();
+        names.sort();
+        names
+    }
+
+    /// Directory for files this run produces, created on first use
+    pub fn workspace(&self) -> Result<std::path::PathBuf, String> {
+        crate::workspace::run_dir(&self.run_id)
+    }
+
+    /// Fold a finished sub-agent's usage into this run's totals
+    pub fn absorb_usage(&self, other: &RunUsage) {
+        if let Ok(mut usage) = self.usage.lock() {
+            usage.merge(other);
+        }
+    }
+
+    /// Track a newly opened trace span, returning the innermost open span
+    /// as its parent
+    pub fn enter_span(&self, span_id: &str) -> Option<String> {
+        let mut spans = self.spans.lock().ok()?;
+        let parent = spans.last().cloned();
+        spans.push(span_id.to_string());
+        parent
+    }
+
+    pub fn exit_span(&self, span_id: &str) {
+        if let Ok(mut spans) = self.spans.lock() {
+            spans.retain(|id| id != span_id);
+        }
+    }
+
+    pub fn set_plan(&self, steps: Vec<String>) {
+        if let Ok(mut plan) = self.plan.lock() {
+            *plan = Some(steps);
+        }
+    }
+
+    pub fn plan(&self) -> Option<Vec<String>> {
+        self.plan.lock().ok().and_then(|p| p.clone())
+    }
+
+    /// Whether enough time has passed to write another checkpoint; marks the
+    /// checkpoint as taken when it returns `true`
+    pub fn checkpoint_due(&self) -> bool {
+        let interval = Duration::from_secs(self.config.checkpoint_interval_secs);
+        let Ok(mut last) = self.last_checkpoint.lock() else {
+            return false;
+        };
+        if last.is_some_and(|at| at.elapsed() < interval) {
+            return false;
+        }
+        *last = Some(Instant::now());
+        true
+    }
+}
+
+/// Progress of a live run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunStatus {
+    pub run_id: String,
+    pub parent_run_id: Option<String>,
+    /// Tool calls made so far
+    pub step: usize,
+    pub max_steps: usize,
+    pub last_tool: Option<String>,
+    /// Waiting for the user to hand control back
+    pub paused: bool,
+    /// Time since the run started (or was resumed)
+    pub elapsed_ms: u64,
+    /// Token spend so far
+    pub usage: RunUsage,
+}
+
+/// Make a run visible to commands while it executes
+pub fn register(ctx: Arc<RunContext>) {
+    let runs = ACTIVE_RUNS.get_or_init(|| Mutex::new(HashMap::new()));
+    if let Ok(mut runs) = runs.lock() {
+        runs.insert(ctx.run_id.clone(), ctx);
+    }
+}
+
+pub fn unregister(run_id: &str) {
+    if let Some(runs) = ACTIVE_RUNS.get() {
+        if let Ok(mut runs) = runs.lock() {
+            runs.remove(run_id);
+        }
+    }
+}
+
+/// Ids of all live runs
+pub fn active_ids() -> Vec<String> {
+    ACTIVE_RUNS
+        .get()
+        .and_then(|runs| runs.lock().ok().map(|runs| runs.keys().cloned().collect()))
+        .unwrap_or_default()
+}
+
+/// Whether any run is executing, and how far each has got
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentStatus {
+    pub active: bool,
+    /// Live runs, oldest first
+    pub runs: Vec<RunStatus>,
+}
+
+pub fn agent_status() -> AgentStatus {
+    let mut runs: Vec<RunStatus> = ACTIVE_RUNS
+        .get()
+        .and_then(|runs| {
+            runs.lock()
+                .ok()
+                .map(|runs| runs.values().map(|run| run.status()).collect())
+        })
+        .unwrap_or_default();
+    runs.sort_by(|a, b| b.elapsed_ms.cmp(&a.elapsed_ms));
+    AgentStatus {
+        active: !runs.is_empty(),
+        runs,
+    }
+}
+
+/// Look up a live run by id
+pub fn active(run_id: &str) -> Option<Arc<RunContext>> {
+    ACTIVE_RUNS
+        .get()
+        .and_then(|runs| runs.lock().ok().and_then(|runs| runs.get(run_id).cloned()))
+}
+
+// ============================================================================
+// Run Results
+// ============================================================================
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunState {
+    /// Waiting in the run queue
+    Queued,
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Outcome of a run started in the background, as far as it has got
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunResult {
+    pub run_id: String,
+    pub state: RunState,
+    pub report: Option<String>,
+    pub error: Option<String>,
+    /// Automatic retry whose outcome this is, when the first attempt failed
+    #[serde(default)]
+    pub retried_as: Option<String>,
+}
+
+impl RunResult {
+    pub fn pending(run_id: &str, state: RunState) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            state,
+            report: None,
+            error: None,
+            retried_as: None,
+        }
+    }
+
+    pub fn finished(run_id: &str, result: &Result<String, String>) -> Self {
+        Self {
+            run_id: run_id.to_string(),
+            state: match result {
+                Ok(_) => RunState::Completed,
+                Err(_) => RunState::Failed,
+            },
+            report: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().cloned(),
+            retried_as: None,
+        }
+    }
+
+    fn from_record(record: RunRecord) -> Self {
+        Self {
+            state: match record.status.as_str() {
+                "completed" => RunState::Completed,
+                "running" => RunState::Running,
+                _ => RunState::Failed,
+            },
+            run_id: record.run_id,
+            report: record.report,
+            error: record.error,
+            retried_as: None,
+        }
+    }
+
+    fn is_finished(&self) -> bool {
+        matches!(self.state, RunState::Completed | RunState::Failed)
+    }
+}
+
+/// Store the latest state of a background run, forgetting the oldest
+/// finished results beyond [`MAX_RESULTS`]
+pub fn set_result(result: RunResult) {
+    let Ok(mut results) = RESULTS.lock() else {
+        return;
+    };
+    results.retain(|r| r.run_id != result.run_id);
+    results.push(result);
+    let finished = results.iter().filter(|r| r.is_finished()).count();
+    let mut excess = finished.saturating_sub(MAX_RESULTS);
+    results.retain(|r| {
+        if excess > 0 && r.is_finished() {
+            excess -= 1;
+            false
+        } else {
+            true
+        }
+    });
+}
+
+/// State of a background run, falling back to its persisted record once its
+/// result has been forgotten. A finished run that was retried automatically
+/// reports the retry's outcome.
+pub fn get_result(conn: &Connection, run_id: &str) -> rusqlite::Result<Option<RunResult>> {
+    let kept = RESULTS
+        .lock()
+        .ok()
+        .and_then(|results| results.iter().find(|r| r.run_id == run_id).cloned());
+    let result = match kept {
+        Some(result) => result,
+        None => match get_record(conn, run_id)? {
+            Some(record) => RunResult::from_record(record),
+            None => return Ok(None),
+        },
+    };
+    if !result.is_finished() {
+        return Ok(Some(result));
+    }
+    Ok(Some(match get_retry(conn, run_id)? {
+        Some(retry) => RunResult {
+            run_id: run_id.to_string(),
+            retried_as: Some(retry.run_id.clone()),
+            ..RunResult::from_record(retry)
+        },
+        None => result,
+    }))
+}
+
+// ============================================================================
+// Run Records
+// ============================================================================
+
+/// Persisted summary of an agent run
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RunRecord {
+    pub run_id: String,
+    pub prompt: String,
+    pub provider: String,
+    pub model: String,
+    /// "running", "completed", "failed" or "interrupted"
+    pub status: String,
+    pub started_at: i64,
+    pub finished_at: Option<i64>,
+    pub report: Option<String>,
+    pub error: Option<String>,
+    pub usage: RunUsage,
+    /// Runs of the same prompt/template share a series
+    pub series: String,
+    /// Previous run in the same series, if any
+    pub previous_run_id: Option<String>,
+    /// Failed run this one automatically retries
+    pub retry_of: Option<String>,
+    /// Verdict against the run's success criteria, if it declared any
+    pub validation: Option<Validation>,
+    /// Pages the run read
+    #[serde(default)]
+    pub sources: Vec<String>,
+    /// Findings the run recorded
+    #[serde(default)]
+    pub key_discoveries: Vec<String>,
+}
+
+const RUN_COLUMNS: &str = "run_id, prompt, provider, model, status, started_at, finished_at, \
+     report, error, usage, series, previous_run_id, retry_of, validation, \
+     sources, key_discoveries";
+
+/// Series key for a prompt: re-running the same prompt (ignoring case and
+/// whitespace) links the runs together
+pub fn series_key(prompt: &str) -> String {
+    prompt
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+fn json_list(value: Option<String>) -> Vec<String> {
+    value
+        .and_then(|v| serde_json::from_str(&v).ok())
+        .unwrap_or_default()
+}
+
+fn run_from_row(row: &Row<'_>) -> rusqlite::Result<RunRecord> {
+    let usage: String = row.get(9)?;
+    Ok(RunRecord {
+        run_id: row.get(0)?,
+        prompt: row.get(1)?,
+        provider: row.get(2)?,
+        model: row.get(3)?,
+        status: row.get(4)?,
+        started_at: row.get(5)?,
+        finished_at: row.get(6)?,
+        report: row.get(7)?,
+        error: row.get(8)?,
+        usage: serde_json::from_str(&usage).unwrap_or_default(),
+        series: row.get(10)?,
+        previous_run_id: row.get(11)?,
+        retry_of: row.get(12)?,
+        validation: row
+            .get::<_, Option<String>>(13)?
+            .and_then(|v| serde_json::from_str(&v).ok()),
+        sources: json_list(row.get(14)?),
+        key_discoveries: json_list(row.get(15)?),
+    })
+}
+
+pub fn insert_record(conn: &Connection, record: &RunRecord) -> rusqlite::Result<()> {
+    conn.execute(
+        &format!(
+            "INSERT INTO runs ({}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, \
+             ?15, ?16)",
+            RUN_COLUMNS
+        ),
+        params![
+            record.run_id,
+            record.prompt,
+            record.provider,
+            record.model,
+            record.status,
+            record.started_at,
+            record.finished_at,
+            record.report,
+            record.error,
+            serde_json::to_string(&record.usage).unwrap_or_default(),
+            record.series,
+            record.previous_run_id,
+            record.retry_of,
+            record
+                .validation
+                .as_ref()
+                .and_then(|v| serde_json::to_string(v).ok()),
+            serde_json::to_string(&record.sources).unwrap_or_default(),
+            serde_json::to_string(&record.key_discoveries).unwrap_or_default()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Mark a run finished with its outcome and final usage
+pub fn finish_record(
+    conn: &Connection,
+    run_id: &str,
+    result: &Result<String, String>,
+    usage: &RunUsage,
+) -> rusqlite::Result<()> {
+    let (status, report, error) = match result {
+        Ok(report) => ("completed", Some(report.as_str()), None),
+        Err(e) => ("failed", None, Some(e.as_str())),
+    };
+    conn.execute(
+        "UPDATE runs SET status = ?2, finished_at = ?3, report = ?4, error = ?5, usage = ?6
+         WHERE run_id = ?1",
+        params![
+            run_id,
+            status,
+            chrono::Utc::now().timestamp_millis(),
+            report,
+            error,
+            serde_json::to_string(usage).unwrap_or_default()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Store a completed run's verdict against its success criteria
+pub fn set_validation(
+    conn: &Connection,
+    run_id: &str,
+    validation: &Validation,
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE runs SET validation = ?2 WHERE run_id = ?1",
+        params![
+            run_id,
+            serde_json::to_string(validation).unwrap_or_default()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Store the pages a finished run read and the findings it recorded
+pub fn set_provenance(
+    conn: &Connection,
+    run_id: &str,
+    sources: &[String],
+    key_discoveries: &[String],
+) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE runs SET sources = ?2, key_discoveries = ?3 WHERE run_id = ?1",
+        params![
+            run_id,
+            serde_json::to_string(sources).unwrap_or_default(),
+            serde_json::to_string(key_discoveries).unwrap_or_default()
+        ],
+    )?;
+    Ok(())
+}
+
+/// Flag runs left "running" by a previous process (crash or restart) so they
+/// can be resumed from their checkpoint
+pub fn mark_interrupted(conn: &Connection) -> rusqlite::Result<usize> {
+    conn.execute(
+        "UPDATE runs SET status = 'interrupted' WHERE status = 'running'",
+        [],
+    )
+}
+
+/// Flag a resumed run as running again
+pub fn mark_resumed(conn: &Connection, run_id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE runs SET status = 'running', finished_at = NULL, error = NULL WHERE run_id = ?1",
+        params![run_id],
+    )?;
+    Ok(())
+}
+
+pub fn get_record(conn: &Connection, run_id: &str) -> rusqlite::Result<Option<RunRecord>> {
+    conn.query_row(
+        &format!("SELECT {} FROM runs WHERE run_id = ?1", RUN_COLUMNS),
+        params![run_id],
+        run_from_row,
+    )
+    .optional()
+}
+
+/// The automatic retry of a failed run, if it was retried
+pub fn get_retry(conn: &Connection, run_id: &str) -> rusqlite::Result<Option<RunRecord>> {
+    conn.query_row(
+        &format!("SELECT {} FROM runs WHERE retry_of = ?1", RUN_COLUMNS),
+        params![run_id],
+        run_from_row,
+    )
+    .optional()
+}
+
+/// Most recent run in a series
+pub fn latest_in_series(conn: &Connection, series: &str) -> rusqlite::Result<Option<String>> {
+    conn.query_row(
+        "SELECT run_id FROM runs WHERE series = ?1 ORDER BY started_at DESC LIMIT 1",
+        params![series],
+        |row| row.get(0),
+    )
+    .optional()
+}
+
+/// All runs in the same series as `run_id`, oldest first
+pub fn series_history(conn: &Connection, run_id: &str) -> rusqlite::Result<Vec<RunRecord>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM runs WHERE series = (SELECT series FROM runs WHERE run_id = ?1)
+         ORDER BY started_at ASC",
+        RUN_COLUMNS
+    ))?;
+    let rows = stmt.query_map(params![run_id], run_from_row)?;
+    rows.collect()
+}
+
+/// Get the context of the run executing on this task, if any
+pub fn current() -> Option<Arc<RunContext>> {
+    CURRENT_RUN.try_with(|ctx| ctx.clone()).ok()
+}
+
+/// Execute `fut` with `ctx` installed as the current run, inside a trace span
+/// covering the whole run
+pub async fn scope<F: Future>(ctx: Arc<RunContext>, fut: F) -> F::Output {
+    let run_id = ctx.run_id.clone();
+    CURRENT_RUN
+        .scope(ctx, async move {
+            let _span = crate::trace_span!("nexus::run", "run", run_id = run_id);
+            fut.await
+        })
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frontier::Admission;
+
+    #[test]
+    fn test_step_budget() {
+        let config = Config {
+            max_steps: 2,
+            ..Config::default()
+        };
+        let ctx = RunContext::new(config);
+        assert_eq!(ctx.next_step(), Ok(1));
+        assert!(!ctx.budget_exhausted());
+        assert_eq!(ctx.next_step(), Ok(2));
+        assert!(ctx.budget_exhausted());
+        assert!(ctx.next_step().is_err());
+
+        ctx.record_tool("navigate");
+        let status = ctx.status();
+        assert_eq!(status.step, 2);
+        assert_eq!(status.max_steps, 2);
+        assert_eq!(status.last_tool.as_deref(), Some("navigate"));
+    }
+
+    #[tokio::test]
+    async fn test_pause_timeout() {
+        let config = Config {
+            pause_timeout_secs: 0,
+            ..Config::default()
+        };
+        let ctx = RunContext::new(config);
+        ctx.pause();
+        ctx.wait_while_paused().await;
+        assert!(!ctx.is_paused());
+        assert_eq!(
+            ctx.stop_reason().as_deref(),
+            Some("pause limit of 0 seconds")
+        );
+        assert!(ctx.next_step().is_err());
+    }
+
+    #[test]
+    fn test_run_results() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+        let run_id = Uuid::new_v4().to_string();
+        assert_eq!(get_result(&conn, &run_id).unwrap(), None);
+
+        set_result(RunResult::pending(&run_id, RunState::Queued));
+        set_result(RunResult::pending(&run_id, RunState::Running));
+        assert_eq!(
+            get_result(&conn, &run_id).unwrap().map(|r| r.state),
+            Some(RunState::Running)
+        );
+
+        set_result(RunResult::finished(&run_id, &Err("timed out".to_string())));
+        let result = get_result(&conn, &run_id).unwrap().unwrap();
+        assert_eq!(result.state, RunState::Failed);
+        assert_eq!(result.error.as_deref(), Some("timed out"));
+
+        // An automatic retry's outcome is reported under the original id
+        let retry = RunRecord {
+            run_id: "retry-1".to_string(),
+            prompt: "find things".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            status: "running".to_string(),
+            started_at: 1,
+            finished_at: None,
+            report: None,
+            error: None,
+            usage: RunUsage::new("gpt-4o"),
+            series: series_key("find things"),
+            previous_run_id: None,
+            retry_of: Some(run_id.clone()),
+            validation: None,
+            sources: Vec::new(),
+            key_discoveries: Vec::new(),
+        };
+        insert_record(&conn, &retry).unwrap();
+        finish_record(&conn, "retry-1", &Ok("# Report".to_string()), &retry.usage).unwrap();
+        let result = get_result(&conn, &run_id).unwrap().unwrap();
+        assert_eq!(result.run_id, run_id);
+        assert_eq!(result.state, RunState::Completed);
+        assert_eq!(result.report.as_deref(), Some("# Report"));
+        assert_eq!(result.retried_as.as_deref(), Some("retry-1"));
+    }
+
+    #[test]
+    fn test_resume_from_checkpoint() {
+        let checkpoint = Checkpoint {
+            run_id: "run-1".to_string(),
+            saved_at: 0,
+            steps: 3,
+            current_url: None,
+            thread: serde_json::Value::Null,
+            memory: Vec::new(),
+            findings: vec!["a finding".to_string()],
+            sources: vec!["https://example.com/".to_string()],
+            plan: Some(vec!["step one".to_string()]),
+            usage: Some(RunUsage {
+                llm_calls: 2,
+                prompt_tokens: 900,
+                completion_tokens: 100,
+                ..RunUsage::default()
+            }),
+        };
+        let ctx = RunContext::resume(Config::default(), &checkpoint);
+        assert_eq!(ctx.run_id, "run-1");
+        assert_eq!(ctx.next_step(), Ok(4));
+        assert_eq!(ctx.findings(), checkpoint.findings);
+        assert_eq!(ctx.sources(), checkpoint.sources);
+        assert_eq!(ctx.plan(), checkpoint.plan);
+        assert_eq!(ctx.usage().total_tokens(), 1000);
+
+        assert!(ctx.checkpoint_due());
+        assert!(!ctx.checkpoint_due());
+    }
+
+    #[test]
+    fn test_retry_config_and_post_mortem() {
+        let config = Config {
+            model: "gpt-4o".to_string(),
+            max_tokens: Some(1000),
+            auto_retry_on_failure: true,
+            ..Config::default()
+        };
+        let ctx = RunContext::new(config);
+        ctx.record_usage(300, 100);
+        ctx.record_failure("https://flaky.example: net::ERR_TIMED_OUT");
+
+        let retry = ctx.retry_config().unwrap();
+        assert_eq!(retry.max_tokens, Some(600));
+        assert!(!retry.auto_retry_on_failure);
+
+        let post_mortem = ctx.post_mortem("Navigation timed out");
+        assert!(post_mortem.contains("failed: Navigation timed out"));
+        assert!(post_mortem.contains("- https://flaky.example"));
+
+        ctx.record_usage(600, 0);
+        assert!(ctx.retry_config().is_none());
+    }
+
+    #[test]
+    fn test_spend_budget() {
+        let config = Config {
+            model: "gpt-4o".to_string(),
+            max_tokens: Some(1000),
+            ..Config::default()
+        };
+        let ctx = RunContext::new(config);
+        assert!(ctx.admit_llm_call());
+        ctx.record_usage(900, 200);
+        assert!(ctx.spend_exceeded().is_some());
+        assert!(ctx.next_step().is_err());
+        // One grace call for the final report, then abort
+        assert!(ctx.admit_llm_call());
+        assert!(!ctx.admit_llm_call());
+    }
+
+    #[test]
+    fn test_budget_share() {
+        let config = Config {
+            max_steps: 20,
+            max_tokens: Some(1000),
+            ..Config::default()
+        };
+        let ctx = RunContext::new(config);
+        ctx.next_step().unwrap();
+        ctx.next_step().unwrap();
+        ctx.record_usage(300, 100);
+
+        let share = ctx.budget_share(3);
+        assert_eq!(share.max_steps, 6);
+        assert_eq!(share.max_tokens, Some(200));
+        assert_eq!(share.max_cost_usd, None);
+    }
+
+    #[test]
+    fn test_sub_agent_shares_frontier() {
+        fn admit(ctx: &RunContext, url: &str) -> Admission {
+            ctx.frontier.lock().unwrap().admit(url)
+        }
+        let config = Config {
+            max_pages_per_domain: Some(2),
+            ..Config::default()
+        };
+        let parent = RunContext::new(config);
+        admit(&parent, "https://example.com/a");
+
+        let child = RunContext::sub_agent(&parent, 2);
+        let sibling = RunContext::sub_agent(&parent, 2);
+        assert_eq!(admit(&child, "https://example.com/a"), Admission::Revisit);
+        assert_eq!(admit(&child, "https://example.com/b"), Admission::Admitted);
+        assert_eq!(
+            admit(&sibling, "https://example.com/c"),
+            Admission::DomainCapReached {
+                domain: "example.com".to_string(),
+                cap: 2
+            }
+        );
+        let snapshot = parent.frontier.lock().unwrap().snapshot();
+        assert_eq!(snapshot.per_domain.get("example.com"), Some(&2));
+    }
+
+    #[test]
+    fn test_run_record_lifecycle() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+
+        let record = RunRecord {
+            run_id: "run-1".to_string(),
+            prompt: "find things".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-4o".to_string(),
+            status: "running".to_string(),
+            started_at: 1,
+            finished_at: None,
+            report: None,
+            error: None,
+            usage: RunUsage::new("gpt-4o"),
+            series: series_key("find things"),
+            previous_run_id: None,
+            retry_of: None,
+            validation: None,
+            sources: Vec::new(),
+            key_discoveries: Vec::new(),
+        };
+        insert_record(&conn, &record).unwrap();
+
+        let mut usage = RunUsage::new("gpt-4o");
+        usage.add(100, 50);
+        finish_record(&conn, "run-1", &Ok("# Report".to_string()), &usage).unwrap();
+
+        let stored = get_record(&conn, "run-1").unwrap().unwrap();
+        assert_eq!(stored.status, "completed");
+        assert_eq!(stored.report.as_deref(), Some("# Report"));
+        assert_eq!(stored.usage, usage);
+        assert!(get_record(&conn, "missing").unwrap().is_none());
+
+        let validation = Validation {
+            passed: false,
+            reasons: vec!["Report is missing the field \"Price\"".to_string()],
+        };
+        set_validation(&conn, "run-1", &validation).unwrap();
+        let stored = get_record(&conn, "run-1").unwrap().unwrap();
+        assert_eq!(stored.validation, Some(validation));
+
+        let sources = vec!["https://example.com/".to_string()];
+        set_provenance(&conn, "run-1", &sources, &[]).unwrap();
+        let stored = get_record(&conn, "run-1").unwrap().unwrap();
+        assert_eq!(stored.sources, sources);
+        assert!(stored.key_discoveries.is_empty());
+
+        let series = series_key("  Find   THINGS ");
+        let previous = latest_in_series(&conn, &series).unwrap();
+        assert_eq!(previous.as_deref(), Some("run-1"));
+        let rerun = RunRecord {
+            run_id: "run-2".to_string(),
+            started_at: 2,
+            previous_run_id: previous,
+            ..record
+        };
+        insert_record(&conn, &rerun).unwrap();
+        let versions = series_history(&conn, "run-2").unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[1].previous_run_id.as_deref(), Some("run-1"));
+    }
+}
@@ -0,0 +1,293 @@
+//! WASI sandbox for untrusted plugin tools
+//!
+//! WASM plugins run as WASI preview1 commands: the tool arguments are written
+//! to stdin as JSON and the module prints `{"ok": ...}` or `{"error": "..."}`
+//! to stdout. Nothing is accessible by default; manifests grant filesystem
+//! subpaths and network domains explicitly, and every call is bounded by fuel,
+//! memory and wall-clock limits.
+//!
+//! Network access goes through a host import, checked against the granted
+//! domains:
+//!
+//! ```c
+//! // Returns the number of bytes written to `out`, or a negative error code.
+//! __attribute__((import_module("nexus"), import_name("http_get")))
+//! int32_t http_get(const char* url, int32_t url_len, char* out, int32_t out_cap);
+//! ```
+
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use wasmtime::{
+    Caller, Config as EngineConfig, Engine, Linker, Module, Store, StoreLimits, StoreLimitsBuilder,
+    UpdateDeadline,
+};
+use wasmtime_wasi::pipe::{MemoryInputPipe, MemoryOutputPipe};
+use wasmtime_wasi::preview1::{self, WasiP1Ctx};
+use wasmtime_wasi::{DirPerms, FilePerms, I32Exit, WasiCtxBuilder};
+
+/// Error codes returned by the `nexus.http_get` host import
+const HTTP_DENIED: i32 = -1;
+const HTTP_FAILED: i32 = -2;
+const HTTP_BAD_ARGS: i32 = -3;
+
+/// Redirects `nexus.http_get` follows, each checked against the grants
+const MAX_REDIRECTS: usize = 5;
+
+/// Capabilities explicitly granted to a sandboxed plugin
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capabilities {
+    /// Domains (and their subdomains) reachable through `nexus.http_get`
+    pub network_domains: Vec<String>,
+    /// Host directories mounted into the guest
+    pub filesystem: Vec<FsGrant>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FsGrant {
+    pub host_path: PathBuf,
+    pub guest_path: String,
+    #[serde(default)]
+    pub writable: bool,
+}
+
+/// Per-call resource limits for a sandboxed plugin
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ResourceLimits {
+    pub fuel: u64,
+    pub memory_mb: usize,
+    pub timeout_ms: u64,
+    pub max_output_bytes: usize,
+}
+
+impl Default for ResourceLimits {
+    fn default() -> Self {
+        Self {
+            fuel: 1_000_000_000,
+            memory_mb: 64,
+            timeout_ms: 10_000,
+            max_output_bytes: 1024 * 1024,
+        }
+    }
+}
+
+struct SandboxState {
+    wasi: WasiP1Ctx,
+    limits: StoreLimits,
+    network_domains: Vec<String>,
+    /// When the call's wall-clock limit runs out
+    deadline: Instant,
+}
+
+/// A compiled WASM module with its capability grants
+pub struct WasmSandbox {
+    name: String,
+    engine: Engine,
+    module: Module,
+    capabilities: Capabilities,
+    limits: ResourceLimits,
+}
+
+impl WasmSandbox {
+    pub fn load(
+        name: &str,
+        path: &Path,
+        capabilities: Capabilities,
+        limits: ResourceLimits,
+    ) -> Result<Self> {
+        let mut config = EngineConfig::new();
+        config.consume_fuel(true);
+        config.epoch_interruption(true);
+        let engine = Engine::new(&config)?;
+        let module = Module::from_file(&engine, path)?;
+        Ok(Self {
+            name: name.to_string(),
+            engine,
+            module,
+            capabilities,
+            limits,
+        })
+    }
+
+    /// Run the module once with `args` on stdin, returning its stdout
+    pub fn call(&self, args: &str) -> Result<String> {
+        let stdout = MemoryOutputPipe::new(self.limits.max_output_bytes);
+        let stderr = MemoryOutputPipe::new(64 * 1024);
+
+        let mut builder = WasiCtxBuilder::new();
+        builder
+            .stdin(MemoryInputPipe::new(args.as_bytes().to_vec()))
+            .stdout(stdout.clone())
+            .stderr(stderr.clone());
+        for grant in &self.capabilities.filesystem {
+            let (dir_perms, file_perms) = if grant.writable {
+                (DirPerms::all(), FilePerms::all())
+            } else {
+                (DirPerms::READ, FilePerms::READ)
+            };
+            builder.preopened_dir(&grant.host_path, &grant.guest_path, dir_perms, file_perms)?;
+        }
+
+        let timeout = Duration::from_millis(self.limits.timeout_ms);
+        let deadline = Instant::now() + timeout;
+        let state = SandboxState {
+            wasi: builder.build_p1(),
+            limits: StoreLimitsBuilder::new()
+                .memory_size(self.limits.memory_mb * 1024 * 1024)
+                .instances(1)
+                .build(),
+            network_domains: self.capabilities.network_domains.clone(),
+            deadline,
+        };
+        let mut store = Store::new(&self.engine, state);
+        store.limiter(|s| &mut s.limits);
+        store.set_fuel(self.limits.fuel)?;
+        store.set_epoch_deadline(1);
+        // The engine is shared by concurrent calls, so an epoch bump only
+        // stops the calls whose own deadline has passed
+        store.epoch_deadline_callback(|store| {
+            if Instant::now() >= store.data().deadline {
+                Err(anyhow!("wall-clock limit exceeded"))
+            } else {
+                Ok(UpdateDeadline::Continue(1))
+            }
+        });
+
+        let mut linker: Linker<SandboxState> = Linker::new(&self.engine);
+        preview1::add_to_linker_sync(&mut linker, |s| &mut s.wasi)?;
+        linker.func_wrap("nexus", "http_get", http_get)?;
+
+        // Wall-clock limit: bump the epoch once the timeout elapses.
+        let engine = self.engine.clone();
+        let (done_tx, done_rx) = std::sync::mpsc::channel::<()>();
+        std::thread::spawn(move || {
+            if done_rx.recv_timeout(timeout).is_err() {
+                engine.increment_epoch();
+            }
+        });
+
+        crate::trace_info!("nexus::sandbox", "Sandboxed call started", plugin = self.name);
+        let started = std::time::Instant::now();
+        let result = linker
+            .instantiate(&mut store, &self.module)
+            .and_then(|instance| instance.get_typed_func::<(), ()>(&mut store, "_start"))
+            .and_then(|start| start.call(&mut store, ()));
+        let _ = done_tx.send(());
+        let fuel_used = self.limits.fuel - store.get_fuel().unwrap_or(0);
+
+        if let Err(e) = result {
+            match e.downcast_ref::<I32Exit>() {
+                Some(I32Exit(0)) => {}
+                _ => {
+                    let stderr_text = String::from_utf8_lossy(&stderr.contents()).into_owned();
+                    crate::trace_error!(
+                        "nexus::sandbox",
+                        "Sandboxed call failed",
+                        plugin = self.name,
+                        error = e.to_string(),
+                        stderr = stderr_text,
+                        fuel_used = fuel_used
+                    );
+                    return Err(anyhow!("Plugin '{}' trapped: {}", self.name, e));
+                }
+            }
+        }
+
+        crate::trace_info!(
+            "nexus::sandbox",
+            "Sandboxed call finished",
+            plugin = self.name,
+            fuel_used = fuel_used,
+            elapsed_ms = started.elapsed().as_millis() as u64
+        );
+        String::from_utf8(stdout.contents().to_vec()).map_err(|e| anyhow!(e))
+    }
+}
+
+fn read_guest_bytes(caller: &mut Caller<'_, SandboxState>, ptr: i32, len: i32) -> Option<Vec<u8>> {
+    let memory = caller.get_export("memory")?.into_memory()?;
+    let start = usize::try_from(ptr).ok()?;
+    let end = start.checked_add(usize::try_from(len).ok()?)?;
+    memory.data(&*caller).get(start..end).map(|b| b.to_vec())
+}
+
+fn http_get(
+    mut caller: Caller<'_, SandboxState>,
+    url_ptr: i32,
+    url_len: i32,
+    out_ptr: i32,
+    out_cap: i32,
+) -> i32 {
+    let Some(url) = read_guest_bytes(&mut caller, url_ptr, url_len)
+        .and_then(|bytes| String::from_utf8(bytes).ok())
+    else {
+        return HTTP_BAD_ARGS;
+    };
+
+    if !crate::approval::url_matches_domains(&url, &caller.data().network_domains) {
+        crate::trace_warn!("nexus::sandbox", "Network access denied", url = url);
+        return HTTP_DENIED;
+    }
+
+    let remaining = caller
+        .data()
+        .deadline
+        .saturating_duration_since(Instant::now());
+    let domains = caller.data().network_domains.clone();
+    // Every hop must stay within the granted domains
+    let redirects = reqwest::redirect::Policy::custom(move |attempt| {
+        if attempt.previous().len() >= MAX_REDIRECTS {
+            attempt.error("too many redirects")
+        } else if crate::approval::url_matches_domains(attempt.url().as_str(), &domains) {
+            attempt.follow()
+        } else {
+            attempt.error("redirect to a domain that is not granted")
+        }
+    });
+    let body = match reqwest::blocking::Client::builder()
+        .redirect(redirects)
+        .timeout(remaining)
+        .build()
+        .and_then(|client| client.get(&url).send())
+        .and_then(|r| r.bytes())
+    {
+        Ok(body) => body,
+        Err(e) => {
+            crate::trace_error!(
+                "nexus::sandbox",
+                "Plugin fetch failed",
+                url = url,
+                error = e.to_string()
+            );
+            return HTTP_FAILED;
+        }
+    };
+
+    let Some(memory) = caller.get_export("memory").and_then(|e| e.into_memory()) else {
+        return HTTP_BAD_ARGS;
+    };
+    let len = body.len().min(out_cap.max(0) as usize);
+    match memory.write(&mut caller, out_ptr as usize, &body[..len]) {
+        Ok(()) => len as i32,
+        Err(_) => HTTP_BAD_ARGS,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_manifest_defaults_grant_nothing() {
+        let caps: Capabilities = serde_json::from_str("{}").unwrap();
+        assert!(caps.network_domains.is_empty());
+        assert!(caps.filesystem.is_empty());
+
+        let limits: ResourceLimits = serde_json::from_str(r#"{"memory_mb": 8}"#).unwrap();
+        assert_eq!(limits.memory_mb, 8);
+        assert_eq!(limits.timeout_ms, ResourceLimits::default().timeout_ms);
+    }
+}
@@ -0,0 +1,254 @@
+//! Background scheduler for saved tasks
+//!
+//! Saved tasks with a cron schedule are checked every [`TICK`] and run in the
+//! background when an occurrence has come up since the last check. Runs land
+//! in the run history like any other, and a `scheduled-run` event is emitted
+//! when one finishes. Occurrences missed while the app was closed are skipped
+//! rather than caught up on.
+//!
+//! Schedules use local time and the standard five fields (minute, hour, day
+//! of month, month, day of week); a leading seconds field and a trailing year
+//! field are also accepted. Days of the week are numbered the standard way,
+//! 0 (or 7) = Sunday through 6 = Saturday, or given by name (`MON-FRI`).
+
+use crate::config::ConfigManager;
+use crate::tasks::SavedTask;
+use chrono::{DateTime, Local, TimeZone};
+use cron::Schedule;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::str::FromStr;
+use std::sync::{Mutex, OnceLock};
+use tauri::{Emitter, Manager};
+use tokio::time::{sleep, Duration};
+
+/// How often schedules are checked
+const TICK: Duration = Duration::from_secs(30);
+
+/// Tasks with a scheduled run in progress
+static RUNNING: OnceLock<Mutex<HashSet<String>>> = OnceLock::new();
+
+/// Outcome of a scheduled run, emitted as a `scheduled-run` event
+#[derive(Debug, Clone, Serialize)]
+pub struct ScheduledRun {
+    pub task_id: String,
+    pub task_name: String,
+    pub run_id: Option<String>,
+    pub success: bool,
+    pub error: Option<String>,
+}
+
+/// Parse a cron expression, accepting the standard five-field form
+pub fn parse_schedule(expression: &str) -> Result<Schedule, String> {
+    let mut fields: Vec<String> = expression.split_whitespace().map(str::to_string).collect();
+    if fields.len() == 5 {
+        fields.insert(0, "0".to_string());
+    }
+    if let Some(weekdays) = fields.get_mut(5) {
+        *weekdays = cron_weekdays(weekdays);
+    }
+    Schedule::from_str(&fields.join(" "))
+        .map_err(|e| format!("Invalid schedule '{}': {}", expression, e))
+}
+
+/// Renumber a day-of-week field from the standard 0 (or 7) = Sunday to the
+/// `cron` crate's 1 = Sunday. Names, `*` and step sizes are kept as they are;
+/// a range that now wraps past Saturday is split in two.
+fn cron_weekdays(field: &str) -> String {
+    let weekday = |day: &str| {
+        day.parse::<u8>()
+            .ok()
+            .filter(|d| *d <= 7)
+            .map(|d| d % 7 + 1)
+    };
+    field
+        .split(',')
+        .map(|part| {
+            let (days, step) = match part.split_once('/') {
+                Some((days, step)) => (days, format!("/{}", step)),
+                None => (part, String::new()),
+            };
+            match days.split_once('-') {
+                Some((from, to)) => match (weekday(from), weekday(to)) {
+                    (Some(from), Some(to)) if to < from => {
+                        format!("{}-7{},1-{}{}", from, step, to, step)
+                    }
+                    (Some(from), Some(to)) => format!("{}-{}{}", from, to, step),
+                    _ => part.to_string(),
+                },
+                None => match weekday(days) {
+                    Some(day) => format!("{}{}", day, step),
+                    None => part.to_string(),
+                },
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Whether the schedule has an occurrence after `after`, up to and including
+/// `now`
+pub fn is_due<Tz: TimeZone>(schedule: &Schedule, after: &DateTime<Tz>, now: &DateTime<Tz>) -> bool {
+    schedule
+        .after(after)
+        .next()
+        .is_some_and(|next| next <= *now)
+}
+
+/// The next time a schedule fires, in milliseconds since the epoch
+pub fn next_run(expression: &str) -> Result<Option<i64>, String> {
+    Ok(parse_schedule(expression)?
+        .upcoming(Local)
+        .next()
+        .map(|t| t.timestamp_millis()))
+}
+
+/// Start checking schedules in the background
+pub fn start_scheduler() {
+    let _ = RUNNING.set(Mutex::new(HashSet::new()));
+    tauri::async_runtime::spawn(async {
+        let mut last_check = Local::now();
+        loop {
+            sleep(TICK).await;
+            let now = Local::now();
+            run_due_tasks(&last_check, &now);
+            last_check = now;
+        }
+    });
+}
+
+fn run_due_tasks(after: &DateTime<Local>, now: &DateTime<Local>) {
+    let tasks = match crate::db::with_db(crate::tasks::scheduled) {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            crate::trace_warn!(
+                "nexus::scheduler",
+                "Could not load scheduled tasks",
+                error = e
+            );
+            return;
+        }
+    };
+    for task in tasks {
+        let Some(expression) = task.schedule.as_deref() else {
+            continue;
+        };
+        match parse_schedule(expression) {
+            Ok(schedule) if is_due(&schedule, after, now) => start_run(task),
+            Ok(_) => {}
+            Err(e) => crate::trace_warn!(
+                "nexus::scheduler",
+                "Skipping task with invalid schedule",
+                task_id = task.id,
+                error = e
+            ),
+        }
+    }
+}
+
+fn start_run(task: SavedTask) {
+    let Some(running) = RUNNING.get() else {
+        return;
+    };
+    if !running.lock().unwrap().insert(task.id.clone()) {
+        crate::trace_info!(
+            "nexus::scheduler",
+            "Previous scheduled run still in progress, skipping",
+            task_id = task.id
+        );
+        return;
+    }
+    crate::trace_info!(
+        "nexus::scheduler",
+        "Starting scheduled run",
+        task_id = task.id,
+        name = task.name
+    );
+
+    tauri::async_runtime::spawn(async move {
+        let (run_id, result) = match crate::GLOBAL_APP.get() {
+            Some(app) => {
+                let config = app.state::<Mutex<ConfigManager>>().lock().unwrap().load();
+                let (run_id, result) = crate::tasks::run(&task, &config).await;
+                (Some(run_id), result)
+            }
+            None => (None, Err("App not initialized".to_string())),
+        };
+        running.lock().unwrap().remove(&task.id);
+
+        let outcome = ScheduledRun {
+            task_id: task.id.clone(),
+            task_name: task.name.clone(),
+            run_id,
+            success: result.is_ok(),
+            error: result.err(),
+        };
+        crate::trace_info!(
+            "nexus::scheduler",
+            "Scheduled run finished",
+            task_id = outcome.task_id,
+            success = outcome.success
+        );
+        if let Some(app) = crate::GLOBAL_APP.get() {
+            let _ = app.emit("scheduled-run", &outcome);
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    #[test]
+    fn test_schedules() {
+        let weekday_mornings = parse_schedule("30 9 * * MON-FRI").unwrap();
+        // 2024-01-01 was a Monday
+        let monday = Utc.with_ymd_and_hms(2024, 1, 1, 9, 0, 0).unwrap();
+        let before = Utc.with_ymd_and_hms(2024, 1, 1, 9, 29, 45).unwrap();
+        let after = Utc.with_ymd_and_hms(2024, 1, 1, 9, 30, 15).unwrap();
+        assert!(!is_due(&weekday_mornings, &monday, &before));
+        assert!(is_due(&weekday_mornings, &before, &after));
+        assert!(!is_due(
+            &weekday_mornings,
+            &after,
+            &(after + chrono::Duration::hours(12))
+        ));
+
+        let saturday = Utc.with_ymd_and_hms(2024, 1, 6, 0, 0, 0).unwrap();
+        assert!(!is_due(
+            &weekday_mornings,
+            &saturday,
+            &(saturday + chrono::Duration::days(1))
+        ));
+
+        // Numbered weekdays count from Sunday = 0
+        let numbered = parse_schedule("30 9 * * 1-5").unwrap();
+        assert!(is_due(&numbered, &before, &after));
+        let sunday = Utc.with_ymd_and_hms(2024, 1, 7, 0, 0, 0).unwrap();
+        let weekend = parse_schedule("0 9 * * 6,0").unwrap();
+        assert!(is_due(
+            &weekend,
+            &saturday,
+            &(saturday + chrono::Duration::hours(12))
+        ));
+        assert!(is_due(
+            &weekend,
+            &sunday,
+            &(sunday + chrono::Duration::hours(12))
+        ));
+        assert!(!is_due(
+            &weekend,
+            &monday,
+            &(monday + chrono::Duration::hours(12))
+        ));
+        assert_eq!(cron_weekdays("7"), "1");
+        assert_eq!(cron_weekdays("5-7"), "6-7,1-1");
+        assert_eq!(cron_weekdays("*/2,MON"), "*/2,MON");
+
+        // Seconds-precision expressions pass through unchanged
+        assert!(parse_schedule("0 */15 * * * *").is_ok());
+        assert!(parse_schedule("every day").is_err());
+        assert!(next_run("0 9 * * *").unwrap().is_some());
+    }
+}
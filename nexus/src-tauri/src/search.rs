@@ -1,18 +1,169 @@
-use anyhow::Result;
-use grep::regex::RegexMatcher;
+use crate::memory::MemoryEntry;
+use crate::tracing::TraceEvent;
+use anyhow::{anyhow, Result};
+use grep::matcher::Matcher;
+use grep::regex::{RegexMatcher, RegexMatcherBuilder};
 use grep::searcher::Searcher;
 use grep::searcher::sinks::UTF8;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use similar::TextDiff;
+use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
+use std::ops::Range;
 
-pub fn search_content(content: &str, query: &str) -> Result<Vec<String>> {
-    let matcher = RegexMatcher::new(query)?;
+/// Minimum similarity (0-1) between a line and the query for a fuzzy match
+pub const FUZZY_THRESHOLD: f32 = 0.75;
+
+/// Score added to a match that is itself a markdown heading
+const HEADING_BOOST: f32 = 1.0;
+
+/// Score added to a match under a heading that mentions the query
+const SECTION_BOOST: f32 = 0.5;
+
+/// How a search query is matched against page lines
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(default)]
+pub struct SearchOptions {
+    /// Ignore letter case
+    pub case_insensitive: bool,
+    /// Treat the query as plain text instead of a regular expression
+    pub literal: bool,
+    /// Only match the query where it stands as a whole word
+    pub whole_word: bool,
+    /// Non-blank lines of context to return on each side of a match; unset
+    /// uses the configured `search_context_lines`
+    pub context_lines: Option<usize>,
+    /// Match lines whose words resemble the query's, tolerating typos and
+    /// small wording differences; the other matching flags are ignored
+    pub fuzzy: bool,
+    /// Treat the query as terms combined with AND, OR, NOT and parentheses,
+    /// e.g. `price AND (annual OR yearly)`; ignored in fuzzy mode
+    pub boolean: bool,
+    /// Return only this many of the best matches; unset uses the configured
+    /// `search_max_results`
+    pub max_results: Option<usize>,
+}
+
+/// A matching line, where the match sits in the searched content, and the
+/// lines around it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchMatch {
+    /// 1-based line number in the searched content
+    pub line_number: u64,
+    /// Byte offsets of the first match on the line within the searched content
+    pub byte_range: Range<usize>,
+    pub line: String,
+    /// The text the query matched
+    pub match_text: String,
+    /// Relevance to the query; results are ordered by it, highest first
+    pub score: f32,
+    /// How closely the line resembles the query (0-1), for fuzzy matches
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub similarity: Option<f32>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub before: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub after: Vec<String>,
+}
+
+/// Lines of `content` matching `query`, trimmed, best first and at most
+/// `max_results` of them, each with up to `context_lines` non-blank
+/// neighbours on either side
+pub fn search_content(
+    content: &str,
+    query: &str,
+    options: SearchOptions,
+) -> Result<Vec<SearchMatch>> {
+    let (mut matches, terms) = if options.fuzzy {
+        (fuzzy_matches(content, query), query_terms(query))
+    } else if options.boolean {
+        let terms = QueryExpr::parse(query)?
+            .terms(false)
+            .into_iter()
+            .flat_map(query_terms)
+            .collect();
+        (boolean_matches(content, query, options)?, terms)
+    } else {
+        (regex_matches(content, query, options)?, query_terms(query))
+    };
+    rank(content, &terms, &mut matches, options.max_results);
+
+    let context_lines = options.context_lines.unwrap_or(0);
+    if context_lines > 0 {
+        let lines: Vec<&str> = content.lines().map(str::trim).collect();
+        for m in &mut matches {
+            let index = (m.line_number as usize - 1).min(lines.len());
+            m.before = lines[..index]
+                .iter()
+                .rev()
+                .filter(|l| !l.is_empty())
+                .take(context_lines)
+                .map(|l| l.to_string())
+                .collect();
+            m.before.reverse();
+            m.after = lines[(index + 1).min(lines.len())..]
+                .iter()
+                .filter(|l| !l.is_empty())
+                .take(context_lines)
+                .map(|l| l.to_string())
+                .collect();
+        }
+    }
+
+    Ok(matches)
+}
+
+fn build_matcher(query: &str, options: SearchOptions) -> Result<RegexMatcher> {
+    let pattern = if options.literal {
+        regex::escape(query)
+    } else {
+        query.to_string()
+    };
+    RegexMatcherBuilder::new()
+        .case_insensitive(options.case_insensitive)
+        .word(options.whole_word)
+        .build(&pattern)
+        .map_err(|e| {
+            if options.literal {
+                anyhow!(e)
+            } else {
+                anyhow!(
+                    "Invalid regex '{}': {}. Search with literal set to match it as plain text",
+                    query,
+                    e
+                )
+            }
+        })
+}
+
+fn regex_matches(content: &str, query: &str, options: SearchOptions) -> Result<Vec<SearchMatch>> {
+    let matcher = build_matcher(query, options)?;
+    let mut line_starts = vec![0];
+    line_starts.extend(content.match_indices('\n').map(|(i, _)| i + 1));
     let mut matches = Vec::new();
 
     Searcher::new().search_reader(
         &matcher,
         Cursor::new(content.as_bytes()),
-        UTF8(|_lnum, line| {
-            matches.push(line.trim().to_string());
+        UTF8(|line_number, line| {
+            let line_start = line_starts[line_number as usize - 1];
+            let found = matcher
+                .find(line.as_bytes())
+                .ok()
+                .flatten()
+                .map(|m| m.start()..m.end())
+                .unwrap_or(0..0);
+            matches.push(SearchMatch {
+                line_number,
+                byte_range: line_start + found.start..line_start + found.end,
+                line: line.trim().to_string(),
+                match_text: line[found].to_string(),
+                score: 0.0,
+                similarity: None,
+                before: Vec::new(),
+                after: Vec::new(),
+            });
             Ok(true)
         }),
     )?;
@@ -20,16 +171,744 @@ pub fn search_content(content: &str, query: &str) -> Result<Vec<String>> {
     Ok(matches)
 }
 
+/// Lines whose words resemble those of `query` closely enough, in page order
+fn fuzzy_matches(content: &str, query: &str) -> Vec<SearchMatch> {
+    let query_words: Vec<String> = words(query)
+        .into_iter()
+        .map(|(_, word)| word.to_lowercase())
+        .collect();
+    let mut matches = Vec::new();
+    if query_words.is_empty() {
+        return matches;
+    }
+
+    let mut line_start = 0;
+    for (index, line) in content.split('\n').enumerate() {
+        if let Some((score, span)) = fuzzy_score(&query_words, line) {
+            matches.push(SearchMatch {
+                line_number: index as u64 + 1,
+                byte_range: line_start + span.start..line_start + span.end,
+                line: line.trim().to_string(),
+                match_text: line[span].to_string(),
+                score: 0.0,
+                similarity: Some(score),
+                before: Vec::new(),
+                after: Vec::new(),
+            });
+        }
+        line_start += line.len() + 1;
+    }
+    matches
+}
+
+/// Mean similarity of each query word to its closest word in `line`, with the
+/// span covering those words; None below [`FUZZY_THRESHOLD`]
+fn fuzzy_score(query_words: &[String], line: &str) -> Option<(f32, Range<usize>)> {
+    let line_words: Vec<(Range<usize>, String)> = words(line)
+        .into_iter()
+        .map(|(range, word)| (range, word.to_lowercase()))
+        .collect();
+    let mut total = 0.0;
+    let mut span: Option<Range<usize>> = None;
+    for query_word in query_words {
+        let (similarity, range) = line_words
+            .iter()
+            .map(|(range, word)| {
+                let similarity = TextDiff::from_chars(query_word.as_str(), word.as_str()).ratio();
+                (similarity, range)
+            })
+            .max_by(|a, b| a.0.total_cmp(&b.0))?;
+        total += similarity;
+        span = Some(match span {
+            Some(span) => span.start.min(range.start)..span.end.max(range.end),
+            None => range.clone(),
+        });
+    }
+    let score = total / query_words.len() as f32;
+    (score >= FUZZY_THRESHOLD).then_some((score, span?))
+}
+
+/// Runs of alphanumeric characters in `text` with their byte ranges
+fn words(text: &str) -> Vec<(Range<usize>, &str)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push((s..i, &text[s..i]));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        words.push((s..text.len(), &text[s..]));
+    }
+    words
+}
+
+/// Score each match and keep the best `max_results`, highest first with ties
+/// in page order
+fn rank(
+    content: &str,
+    terms: &[String],
+    matches: &mut Vec<SearchMatch>,
+    max_results: Option<usize>,
+) {
+    let mut sections = Vec::new();
+    let mut heading = None;
+    for line in content.split('\n') {
+        if is_heading(line) {
+            heading = Some(line.trim().to_lowercase());
+        }
+        sections.push(heading.clone());
+    }
+    for m in matches.iter_mut() {
+        let section = sections
+            .get(m.line_number as usize - 1)
+            .and_then(|s| s.as_deref());
+        m.score = relevance(&m.line, terms, section) * m.similarity.unwrap_or(1.0);
+    }
+    sort_and_cap(matches, max_results);
+}
+
+fn sort_and_cap(matches: &mut Vec<SearchMatch>, max_results: Option<usize>) {
+    matches.sort_by(|a, b| {
+        b.score
+            .total_cmp(&a.score)
+            .then(a.line_number.cmp(&b.line_number))
+    });
+    if let Some(max_results) = max_results {
+        matches.truncate(max_results);
+    }
+}
+
+/// Lowercased words of `query` that score a match, skipping boolean operators
+/// and single characters such as regex escapes
+fn query_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = Vec::new();
+    for (_, word) in words(query) {
+        let word = word.to_lowercase();
+        if word.chars().count() > 1
+            && !matches!(word.as_str(), "and" | "or" | "not")
+            && !terms.contains(&word)
+        {
+            terms.push(word);
+        }
+    }
+    terms
+}
+
+/// How well `line` covers the query terms, how often and how close together
+/// they appear, boosted for headings and for lines in a section whose heading
+/// mentions the query
+fn relevance(line: &str, terms: &[String], section: Option<&str>) -> f32 {
+    let mut score = 1.0;
+    // (word index, term index) of every term occurrence, in line order
+    let mut hits = Vec::new();
+    for (index, (_, word)) in words(line).into_iter().enumerate() {
+        let word = word.to_lowercase();
+        for (term_index, term) in terms.iter().enumerate() {
+            if word.contains(term.as_str()) {
+                hits.push((index, term_index));
+            }
+        }
+    }
+    let found: HashSet<usize> = hits.iter().map(|&(_, term)| term).collect();
+    if !found.is_empty() {
+        let coverage = found.len() as f32 / terms.len() as f32;
+        let frequency = (1.0 + hits.len() as f32 / terms.len() as f32).ln();
+        let proximity = found.len() as f32 / shortest_window(&hits, found.len()) as f32;
+        score += 2.0 * coverage + frequency + proximity;
+    }
+    if is_heading(line) {
+        score += HEADING_BOOST;
+    } else if section.is_some_and(|heading| terms.iter().any(|t| heading.contains(t.as_str()))) {
+        score += SECTION_BOOST;
+    }
+    score
+}
+
+/// Fewest consecutive words containing `distinct` different terms
+fn shortest_window(hits: &[(usize, usize)], distinct: usize) -> usize {
+    let mut counts: HashMap<usize, usize> = HashMap::new();
+    let mut shortest = usize::MAX;
+    let mut start = 0;
+    for &(end, term) in hits {
+        *counts.entry(term).or_default() += 1;
+        while counts.len() == distinct {
+            let (first, first_term) = hits[start];
+            shortest = shortest.min(end - first + 1);
+            let count = counts.entry(first_term).or_default();
+            *count -= 1;
+            if *count == 0 {
+                counts.remove(&first_term);
+            }
+            start += 1;
+        }
+    }
+    shortest
+}
+
+fn is_heading(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with('#') && line.trim_start_matches('#').starts_with(' ')
+}
+
+/// Lines satisfying the boolean query, each term matched as in a plain search
+fn boolean_matches(content: &str, query: &str, options: SearchOptions) -> Result<Vec<SearchMatch>> {
+    let expr = QueryExpr::parse(query)?;
+    let mut matchers = HashMap::new();
+    for term in expr.terms(true) {
+        matchers.insert(term, build_matcher(term, options)?);
+    }
+    let find = |term: &str, line: &str| {
+        matchers[term]
+            .find(line.as_bytes())
+            .ok()
+            .flatten()
+            .map(|m| m.start()..m.end())
+    };
+    let highlighted = expr.terms(false);
+
+    let mut matches = Vec::new();
+    let mut line_start = 0;
+    for (index, line) in content.split('\n').enumerate() {
+        if !line.trim().is_empty() && expr.eval(&|term| find(term, line).is_some()) {
+            // Point at the earliest term that made the line match; a purely
+            // negative query has none, so the whole line stands in
+            let span = highlighted
+                .iter()
+                .filter_map(|term| find(term, line))
+                .min_by_key(|range| range.start)
+                .unwrap_or(0..line.trim_end().len());
+            matches.push(SearchMatch {
+                line_number: index as u64 + 1,
+                byte_range: line_start + span.start..line_start + span.end,
+                line: line.trim().to_string(),
+                match_text: line[span].to_string(),
+                score: 0.0,
+                similarity: None,
+                before: Vec::new(),
+                after: Vec::new(),
+            });
+        }
+        line_start += line.len() + 1;
+    }
+    Ok(matches)
+}
+
+/// A boolean search query: terms combined with AND, OR, NOT and parentheses
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryExpr {
+    Term(String),
+    And(Box<QueryExpr>, Box<QueryExpr>),
+    Or(Box<QueryExpr>, Box<QueryExpr>),
+    Not(Box<QueryExpr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Term(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+impl QueryExpr {
+    /// Parse a query like `price AND (annual OR yearly) NOT trial`.
+    /// Operators are uppercase and terms next to each other without one are
+    /// ANDed; adjacent bare words form a single phrase, and double quotes keep
+    /// operator words or parentheses inside a term.
+    pub fn parse(query: &str) -> Result<QueryExpr> {
+        let tokens = tokenize(query)?;
+        let mut pos = 0;
+        let expr = parse_or(&tokens, &mut pos)?;
+        match tokens.get(pos) {
+            Some(token) => Err(anyhow!("Unexpected {} in query", describe(token))),
+            None => Ok(expr),
+        }
+    }
+
+    /// Terms in the query, optionally including the negated ones
+    fn terms(&self, include_negated: bool) -> Vec<&str> {
+        match self {
+            QueryExpr::Term(term) => vec![term.as_str()],
+            QueryExpr::And(a, b) | QueryExpr::Or(a, b) => {
+                let mut terms = a.terms(include_negated);
+                terms.extend(b.terms(include_negated));
+                terms
+            }
+            QueryExpr::Not(expr) if include_negated => expr.terms(true),
+            QueryExpr::Not(_) => Vec::new(),
+        }
+    }
+
+    fn eval(&self, contains: &dyn Fn(&str) -> bool) -> bool {
+        match self {
+            QueryExpr::Term(term) => contains(term),
+            QueryExpr::And(a, b) => a.eval(contains) && b.eval(contains),
+            QueryExpr::Or(a, b) => a.eval(contains) || b.eval(contains),
+            QueryExpr::Not(expr) => !expr.eval(contains),
+        }
+    }
+}
+
+fn tokenize(query: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut phrase: Vec<String> = Vec::new();
+    let mut chars = query.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '(' | ')' => {
+                flush_phrase(&mut phrase, &mut tokens);
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '"' => {
+                flush_phrase(&mut phrase, &mut tokens);
+                let mut term = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some(c) => term.push(c),
+                        None => return Err(anyhow!("Unclosed quote in query")),
+                    }
+                }
+                tokens.push(Token::Term(term));
+            }
+            c => {
+                let mut word = c.to_string();
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || matches!(c, '(' | ')' | '"') {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                let operator = match word.as_str() {
+                    "AND" => Some(Token::And),
+                    "OR" => Some(Token::Or),
+                    "NOT" => Some(Token::Not),
+                    _ => None,
+                };
+                match operator {
+                    Some(operator) => {
+                        flush_phrase(&mut phrase, &mut tokens);
+                        tokens.push(operator);
+                    }
+                    None => phrase.push(word),
+                }
+            }
+        }
+    }
+    flush_phrase(&mut phrase, &mut tokens);
+    Ok(tokens)
+}
+
+fn flush_phrase(phrase: &mut Vec<String>, tokens: &mut Vec<Token>) {
+    if !phrase.is_empty() {
+        tokens.push(Token::Term(phrase.join(" ")));
+        phrase.clear();
+    }
+}
+
+fn parse_or(tokens: &[Token], pos: &mut usize) -> Result<QueryExpr> {
+    let mut expr = parse_and(tokens, pos)?;
+    while tokens.get(*pos) == Some(&Token::Or) {
+        *pos += 1;
+        expr = QueryExpr::Or(Box::new(expr), Box::new(parse_and(tokens, pos)?));
+    }
+    Ok(expr)
+}
+
+fn parse_and(tokens: &[Token], pos: &mut usize) -> Result<QueryExpr> {
+    let mut expr = parse_unary(tokens, pos)?;
+    loop {
+        match tokens.get(*pos) {
+            Some(Token::And) => *pos += 1,
+            Some(Token::Not | Token::Open | Token::Term(_)) => {}
+            _ => break,
+        }
+        expr = QueryExpr::And(Box::new(expr), Box::new(parse_unary(tokens, pos)?));
+    }
+    Ok(expr)
+}
+
+fn parse_unary(tokens: &[Token], pos: &mut usize) -> Result<QueryExpr> {
+    let token = tokens
+        .get(*pos)
+        .ok_or_else(|| anyhow!("Query ends where a search term was expected"))?;
+    *pos += 1;
+    match token {
+        Token::Term(term) => Ok(QueryExpr::Term(term.clone())),
+        Token::Not => Ok(QueryExpr::Not(Box::new(parse_unary(tokens, pos)?))),
+        Token::Open => {
+            let expr = parse_or(tokens, pos)?;
+            if tokens.get(*pos) != Some(&Token::Close) {
+                return Err(anyhow!("Missing ')' in query"));
+            }
+            *pos += 1;
+            Ok(expr)
+        }
+        token => Err(anyhow!("Expected a search term, found {}", describe(token))),
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Term(term) => format!("'{}'", term),
+        Token::And => "AND".to_string(),
+        Token::Or => "OR".to_string(),
+        Token::Not => "NOT".to_string(),
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+    }
+}
+
+/// An item from a searched source together with its matching lines
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit<T> {
+    pub item: T,
+    pub matches: Vec<SearchMatch>,
+}
+
+/// Results of searching the current page, memory and traces at once
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct UnifiedResults {
+    /// Matches in the current page's markdown
+    pub page: Vec<SearchMatch>,
+    pub memories: Vec<SearchHit<MemoryEntry>>,
+    pub traces: Vec<SearchHit<TraceEvent>>,
+}
+
+/// Search each source with the same query; memories and trace events are
+/// ordered by their best match and capped like page matches
+pub fn search_sources(
+    query: &str,
+    options: SearchOptions,
+    page: Option<&str>,
+    memories: Vec<MemoryEntry>,
+    traces: Vec<TraceEvent>,
+) -> Result<UnifiedResults> {
+    let page = match page {
+        Some(content) => search_content(content, query, options)?,
+        None => Vec::new(),
+    };
+    let memories = search_items(memories, query, options, |m| m.content.clone())?;
+    let traces = search_traces(traces, query, options)?;
+    Ok(UnifiedResults {
+        page,
+        memories,
+        traces,
+    })
+}
+
+/// Search trace events' messages and JSON fields, best match first
+pub fn search_traces(
+    events: Vec<TraceEvent>,
+    query: &str,
+    options: SearchOptions,
+) -> Result<Vec<SearchHit<TraceEvent>>> {
+    // Message and fields on separate lines, so a hit says which one matched
+    search_items(events, query, options, |e| {
+        format!("{}\n{}", e.message, e.fields)
+    })
+}
+
+fn search_items<T>(
+    items: Vec<T>,
+    query: &str,
+    options: SearchOptions,
+    text_of: impl Fn(&T) -> String,
+) -> Result<Vec<SearchHit<T>>> {
+    let mut hits = Vec::new();
+    for item in items {
+        let matches = search_content(&text_of(&item), query, options)?;
+        if !matches.is_empty() {
+            hits.push(SearchHit { item, matches });
+        }
+    }
+    // Matches are ranked within each item, so the first is its best
+    hits.sort_by(|a, b| b.matches[0].score.total_cmp(&a.matches[0].score));
+    if let Some(max_results) = options.max_results {
+        hits.truncate(max_results);
+    }
+    Ok(hits)
+}
+
+/// Search for `query` and, as literal case-insensitive text, each of its
+/// localized `variants`, merging the matching lines without duplicates
+pub fn search_with_variants(
+    content: &str,
+    query: &str,
+    variants: &[String],
+    options: SearchOptions,
+) -> Result<Vec<SearchMatch>> {
+    let mut matches = search_content(content, query, options)?;
+    let variant_options = SearchOptions {
+        case_insensitive: true,
+        literal: true,
+        boolean: false,
+        ..options
+    };
+    for variant in variants {
+        for found in search_content(content, variant, variant_options)? {
+            if !matches.iter().any(|m| m.line_number == found.line_number) {
+                matches.push(found);
+            }
+        }
+    }
+    sort_and_cap(&mut matches, options.max_results);
+    Ok(matches)
+}
+
+/// JavaScript expression returning the whitespace-collapsed text of every
+/// element matching the CSS `selector`, skipping empty ones
+pub fn selector_text_script(selector: &str) -> String {
+    let selector = serde_json::to_string(selector).unwrap_or_else(|_| "\"\"".to_string());
+    format!(
+        r#"Array.from(document.querySelectorAll({selector}))
+            .map(el => (el.innerText || el.textContent || "").replace(/\s+/g, " ").trim())
+            .filter(text => text.length > 0)"#
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn lines(matches: &[SearchMatch]) -> Vec<&str> {
+        matches.iter().map(|m| m.line.as_str()).collect()
+    }
+
     #[test]
     fn test_search_content() {
         let content = "Hello world\nThis is a test\nGoodbye world";
-        let matches = search_content(content, "world").unwrap();
+        let matches = search_content(content, "world", SearchOptions::default()).unwrap();
+        assert_eq!(matches.len(), 2);
+        assert_eq!(matches[0].line, "Hello world");
+        assert_eq!(matches[1].line, "Goodbye world");
+        assert_eq!(matches[1].line_number, 3);
+        assert_eq!(matches[1].byte_range, 35..40);
+        assert_eq!(&content[matches[1].byte_range.clone()], "world");
+
+        let matches = search_content(content, "t[a-z]+t", SearchOptions::default()).unwrap();
+        assert_eq!(matches[0].match_text, "test");
+        assert_eq!(matches[0].byte_range, 22..26);
+    }
+
+    #[test]
+    fn test_search_options() {
+        let content = "Price (USD): $10\nprice list\nPricelist archive";
+
+        assert!(search_content(content, "Price (USD", SearchOptions::default()).is_err());
+        let literal = SearchOptions {
+            literal: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            lines(&search_content(content, "Price (USD", literal).unwrap()),
+            vec!["Price (USD): $10"]
+        );
+
+        let any_case = SearchOptions {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        assert_eq!(search_content(content, "price", any_case).unwrap().len(), 3);
+
+        let whole_word = SearchOptions {
+            case_insensitive: true,
+            whole_word: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            lines(&search_content(content, "price", whole_word).unwrap()),
+            vec!["Price (USD): $10", "price list"]
+        );
+    }
+
+    #[test]
+    fn test_search_with_variants() {
+        let content = "Price: $10\nPreis (netto): 9 €\nprix: 11 €";
+        let variants = vec!["preis (netto)".to_string(), "Prix".to_string()];
+        let matches =
+            search_with_variants(content, "Price", &variants, SearchOptions::default()).unwrap();
+        assert_eq!(
+            lines(&matches),
+            vec!["Price: $10", "Preis (netto): 9 €", "prix: 11 €"]
+        );
+    }
+
+    #[test]
+    fn test_fuzzy_search() {
+        let content = "Widget price: $10\nShipping is free\nPricing FAQ";
+        let fuzzy = SearchOptions {
+            fuzzy: true,
+            ..Default::default()
+        };
+        let matches = search_content(content, "widgt pirce", fuzzy).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_number, 1);
+        assert_eq!(matches[0].match_text, "Widget price");
+        assert_eq!(matches[0].byte_range, 0..12);
+        assert!(matches[0].similarity.unwrap() >= FUZZY_THRESHOLD);
+
+        let matches = search_content(content, "shiping", fuzzy).unwrap();
+        assert_eq!(lines(&matches), vec!["Shipping is free"]);
+        assert_eq!(&content[matches[0].byte_range.clone()], "Shipping");
+
+        assert!(search_content(content, "refund", fuzzy).unwrap().is_empty());
+        assert!(search_content(content, "(", fuzzy).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_parse_query() {
+        use QueryExpr::*;
+        let term = |t: &str| Box::new(Term(t.to_string()));
+
+        assert_eq!(
+            QueryExpr::parse("price AND (annual OR yearly)").unwrap(),
+            And(term("price"), Box::new(Or(term("annual"), term("yearly"))))
+        );
+        assert_eq!(
+            QueryExpr::parse("price list NOT \"free OR trial\"").unwrap(),
+            And(term("price list"), Box::new(Not(term("free OR trial"))))
+        );
+        assert_eq!(
+            QueryExpr::parse("a OR b c").unwrap(),
+            Or(term("a"), term("b c"))
+        );
+        assert!(QueryExpr::parse("(price").is_err());
+        assert!(QueryExpr::parse("price)").is_err());
+        assert!(QueryExpr::parse("price AND").is_err());
+        assert!(QueryExpr::parse("OR price").is_err());
+        assert!(QueryExpr::parse("\"price").is_err());
+    }
+
+    #[test]
+    fn test_boolean_search() {
+        let content = "Price: $100 per year\n\nYearly price, billed annually\nMonthly price: $10\nAnnual report";
+        let boolean = SearchOptions {
+            boolean: true,
+            case_insensitive: true,
+            ..Default::default()
+        };
+
+        let matches = search_content(content, "price AND (annual OR year)", boolean).unwrap();
+        assert_eq!(
+            lines(&matches),
+            vec!["Yearly price, billed annually", "Price: $100 per year"]
+        );
+        assert_eq!(matches[0].line_number, 3);
+        assert_eq!(matches[0].match_text, "Year");
+
+        let matches = search_content(content, "price NOT monthly NOT year", boolean).unwrap();
+        assert!(matches.is_empty());
+
+        let matches = search_content(content, "NOT price", boolean).unwrap();
+        assert_eq!(lines(&matches), vec!["Annual report"]);
+        assert_eq!(matches[0].match_text, "Annual report");
+
+        assert!(search_content(content, "price AND (", boolean).is_err());
+    }
+
+    #[test]
+    fn test_search_ranking() {
+        let content = "# Annual pricing\nSome intro\nThe price is listed below\n## Details\nprice price price\nprice and annual plan together\nThe price is listed below";
+        let options = SearchOptions {
+            boolean: true,
+            case_insensitive: true,
+            ..Default::default()
+        };
+
+        let ranked: Vec<u64> = search_content(content, "price OR annual", options)
+            .unwrap()
+            .iter()
+            .map(|m| m.line_number)
+            .collect();
+        // The heading first, then the line with both terms, and the same
+        // sentence ranks higher in the section about the query than outside it
+        assert_eq!(ranked, vec![1, 6, 5, 3, 7]);
+
+        let capped = SearchOptions {
+            max_results: Some(2),
+            ..options
+        };
+        let matches = search_content(content, "price OR annual", capped).unwrap();
         assert_eq!(matches.len(), 2);
-        assert_eq!(matches[0], "Hello world");
-        assert_eq!(matches[1], "Goodbye world");
+        assert!(matches[0].score > matches[1].score);
+    }
+
+    #[test]
+    fn test_search_sources() {
+        let mut memory = crate::memory::Memory::new();
+        memory.add("Widget A costs $42".to_string(), Vec::new());
+        memory.add("Widget B is out of stock".to_string(), Vec::new());
+        let trace = |message: &str, fields: &str| TraceEvent {
+            id: message.to_string(),
+            session_id: "session".to_string(),
+            timestamp: 0,
+            level: "INFO".to_string(),
+            target: "nexus::agent".to_string(),
+            span_name: None,
+            message: message.to_string(),
+            fields: fields.to_string(),
+        };
+        let traces = vec![
+            trace("Navigation complete", r#"{"url":"https://example.com/a"}"#),
+            trace("Extracted price", r#"{"value":"$42"}"#),
+        ];
+
+        let results = search_sources(
+            "42",
+            SearchOptions::default(),
+            Some("# Widgets\nWidget A: $42\nWidget B: $50"),
+            memory.get_all(),
+            traces,
+        )
+        .unwrap();
+        assert_eq!(lines(&results.page), vec!["Widget A: $42"]);
+        assert_eq!(results.memories.len(), 1);
+        assert_eq!(results.memories[0].item.content, "Widget A costs $42");
+        assert_eq!(results.traces.len(), 1);
+        assert_eq!(results.traces[0].item.message, "Extracted price");
+        assert_eq!(results.traces[0].matches[0].line_number, 2);
+
+        let results =
+            search_sources("42", SearchOptions::default(), None, Vec::new(), Vec::new()).unwrap();
+        assert!(results.page.is_empty());
+    }
+
+    #[test]
+    fn test_selector_text_script() {
+        let script = selector_text_script(r#"a[href="/pricing"]"#);
+        assert!(script.contains(r#"querySelectorAll("a[href=\"/pricing\"]")"#));
+    }
+
+    #[test]
+    fn test_search_context_lines() {
+        let content = "# Pricing\n\nBasic plan\n\nPrice: $10\nBilled monthly\n\nFooter";
+        let options = SearchOptions {
+            context_lines: Some(2),
+            ..Default::default()
+        };
+        let matches = search_content(content, "Price", options).unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].line_number, 5);
+        assert_eq!(matches[0].before, vec!["# Pricing", "Basic plan"]);
+        assert_eq!(matches[0].after, vec!["Billed monthly", "Footer"]);
+
+        let matches = search_content(content, "Footer", options).unwrap();
+        assert_eq!(matches[0].after, Vec::<String>::new());
+        assert!(
+            search_content(content, "Basic", SearchOptions::default()).unwrap()[0]
+                .before
+                .is_empty()
+        );
     }
 }
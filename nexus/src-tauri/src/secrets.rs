@@ -0,0 +1,67 @@
+//! OS keychain storage for credentials
+//!
+//! API keys are kept in the platform keychain (macOS Keychain, Windows
+//! Credential Manager, Secret Service on Linux) rather than in config.json,
+//! which only holds a `keyring:<account>` reference to the stored secret.
+//! Where no keychain is available the key stays in the config file.
+
+use keyring::Entry;
+
+/// Keychain service the app's secrets are stored under
+const SERVICE: &str = "nexus";
+
+/// Prefix marking a config value as a reference to a keychain secret
+const REFERENCE_PREFIX: &str = "keyring:";
+
+/// The config value referring to the secret stored for `account`
+pub fn reference(account: &str) -> String {
+    format!("{}{}", REFERENCE_PREFIX, account)
+}
+
+/// The account a config value refers to, if it is a keychain reference
+pub fn parse_reference(value: &str) -> Option<&str> {
+    value
+        .strip_prefix(REFERENCE_PREFIX)
+        .filter(|account| !account.is_empty())
+}
+
+fn entry(account: &str) -> Result<Entry, String> {
+    Entry::new(SERVICE, account).map_err(|e| e.to_string())
+}
+
+/// Store `secret` for `account`, replacing any previous one
+pub fn store(account: &str, secret: &str) -> Result<(), String> {
+    entry(account)?
+        .set_password(secret)
+        .map_err(|e| e.to_string())
+}
+
+/// The secret stored for `account`, if any
+pub fn load(account: &str) -> Result<Option<String>, String> {
+    match entry(account)?.get_password() {
+        Ok(secret) => Ok(Some(secret)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+/// Remove the secret stored for `account`; missing secrets are not an error
+pub fn delete(account: &str) -> Result<(), String> {
+    match entry(account)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_references() {
+        assert_eq!(reference("api_key"), "keyring:api_key");
+        assert_eq!(parse_reference("keyring:api_key"), Some("api_key"));
+        assert_eq!(parse_reference("keyring:"), None);
+        assert_eq!(parse_reference("sk-plaintext"), None);
+    }
+}
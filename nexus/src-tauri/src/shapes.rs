@@ -0,0 +1,302 @@
+//! Report output shapes
+//!
+//! By default a run produces a [`NexusReport`]: prose plus key discoveries
+//! and sources. A run can instead ask for a preset shape (comparison table,
+//! timeline, FAQ, raw data) or a custom JSON Schema; the agent worker is then
+//! built with the matching structured output type, and the result is rendered
+//! to Markdown for the run's stored report.
+
+use crate::agent::NexusReport;
+use crate::config::Config;
+use radkit::macros::LLMOutput;
+use schemars::JsonSchema;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// Named report shapes a run can ask for
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputPreset {
+    Report,
+    ComparisonTable,
+    Timeline,
+    Faq,
+    RawData,
+}
+
+/// Structured output type the agent worker can produce
+pub trait ReportShape:
+    LLMOutput + JsonSchema + DeserializeOwned + Serialize + Send + Sync + 'static
+{
+    /// Markdown rendering stored as the run's report
+    fn to_markdown(&self) -> String;
+
+    /// Problems with the output the model should have avoided
+    fn check(&self, _config: &Config) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+impl ReportShape for NexusReport {
+    fn to_markdown(&self) -> String {
+        self.markdown_report.clone()
+    }
+}
+
+fn sources_section(sources: &[String]) -> String {
+    if sources.is_empty() {
+        return String::new();
+    }
+    let list: Vec<String> = sources.iter().map(|s| format!("- {}", s)).collect();
+    format!("\n\n## Sources\n\n{}", list.join("\n"))
+}
+
+/// Keep table cells on one line and from breaking the table
+fn cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', " ")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct ComparisonRow {
+    /// The item being compared, e.g. a product or vendor.
+    pub item: String,
+    /// One value per column, in column order.
+    pub values: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct ComparisonReport {
+    /// Short summary of how the items compare.
+    pub summary: String,
+    /// Attributes compared, e.g. "Price", "Warranty".
+    pub columns: Vec<String>,
+    /// One row per item compared.
+    pub rows: Vec<ComparisonRow>,
+    /// URLs the comparison is based on.
+    pub sources: Vec<String>,
+}
+
+impl ReportShape for ComparisonReport {
+    fn to_markdown(&self) -> String {
+        let header: Vec<String> = std::iter::once("Item".to_string())
+            .chain(self.columns.iter().map(|c| cell(c)))
+            .collect();
+        let mut table = format!(
+            "| {} |\n|{}\n",
+            header.join(" | "),
+            " --- |".repeat(header.len())
+        );
+        for row in &self.rows {
+            let mut cells = vec![cell(&row.item)];
+            cells.extend(
+                (0..self.columns.len())
+                    .map(|i| row.values.get(i).map(|v| cell(v)).unwrap_or_default()),
+            );
+            table.push_str(&format!("| {} |\n", cells.join(" | ")));
+        }
+        format!(
+            "{}\n\n{}{}",
+            self.summary,
+            table.trim_end(),
+            sources_section(&self.sources)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct TimelineEvent {
+    /// Date or period, as precise as the sources allow.
+    pub date: String,
+    /// What happened.
+    pub event: String,
+    /// URL supporting this event, if any.
+    pub source: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct TimelineReport {
+    /// Short overview of the timeline.
+    pub summary: String,
+    /// Events in chronological order.
+    pub events: Vec<TimelineEvent>,
+    /// URLs the timeline is based on.
+    pub sources: Vec<String>,
+}
+
+impl ReportShape for TimelineReport {
+    fn to_markdown(&self) -> String {
+        let events: Vec<String> = self
+            .events
+            .iter()
+            .map(|e| match &e.source {
+                Some(source) => format!("- **{}**: {} ([source]({}))", e.date, e.event, source),
+                None => format!("- **{}**: {}", e.date, e.event),
+            })
+            .collect();
+        format!(
+            "{}\n\n## Timeline\n\n{}{}",
+            self.summary,
+            events.join("\n"),
+            sources_section(&self.sources)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct FaqEntry {
+    pub question: String,
+    pub answer: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct FaqReport {
+    /// Questions and answers covering the task.
+    pub entries: Vec<FaqEntry>,
+    /// URLs the answers are based on.
+    pub sources: Vec<String>,
+}
+
+impl ReportShape for FaqReport {
+    fn to_markdown(&self) -> String {
+        let entries: Vec<String> = self
+            .entries
+            .iter()
+            .map(|e| format!("### {}\n\n{}", e.question, e.answer))
+            .collect();
+        format!("{}{}", entries.join("\n\n"), sources_section(&self.sources))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct RawDataReport {
+    /// Records collected, each a map of field name to value.
+    pub records: Vec<BTreeMap<String, String>>,
+    /// URLs the records were collected from.
+    pub sources: Vec<String>,
+}
+
+impl ReportShape for RawDataReport {
+    fn to_markdown(&self) -> String {
+        format!(
+            "```json\n{}\n```{}",
+            serde_json::to_string_pretty(&self.records).unwrap_or_default(),
+            sources_section(&self.sources)
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct SchemaReport {
+    /// Short Markdown summary of the findings.
+    pub summary: String,
+    /// The findings as one JSON document matching the requested JSON Schema.
+    pub data_json: String,
+    /// URLs the data was collected from.
+    pub sources: Vec<String>,
+}
+
+impl ReportShape for SchemaReport {
+    fn to_markdown(&self) -> String {
+        let data = serde_json::from_str::<serde_json::Value>(&self.data_json)
+            .and_then(|v| serde_json::to_string_pretty(&v))
+            .unwrap_or_else(|_| self.data_json.clone());
+        format!(
+            "{}\n\n```json\n{}\n```{}",
+            self.summary,
+            data,
+            sources_section(&self.sources)
+        )
+    }
+
+    fn check(&self, config: &Config) -> Result<(), String> {
+        let Some(schema) = &config.output_schema else {
+            return Ok(());
+        };
+        let value = serde_json::from_str(&self.data_json)
+            .map_err(|e| format!("Report data is not valid JSON: {}", e))?;
+        crate::structured::validate(schema, &value).map_err(|errors| {
+            format!(
+                "Report data does not match the schema: {}",
+                errors.join("; ")
+            )
+        })
+    }
+}
+
+/// Instructions telling the worker what shape of output is expected
+pub fn shape_instructions(config: &Config) -> Option<String> {
+    if let Some(schema) = &config.output_schema {
+        return Some(format!(
+            "Return your findings in data_json as JSON matching this JSON Schema:\n{}",
+            serde_json::to_string_pretty(schema).unwrap_or_default()
+        ));
+    }
+    match config.output_preset? {
+        OutputPreset::Report => None,
+        OutputPreset::ComparisonTable => Some(
+            "Present the result as a comparison table: one row per item, one column per \
+             attribute compared."
+                .to_string(),
+        ),
+        OutputPreset::Timeline => {
+            Some("Present the result as a chronological timeline of dated events.".to_string())
+        }
+        OutputPreset::Faq => {
+            Some("Present the result as questions and answers covering the task.".to_string())
+        }
+        OutputPreset::RawData => Some(
+            "Return the raw records collected, one map of field name to value per record, \
+             without commentary."
+                .to_string(),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_comparison_markdown() {
+        let report = ComparisonReport {
+            summary: "B is cheaper.".to_string(),
+            columns: vec!["Price".to_string(), "Warranty".to_string()],
+            rows: vec![
+                ComparisonRow {
+                    item: "A".to_string(),
+                    values: vec!["$10".to_string(), "1 year".to_string()],
+                },
+                ComparisonRow {
+                    item: "B|2".to_string(),
+                    values: vec!["$8".to_string()],
+                },
+            ],
+            sources: vec!["https://example.com".to_string()],
+        };
+        assert_eq!(
+            report.to_markdown(),
+            "B is cheaper.\n\n| Item | Price | Warranty |\n| --- | --- | --- |\n\
+             | A | $10 | 1 year |\n| B\\|2 | $8 |  |\n\n## Sources\n\n- https://example.com"
+        );
+    }
+
+    #[test]
+    fn test_schema_report_check() {
+        let config = Config {
+            output_schema: Some(serde_json::json!({
+                "type": "object",
+                "required": ["price"]
+            })),
+            ..Config::default()
+        };
+        let mut report = SchemaReport {
+            summary: "Found it.".to_string(),
+            data_json: "{\"price\": 10}".to_string(),
+            sources: Vec::new(),
+        };
+        assert!(report.check(&config).is_ok());
+        report.data_json = "{}".to_string();
+        assert!(report.check(&config).is_err());
+    }
+}
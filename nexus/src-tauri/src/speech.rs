@@ -0,0 +1,265 @@
+//! Local speech input and output
+//!
+//! Voice prompts are transcribed on-device with whisper.cpp (via `whisper-rs`)
+//! so audio never leaves the machine. The model file is configured through
+//! `Config::whisper_model_path` (any ggml whisper model, e.g. `ggml-base.en.bin`)
+//! and kept loaded between calls. Input is WAV audio at any sample rate; it is
+//! mixed down to mono and resampled to the 16 kHz whisper expects.
+//!
+//! Readout goes through the platform's speech synthesizer (`say` on macOS,
+//! System.Speech on Windows, espeak on Linux), optionally preceded by an
+//! LLM-written spoken summary of a finished report.
+
+use crate::config::Config;
+use radkit::agent::LlmFunction;
+use radkit::macros::LLMOutput;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use std::io::Cursor;
+use std::process::Stdio;
+use std::sync::{Mutex, OnceLock};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use whisper_rs::{FullParams, SamplingStrategy, WhisperContext, WhisperContextParameters};
+
+/// Sample rate whisper models are trained on
+const WHISPER_SAMPLE_RATE: u32 = 16_000;
+
+/// Loaded model, keyed by the path it was loaded from
+static WHISPER: OnceLock<Mutex<Option<(String, WhisperContext)>>> = OnceLock::new();
+
+/// Decode WAV bytes into 16 kHz mono samples in [-1, 1]
+pub fn decode_wav(bytes: &[u8]) -> Result<Vec<f32>, String> {
+    let mut reader = hound::WavReader::new(Cursor::new(bytes))
+        .map_err(|e| format!("Invalid WAV audio: {}", e))?;
+    let spec = reader.spec();
+    let samples: Vec<f32> = match spec.sample_format {
+        hound::SampleFormat::Float => reader
+            .samples::<f32>()
+            .collect::<Result<_, _>>()
+            .map_err(|e| e.to_string())?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|s| s.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(|e| e.to_string())?
+        }
+    };
+
+    let channels = spec.channels.max(1) as usize;
+    let mono: Vec<f32> = samples
+        .chunks(channels)
+        .map(|frame| frame.iter().sum::<f32>() / frame.len() as f32)
+        .collect();
+    Ok(resample(&mono, spec.sample_rate, WHISPER_SAMPLE_RATE))
+}
+
+/// Linear-interpolation resampler; plenty for speech recognition
+fn resample(samples: &[f32], from: u32, to: u32) -> Vec<f32> {
+    if from == to || samples.is_empty() {
+        return samples.to_vec();
+    }
+    let ratio = from as f64 / to as f64;
+    let len = (samples.len() as f64 / ratio).floor() as usize;
+    (0..len)
+        .map(|i| {
+            let pos = i as f64 * ratio;
+            let idx = pos.floor() as usize;
+            let frac = (pos - idx as f64) as f32;
+            let a = samples[idx];
+            let b = samples.get(idx + 1).copied().unwrap_or(a);
+            a + (b - a) * frac
+        })
+        .collect()
+}
+
+/// Transcribe 16 kHz mono samples with the model at `model_path`
+pub fn transcribe(model_path: &str, audio: &[f32]) -> Result<String, String> {
+    let cache = WHISPER.get_or_init(|| Mutex::new(None));
+    let mut cache = cache
+        .lock()
+        .map_err(|_| "Whisper model lock poisoned".to_string())?;
+    if cache.as_ref().map(|(path, _)| path.as_str()) != Some(model_path) {
+        crate::trace_info!("nexus::speech", "Loading whisper model", path = model_path);
+        let ctx = WhisperContext::new_with_params(model_path, WhisperContextParameters::default())
+            .map_err(|e| format!("Failed to load whisper model {}: {}", model_path, e))?;
+        *cache = Some((model_path.to_string(), ctx));
+    }
+    let (_, ctx) = cache.as_ref().unwrap();
+
+    let mut state = ctx.create_state().map_err(|e| e.to_string())?;
+    let mut params = FullParams::new(SamplingStrategy::Greedy { best_of: 1 });
+    params.set_language(Some("auto"));
+    params.set_print_progress(false);
+    params.set_print_realtime(false);
+    params.set_print_special(false);
+    params.set_print_timestamps(false);
+    state.full(params, audio).map_err(|e| e.to_string())?;
+
+    let segments = state.full_n_segments().map_err(|e| e.to_string())?;
+    let mut text = String::new();
+    for i in 0..segments {
+        let segment = state.full_get_segment_text(i).map_err(|e| e.to_string())?;
+        text.push_str(segment.trim());
+        text.push(' ');
+    }
+    Ok(text.trim().to_string())
+}
+
+// ============================================================================
+// Text-to-speech
+// ============================================================================
+
+/// Synthesizer commands to try, in order; each reads the text from stdin
+#[cfg(target_os = "macos")]
+const SPEECH_COMMANDS: &[(&str, &[&str])] = &[("say", &["-f", "-"])];
+#[cfg(target_os = "windows")]
+const SPEECH_COMMANDS: &[(&str, &[&str])] = &[(
+    "powershell",
+    &[
+        "-NoProfile",
+        "-Command",
+        "Add-Type -AssemblyName System.Speech; \
+         (New-Object System.Speech.Synthesis.SpeechSynthesizer).Speak([Console]::In.ReadToEnd())",
+    ],
+)];
+#[cfg(not(any(target_os = "macos", target_os = "windows")))]
+const SPEECH_COMMANDS: &[(&str, &[&str])] =
+    &[("espeak-ng", &["--stdin"]), ("espeak", &["--stdin"])];
+
+/// A report condensed for listening
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct SpokenSummary {
+    /// About 150 words of plain conversational sentences, no lists, markup or URLs.
+    pub script: String,
+}
+
+/// Strip Markdown syntax that synthesizers would otherwise read aloud
+pub fn plain_text_for_speech(markdown: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_code = false;
+    for line in markdown.lines() {
+        let line = line.trim();
+        if line.starts_with("```") {
+            in_code = !in_code;
+            continue;
+        }
+        if in_code || line.is_empty() || line.chars().all(|c| "-|=*_: ".contains(c)) {
+            continue;
+        }
+        let line = line.trim_start_matches(['#', '>', '-', '*', '+', ' ']);
+        let mut text = String::with_capacity(line.len());
+        let mut chars = line.chars().peekable();
+        while let Some(c) = chars.next() {
+            match c {
+                '*' | '_' | '`' | '|' => {}
+                // [label](url) -> label
+                ']' if chars.peek() == Some(&'(') => {
+                    for c in chars.by_ref() {
+                        if c == ')' {
+                            break;
+                        }
+                    }
+                }
+                '[' => {}
+                _ => text.push(c),
+            }
+        }
+        lines.push(text.trim().to_string());
+    }
+    lines.join("\n")
+}
+
+/// Read `text` aloud with the platform synthesizer, returning once done
+pub async fn speak(text: &str) -> Result<(), String> {
+    let text = plain_text_for_speech(text);
+    if text.is_empty() {
+        return Ok(());
+    }
+    let mut last_error = "No speech synthesizer available".to_string();
+    for (program, args) in SPEECH_COMMANDS {
+        let mut child = match Command::new(program)
+            .args(*args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) => {
+                last_error = format!("{}: {}", program, e);
+                continue;
+            }
+        };
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .await
+                .map_err(|e| e.to_string())?;
+        }
+        let status = child.wait().await.map_err(|e| e.to_string())?;
+        if status.success() {
+            return Ok(());
+        }
+        last_error = format!("{} exited with {}", program, status);
+    }
+    Err(last_error)
+}
+
+/// Ask the LLM for a roughly one-minute spoken summary of a report
+pub async fn spoken_summary(config: &Config, report: &str) -> Result<String, String> {
+    let llm = crate::providers::build_llm(config)?;
+    let function = LlmFunction::<SpokenSummary>::new_with_system_instructions(
+        llm,
+        "You turn research reports into short scripts to be read aloud. Lead with the answer, \
+         keep the key numbers, skip sources and formatting. About 150 words.",
+    );
+    let summary = function
+        .run(format!("Report:\n\n{}", report))
+        .await
+        .map_err(|e| e.to_string())?;
+    Ok(summary.script)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wav_bytes(sample_rate: u32, channels: u16, samples: &[i16]) -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut cursor = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut cursor, spec).unwrap();
+        for &s in samples {
+            writer.write_sample(s).unwrap();
+        }
+        writer.finalize().unwrap();
+        cursor.into_inner()
+    }
+
+    #[test]
+    fn test_decode_wav_downmixes_and_resamples() {
+        // One second of stereo 32 kHz audio: left at half scale, right silent
+        let frames: Vec<i16> = (0..32_000).flat_map(|_| [i16::MAX / 2, 0]).collect();
+        let audio = decode_wav(&wav_bytes(32_000, 2, &frames)).unwrap();
+        assert_eq!(audio.len(), 16_000);
+        assert!((audio[100] - 0.25).abs() < 0.01);
+
+        assert!(decode_wav(b"not a wav file").is_err());
+    }
+
+    #[test]
+    fn test_plain_text_for_speech() {
+        let report = "# Findings\n\n- **Widget** costs $10 ([source](https://example.com))\n\n```\ncode\n```\n| a | b |\n|---|---|";
+        assert_eq!(
+            plain_text_for_speech(report),
+            "Findings\nWidget costs $10 (source)\na  b"
+        );
+    }
+}
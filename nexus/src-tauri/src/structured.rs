@@ -0,0 +1,150 @@
+//! Schema-driven structured extraction
+//!
+//! Unlike recipes, which replay CSS selectors, structured extraction hands the
+//! page to the LLM together with a caller-supplied JSON Schema and returns
+//! JSON validated against it. Output that fails validation is sent back to the
+//! model with the errors for one correction pass before giving up. Schemas
+//! must be self-contained: `$ref`s to remote URLs or files are not fetched.
+
+use crate::config::Config;
+use radkit::agent::LlmFunction;
+use radkit::macros::LLMOutput;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Attempts at producing valid output, including the first
+const MAX_ATTEMPTS: usize = 2;
+
+/// Maximum characters of page content sent to the model
+const MAX_PAGE_CHARS: usize = 30_000;
+
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, LLMOutput)]
+pub struct StructuredOutput {
+    /// The extracted data as a single JSON document that matches the schema.
+    pub json: String,
+}
+
+/// Check `value` against `schema`, returning every validation error
+pub fn validate(schema: &Value, value: &Value) -> Result<(), Vec<String>> {
+    let validator =
+        jsonschema::validator_for(schema).map_err(|e| vec![format!("Invalid schema: {}", e)])?;
+    let errors: Vec<String> = validator
+        .iter_errors(value)
+        .map(|e| {
+            let path = e.instance_path.to_string();
+            if path.is_empty() {
+                e.to_string()
+            } else {
+                format!("{}: {}", path, e)
+            }
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parse the model's JSON, tolerating a surrounding ```json fence
+fn parse_output(text: &str) -> Result<Value, String> {
+    let trimmed = text.trim();
+    let unfenced = trimmed
+        .strip_prefix("```json")
+        .or_else(|| trimmed.strip_prefix("```"))
+        .and_then(|rest| rest.strip_suffix("```"))
+        .unwrap_or(trimmed);
+    serde_json::from_str(unfenced.trim()).map_err(|e| format!("Output is not valid JSON: {}", e))
+}
+
+/// Extract data matching `schema` from the page's Markdown
+pub async fn extract(
+    config: &Config,
+    url: &str,
+    page: &str,
+    schema: &Value,
+    instructions: Option<&str>,
+) -> Result<Value, String> {
+    // Reject broken schemas before spending an LLM call on them
+    jsonschema::validator_for(schema).map_err(|e| format!("Invalid schema: {}", e))?;
+
+    let page: String = page.chars().take(MAX_PAGE_CHARS).collect();
+    let llm = crate::providers::build_llm(config)?;
+    let function = LlmFunction::<StructuredOutput>::new_with_system_instructions(
+        llm,
+        "You extract structured data from web pages. Return only data present on the page, \
+         as JSON matching the given JSON Schema exactly. Use null for optional values that \
+         are missing and never invent values.",
+    );
+
+    let mut prompt = format!(
+        "Page: {}\n\nJSON Schema:\n{}\n\n{}Page content:\n{}",
+        url,
+        serde_json::to_string_pretty(schema).unwrap_or_default(),
+        instructions
+            .filter(|i| !i.trim().is_empty())
+            .map(|i| format!("Instructions: {}\n\n", i))
+            .unwrap_or_default(),
+        page
+    );
+    let mut last_errors = Vec::new();
+    for attempt in 1..=MAX_ATTEMPTS {
+        let output = function
+            .run(prompt.clone())
+            .await
+            .map_err(|e| e.to_string())?;
+        let errors = match parse_output(&output.json) {
+            Ok(value) => match validate(schema, &value) {
+                Ok(()) => return Ok(value),
+                Err(errors) => errors,
+            },
+            Err(e) => vec![e],
+        };
+        crate::trace_warn!(
+            "nexus::structured",
+            "Extraction failed validation",
+            attempt = attempt,
+            errors = errors.join("; ")
+        );
+        prompt = format!(
+            "{}\n\nYour previous answer was:\n{}\n\nIt failed validation:\n- {}\n\nReturn corrected JSON.",
+            prompt,
+            output.json,
+            errors.join("\n- ")
+        );
+        last_errors = errors;
+    }
+    Err(format!(
+        "Extracted data does not match the schema: {}",
+        last_errors.join("; ")
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_and_parse() {
+        let schema = json!({
+            "type": "object",
+            "properties": {
+                "name": { "type": "string" },
+                "price": { "type": "number" }
+            },
+            "required": ["name", "price"]
+        });
+        let value = parse_output("```json\n{\"name\": \"Widget\", \"price\": 10}\n```").unwrap();
+        assert!(validate(&schema, &value).is_ok());
+
+        let errors = validate(&schema, &json!({ "name": "Widget", "price": "ten" })).unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].starts_with("/price"));
+
+        assert!(parse_output("not json").is_err());
+        assert!(validate(&json!({ "type": 42 }), &value).is_err());
+        assert!(validate(&json!({ "$ref": "file:///etc/passwd" }), &value).is_err());
+    }
+}
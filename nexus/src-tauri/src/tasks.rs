@@ -0,0 +1,269 @@
+//! Saved tasks
+//!
+//! A saved task is a named prompt plus the config fields to override for its
+//! runs, e.g. a weekly "summarize competitor pricing" job that always uses a
+//! particular model and output preset. Every run of a task has the same
+//! prompt, so its runs share a report series and can be compared with
+//! `get_run_versions` and `diff_reports`. Tasks with a cron schedule are run
+//! in the background by the [`crate::scheduler`].
+
+use crate::config::Config;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SavedTask {
+    pub id: String,
+    pub name: String,
+    pub prompt: String,
+    /// Config fields to override for the task's runs, by config key
+    #[serde(default)]
+    pub config_overrides: Map<String, Value>,
+    pub created_at: i64,
+    /// The task's most recent run
+    #[serde(default)]
+    pub last_run_id: Option<String>,
+    /// Cron expression for running the task in the background
+    #[serde(default)]
+    pub schedule: Option<String>,
+}
+
+impl SavedTask {
+    /// `config` with the task's overrides applied
+    pub fn apply_overrides(&self, config: &Config) -> Result<Config, String> {
+        // Switching provider brings along the key and model last used with it,
+        // unless the overrides set those too
+        let mut config = config.clone();
+        if let Some(provider) = self
+            .config_overrides
+            .get("provider")
+            .and_then(Value::as_str)
+        {
+            config.switch_provider(provider);
+        }
+        let mut value = serde_json::to_value(&config).map_err(|e| e.to_string())?;
+        let fields = value
+            .as_object_mut()
+            .ok_or_else(|| "Config is not an object".to_string())?;
+        for (key, override_value) in &self.config_overrides {
+            if !fields.contains_key(key) {
+                return Err(format!("Unknown config field '{}'", key));
+            }
+            fields.insert(key.clone(), override_value.clone());
+        }
+        serde_json::from_value(value).map_err(|e| format!("Invalid config override: {}", e))
+    }
+}
+
+/// Run `task` with its overrides applied to `config`, recording the run as
+/// the task's latest. Returns the run's id alongside its outcome.
+pub async fn run(task: &SavedTask, config: &Config) -> (String, Result<String, String>) {
+    let run_id = uuid::Uuid::new_v4().to_string();
+    let config = match task.apply_overrides(config) {
+        Ok(config) => config,
+        Err(e) => return (run_id, Err(e)),
+    };
+    let result =
+        crate::agent::run_agent_loop_with_id(task.prompt.clone(), config, run_id.clone()).await;
+
+    // The run itself is in the history either way; don't report it as failed
+    // because the task couldn't be updated
+    if let Err(e) = crate::db::with_db(|conn| set_last_run(conn, &task.id, &run_id)) {
+        crate::trace_warn!(
+            "nexus::tasks",
+            "Failed to record the task's latest run",
+            task_id = task.id,
+            error = e
+        );
+    }
+    (run_id, result)
+}
+
+/// Queue `task` like [`run`] but return its run id straight away; the
+/// outcome arrives as a `run-finished` event
+pub fn spawn(task: &SavedTask, config: &Config) -> Result<String, String> {
+    let config = task.apply_overrides(config)?;
+    let run_id = uuid::Uuid::new_v4().to_string();
+    if let Err(e) = crate::db::with_db(|conn| set_last_run(conn, &task.id, &run_id)) {
+        crate::trace_warn!(
+            "nexus::tasks",
+            "Failed to record the task's latest run",
+            task_id = task.id,
+            error = e
+        );
+    }
+    Ok(crate::agent::spawn_agent_run_with_id(
+        task.prompt.clone(),
+        config,
+        run_id,
+    ))
+}
+
+const TASK_COLUMNS: &str = "id, name, prompt, config_overrides, created_at, last_run_id, schedule";
+
+fn task_from_row(row: &Row<'_>) -> rusqlite::Result<SavedTask> {
+    let overrides: String = row.get(3)?;
+    Ok(SavedTask {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        prompt: row.get(2)?,
+        config_overrides: serde_json::from_str(&overrides).unwrap_or_default(),
+        created_at: row.get(4)?,
+        last_run_id: row.get(5)?,
+        schedule: row.get(6)?,
+    })
+}
+
+pub fn save(conn: &Connection, task: &SavedTask) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO saved_tasks
+            (id, name, prompt, config_overrides, created_at, last_run_id, schedule)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+         ON CONFLICT(id) DO UPDATE SET
+            name = excluded.name,
+            prompt = excluded.prompt,
+            config_overrides = excluded.config_overrides,
+            schedule = excluded.schedule",
+        params![
+            task.id,
+            task.name,
+            task.prompt,
+            Value::Object(task.config_overrides.clone()).to_string(),
+            task.created_at,
+            task.last_run_id,
+            task.schedule
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn list(conn: &Connection) -> rusqlite::Result<Vec<SavedTask>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_tasks ORDER BY name",
+        TASK_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], task_from_row)?;
+    rows.collect()
+}
+
+/// Tasks that have a cron schedule
+pub fn scheduled(conn: &Connection) -> rusqlite::Result<Vec<SavedTask>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM saved_tasks WHERE schedule IS NOT NULL AND schedule != ''",
+        TASK_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], task_from_row)?;
+    rows.collect()
+}
+
+pub fn get(conn: &Connection, id: &str) -> rusqlite::Result<Option<SavedTask>> {
+    conn.query_row(
+        &format!("SELECT {} FROM saved_tasks WHERE id = ?1", TASK_COLUMNS),
+        params![id],
+        task_from_row,
+    )
+    .optional()
+}
+
+pub fn set_last_run(conn: &Connection, id: &str, run_id: &str) -> rusqlite::Result<()> {
+    conn.execute(
+        "UPDATE saved_tasks SET last_run_id = ?2 WHERE id = ?1",
+        params![id, run_id],
+    )?;
+    Ok(())
+}
+
+pub fn delete(conn: &Connection, id: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM saved_tasks WHERE id = ?1", params![id])? > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn sample() -> SavedTask {
+        let mut config_overrides = Map::new();
+        config_overrides.insert("model".to_string(), json!("gpt-4o-mini"));
+        config_overrides.insert("output_preset".to_string(), json!("comparison_table"));
+        SavedTask {
+            id: "task-1".to_string(),
+            name: "Competitor pricing".to_string(),
+            prompt: "Compare pricing of the top three CRM vendors".to_string(),
+            config_overrides,
+            created_at: 1,
+            last_run_id: None,
+            schedule: None,
+        }
+    }
+
+    #[test]
+    fn test_apply_overrides() {
+        let config = sample().apply_overrides(&Config::default()).unwrap();
+        assert_eq!(config.model, "gpt-4o-mini");
+        assert_eq!(
+            config.output_preset,
+            Some(crate::shapes::OutputPreset::ComparisonTable)
+        );
+
+        let mut base = Config {
+            provider: "groq".to_string(),
+            api_key: "gsk".to_string(),
+            ..Config::default()
+        };
+        base.switch_provider("openai");
+        let mut task = sample();
+        task.config_overrides
+            .insert("provider".to_string(), json!("groq"));
+        let config = task.apply_overrides(&base).unwrap();
+        assert_eq!(config.api_key, "gsk");
+        assert_eq!(config.model, "gpt-4o-mini");
+
+        let mut task = sample();
+        task.config_overrides
+            .insert("no_such_field".to_string(), json!(true));
+        assert!(task.apply_overrides(&Config::default()).is_err());
+
+        let mut task = sample();
+        task.config_overrides
+            .insert("max_steps".to_string(), json!("many"));
+        assert!(task.apply_overrides(&Config::default()).is_err());
+    }
+
+    #[test]
+    fn test_task_crud() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+
+        save(&conn, &sample()).unwrap();
+        set_last_run(&conn, "task-1", "run-1").unwrap();
+        let stored = get(&conn, "task-1").unwrap().unwrap();
+        assert_eq!(stored.last_run_id.as_deref(), Some("run-1"));
+        assert_eq!(stored.config_overrides, sample().config_overrides);
+
+        // Editing a task keeps its run history
+        let renamed = SavedTask {
+            name: "CRM pricing".to_string(),
+            ..sample()
+        };
+        save(&conn, &renamed).unwrap();
+        let stored = get(&conn, "task-1").unwrap().unwrap();
+        assert_eq!(stored.name, "CRM pricing");
+        assert_eq!(stored.last_run_id.as_deref(), Some("run-1"));
+        assert_eq!(list(&conn).unwrap().len(), 1);
+        assert!(scheduled(&conn).unwrap().is_empty());
+
+        let weekly = SavedTask {
+            schedule: Some("0 9 * * MON".to_string()),
+            ..renamed
+        };
+        save(&conn, &weekly).unwrap();
+        let due = scheduled(&conn).unwrap();
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].schedule, weekly.schedule);
+
+        assert!(delete(&conn, "task-1").unwrap());
+        assert!(get(&conn, "task-1").unwrap().is_none());
+    }
+}
@@ -0,0 +1,246 @@
+//! Prompt template library
+//!
+//! Templates are reusable prompts with `{variable}` placeholders, e.g.
+//! "price-check {product} across retailers". Filling in the variables yields
+//! the run's prompt. A template can also carry the success criteria its runs
+//! are validated against and the output shape they should produce. Use `{{`
+//! and `}}` for literal braces.
+
+use crate::criteria::SuccessCriteria;
+use crate::shapes::OutputPreset;
+use rusqlite::{params, Connection, OptionalExtension, Row};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PromptTemplate {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Prompt text with `{variable}` placeholders
+    pub prompt: String,
+    #[serde(default)]
+    pub success_criteria: Option<SuccessCriteria>,
+    #[serde(default)]
+    pub output_preset: Option<OutputPreset>,
+}
+
+impl PromptTemplate {
+    /// Placeholder names in order of first appearance
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for part in parse(&self.prompt) {
+            if let Part::Variable(name) = part {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// The prompt with every placeholder filled in
+    pub fn render(&self, values: &HashMap<String, String>) -> Result<String, String> {
+        let mut missing = Vec::new();
+        let mut out = String::new();
+        for part in parse(&self.prompt) {
+            match part {
+                Part::Text(text) => out.push_str(&text),
+                Part::Variable(name) => match values.get(&name) {
+                    Some(value) if !value.trim().is_empty() => out.push_str(value.trim()),
+                    _ => {
+                        if !missing.contains(&name) {
+                            missing.push(name);
+                        }
+                    }
+                },
+            }
+        }
+        if missing.is_empty() {
+            Ok(out)
+        } else {
+            Err(format!(
+                "Missing template variables: {}",
+                missing.join(", ")
+            ))
+        }
+    }
+}
+
+enum Part {
+    Text(String),
+    Variable(String),
+}
+
+/// Split a template into literal text and `{variable}` placeholders;
+/// unterminated or empty braces are kept as text
+fn parse(template: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut text = String::new();
+    let mut chars = template.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                text.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                text.push('}');
+            }
+            '{' => {
+                let mut name = String::new();
+                let mut closed = false;
+                for c in chars.by_ref() {
+                    if c == '}' {
+                        closed = true;
+                        break;
+                    }
+                    name.push(c);
+                }
+                let trimmed = name.trim();
+                if closed && !trimmed.is_empty() && !trimmed.contains('{') {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Variable(trimmed.to_string()));
+                } else {
+                    text.push('{');
+                    text.push_str(&name);
+                    if closed {
+                        text.push('}');
+                    }
+                }
+            }
+            c => text.push(c),
+        }
+    }
+    if !text.is_empty() {
+        parts.push(Part::Text(text));
+    }
+    parts
+}
+
+const TEMPLATE_COLUMNS: &str = "name, description, prompt, success_criteria, output_preset";
+
+fn template_from_row(row: &Row<'_>) -> rusqlite::Result<PromptTemplate> {
+    let criteria: Option<String> = row.get(3)?;
+    let preset: Option<String> = row.get(4)?;
+    Ok(PromptTemplate {
+        name: row.get(0)?,
+        description: row.get(1)?,
+        prompt: row.get(2)?,
+        success_criteria: criteria.and_then(|c| serde_json::from_str(&c).ok()),
+        output_preset: preset.and_then(|p| serde_json::from_str(&p).ok()),
+    })
+}
+
+pub fn save(conn: &Connection, template: &PromptTemplate) -> rusqlite::Result<()> {
+    conn.execute(
+        "INSERT INTO templates (name, description, prompt, success_criteria, output_preset, updated_at)
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6)
+         ON CONFLICT(name) DO UPDATE SET
+            description = excluded.description,
+            prompt = excluded.prompt,
+            success_criteria = excluded.success_criteria,
+            output_preset = excluded.output_preset,
+            updated_at = excluded.updated_at",
+        params![
+            template.name,
+            template.description,
+            template.prompt,
+            template
+                .success_criteria
+                .as_ref()
+                .and_then(|c| serde_json::to_string(c).ok()),
+            template
+                .output_preset
+                .and_then(|p| serde_json::to_string(&p).ok()),
+            chrono::Utc::now().timestamp_millis()
+        ],
+    )?;
+    Ok(())
+}
+
+pub fn list(conn: &Connection) -> rusqlite::Result<Vec<PromptTemplate>> {
+    let mut stmt = conn.prepare(&format!(
+        "SELECT {} FROM templates ORDER BY name",
+        TEMPLATE_COLUMNS
+    ))?;
+    let rows = stmt.query_map([], template_from_row)?;
+    rows.collect()
+}
+
+pub fn get(conn: &Connection, name: &str) -> rusqlite::Result<Option<PromptTemplate>> {
+    conn.query_row(
+        &format!("SELECT {} FROM templates WHERE name = ?1", TEMPLATE_COLUMNS),
+        params![name],
+        template_from_row,
+    )
+    .optional()
+}
+
+pub fn delete(conn: &Connection, name: &str) -> rusqlite::Result<bool> {
+    Ok(conn.execute("DELETE FROM templates WHERE name = ?1", params![name])? > 0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> PromptTemplate {
+        PromptTemplate {
+            name: "price-check".to_string(),
+            description: Some("Compare retailer prices".to_string()),
+            prompt: "Price-check {product} across {site} and {{others}}; list {product} deals"
+                .to_string(),
+            success_criteria: Some(SuccessCriteria {
+                min_sources: Some(3),
+                ..SuccessCriteria::default()
+            }),
+            output_preset: Some(OutputPreset::ComparisonTable),
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let template = sample();
+        assert_eq!(template.variables(), vec!["product", "site"]);
+
+        let mut values = HashMap::new();
+        values.insert("product".to_string(), "AirPods Pro".to_string());
+        assert_eq!(
+            template.render(&values).unwrap_err(),
+            "Missing template variables: site"
+        );
+        values.insert("site".to_string(), " amazon.com ".to_string());
+        assert_eq!(
+            template.render(&values).unwrap(),
+            "Price-check AirPods Pro across amazon.com and {others}; list AirPods Pro deals"
+        );
+
+        let odd = PromptTemplate {
+            prompt: "keep {} and {unclosed".to_string(),
+            ..sample()
+        };
+        assert!(odd.variables().is_empty());
+        assert_eq!(
+            odd.render(&HashMap::new()).unwrap(),
+            "keep {} and {unclosed"
+        );
+    }
+
+    #[test]
+    fn test_template_crud() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+
+        save(&conn, &sample()).unwrap();
+        assert_eq!(get(&conn, "price-check").unwrap(), Some(sample()));
+        assert_eq!(list(&conn).unwrap().len(), 1);
+
+        assert!(delete(&conn, "price-check").unwrap());
+        assert!(!delete(&conn, "price-check").unwrap());
+        assert!(list(&conn).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,308 @@
+//! Rotating NDJSON trace logs on disk
+//!
+//! When `trace_log_files` is enabled, every trace event is also appended to
+//! `traces/<session_id>.ndjson` under the app data directory, one JSON object
+//! per line. Events are handed to a background thread, which writes and
+//! flushes them in batches as they arrive. SQLite is only written every few
+//! seconds, so these files still hold the last events before a crash.
+//!
+//! A session's file is rotated once it would grow past `trace_log_max_bytes`:
+//! `<session>.ndjson` becomes `<session>.1.ndjson`, older rotations shift up,
+//! and only `trace_log_max_files` files are kept per session. When a new
+//! session starts logging, the files of all but the `trace_log_max_sessions`
+//! most recent sessions are deleted.
+
+use crate::config::Config;
+use crate::tracing::TraceEvent;
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{mpsc, Mutex, OnceLock};
+use std::time::SystemTime;
+
+static LOG_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// The active log, `None` while file logging is disabled
+static LOG: Mutex<Option<RotatingLog>> = Mutex::new(None);
+
+/// Whether events are queued for the writer at all
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Events waiting for the writer thread
+static QUEUE: OnceLock<mpsc::Sender<TraceEvent>> = OnceLock::new();
+
+/// Set the directory trace logs are written to
+pub fn init_trace_logs(data_dir: &Path) {
+    let _ = LOG_DIR.set(data_dir.join("traces"));
+}
+
+/// Start, stop or resize file logging to match `config`
+pub fn configure(config: &Config) {
+    let Ok(mut log) = LOG.lock() else {
+        return;
+    };
+    let Some(dir) = LOG_DIR.get().filter(|_| config.trace_log_files) else {
+        ENABLED.store(false, Ordering::Relaxed);
+        *log = None;
+        return;
+    };
+    match log.as_mut() {
+        Some(current) => {
+            current.max_bytes = config.trace_log_max_bytes;
+            current.max_files = config.trace_log_max_files;
+            current.max_sessions = config.trace_log_max_sessions;
+        }
+        None => {
+            *log = Some(RotatingLog::new(
+                dir.clone(),
+                config.trace_log_max_bytes,
+                config.trace_log_max_files,
+                config.trace_log_max_sessions,
+            ))
+        }
+    }
+    QUEUE.get_or_init(start_writer);
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Queue `event` for its session's log file, if file logging is enabled
+pub fn append(event: &TraceEvent) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    if let Some(queue) = QUEUE.get() {
+        let _ = queue.send(event.clone());
+    }
+}
+
+/// Spawn the thread that writes queued events to the active log
+fn start_writer() -> mpsc::Sender<TraceEvent> {
+    let (tx, rx) = mpsc::channel::<TraceEvent>();
+    std::thread::spawn(move || {
+        while let Ok(first) = rx.recv() {
+            let batch: Vec<TraceEvent> = std::iter::once(first).chain(rx.try_iter()).collect();
+            let Ok(mut guard) = LOG.lock() else {
+                continue;
+            };
+            let Some(log) = guard.as_mut() else {
+                continue;
+            };
+            let written = batch
+                .iter()
+                .try_for_each(|event| log.write(event))
+                .and_then(|()| log.flush());
+            if let Err(e) = written {
+                // Not traced: the event would come straight back here
+                log::warn!("Could not write trace log: {}", e);
+            }
+        }
+    });
+    tx
+}
+
+/// Size-rotated NDJSON files, one set per trace session
+pub struct RotatingLog {
+    dir: PathBuf,
+    max_bytes: u64,
+    max_files: usize,
+    max_sessions: usize,
+    /// Session of the open file, and the file with its current size
+    open: Option<(String, BufWriter<File>, u64)>,
+}
+
+impl RotatingLog {
+    pub fn new(dir: PathBuf, max_bytes: u64, max_files: usize, max_sessions: usize) -> Self {
+        Self {
+            dir,
+            max_bytes,
+            max_files,
+            max_sessions,
+            open: None,
+        }
+    }
+
+    pub fn write(&mut self, event: &TraceEvent) -> std::io::Result<()> {
+        let mut line = serde_json::to_string(event)?;
+        line.push('\n');
+        let len = line.len() as u64;
+
+        let reopen = match &self.open {
+            Some((session, _, size)) => {
+                session != &event.session_id || (*size > 0 && size + len > self.max_bytes)
+            }
+            None => true,
+        };
+        if reopen {
+            let rotate =
+                matches!(&self.open, Some((session, _, _)) if session == &event.session_id);
+            self.flush()?;
+            self.open = None;
+            fs::create_dir_all(&self.dir)?;
+            if rotate {
+                self.rotate(&event.session_id)?;
+            }
+            let path = self.path(&event.session_id, 0);
+            let file = OpenOptions::new().create(true).append(true).open(&path)?;
+            let size = file.metadata()?.len();
+            if !rotate {
+                self.prune(&event.session_id)?;
+            }
+            self.open = Some((event.session_id.clone(), BufWriter::new(file), size));
+        }
+
+        let (_, file, size) = self.open.as_mut().unwrap();
+        file.write_all(line.as_bytes())?;
+        *size += len;
+        Ok(())
+    }
+
+    /// Write out buffered events
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        match self.open.as_mut() {
+            Some((_, file, _)) => file.flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Delete the files of all but the `max_sessions` most recently written
+    /// sessions, always keeping `current`
+    fn prune(&self, current: &str) -> std::io::Result<()> {
+        let current = sanitize(current);
+        let mut sessions: HashMap<String, (SystemTime, Vec<PathBuf>)> = HashMap::new();
+        for entry in fs::read_dir(&self.dir)?.flatten() {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let Some(session) = name
+                .strip_suffix(".ndjson")
+                .and_then(|stem| stem.split('.').next())
+                .filter(|session| *session != current)
+            else {
+                continue;
+            };
+            let modified = entry
+                .metadata()
+                .and_then(|m| m.modified())
+                .unwrap_or(SystemTime::UNIX_EPOCH);
+            let files = sessions
+                .entry(session.to_string())
+                .or_insert((modified, Vec::new()));
+            files.0 = files.0.max(modified);
+            files.1.push(entry.path());
+        }
+        let mut older: Vec<(SystemTime, Vec<PathBuf>)> = sessions.into_values().collect();
+        older.sort_by(|a, b| b.0.cmp(&a.0));
+        for (_, files) in older.into_iter().skip(self.max_sessions.saturating_sub(1)) {
+            for file in files {
+                fs::remove_file(file)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Shift `session`'s files up by one, dropping any beyond `max_files`
+    fn rotate(&self, session: &str) -> std::io::Result<()> {
+        let keep = self.max_files.max(1);
+        let oldest = self.path(session, keep - 1);
+        if oldest.exists() {
+            fs::remove_file(&oldest)?;
+        }
+        for index in (0..keep - 1).rev() {
+            let from = self.path(session, index);
+            if from.exists() {
+                fs::rename(&from, self.path(session, index + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// `<session>.ndjson` for the live file, `<session>.<index>.ndjson` for
+    /// rotated ones
+    fn path(&self, session: &str, index: usize) -> PathBuf {
+        let session = sanitize(session);
+        match index {
+            0 => self.dir.join(format!("{}.ndjson", session)),
+            n => self.dir.join(format!("{}.{}.ndjson", session, n)),
+        }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+}
+
+/// Keep session ids from escaping the log directory
+fn sanitize(session: &str) -> String {
+    session
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(session_id: &str, message: &str) -> TraceEvent {
+        TraceEvent {
+            id: uuid::Uuid::new_v4().to_string(),
+            session_id: session_id.to_string(),
+            timestamp: 0,
+            level: "INFO".to_string(),
+            target: "nexus::test".to_string(),
+            span_name: None,
+            message: message.to_string(),
+            fields: "{}".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_rotation() {
+        let dir = std::env::temp_dir().join(format!("nexus-tracelog-{}", uuid::Uuid::new_v4()));
+        let line_len = serde_json::to_string(&event("s", "m0")).unwrap().len() as u64 + 1;
+        // Two events per file, three files kept
+        let mut log = RotatingLog::new(dir, line_len * 2, 3, 10);
+        for i in 0..7 {
+            log.write(&event("s", &format!("m{}", i))).unwrap();
+        }
+        log.write(&event("other", "x")).unwrap();
+        log.flush().unwrap();
+
+        let messages = |path: PathBuf| -> Vec<String> {
+            fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(|line| serde_json::from_str::<TraceEvent>(line).unwrap().message)
+                .collect()
+        };
+        assert_eq!(messages(log.path("s", 0)), vec!["m6"]);
+        assert_eq!(messages(log.path("s", 1)), vec!["m4", "m5"]);
+        assert_eq!(messages(log.path("s", 2)), vec!["m2", "m3"]);
+        assert!(!log.path("s", 3).exists());
+        assert_eq!(messages(log.path("other", 0)), vec!["x"]);
+
+        fs::remove_dir_all(log.dir()).unwrap();
+    }
+
+    #[test]
+    fn test_session_retention() {
+        let dir = std::env::temp_dir().join(format!("nexus-tracelog-{}", uuid::Uuid::new_v4()));
+        let mut log = RotatingLog::new(dir, 1024 * 1024, 3, 1);
+        log.write(&event("a", "m")).unwrap();
+        log.write(&event("b", "m")).unwrap();
+        log.write(&event("c", "m")).unwrap();
+        log.flush().unwrap();
+
+        assert!(!log.path("a", 0).exists());
+        assert!(!log.path("b", 0).exists());
+        assert!(log.path("c", 0).exists());
+
+        fs::remove_dir_all(log.dir()).unwrap();
+    }
+}
@@ -2,17 +2,60 @@
 //!
 //! Provides operation-level tracing that records every significant action
 //! the agent performs, stored in SQLite for debugging and observability.
+//!
+//! Events are buffered in memory for the live view and written to the
+//! `traces` table in `nexus.db` in batches by a background writer, so past
+//! sessions can be loaded after a restart. The live buffer holds at most
+//! `trace_buffer_size` events; older ones are evicted from it but remain in
+//! the database. Live events reach the frontend as `trace-events` batches,
+//! only at the levels the UI subscribed to with `set_trace_stream_level`.
+//!
+//! Timed operations are recorded as spans with [`trace_span!`]: a start
+//! event when the span opens and an end event with its `duration_ms` when the
+//! guard drops. Within a run, spans nest under the innermost open span, so
+//! the viewer can show which LLM calls and page loads a step spent its time on.
+//! Persisted batches are also forwarded to an OTLP collector when one is
+//! configured (see [`crate::otlp`]). Messages and fields are redacted by
+//! [`crate::redact`] as they are recorded, so secrets reach none of these.
 
 use chrono::Utc;
+use rusqlite::{params, Connection, Row};
 use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, AtomicU8, AtomicUsize, Ordering};
 use std::sync::{Arc, OnceLock};
+use std::time::Instant;
 use tauri_plugin_sql::{Migration, MigrationKind};
 use tokio::sync::Mutex;
+use tokio::time::{sleep, Duration};
 use uuid::Uuid;
 
 /// Global trace store instance
 pub static TRACE_STORE: OnceLock<Arc<Mutex<TraceStore>>> = OnceLock::new();
 
+/// How often buffered events are written to SQLite
+const FLUSH_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Unwritten events kept while the database is unavailable; older ones are
+/// dropped first
+const MAX_PENDING_EVENTS: usize = 10_000;
+
+/// Events kept in the live buffer, from `Config::trace_buffer_size`
+static BUFFER_SIZE: AtomicUsize = AtomicUsize::new(5_000);
+
+/// Least severe level streamed to the frontend, as a [`level_rank`];
+/// [`STREAM_OFF`] until the UI subscribes
+static STREAM_LEVEL: AtomicU8 = AtomicU8::new(STREAM_OFF);
+
+const STREAM_OFF: u8 = u8::MAX;
+
+/// Milliseconds between `trace-events` batches, from
+/// `Config::trace_stream_interval_ms`
+static STREAM_INTERVAL_MS: AtomicU64 = AtomicU64::new(250);
+
+/// Events queued for one stream batch; older ones are dropped first
+const MAX_STREAM_BATCH: usize = 1_000;
+
 /// Represents a single trace event in the flight recorder
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TraceEvent {
@@ -26,17 +69,114 @@ pub struct TraceEvent {
     pub fields: String, // JSON-encoded additional data
 }
 
+/// File formats for exporting a session's events
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TraceExportFormat {
+    /// One JSON event per line
+    Jsonl,
+    Csv,
+}
+
+/// Which of a session's events to return, and in what order
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default)]
+pub struct TraceFilter {
+    pub level: Option<String>,
+    /// Only events whose target starts with this, e.g. "nexus::browser"
+    pub target: Option<String>,
+    /// Earliest timestamp in milliseconds since the epoch, inclusive
+    pub since: Option<i64>,
+    /// Latest timestamp in milliseconds since the epoch, inclusive
+    pub until: Option<i64>,
+    /// Most events returned; all when unset
+    pub limit: Option<usize>,
+    /// Matching events skipped before the first one returned
+    pub offset: usize,
+    pub newest_first: bool,
+}
+
+impl TraceFilter {
+    pub fn matches(&self, event: &TraceEvent) -> bool {
+        self.level
+            .as_ref()
+            .map_or(true, |level| &event.level == level)
+            && self
+                .target
+                .as_ref()
+                .map_or(true, |target| event.target.starts_with(target.as_str()))
+            && self.since.map_or(true, |since| event.timestamp >= since)
+            && self.until.map_or(true, |until| event.timestamp <= until)
+    }
+
+    /// The page of `events`, given oldest first, this filter selects
+    pub fn page<'a>(&self, events: impl Iterator<Item = &'a TraceEvent>) -> TracePage {
+        let mut matching: Vec<&TraceEvent> = events.filter(|event| self.matches(event)).collect();
+        let total = matching.len();
+        if self.newest_first {
+            matching.reverse();
+        }
+        let events = matching
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        TracePage { events, total }
+    }
+}
+
+/// One page of the events matching a [`TraceFilter`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TracePage {
+    pub events: Vec<TraceEvent>,
+    /// Matching events across all pages
+    pub total: usize,
+}
+
+/// A recorded session in the trace database
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraceSession {
+    pub session_id: String,
+    pub started_at: i64,
+    pub ended_at: i64,
+    pub event_count: usize,
+    /// Agent runs traced in the session, in the order they started
+    pub run_ids: Vec<String>,
+}
+
+/// Live buffer and persistence counters
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TraceStats {
+    pub buffered: usize,
+    pub buffer_size: usize,
+    /// Events waiting to be written to SQLite
+    pub pending: usize,
+    /// Events evicted from this session's live buffer; they remain in SQLite
+    pub evicted: u64,
+    /// Events lost since startup because SQLite was unavailable for too long
+    pub dropped: u64,
+}
+
 /// Manages trace storage and retrieval
 pub struct TraceStore {
     session_id: String,
-    events: Vec<TraceEvent>, // In-memory buffer, synced to SQLite
+    events: VecDeque<TraceEvent>, // In-memory ring buffer, synced to SQLite
+    pending: Vec<TraceEvent>,     // Not yet written to SQLite
+    outgoing: Vec<TraceEvent>,    // Not yet streamed to the frontend
+    evicted: u64,
+    dropped: u64,
 }
 
 impl TraceStore {
     pub fn new() -> Self {
         Self {
             session_id: Uuid::new_v4().to_string(),
-            events: Vec::new(),
+            events: VecDeque::new(),
+            pending: Vec::new(),
+            outgoing: Vec::new(),
+            evicted: 0,
+            dropped: 0,
         }
     }
 
@@ -54,6 +194,9 @@ impl TraceStore {
         message: &str,
         fields: serde_json::Value,
     ) {
+        // Secrets never reach the live view, SQLite or an OTLP collector
+        let mut fields = fields;
+        crate::redact::redact_fields(&mut fields);
         let event = TraceEvent {
             id: Uuid::new_v4().to_string(),
             session_id: self.session_id.clone(),
@@ -61,50 +204,465 @@ impl TraceStore {
             level: level.to_string(),
             target: target.to_string(),
             span_name: span_name.map(|s| s.to_string()),
-            message: message.to_string(),
+            message: crate::redact::redact(message),
             fields: fields.to_string(),
         };
 
-        // Emit to frontend immediately
-        if let Some(app) = crate::GLOBAL_APP.get() {
-            let _ = tauri::Emitter::emit(app, "trace-event", &event);
+        crate::tracelog::append(&event);
+        self.stream(&event, STREAM_LEVEL.load(Ordering::Relaxed));
+        self.store(event, BUFFER_SIZE.load(Ordering::Relaxed));
+    }
+
+    /// Queue `event` for the frontend if it is at least as severe as
+    /// `min_rank`
+    fn stream(&mut self, event: &TraceEvent, min_rank: u8) {
+        if level_rank(&event.level) < min_rank {
+            return;
+        }
+        if self.outgoing.len() >= MAX_STREAM_BATCH {
+            self.outgoing.remove(0);
+        }
+        self.outgoing.push(event.clone());
+    }
+
+    fn store(&mut self, event: TraceEvent, buffer_size: usize) {
+        if self.pending.len() >= MAX_PENDING_EVENTS {
+            self.pending.remove(0);
+            self.dropped += 1;
         }
+        self.pending.push(event.clone());
+        self.events.push_back(event);
+        while self.events.len() > buffer_size {
+            self.events.pop_front();
+            self.evicted += 1;
+        }
+    }
 
-        self.events.push(event);
+    /// Events recorded since the last call, for writing to SQLite
+    pub fn take_pending(&mut self) -> Vec<TraceEvent> {
+        std::mem::take(&mut self.pending)
     }
 
-    /// Get all events for the current session
+    /// Queue events again after a failed write, ahead of newer ones
+    fn requeue(&mut self, mut events: Vec<TraceEvent>) {
+        events.append(&mut self.pending);
+        let excess = events.len().saturating_sub(MAX_PENDING_EVENTS);
+        events.drain(..excess);
+        self.dropped += excess as u64;
+        self.pending = events;
+    }
+
+    /// Get the buffered events for the current session
     pub fn get_events(&self) -> Vec<TraceEvent> {
-        self.events.clone()
+        self.events.iter().cloned().collect()
     }
 
-    /// Get events filtered by level
-    pub fn get_events_by_level(&self, level: &str) -> Vec<TraceEvent> {
-        self.events
-            .iter()
-            .filter(|e| e.level == level)
-            .cloned()
-            .collect()
+    /// Get the buffered events selected by `filter`
+    pub fn query(&self, filter: &TraceFilter) -> TracePage {
+        filter.page(self.events.iter())
     }
 
-    /// Clear all events and start a new session
+    /// Clear all events and start a new session. Events not yet written
+    /// still reach SQLite under the old session.
     pub fn reset(&mut self) {
         self.events.clear();
+        self.evicted = 0;
         self.session_id = Uuid::new_v4().to_string();
     }
 
+    pub fn stats(&self) -> TraceStats {
+        TraceStats {
+            buffered: self.events.len(),
+            buffer_size: BUFFER_SIZE.load(Ordering::Relaxed),
+            pending: self.pending.len(),
+            evicted: self.evicted,
+            dropped: self.dropped,
+        }
+    }
+
     /// Get event count
     pub fn count(&self) -> usize {
         self.events.len()
     }
 }
 
+/// A timed operation in the flight recorder, ended when dropped
+pub struct TraceSpan {
+    id: String,
+    parent_id: Option<String>,
+    target: String,
+    name: String,
+    started: Instant,
+    run: Option<Arc<crate::run::RunContext>>,
+    error: Option<String>,
+}
+
+impl TraceSpan {
+    /// Open a span and record its start; prefer [`trace_span!`]
+    pub fn enter(target: &str, name: &str, fields: serde_json::Value) -> Self {
+        let id = Uuid::new_v4().to_string();
+        let run = crate::run::current();
+        let parent_id = run.as_ref().and_then(|run| run.enter_span(&id));
+        let span = Self {
+            id,
+            parent_id,
+            target: target.to_string(),
+            name: name.to_string(),
+            started: Instant::now(),
+            run,
+            error: None,
+        };
+        let mut fields = fields;
+        if let Some(map) = fields.as_object_mut() {
+            map.extend(span.span_fields());
+        }
+        record_trace(
+            "INFO",
+            target,
+            Some(name),
+            &format!("{} started", name),
+            fields,
+        );
+        span
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn parent_id(&self) -> Option<&str> {
+        self.parent_id.as_deref()
+    }
+
+    /// Mark the operation as failed; its end event is recorded at ERROR level
+    /// with `error` in its fields
+    pub fn fail(&mut self, error: impl ToString) {
+        self.error = Some(error.to_string());
+    }
+
+    fn span_fields(&self) -> serde_json::Map<String, serde_json::Value> {
+        let mut fields = serde_json::Map::new();
+        fields.insert("span_id".to_string(), self.id.clone().into());
+        fields.insert("parent_span_id".to_string(), self.parent_id.clone().into());
+        fields
+    }
+}
+
+impl Drop for TraceSpan {
+    fn drop(&mut self) {
+        if let Some(run) = &self.run {
+            run.exit_span(&self.id);
+        }
+        let mut fields = self.span_fields();
+        fields.insert(
+            "duration_ms".to_string(),
+            (self.started.elapsed().as_millis() as u64).into(),
+        );
+        if let Some(error) = self.error.take() {
+            fields.insert("error".to_string(), error.into());
+        }
+        let level = if fields.contains_key("error") {
+            "ERROR"
+        } else {
+            "INFO"
+        };
+        record_trace(
+            level,
+            &self.target,
+            Some(&self.name),
+            &format!("{} finished", self.name),
+            fields.into(),
+        );
+    }
+}
+
+/// Limit the live buffer to `size` events; takes effect on the next event
+pub fn set_buffer_size(size: usize) {
+    BUFFER_SIZE.store(size.max(1), Ordering::Relaxed);
+}
+
 /// Initialize the trace store
 pub fn init_tracing() {
     let store = Arc::new(Mutex::new(TraceStore::new()));
     let _ = TRACE_STORE.set(store);
 }
 
+/// Write `events` to the traces table in one transaction
+pub fn save_events(conn: &Connection, events: &[TraceEvent]) -> rusqlite::Result<()> {
+    let tx = conn.unchecked_transaction()?;
+    {
+        let mut stmt = tx.prepare(
+            "INSERT OR IGNORE INTO traces
+                (id, session_id, timestamp, level, target, span_name, message, fields)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        )?;
+        for event in events {
+            stmt.execute(params![
+                event.id,
+                event.session_id,
+                event.timestamp,
+                event.level,
+                event.target,
+                event.span_name,
+                event.message,
+                event.fields
+            ])?;
+        }
+    }
+    tx.commit()
+}
+
+/// Recorded sessions, most recent first
+pub fn list_sessions(conn: &Connection) -> rusqlite::Result<Vec<TraceSession>> {
+    let mut stmt = conn.prepare(
+        "SELECT session_id, MIN(timestamp), MAX(timestamp), COUNT(*)
+         FROM traces GROUP BY session_id ORDER BY MIN(timestamp) DESC",
+    )?;
+    let rows = stmt.query_map([], |row| {
+        Ok(TraceSession {
+            session_id: row.get(0)?,
+            started_at: row.get(1)?,
+            ended_at: row.get(2)?,
+            event_count: row.get::<_, i64>(3)? as usize,
+            run_ids: Vec::new(),
+        })
+    })?;
+    let mut sessions = rows.collect::<rusqlite::Result<Vec<_>>>()?;
+
+    let mut stmt = conn.prepare(
+        "SELECT session_id, json_extract(fields, '$.run_id') AS run_id
+         FROM traces WHERE json_extract(fields, '$.run_id') IS NOT NULL
+         GROUP BY session_id, run_id ORDER BY MIN(timestamp)",
+    )?;
+    let runs = stmt.query_map([], |row| {
+        Ok((row.get::<_, String>(0)?, row.get::<_, String>(1)?))
+    })?;
+    for run in runs {
+        let (session_id, run_id) = run?;
+        if let Some(session) = sessions.iter_mut().find(|s| s.session_id == session_id) {
+            session.run_ids.push(run_id);
+        }
+    }
+    Ok(sessions)
+}
+
+/// The page of a session's events selected by `filter`
+pub fn load_session(
+    conn: &Connection,
+    session_id: &str,
+    filter: &TraceFilter,
+) -> rusqlite::Result<TracePage> {
+    const WHERE: &str = "WHERE session_id = ?1
+           AND (?2 IS NULL OR level = ?2)
+           AND (?3 IS NULL OR substr(target, 1, length(?3)) = ?3)
+           AND (?4 IS NULL OR timestamp >= ?4)
+           AND (?5 IS NULL OR timestamp <= ?5)";
+    let conditions = params![
+        session_id,
+        filter.level,
+        filter.target,
+        filter.since,
+        filter.until
+    ];
+    let total: i64 = conn.query_row(
+        &format!("SELECT COUNT(*) FROM traces {}", WHERE),
+        conditions,
+        |row| row.get(0),
+    )?;
+
+    let order = if filter.newest_first { "DESC" } else { "ASC" };
+    let mut stmt = conn.prepare(&format!(
+        "SELECT id, session_id, timestamp, level, target, span_name, message, fields
+         FROM traces {}
+         ORDER BY timestamp {order}, rowid {order} LIMIT ?6 OFFSET ?7",
+        WHERE,
+        order = order
+    ))?;
+    // A negative LIMIT is unbounded in SQLite
+    let limit = filter.limit.map_or(-1, |limit| limit as i64);
+    let rows = stmt.query_map(
+        params![
+            session_id,
+            filter.level,
+            filter.target,
+            filter.since,
+            filter.until,
+            limit,
+            filter.offset as i64
+        ],
+        event_from_row,
+    )?;
+    Ok(TracePage {
+        events: rows.collect::<rusqlite::Result<_>>()?,
+        total: total as usize,
+    })
+}
+
+/// Every recorded event, or only `session_id`'s, oldest first
+pub fn load_events(
+    conn: &Connection,
+    session_id: Option<&str>,
+) -> rusqlite::Result<Vec<TraceEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, session_id, timestamp, level, target, span_name, message, fields
+         FROM traces WHERE ?1 IS NULL OR session_id = ?1
+         ORDER BY timestamp, rowid",
+    )?;
+    let rows = stmt.query_map(params![session_id], event_from_row)?;
+    rows.collect()
+}
+
+/// Every event recorded during `run_id`, oldest first
+pub fn load_run_events(conn: &Connection, run_id: &str) -> rusqlite::Result<Vec<TraceEvent>> {
+    let mut stmt = conn.prepare(
+        "SELECT id, session_id, timestamp, level, target, span_name, message, fields
+         FROM traces WHERE json_extract(fields, '$.run_id') = ?1
+         ORDER BY timestamp, rowid",
+    )?;
+    let rows = stmt.query_map(params![run_id], event_from_row)?;
+    rows.collect()
+}
+
+/// `events` rendered in `format`, one event per line after any header
+pub fn render_events(events: &[TraceEvent], format: TraceExportFormat) -> String {
+    let mut out = String::new();
+    match format {
+        TraceExportFormat::Jsonl => {
+            for event in events {
+                out.push_str(&serde_json::to_string(event).unwrap_or_default());
+                out.push('\n');
+            }
+        }
+        TraceExportFormat::Csv => {
+            out.push_str("id,session_id,timestamp,level,target,span_name,message,fields\n");
+            for event in events {
+                let timestamp = event.timestamp.to_string();
+                let row = [
+                    event.id.as_str(),
+                    event.session_id.as_str(),
+                    timestamp.as_str(),
+                    event.level.as_str(),
+                    event.target.as_str(),
+                    event.span_name.as_deref().unwrap_or(""),
+                    event.message.as_str(),
+                    event.fields.as_str(),
+                ];
+                let row: Vec<String> = row.iter().map(|value| csv_field(value)).collect();
+                out.push_str(&row.join(","));
+                out.push('\n');
+            }
+        }
+    }
+    out
+}
+
+/// Quote a CSV value when it contains a delimiter, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn event_from_row(row: &Row<'_>) -> rusqlite::Result<TraceEvent> {
+    Ok(TraceEvent {
+        id: row.get(0)?,
+        session_id: row.get(1)?,
+        timestamp: row.get(2)?,
+        level: row.get(3)?,
+        target: row.get(4)?,
+        span_name: row.get(5)?,
+        message: row.get(6)?,
+        fields: row.get(7)?,
+    })
+}
+
+/// Write buffered events to SQLite
+pub async fn flush() {
+    let Some(store) = TRACE_STORE.get() else {
+        return;
+    };
+    let events = store.lock().await.take_pending();
+    if events.is_empty() {
+        return;
+    }
+    if let Err(e) = crate::db::with_db(|conn| save_events(conn, &events)) {
+        store.lock().await.requeue(events);
+        // Not traced: the warning would queue yet another unwritable event
+        log::warn!("Could not persist traces: {}", e);
+        return;
+    }
+    // Exported in the background so flushing never waits on the collector;
+    // batches it rejects are only kept in SQLite
+    tauri::async_runtime::spawn(async move {
+        if let Err(e) = crate::otlp::export(&events).await {
+            log::warn!("Could not export traces over OTLP: {}", e);
+        }
+    });
+}
+
+/// Severity order of trace levels, least severe first
+pub fn level_rank(level: &str) -> u8 {
+    match level {
+        "TRACE" => 0,
+        "DEBUG" => 1,
+        "WARN" => 3,
+        "ERROR" => 4,
+        _ => 2,
+    }
+}
+
+/// Stream events at `level` and above to the frontend; `None` stops the
+/// stream
+pub fn set_stream_level(level: Option<&str>) -> Result<(), String> {
+    let rank = match level {
+        None => STREAM_OFF,
+        Some(level @ ("TRACE" | "DEBUG" | "INFO" | "WARN" | "ERROR")) => level_rank(level),
+        Some(other) => return Err(format!("Unknown trace level '{}'", other)),
+    };
+    STREAM_LEVEL.store(rank, Ordering::Relaxed);
+    Ok(())
+}
+
+/// Batch streamed events every `interval_ms`; takes effect after the
+/// current wait
+pub fn set_stream_interval(interval_ms: u64) {
+    STREAM_INTERVAL_MS.store(interval_ms.max(10), Ordering::Relaxed);
+}
+
+/// Emit queued events to the frontend as `trace-events` batches
+pub fn start_trace_stream() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            sleep(Duration::from_millis(
+                STREAM_INTERVAL_MS.load(Ordering::Relaxed),
+            ))
+            .await;
+            let Some(store) = TRACE_STORE.get() else {
+                continue;
+            };
+            let batch = std::mem::take(&mut store.lock().await.outgoing);
+            if batch.is_empty() {
+                continue;
+            }
+            if let Some(app) = crate::GLOBAL_APP.get() {
+                let _ = tauri::Emitter::emit(app, "trace-events", &batch);
+            }
+        }
+    });
+}
+
+/// Periodically persist buffered trace events in the background
+pub fn start_trace_writer() {
+    tauri::async_runtime::spawn(async {
+        loop {
+            sleep(FLUSH_INTERVAL).await;
+            flush().await;
+        }
+    });
+}
+
 /// Get SQLite migrations for trace table
 pub fn get_migrations() -> Vec<Migration> {
     vec![Migration {
@@ -185,6 +743,20 @@ macro_rules! trace_warn {
     };
 }
 
+/// Open a timed span; it ends, recording its duration, when the returned
+/// guard is dropped
+#[macro_export]
+macro_rules! trace_span {
+    ($target:expr, $name:expr) => {
+        $crate::tracing::TraceSpan::enter($target, $name, serde_json::json!({}))
+    };
+    ($target:expr, $name:expr, $($key:ident = $value:expr),+) => {
+        $crate::tracing::TraceSpan::enter($target, $name, serde_json::json!({
+            $(stringify!($key): $value),+
+        }))
+    };
+}
+
 /// Helper function called by macros
 pub fn record_trace(
     level: &str,
@@ -193,6 +765,12 @@ pub fn record_trace(
     message: &str,
     fields: serde_json::Value,
 ) {
+    // Tag events recorded within a run, so they can be found by run later
+    let mut fields = fields;
+    if let (Some(run), Some(map)) = (crate::run::current(), fields.as_object_mut()) {
+        map.entry("run_id")
+            .or_insert_with(|| run.run_id.clone().into());
+    }
     if let Some(store) = TRACE_STORE.get() {
         // Use try_lock to avoid blocking - traces are best-effort
         if let Ok(mut guard) = store.try_lock() {
@@ -200,3 +778,234 @@ pub fn record_trace(
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(id: &str, session_id: &str, timestamp: i64, level: &str) -> TraceEvent {
+        TraceEvent {
+            id: id.to_string(),
+            session_id: session_id.to_string(),
+            timestamp,
+            level: level.to_string(),
+            target: "nexus::test".to_string(),
+            span_name: None,
+            message: format!("event {}", id),
+            fields: "{}".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_save_and_load_sessions() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+
+        let mut events = vec![
+            event("1", "old", 100, "INFO"),
+            event("2", "old", 200, "ERROR"),
+            event("3", "new", 300, "INFO"),
+        ];
+        events[0].fields = r#"{"run_id":"run-a"}"#.to_string();
+        events[1].fields = r#"{"run_id":"run-a","step":2}"#.to_string();
+        save_events(&conn, &events).unwrap();
+        // Re-saving a batch after a partial failure doesn't duplicate events
+        save_events(&conn, &events[..1]).unwrap();
+
+        let sessions = list_sessions(&conn).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].session_id, "new");
+        assert!(sessions[0].run_ids.is_empty());
+        assert_eq!(
+            sessions[1],
+            TraceSession {
+                session_id: "old".to_string(),
+                started_at: 100,
+                ended_at: 200,
+                event_count: 2,
+                run_ids: vec!["run-a".to_string()],
+            }
+        );
+
+        let loaded = load_session(&conn, "old", &TraceFilter::default()).unwrap();
+        assert_eq!(
+            loaded
+                .events
+                .iter()
+                .map(|e| e.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["1", "2"]
+        );
+        let errors = TraceFilter {
+            level: Some("ERROR".to_string()),
+            ..TraceFilter::default()
+        };
+        let errors = load_session(&conn, "old", &errors).unwrap();
+        assert_eq!(errors.total, 1);
+        assert_eq!(errors.events[0].message, "event 2");
+
+        assert_eq!(load_events(&conn, None).unwrap().len(), 3);
+        assert_eq!(load_events(&conn, Some("new")).unwrap()[0].id, "3");
+    }
+
+    #[test]
+    fn test_filter_and_paginate() {
+        let conn = Connection::open_in_memory().unwrap();
+        crate::db::migrate(&conn).unwrap();
+        let mut store = TraceStore::new();
+        let events: Vec<TraceEvent> = (1..=6)
+            .map(|i| {
+                let mut event = event(&i.to_string(), "s", i * 100, "INFO");
+                if i % 2 == 0 {
+                    event.target = "nexus::browser".to_string();
+                }
+                event
+            })
+            .collect();
+        save_events(&conn, &events).unwrap();
+        for event in events {
+            store.store(event, 10);
+        }
+
+        let filter = TraceFilter {
+            target: Some("nexus::brow".to_string()),
+            since: Some(200),
+            until: Some(600),
+            limit: Some(2),
+            newest_first: true,
+            ..TraceFilter::default()
+        };
+        let ids = |page: TracePage| -> (usize, Vec<String>) {
+            (page.total, page.events.into_iter().map(|e| e.id).collect())
+        };
+        let expected = (3, vec!["6".to_string(), "4".to_string()]);
+        assert_eq!(ids(store.query(&filter)), expected);
+        assert_eq!(ids(load_session(&conn, "s", &filter).unwrap()), expected);
+
+        let filter = TraceFilter {
+            offset: 2,
+            newest_first: false,
+            ..filter
+        };
+        let expected = (3, vec!["6".to_string()]);
+        assert_eq!(ids(store.query(&filter)), expected);
+        assert_eq!(ids(load_session(&conn, "s", &filter).unwrap()), expected);
+    }
+
+    #[test]
+    fn test_render_events() {
+        let mut quoted = event("2", "s", 20, "WARN");
+        quoted.message = "Said \"hi\", then left".to_string();
+        quoted.fields = r#"{"a":1,"b":2}"#.to_string();
+        quoted.span_name = Some("navigate".to_string());
+        let events = vec![event("1", "s", 10, "INFO"), quoted];
+
+        let jsonl = render_events(&events, TraceExportFormat::Jsonl);
+        let lines: Vec<&str> = jsonl.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: TraceEvent = serde_json::from_str(lines[1]).unwrap();
+        assert_eq!(parsed.message, "Said \"hi\", then left");
+
+        let csv = render_events(&events, TraceExportFormat::Csv);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(
+            lines[0],
+            "id,session_id,timestamp,level,target,span_name,message,fields"
+        );
+        assert_eq!(lines[1], "1,s,10,INFO,nexus::test,,event 1,{}");
+        assert_eq!(
+            lines[2],
+            r#"2,s,20,WARN,nexus::test,navigate,"Said ""hi"", then left","{""a"":1,""b"":2}""#
+        );
+    }
+
+    #[test]
+    fn test_buffer_evicts_oldest() {
+        let mut store = TraceStore::new();
+        for id in ["1", "2", "3"] {
+            store.store(event(id, "s", 0, "INFO"), 2);
+        }
+        let ids: Vec<_> = store.get_events().into_iter().map(|e| e.id).collect();
+        assert_eq!(ids, vec!["2", "3"]);
+        // Evicted events are still written to SQLite
+        assert_eq!(store.take_pending().len(), 3);
+
+        let stats = store.stats();
+        assert_eq!(stats.buffered, 2);
+        assert_eq!(stats.evicted, 1);
+        assert_eq!(stats.dropped, 0);
+
+        store.requeue(
+            (0..MAX_PENDING_EVENTS + 5)
+                .map(|_| event("x", "s", 0, "INFO"))
+                .collect(),
+        );
+        assert_eq!(store.stats().pending, MAX_PENDING_EVENTS);
+        assert_eq!(store.stats().dropped, 5);
+
+        store.reset();
+        assert_eq!(store.stats().evicted, 0);
+        assert_eq!(store.stats().dropped, 5);
+    }
+
+    #[tokio::test]
+    async fn test_spans_nest_within_a_run() {
+        let outside = crate::trace_span!("nexus::test", "outside");
+        assert!(outside.parent_id().is_none());
+
+        let run = Arc::new(crate::run::RunContext::new(crate::config::Config::default()));
+        crate::run::scope(run, async {
+            let step = crate::trace_span!("nexus::test", "step", step = 1);
+            let root = step.parent_id().map(str::to_string);
+            assert!(root.is_some(), "runs open a root span");
+            {
+                let call = crate::trace_span!("nexus::test", "llm_call");
+                assert_eq!(call.parent_id(), Some(step.id()));
+            }
+            let next = crate::trace_span!("nexus::test", "navigate");
+            assert_eq!(next.parent_id(), Some(step.id()));
+            drop(next);
+            drop(step);
+            let after = crate::trace_span!("nexus::test", "step", step = 2);
+            assert_eq!(after.parent_id().map(str::to_string), root);
+        })
+        .await;
+    }
+
+    #[test]
+    fn test_stream_filters_by_level() {
+        let mut store = TraceStore::new();
+        let min_rank = level_rank("WARN");
+        for level in ["DEBUG", "INFO", "WARN", "ERROR"] {
+            store.stream(&event(level, "s", 0, level), min_rank);
+        }
+        let levels: Vec<_> = store.outgoing.iter().map(|e| e.level.as_str()).collect();
+        assert_eq!(levels, vec!["WARN", "ERROR"]);
+
+        store.stream(&event("x", "s", 0, "ERROR"), STREAM_OFF);
+        assert_eq!(store.outgoing.len(), 2);
+        assert!(set_stream_level(Some("VERBOSE")).is_err());
+    }
+
+    #[test]
+    fn test_pending_events_survive_reset() {
+        let mut store = TraceStore::new();
+        store.record("INFO", "nexus::test", None, "first", serde_json::json!({}));
+        store.reset();
+        store.record("INFO", "nexus::test", None, "second", serde_json::json!({}));
+
+        let pending = store.take_pending();
+        assert_eq!(pending.len(), 2);
+        assert_ne!(pending[0].session_id, pending[1].session_id);
+        assert!(store.take_pending().is_empty());
+
+        store.record("INFO", "nexus::test", None, "third", serde_json::json!({}));
+        store.requeue(pending);
+        let messages: Vec<_> = store
+            .take_pending()
+            .into_iter()
+            .map(|e| e.message)
+            .collect();
+        assert_eq!(messages, vec!["first", "second", "third"]);
+    }
+}
@@ -0,0 +1,278 @@
+//! Token usage and cost tracking
+//!
+//! Every LLM call made by the worker goes through [`MeteredLlm`], which adds
+//! the reported token counts to the current run's [`RunUsage`]. Costs are
+//! estimated from a static per-model pricing table. The same wrapper applies
+//! the provider's shared [`RateLimiter`] before each call and retries calls
+//! the provider throttled.
+
+use crate::ratelimit::{RateLimiter, MAX_THROTTLE_RETRIES};
+use async_trait::async_trait;
+use radkit::errors::AgentResult;
+use radkit::models::{BaseLlm, LlmResponse, Thread};
+use radkit::tools::BaseToolset;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Accumulated token usage for a run
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RunUsage {
+    pub model: String,
+    pub llm_calls: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    /// Estimated cost, `None` when the model has no pricing entry
+    pub cost_usd: Option<f64>,
+}
+
+impl RunUsage {
+    pub fn new(model: &str) -> Self {
+        Self {
+            model: model.to_string(),
+            cost_usd: price_for(model).map(|_| 0.0),
+            ..Self::default()
+        }
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.prompt_tokens + self.completion_tokens
+    }
+
+    /// Add one LLM call's token counts
+    pub fn add(&mut self, prompt_tokens: u64, completion_tokens: u64) {
+        self.llm_calls += 1;
+        self.prompt_tokens += prompt_tokens;
+        self.completion_tokens += completion_tokens;
+        self.cost_usd = price_for(&self.model).map(|p| p.cost(self.prompt_tokens, self.completion_tokens));
+    }
+
+    /// Add another usage total for the same model (e.g. a sub-agent's)
+    pub fn merge(&mut self, other: &RunUsage) {
+        self.llm_calls += other.llm_calls;
+        self.prompt_tokens += other.prompt_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.cost_usd = price_for(&self.model).map(|p| p.cost(self.prompt_tokens, self.completion_tokens));
+    }
+}
+
+/// USD price per million tokens
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pricing {
+    pub input: f64,
+    pub output: f64,
+}
+
+impl Pricing {
+    pub fn cost(&self, prompt_tokens: u64, completion_tokens: u64) -> f64 {
+        (prompt_tokens as f64 * self.input + completion_tokens as f64 * self.output) / 1_000_000.0
+    }
+}
+
+/// Model-name prefixes and their prices, most specific first
+const PRICING: &[(&str, f64, f64)] = &[
+    ("claude-3-opus", 15.0, 75.0),
+    ("claude-opus", 15.0, 75.0),
+    ("claude-3-5-haiku", 0.8, 4.0),
+    ("claude-3-haiku", 0.25, 1.25),
+    ("claude-haiku", 1.0, 5.0),
+    ("claude-3-sonnet", 3.0, 15.0),
+    ("claude-3-5-sonnet", 3.0, 15.0),
+    ("claude-3-7-sonnet", 3.0, 15.0),
+    ("claude-sonnet", 3.0, 15.0),
+    ("gpt-4o-mini", 0.15, 0.6),
+    ("gpt-4o", 2.5, 10.0),
+    ("gpt-4.1-nano", 0.1, 0.4),
+    ("gpt-4.1-mini", 0.4, 1.6),
+    ("gpt-4.1", 2.0, 8.0),
+    ("o3-mini", 1.1, 4.4),
+    ("o4-mini", 1.1, 4.4),
+    ("gemini-1.5-flash", 0.075, 0.3),
+    ("gemini-1.5-pro", 1.25, 5.0),
+    ("gemini-2.0-flash", 0.1, 0.4),
+    ("gemini-2.5-flash", 0.3, 2.5),
+    ("gemini-2.5-pro", 1.25, 10.0),
+    ("grok-3-mini", 0.3, 0.5),
+    ("grok-3", 3.0, 15.0),
+    ("deepseek-chat", 0.27, 1.1),
+    ("deepseek-reasoner", 0.55, 2.19),
+    ("mistral-large", 2.0, 6.0),
+    ("mistral-medium", 0.4, 2.0),
+    ("mistral-small", 0.1, 0.3),
+    ("codestral", 0.3, 0.9),
+    ("llama-3.3-70b", 0.59, 0.79),
+    ("llama-3.1-8b", 0.05, 0.08),
+];
+
+/// Look up pricing for a model, ignoring any `vendor/` routing prefix
+pub fn price_for(model: &str) -> Option<Pricing> {
+    let model = model.rsplit('/').next().unwrap_or(model).to_lowercase();
+    PRICING
+        .iter()
+        .find(|(prefix, _, _)| model.starts_with(prefix))
+        .map(|&(_, input, output)| Pricing { input, output })
+}
+
+/// `BaseLlm` decorator that records token usage on the current run
+pub struct MeteredLlm {
+    inner: Box<dyn BaseLlm>,
+    /// Persist the conversation thread as a run checkpoint before each call
+    checkpoints: bool,
+    limiter: Option<Arc<RateLimiter>>,
+}
+
+impl MeteredLlm {
+    pub fn new(inner: Box<dyn BaseLlm>) -> Self {
+        Self {
+            inner,
+            checkpoints: false,
+            limiter: None,
+        }
+    }
+
+    /// Wait on `limiter` before each call and report token usage back to it
+    pub fn with_rate_limiter(mut self, limiter: Arc<RateLimiter>) -> Self {
+        self.limiter = Some(limiter);
+        self
+    }
+
+    /// Checkpoint the run on LLM calls. Only the agent worker's LLM should do
+    /// this; side calls (summaries, recipe learning) carry unrelated threads.
+    pub fn with_checkpoints(mut self) -> Self {
+        self.checkpoints = true;
+        self
+    }
+}
+
+#[async_trait]
+impl BaseLlm for MeteredLlm {
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    async fn generate_content(
+        &self,
+        thread: Thread,
+        toolset: Option<Arc<dyn BaseToolset>>,
+    ) -> AgentResult<LlmResponse> {
+        if let Some(run) = crate::run::current() {
+            if !run.admit_llm_call() {
+                crate::trace_warn!(
+                    "nexus::usage",
+                    "Spend budget exceeded, aborting run",
+                    run_id = run.run_id
+                );
+                // The worker is raced against `RunContext::aborted`, so this
+                // call never needs to resolve.
+                std::future::pending::<()>().await;
+            }
+            if self.checkpoints {
+                crate::checkpoint::maybe_save(&run, &thread).await;
+            }
+        }
+        let _span = crate::trace_span!("nexus::usage", "llm_call", model = self.inner.model_name());
+        let mut attempt = 0;
+        let response = loop {
+            if let Some(limiter) = &self.limiter {
+                limiter.acquire().await;
+            }
+            let error = match self
+                .inner
+                .generate_content(thread.clone(), toolset.clone())
+                .await
+            {
+                Ok(response) => break response,
+                Err(e) => e,
+            };
+            let message = error.to_string();
+            let Some(throttle) = crate::ratelimit::parse_throttle(&message) else {
+                return Err(error);
+            };
+            attempt += 1;
+            if attempt > MAX_THROTTLE_RETRIES {
+                crate::trace_error!(
+                    "nexus::usage",
+                    "Provider still throttling, giving up",
+                    model = self.inner.model_name(),
+                    attempts = attempt,
+                    error = message
+                );
+                return Err(error);
+            }
+            let wait = throttle.delay(attempt);
+            crate::trace_warn!(
+                "nexus::usage",
+                "Provider throttled the request, backing off",
+                model = self.inner.model_name(),
+                attempt = attempt,
+                wait_ms = wait.as_millis() as u64,
+                error = message
+            );
+            crate::agent::emit_event(
+                "rate_limit",
+                format!(
+                    "Waiting {}s for the {} rate limit (retry {} of {})",
+                    wait.as_secs_f32().ceil() as u64,
+                    self.inner.model_name(),
+                    attempt,
+                    MAX_THROTTLE_RETRIES
+                ),
+            );
+            match &self.limiter {
+                Some(limiter) => limiter.pause(wait),
+                None => tokio::time::sleep(wait).await,
+            }
+        };
+        let usage = response.usage();
+        let prompt_tokens = usage.input_tokens() as u64;
+        let completion_tokens = usage.output_tokens() as u64;
+        if let Some(limiter) = &self.limiter {
+            limiter.record_tokens(prompt_tokens + completion_tokens);
+        }
+
+        if let Some(run) = crate::run::current() {
+            let totals = run.record_usage(prompt_tokens, completion_tokens);
+            crate::trace_debug!(
+                "nexus::usage",
+                "LLM call metered",
+                prompt_tokens = prompt_tokens,
+                completion_tokens = completion_tokens,
+                total_tokens = totals.total_tokens(),
+                cost_usd = totals.cost_usd
+            );
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_lookup() {
+        assert_eq!(price_for("gpt-4o-mini").unwrap().input, 0.15);
+        assert_eq!(price_for("gpt-4o-2024-08-06").unwrap().input, 2.5);
+        assert_eq!(price_for("anthropic/claude-3-5-sonnet").unwrap().output, 15.0);
+        assert!(price_for("my-local-model").is_none());
+    }
+
+    #[test]
+    fn test_usage_accumulates_cost() {
+        let mut usage = RunUsage::new("claude-3-sonnet-20240229");
+        usage.add(1_000_000, 0);
+        usage.add(0, 100_000);
+        assert_eq!(usage.llm_calls, 2);
+        assert_eq!(usage.total_tokens(), 1_100_000);
+        assert!((usage.cost_usd.unwrap() - 4.5).abs() < 1e-9);
+
+        let mut total = RunUsage::new("claude-3-sonnet-20240229");
+        total.merge(&usage);
+        total.merge(&usage);
+        assert_eq!(total.llm_calls, 4);
+        assert!((total.cost_usd.unwrap() - 9.0).abs() < 1e-9);
+
+        let mut unknown = RunUsage::new("local");
+        unknown.add(10, 10);
+        assert_eq!(unknown.cost_usd, None);
+    }
+}
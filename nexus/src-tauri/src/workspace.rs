@@ -0,0 +1,249 @@
+//! Per-run artifact workspaces
+//!
+//! Each run gets a directory under `<app config>/workspaces/<run_id>` for the
+//! files it produces (screenshots, downloads, archives). Workspaces are
+//! pruned by the retention policy in the config (keep the last N runs and/or
+//! drop anything older than M days) when the app starts and after every run,
+//! and can be purged manually with the `purge_artifacts` command.
+
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Directory holding all run workspaces
+pub static WORKSPACE_ROOT: OnceLock<PathBuf> = OnceLock::new();
+
+/// Disk usage of one run's workspace
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RunArtifacts {
+    pub run_id: String,
+    pub bytes: u64,
+    pub files: usize,
+    /// Last modification, in milliseconds since the epoch
+    pub modified_at: i64,
+}
+
+/// Disk usage of all workspaces, most recently modified first
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct ArtifactUsage {
+    pub total_bytes: u64,
+    pub runs: Vec<RunArtifacts>,
+}
+
+/// Workspaces removed by a purge
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+pub struct PurgeReport {
+    pub removed_runs: Vec<String>,
+    pub freed_bytes: u64,
+}
+
+pub fn init_workspaces(config_dir: &Path) {
+    let dir = config_dir.join("workspaces");
+    let _ = fs::create_dir_all(&dir);
+    let _ = WORKSPACE_ROOT.set(dir);
+}
+
+/// The workspace directory for `run_id`, created on first use
+pub fn run_dir(run_id: &str) -> Result<PathBuf, String> {
+    let root = WORKSPACE_ROOT
+        .get()
+        .ok_or_else(|| "Workspaces not initialized".to_string())?;
+    let dir = run_path(root, run_id)?;
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(dir)
+}
+
+/// The directory of `run_id` directly under `root`. Ids left empty by
+/// sanitizing are refused, since they would name the root itself.
+fn run_path(root: &Path, run_id: &str) -> Result<PathBuf, String> {
+    let name = sanitize(run_id);
+    if name.is_empty() {
+        return Err(format!("Invalid run id '{}'", run_id));
+    }
+    let dir = root.join(name);
+    if dir.parent() != Some(root) {
+        return Err(format!("Invalid run id '{}'", run_id));
+    }
+    Ok(dir)
+}
+
+/// Keep run ids from escaping the workspace root
+fn sanitize(run_id: &str) -> String {
+    run_id
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+/// Total size, file count and newest modification time under `dir`
+fn measure(dir: &Path) -> (u64, usize, SystemTime) {
+    let mut bytes = 0;
+    let mut files = 0;
+    let mut newest = fs::metadata(dir)
+        .and_then(|m| m.modified())
+        .unwrap_or(UNIX_EPOCH);
+    let mut stack = vec![dir.to_path_buf()];
+    while let Some(path) = stack.pop() {
+        let Ok(entries) = fs::read_dir(&path) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let Ok(meta) = entry.metadata() else {
+                continue;
+            };
+            if let Ok(modified) = meta.modified() {
+                newest = newest.max(modified);
+            }
+            if meta.is_dir() {
+                stack.push(entry.path());
+            } else {
+                bytes += meta.len();
+                files += 1;
+            }
+        }
+    }
+    (bytes, files, newest)
+}
+
+pub fn usage(root: &Path) -> ArtifactUsage {
+    let mut runs: Vec<RunArtifacts> = fs::read_dir(root)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .map(|entry| {
+            let (bytes, files, modified) = measure(&entry.path());
+            RunArtifacts {
+                run_id: entry.file_name().to_string_lossy().to_string(),
+                bytes,
+                files,
+                modified_at: modified
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_millis() as i64,
+            }
+        })
+        .collect();
+    runs.sort_by(|a, b| b.modified_at.cmp(&a.modified_at));
+    ArtifactUsage {
+        total_bytes: runs.iter().map(|r| r.bytes).sum(),
+        runs,
+    }
+}
+
+/// Delete one run's workspace
+pub fn purge_run(root: &Path, run_id: &str) -> Result<PurgeReport, String> {
+    let dir = run_path(root, run_id)?;
+    if !dir.is_dir() {
+        return Ok(PurgeReport::default());
+    }
+    let (bytes, _, _) = measure(&dir);
+    fs::remove_dir_all(&dir).map_err(|e| e.to_string())?;
+    Ok(PurgeReport {
+        removed_runs: vec![run_id.to_string()],
+        freed_bytes: bytes,
+    })
+}
+
+/// Remove workspaces beyond the newest `keep_runs` or older than
+/// `max_age_days`, never touching the `protected` (still running) runs
+pub fn apply_retention(
+    root: &Path,
+    keep_runs: Option<usize>,
+    max_age_days: Option<u64>,
+    protected: &[String],
+) -> PurgeReport {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis() as i64;
+    let max_age_ms = max_age_days.map(|days| Duration::from_secs(days * 86_400).as_millis() as i64);
+
+    let mut report = PurgeReport::default();
+    for (idx, run) in usage(root).runs.iter().enumerate() {
+        if protected.contains(&run.run_id) {
+            continue;
+        }
+        let over_count = keep_runs.is_some_and(|keep| idx >= keep);
+        let too_old = max_age_ms.is_some_and(|max| now - run.modified_at > max);
+        if !(over_count || too_old) {
+            continue;
+        }
+        match purge_run(root, &run.run_id) {
+            Ok(purged) => {
+                report.removed_runs.extend(purged.removed_runs);
+                report.freed_bytes += purged.freed_bytes;
+            }
+            Err(e) => crate::trace_warn!(
+                "nexus::workspace",
+                "Failed to remove workspace",
+                run_id = run.run_id,
+                error = e
+            ),
+        }
+    }
+    report
+}
+
+/// Apply the configured retention policy to the global workspace root
+pub fn enforce_retention(config: &crate::config::Config) -> PurgeReport {
+    let Some(root) = WORKSPACE_ROOT.get() else {
+        return PurgeReport::default();
+    };
+    if config.artifact_retention_runs.is_none() && config.artifact_retention_days.is_none() {
+        return PurgeReport::default();
+    }
+    let protected = crate::run::active_ids();
+    let report = apply_retention(
+        root,
+        config.artifact_retention_runs,
+        config.artifact_retention_days,
+        &protected,
+    );
+    if !report.removed_runs.is_empty() {
+        crate::trace_info!(
+            "nexus::workspace",
+            "Old run workspaces removed",
+            runs = report.removed_runs.len(),
+            freed_bytes = report.freed_bytes
+        );
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retention_keeps_newest_and_protected() {
+        let root = std::env::temp_dir().join(format!("nexus-ws-{}", uuid::Uuid::new_v4()));
+        for (i, run) in ["old", "middle", "new"].iter().enumerate() {
+            let dir = root.join(run);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("shot.png"), vec![0u8; 10 * (i + 1)]).unwrap();
+            std::thread::sleep(Duration::from_millis(20));
+        }
+
+        let before = usage(&root);
+        assert_eq!(before.total_bytes, 60);
+        assert_eq!(before.runs[0].run_id, "new");
+
+        let report = apply_retention(&root, Some(1), None, &["old".to_string()]);
+        assert_eq!(report.removed_runs, vec!["middle"]);
+        assert_eq!(report.freed_bytes, 20);
+        let runs: Vec<_> = usage(&root).runs.into_iter().map(|r| r.run_id).collect();
+        assert_eq!(runs, vec!["new", "old"]);
+
+        assert_eq!(purge_run(&root, "missing").unwrap().freed_bytes, 0);
+        assert_eq!(purge_run(&root, "new").unwrap().freed_bytes, 30);
+        // Ids that sanitize to nothing would otherwise name the root
+        assert!(purge_run(&root, "").is_err());
+        assert!(purge_run(&root, "../..").is_err());
+        assert!(root.is_dir());
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -1,5 +1,6 @@
-import { useState, useCallback } from "react";
+import { useState, useCallback, useEffect } from "react";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import "./App.css";
 import { Sidebar } from "./components/Sidebar";
 import { BrowserPreview } from "./components/BrowserPreview";
@@ -11,23 +12,67 @@ import { TraceViewer } from "./components/TraceViewer";
 
 type ActiveView = "main" | "traces";
 
+interface RunResult {
+  run_id: string;
+  state: "queued" | "running" | "completed" | "failed";
+  report: string | null;
+  error: string | null;
+  retried_as: string | null;
+}
+
 function App() {
   const [prompt, setPrompt] = useState("");
   const [loading, setLoading] = useState(false);
+  const [runId, setRunId] = useState<string | null>(null);
+  const [paused, setPaused] = useState(false);
   const [showSettings, setShowSettings] = useState(false);
   const [showLeftPanel, setShowLeftPanel] = useState(true);
   const [activeView, setActiveView] = useState<ActiveView>("main");
 
+  // run_agent returns as soon as the run is queued; it finishes later
+  useEffect(() => {
+    if (!runId) return;
+    const unlisten = listen<RunResult>("run-finished", (event) => {
+      if (event.payload.run_id !== runId) return;
+      if (event.payload.error) console.error("Agent error:", event.payload.error);
+      setRunId(null);
+      setPaused(false);
+      setLoading(false);
+    });
+    // The run may have finished before the listener was registered
+    invoke<RunResult>("get_run_result", { runId })
+      .then((result) => {
+        if (result.state === "completed" || result.state === "failed") {
+          setRunId(null);
+          setLoading(false);
+        }
+      })
+      .catch(() => {});
+    return () => {
+      unlisten.then((f) => f());
+    };
+  }, [runId]);
+
+  // Pause the run so the user can drive the browser, then hand back control
+  const togglePause = async () => {
+    if (!runId) return;
+    try {
+      await invoke(paused ? "unpause_run" : "pause_run", { runId });
+      setPaused(!paused);
+    } catch (err) {
+      console.error("Pause error:", err);
+    }
+  };
+
   const handleSubmit = async (e: React.FormEvent) => {
     e.preventDefault();
     if (!prompt.trim() || loading) return;
 
     setLoading(true);
     try {
-      await invoke("run_agent", { prompt });
+      setRunId(await invoke<string>("run_agent", { prompt }));
     } catch (err) {
       console.error("Agent error:", err);
-    } finally {
       setLoading(false);
     }
   };
@@ -64,6 +109,15 @@ function App() {
             </h1>
           </div>
           <div className="flex items-center gap-3">
+            {runId && (
+              <button
+                onClick={togglePause}
+                className="text-[10px] uppercase font-bold text-yellow-500 hover:text-yellow-300 transition-colors"
+                title="Pause the agent to take over the browser"
+              >
+                {paused ? "Hand back control" : "Take over"}
+              </button>
+            )}
             <button
               onClick={() => setShowLeftPanel(!showLeftPanel)}
               className="text-[10px] uppercase font-bold text-gray-500 hover:text-white transition-colors"
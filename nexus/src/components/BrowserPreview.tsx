@@ -1,18 +1,30 @@
-import { useEffect, useState } from 'react';
+import { useEffect, useRef, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
 
+interface PageContent {
+    content: string;
+    total_chars: number;
+    truncated_fraction: number;
+    condensed: boolean;
+}
+
 export function BrowserPreview() {
     const [screenshot, setScreenshot] = useState<string | null>(null);
     const [url, setUrl] = useState<string>('');
     const [loading, setLoading] = useState(false);
+    const [address, setAddress] = useState('');
+    // The page as the agent's tools return it, shown instead of the screenshot
+    const [agentView, setAgentView] = useState<PageContent | null>(null);
+    // Run that navigated last; concurrent runs each browse in their own context
+    const runId = useRef<string | null>(null);
 
     const updatePreview = async () => {
         try {
             setLoading(true);
             const [currentUrl, currentScreenshot] = await Promise.all([
-                invoke<string>('get_current_url'),
-                invoke<string>('take_screenshot'),
+                invoke<string>('get_current_url', { runId: runId.current }),
+                invoke<string>('take_screenshot', { runId: runId.current }),
             ]);
             setUrl(currentUrl);
             setScreenshot(currentScreenshot);
@@ -23,12 +35,58 @@ export function BrowserPreview() {
         }
     };
 
+    // Manual navigation, e.g. to log in before handing over to the agent
+    const navigate = async (e: React.FormEvent) => {
+        e.preventDefault();
+        if (!address.trim()) return;
+        try {
+            setLoading(true);
+            runId.current = null;
+            setUrl(await invoke<string>('navigate', { url: address }));
+            setAddress('');
+            await updatePreview();
+        } catch (err) {
+            alert('Navigation failed: ' + err);
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const toggleAgentView = async () => {
+        if (agentView) {
+            setAgentView(null);
+            return;
+        }
+        try {
+            setLoading(true);
+            setAgentView(await invoke<PageContent>('get_page_markdown', { runId: runId.current }));
+        } catch (err) {
+            alert('Could not read the page: ' + err);
+        } finally {
+            setLoading(false);
+        }
+    };
+
+    const restartBrowser = async () => {
+        try {
+            setLoading(true);
+            await invoke('restart_browser');
+            setScreenshot(null);
+            setUrl('');
+        } catch (err) {
+            alert('Could not restart the browser: ' + err);
+        } finally {
+            setLoading(false);
+        }
+    };
+
     useEffect(() => {
         updatePreview();
 
         // Update on navigation events from backend
         const unlisten = listen('browser-update', (event: any) => {
             setUrl(event.payload.url);
+            runId.current = event.payload.run_id ?? null;
             updatePreview();
         });
 
@@ -45,21 +103,55 @@ export function BrowserPreview() {
         <div className="bg-gray-800/30 rounded-lg p-4 border border-gray-700 h-full flex flex-col">
             <div className="flex justify-between items-center mb-4">
                 <h2 className="text-lg font-bold text-purple-400">Browser Live</h2>
-                <button
-                    onClick={updatePreview}
-                    disabled={loading}
-                    className="text-[10px] bg-blue-500/20 hover:bg-blue-500/40 text-blue-300 px-2 py-1 rounded transition-colors disabled:opacity-50"
-                >
-                    {loading ? 'Refreshing...' : 'Refresh'}
-                </button>
+                <div className="flex gap-2">
+                    <button
+                        onClick={toggleAgentView}
+                        disabled={loading}
+                        title="Show the page as Markdown, the way the agent reads it"
+                        className="text-[10px] bg-purple-500/20 hover:bg-purple-500/40 text-purple-300 px-2 py-1 rounded transition-colors disabled:opacity-50"
+                    >
+                        {agentView ? 'Screenshot' : 'Agent view'}
+                    </button>
+                    <button
+                        onClick={restartBrowser}
+                        disabled={loading}
+                        title="Relaunch the browser with the saved launch settings"
+                        className="text-[10px] bg-gray-500/20 hover:bg-gray-500/40 text-gray-300 px-2 py-1 rounded transition-colors disabled:opacity-50"
+                    >
+                        Restart
+                    </button>
+                    <button
+                        onClick={updatePreview}
+                        disabled={loading}
+                        className="text-[10px] bg-blue-500/20 hover:bg-blue-500/40 text-blue-300 px-2 py-1 rounded transition-colors disabled:opacity-50"
+                    >
+                        {loading ? 'Refreshing...' : 'Refresh'}
+                    </button>
+                </div>
             </div>
 
             <div className="flex-1 flex flex-col min-h-0">
-                <div className="bg-black/40 px-3 py-1.5 rounded-t border-t border-x border-gray-800 text-[10px] text-gray-400 truncate">
-                    {url || 'No active session'}
-                </div>
+                <form onSubmit={navigate} className="bg-black/40 rounded-t border-t border-x border-gray-800">
+                    <input
+                        type="text"
+                        value={address}
+                        placeholder={url || 'No active session'}
+                        onChange={(e) => setAddress(e.target.value)}
+                        className="w-full bg-transparent px-3 py-1.5 text-[10px] text-gray-300 placeholder-gray-400 truncate focus:outline-none"
+                    />
+                </form>
                 <div className="flex-1 bg-gray-900 rounded-b border border-gray-800 overflow-hidden relative group">
-                    {screenshot ? (
+                    {agentView ? (
+                        <div className="absolute inset-0 overflow-auto p-3">
+                            <p className="text-[10px] text-gray-500 mb-2">
+                                {agentView.total_chars} chars
+                                {agentView.condensed && ' · condensed'}
+                                {agentView.truncated_fraction > 0 &&
+                                    ` · ${Math.round(agentView.truncated_fraction * 100)}% truncated`}
+                            </p>
+                            <pre className="text-[11px] text-gray-300 whitespace-pre-wrap">{agentView.content}</pre>
+                        </div>
+                    ) : screenshot ? (
                         <img
                             src={screenshot}
                             alt="Browser Preview"
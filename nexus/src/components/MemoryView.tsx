@@ -2,18 +2,34 @@ import { useEffect, useState } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 
 interface MemoryEntry {
+  id: string;
   content: string;
   tags: string[];
   timestamp: number;
+  source_url?: string;
+  run_id?: string;
+}
+
+interface TagCount {
+  tag: string;
+  count: number;
 }
 
 export function MemoryView() {
   const [memories, setMemories] = useState<MemoryEntry[]>([]);
+  const [editingId, setEditingId] = useState<string | null>(null);
+  const [draftContent, setDraftContent] = useState('');
+  const [draftTags, setDraftTags] = useState('');
+  const [tags, setTags] = useState<TagCount[]>([]);
+  const [selectedTag, setSelectedTag] = useState<string | null>(null);
 
   const fetchMemories = async () => {
     try {
-      const res = await invoke<MemoryEntry[]>('get_memories');
+      const res = selectedTag
+        ? await invoke<MemoryEntry[]>('get_memories_by_tag', { tag: selectedTag })
+        : await invoke<MemoryEntry[]>('get_memories');
       setMemories(res);
+      setTags(await invoke<TagCount[]>('list_memory_tags'));
     } catch (err) {
       console.error('Failed to fetch memories:', err);
     }
@@ -28,11 +44,38 @@ export function MemoryView() {
     }
   };
 
+  const startEditing = (mem: MemoryEntry) => {
+    setEditingId(mem.id);
+    setDraftContent(mem.content);
+    setDraftTags(mem.tags.join(', '));
+  };
+
+  const saveMemory = async () => {
+    if (!editingId) return;
+    try {
+      const tags = draftTags.split(',').map((t) => t.trim()).filter(Boolean);
+      const updated = await invoke<MemoryEntry>('update_memory', { id: editingId, content: draftContent, tags });
+      setMemories((prev) => prev.map((m) => (m.id === updated.id ? updated : m)));
+      setEditingId(null);
+    } catch (err) {
+      console.error('Failed to update memory:', err);
+    }
+  };
+
+  const deleteMemory = async (id: string) => {
+    try {
+      await invoke('delete_memory', { id });
+      setMemories((prev) => prev.filter((m) => m.id !== id));
+    } catch (err) {
+      console.error('Failed to delete memory:', err);
+    }
+  };
+
   useEffect(() => {
     fetchMemories();
     const interval = setInterval(fetchMemories, 5000);
     return () => clearInterval(interval);
-  }, []);
+  }, [selectedTag]);
 
   return (
     <div className="bg-gray-800/30 rounded-lg p-4 border border-gray-700 h-full flex flex-col">
@@ -45,13 +88,64 @@ export function MemoryView() {
           Clear
         </button>
       </div>
+      {tags.length > 0 && (
+        <div className="mb-3 flex flex-wrap gap-1">
+          {tags.map((t) => (
+            <button
+              key={t.tag}
+              onClick={() => setSelectedTag(selectedTag === t.tag ? null : t.tag)}
+              className={`text-[10px] px-1.5 py-0.5 rounded transition-colors ${
+                selectedTag === t.tag ? 'bg-blue-500 text-white' : 'bg-blue-500/20 text-blue-300 hover:bg-blue-500/40'
+              }`}
+            >
+              #{t.tag} ({t.count})
+            </button>
+          ))}
+        </div>
+      )}
       <div className="flex-1 overflow-y-auto space-y-3 pr-2">
         {memories.length === 0 ? (
           <p className="text-gray-500 text-sm italic">No memories yet...</p>
         ) : (
-          memories.map((mem, i) => (
-            <div key={i} className="bg-gray-900/50 p-3 rounded border border-gray-800 text-sm">
-              <p className="text-gray-300 whitespace-pre-wrap">{mem.content}</p>
+          memories.map((mem) => (
+            <div key={mem.id} className="bg-gray-900/50 p-3 rounded border border-gray-800 text-sm">
+              {editingId === mem.id ? (
+                <div className="space-y-2">
+                  <textarea
+                    value={draftContent}
+                    onChange={(e) => setDraftContent(e.target.value)}
+                    rows={4}
+                    className="w-full bg-gray-800 border border-gray-700 rounded px-2 py-1 text-gray-200"
+                  />
+                  <input
+                    type="text"
+                    value={draftTags}
+                    placeholder="tags, comma separated"
+                    onChange={(e) => setDraftTags(e.target.value)}
+                    className="w-full bg-gray-800 border border-gray-700 rounded px-2 py-1 text-xs text-gray-200"
+                  />
+                  <div className="flex gap-2 justify-end">
+                    <button onClick={() => setEditingId(null)} className="text-xs text-gray-400 hover:text-gray-200">
+                      Cancel
+                    </button>
+                    <button onClick={saveMemory} className="text-xs bg-blue-500/20 hover:bg-blue-500/40 text-blue-300 px-2 py-1 rounded">
+                      Save
+                    </button>
+                  </div>
+                </div>
+              ) : (
+                <div className="flex justify-between gap-2">
+                  <p className="text-gray-300 whitespace-pre-wrap">{mem.content}</p>
+                  <div className="flex gap-2 shrink-0 text-[10px]">
+                    <button onClick={() => startEditing(mem)} className="text-gray-500 hover:text-blue-300">
+                      Edit
+                    </button>
+                    <button onClick={() => deleteMemory(mem.id)} className="text-gray-500 hover:text-red-300">
+                      Delete
+                    </button>
+                  </div>
+                </div>
+              )}
               {mem.tags.length > 0 && (
                 <div className="mt-2 flex flex-wrap gap-1">
                   {mem.tags.map((tag, j) => (
@@ -63,6 +157,15 @@ export function MemoryView() {
               )}
               <p className="text-[10px] text-gray-600 mt-2">
                 {new Date(mem.timestamp * 1000).toLocaleString()}
+                {mem.source_url && (
+                  <>
+                    {' · '}
+                    <a href={mem.source_url} target="_blank" rel="noreferrer" className="hover:text-blue-300 underline">
+                      {new URL(mem.source_url).hostname}
+                    </a>
+                  </>
+                )}
+                {mem.run_id && <span title={mem.run_id}>{' · '}run {mem.run_id.slice(0, 8)}</span>}
               </p>
             </div>
           ))
@@ -1,11 +1,47 @@
 import { useState, useEffect } from 'react';
 import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+
+interface ProviderSettings {
+    api_key: string;
+    model: string;
+    base_url: string | null;
+}
 
 interface Config {
     provider: string;
     api_key: string;
     model: string;
     base_url: string | null;
+    providers?: Record<string, ProviderSettings>;
+    ollama_host?: string;
+    ollama_port?: number;
+    temperature?: number | null;
+    max_output_tokens?: number | null;
+    disabled_tools?: string[];
+}
+
+interface ToolInfo {
+    name: string;
+    description: string;
+    parameters: unknown;
+    enabled: boolean;
+    plugin: boolean;
+}
+
+interface ModelInfo {
+    id: string;
+    name: string | null;
+    context_length: number | null;
+}
+
+interface ConnectionTest {
+    ok: boolean;
+    latency_ms: number;
+    model_count: number | null;
+    model_available: boolean | null;
+    error_kind: string | null;
+    error: string | null;
 }
 
 interface SettingsProps {
@@ -19,6 +55,10 @@ const PROVIDERS = [
     { id: 'gemini', name: 'Gemini' },
     { id: 'grok', name: 'Grok' },
     { id: 'deepseek', name: 'DeepSeek' },
+    { id: 'mistral', name: 'Mistral' },
+    { id: 'groq', name: 'Groq' },
+    { id: 'ollama', name: 'Ollama (local)' },
+    { id: 'custom', name: 'Custom (OpenAI-compatible)' },
 ];
 
 export function Settings({ onClose }: SettingsProps) {
@@ -30,6 +70,12 @@ export function Settings({ onClose }: SettingsProps) {
     });
     const [showBaseUrl, setShowBaseUrl] = useState(false);
     const [saving, setSaving] = useState(false);
+    const [models, setModels] = useState<ModelInfo[]>([]);
+    const [modelsError, setModelsError] = useState<string | null>(null);
+    const [loadingModels, setLoadingModels] = useState(false);
+    const [testResult, setTestResult] = useState<ConnectionTest | null>(null);
+    const [testing, setTesting] = useState(false);
+    const [tools, setTools] = useState<ToolInfo[]>([]);
 
     useEffect(() => {
         const loadConfig = async () => {
@@ -42,14 +88,95 @@ export function Settings({ onClose }: SettingsProps) {
             }
         };
         loadConfig();
+        invoke<ToolInfo[]>('list_tools')
+            .then(setTools)
+            .catch((err) => console.error('Failed to list tools:', err));
+
+        // Pick up edits made to config.json outside the app
+        const unlisten = listen<Config>('config-changed', (event) => {
+            setConfig(event.payload);
+            setShowBaseUrl(!!event.payload.base_url);
+        });
+        return () => {
+            unlisten.then((f) => f());
+        };
     }, []);
 
+    // Keep each provider's key and model, so switching back restores them
+    const switchProvider = (provider: string) => {
+        const providers = {
+            ...(config.providers ?? {}),
+            [config.provider]: { api_key: config.api_key, model: config.model, base_url: config.base_url },
+        };
+        const saved = providers[provider];
+        setConfig({
+            ...config,
+            providers,
+            provider,
+            api_key: saved?.api_key ?? '',
+            model: saved?.model ?? '',
+            base_url: saved?.base_url ?? null,
+        });
+        setShowBaseUrl(!!saved?.base_url);
+        setModels([]);
+        setModelsError(null);
+        setTestResult(null);
+    };
+
+    const loadModels = async () => {
+        setLoadingModels(true);
+        setModelsError(null);
+        try {
+            const available = await invoke<ModelInfo[]>('list_models', {
+                provider: config.provider,
+                apiKey: config.api_key,
+                baseUrl: showBaseUrl || config.provider === 'custom' ? config.base_url : null,
+            });
+            setModels(available);
+        } catch (err) {
+            setModels([]);
+            setModelsError(String(err));
+        } finally {
+            setLoadingModels(false);
+        }
+    };
+
+    const testConnection = async () => {
+        setTesting(true);
+        setTestResult(null);
+        try {
+            const result = await invoke<ConnectionTest>('test_provider_connection', {
+                config: {
+                    ...config,
+                    base_url: showBaseUrl || config.provider === 'custom' ? config.base_url : null,
+                },
+            });
+            setTestResult(result);
+        } catch (err) {
+            setTestResult({
+                ok: false,
+                latency_ms: 0,
+                model_count: null,
+                model_available: null,
+                error_kind: 'other',
+                error: String(err),
+            });
+        } finally {
+            setTesting(false);
+        }
+    };
+
+    const toggleTool = (name: string, enabled: boolean) => {
+        const disabled = (config.disabled_tools ?? []).filter((tool) => tool !== name);
+        setConfig({ ...config, disabled_tools: enabled ? disabled : [...disabled, name] });
+    };
+
     const handleSave = async () => {
         setSaving(true);
         try {
             const finalConfig = {
                 ...config,
-                base_url: showBaseUrl ? config.base_url : null,
+                base_url: showBaseUrl || config.provider === 'custom' ? config.base_url : null,
             };
             await invoke('save_config', { config: finalConfig });
             onClose();
@@ -82,7 +209,7 @@ export function Settings({ onClose }: SettingsProps) {
                         </div>
                         <select
                             value={config.provider}
-                            onChange={(e) => setConfig({ ...config, provider: e.target.value })}
+                            onChange={(e) => switchProvider(e.target.value)}
                             className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
                         >
                             {PROVIDERS.map((p) => (
@@ -91,31 +218,93 @@ export function Settings({ onClose }: SettingsProps) {
                         </select>
                     </div>
 
-                    {/* API Key */}
-                    <div className="space-y-2">
-                        <label className="text-sm font-medium text-gray-300">API Key</label>
-                        <input
-                            type="password"
-                            value={config.api_key}
-                            placeholder="Enter your API key"
-                            onChange={(e) => setConfig({ ...config, api_key: e.target.value })}
-                            className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
-                        />
-                        <p className="text-[10px] text-gray-500">API keys are stored locally in your app config directory.</p>
-                    </div>
+                    {/* API Key (not needed for local Ollama models) */}
+                    {config.provider !== 'ollama' && (
+                        <div className="space-y-2">
+                            <label className="text-sm font-medium text-gray-300">API Key</label>
+                            <input
+                                type="password"
+                                value={config.api_key}
+                                placeholder="Enter your API key"
+                                onChange={(e) => setConfig({ ...config, api_key: e.target.value })}
+                                className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
+                            />
+                            <p className="text-[10px] text-gray-500">API keys are stored locally in your app config directory.</p>
+                        </div>
+                    )}
+
+                    {/* Ollama server */}
+                    {config.provider === 'ollama' && (
+                        <div className="flex gap-3">
+                            <div className="space-y-2 flex-1">
+                                <label className="text-sm font-medium text-gray-300">Ollama Host</label>
+                                <input
+                                    type="text"
+                                    value={config.ollama_host ?? 'localhost'}
+                                    placeholder="localhost"
+                                    onChange={(e) => setConfig({ ...config, ollama_host: e.target.value })}
+                                    className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
+                                />
+                            </div>
+                            <div className="space-y-2 w-28">
+                                <label className="text-sm font-medium text-gray-300">Port</label>
+                                <input
+                                    type="number"
+                                    value={config.ollama_port ?? 11434}
+                                    onChange={(e) => setConfig({ ...config, ollama_port: Number(e.target.value) })}
+                                    className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
+                                />
+                            </div>
+                        </div>
+                    )}
 
                     {/* Model */}
                     <div className="space-y-2">
-                        <label className="text-sm font-medium text-gray-300">Model</label>
+                        <div className="flex justify-between items-center">
+                            <label className="text-sm font-medium text-gray-300">Model</label>
+                            <button
+                                onClick={loadModels}
+                                disabled={loadingModels}
+                                className="text-xs text-blue-400 hover:underline disabled:opacity-50"
+                            >
+                                {loadingModels ? 'Loading...' : 'Load available models'}
+                            </button>
+                        </div>
                         <input
                             type="text"
+                            list="model-options"
                             value={config.model}
-                            placeholder="e.g. gpt-4o, claude-3-opus"
+                            placeholder={config.provider === 'ollama' ? 'e.g. llama3.1, qwen2.5' : 'e.g. gpt-4o, claude-3-opus'}
                             onChange={(e) => setConfig({ ...config, model: e.target.value })}
                             className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
                         />
+                        <datalist id="model-options">
+                            {models.map((m) => (
+                                <option key={m.id} value={m.id}>
+                                    {[m.name, m.context_length ? `${Math.round(m.context_length / 1000)}k context` : null]
+                                        .filter(Boolean)
+                                        .join(' · ')}
+                                </option>
+                            ))}
+                        </datalist>
+                        {modelsError && <p className="text-[10px] text-red-400">{modelsError}</p>}
                     </div>
 
+                    {/* Base URL (required for custom OpenAI-compatible servers) */}
+                    {config.provider === 'custom' && (
+                        <div className="space-y-2">
+                            <label className="text-sm font-medium text-gray-300">Base URL</label>
+                            <input
+                                type="text"
+                                value={config.base_url || ''}
+                                placeholder="http://localhost:1234/v1"
+                                onChange={(e) => setConfig({ ...config, base_url: e.target.value })}
+                                className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
+                            />
+                            <p className="text-[10px] text-gray-500">Any server speaking the OpenAI chat-completions API, e.g. LM Studio, vLLM or llama.cpp.</p>
+                        </div>
+                    )}
+
                     {/* Custom Base URL (OpenAI only) */}
                     {config.provider === 'openai' && (
                         <div className="space-y-3 pt-2">
@@ -141,9 +330,76 @@ export function Settings({ onClose }: SettingsProps) {
                             )}
                         </div>
                     )}
+
+                    {/* Generation parameters */}
+                    <div className="flex gap-3">
+                        <div className="space-y-2 flex-1">
+                            <label className="text-sm font-medium text-gray-300">Temperature</label>
+                            <input
+                                type="number"
+                                min={0}
+                                max={config.provider === 'anthropic' ? 1 : 2}
+                                step={0.1}
+                                value={config.temperature ?? ''}
+                                placeholder="Provider default"
+                                onChange={(e) => setConfig({ ...config, temperature: e.target.value === '' ? null : Number(e.target.value) })}
+                                className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
+                            />
+                        </div>
+                        <div className="space-y-2 flex-1">
+                            <label className="text-sm font-medium text-gray-300">Max Output Tokens</label>
+                            <input
+                                type="number"
+                                min={1}
+                                value={config.max_output_tokens ?? ''}
+                                placeholder="Provider default"
+                                onChange={(e) => setConfig({ ...config, max_output_tokens: e.target.value === '' ? null : Number(e.target.value) })}
+                                className="w-full bg-gray-800 border border-gray-700 rounded-lg px-4 py-2.5 text-white focus:outline-none focus:ring-2 focus:ring-blue-500 transition-all"
+                            />
+                        </div>
+                    </div>
+
+                    {/* Agent tools */}
+                    {tools.length > 0 && (
+                        <div className="space-y-2">
+                            <label className="text-sm font-medium text-gray-300">Tools</label>
+                            <div className="max-h-40 overflow-y-auto space-y-1 pr-1">
+                                {tools.map((tool) => (
+                                    <div key={tool.name} className="flex items-center gap-3" title={tool.description}>
+                                        <input
+                                            type="checkbox"
+                                            id={`tool-${tool.name}`}
+                                            checked={!(config.disabled_tools ?? []).includes(tool.name)}
+                                            onChange={(e) => toggleTool(tool.name, e.target.checked)}
+                                            className="w-4 h-4 rounded border-gray-700 bg-gray-800 text-blue-600 focus:ring-blue-500"
+                                        />
+                                        <label htmlFor={`tool-${tool.name}`} className="text-sm text-gray-300 font-mono">
+                                            {tool.name}
+                                            {tool.plugin && <span className="ml-2 text-[10px] text-gray-500">plugin</span>}
+                                        </label>
+                                    </div>
+                                ))}
+                            </div>
+                        </div>
+                    )}
                 </div>
 
+                {testResult && (
+                    <div className={`px-6 py-3 text-xs border-t border-gray-800 ${testResult.ok ? 'text-green-400' : 'text-red-400'}`}>
+                        {testResult.ok
+                            ? `Connected in ${testResult.latency_ms} ms${testResult.model_count !== null ? ` · ${testResult.model_count} models available` : ''}`
+                            : testResult.error}
+                    </div>
+                )}
+
                 <div className="p-6 bg-gray-900/50 border-t border-gray-800 flex gap-3">
+                    <button
+                        onClick={testConnection}
+                        disabled={testing}
+                        className="flex-1 px-4 py-2.5 rounded-lg border border-gray-700 text-gray-300 hover:bg-gray-800 transition-colors disabled:opacity-50"
+                    >
+                        {testing ? 'Testing...' : 'Test Connection'}
+                    </button>
                     <button
                         onClick={onClose}
                         className="flex-1 px-4 py-2.5 rounded-lg border border-gray-700 text-gray-300 hover:bg-gray-800 transition-colors"
@@ -2,7 +2,7 @@ import { useEffect, useState, useRef } from 'react';
 import { listen } from '@tauri-apps/api/event';
 
 interface AgentEvent {
-  type: 'system' | 'tool_call' | 'tool_result' | 'error' | 'success';
+  type: 'system' | 'tool_call' | 'tool_result' | 'error' | 'success' | 'rate_limit';
   message: string;
   timestamp: number;
 }
@@ -44,6 +44,7 @@ export function ThoughtStream() {
                 evt.type === 'tool_call' ? 'text-yellow-400' :
                 evt.type === 'tool_result' ? 'text-green-400' :
                 evt.type === 'success' ? 'text-blue-400 font-bold' :
+                evt.type === 'rate_limit' ? 'text-orange-300' :
                 'text-gray-300'
             }>
                 [{evt.type.toUpperCase()}] {evt.message}
@@ -13,6 +13,30 @@ interface TraceEvent {
     fields: string;
 }
 
+interface TracePage {
+    events: TraceEvent[];
+    total: number;
+}
+
+interface TraceStats {
+    buffered: number;
+    buffer_size: number;
+    pending: number;
+    evicted: number;
+    dropped: number;
+}
+
+interface TraceSession {
+    session_id: string;
+    started_at: number;
+    ended_at: number;
+    event_count: number;
+    run_ids: string[];
+}
+
+// Events fetched per page, newest page first
+const PAGE_SIZE = 500;
+
 const levelColors: Record<string, string> = {
     ERROR: "text-red-400 bg-red-500/10",
     WARN: "text-yellow-400 bg-yellow-500/10",
@@ -33,36 +57,79 @@ export function TraceViewer() {
     const [traces, setTraces] = useState<TraceEvent[]>([]);
     const [filter, setFilter] = useState<string>("");
     const [levelFilter, setLevelFilter] = useState<string>("ALL");
+    const [targetFilter, setTargetFilter] = useState<string>("");
+    const [page, setPage] = useState(0);
+    const [total, setTotal] = useState(0);
     const [autoScroll, setAutoScroll] = useState(true);
     const [expandedId, setExpandedId] = useState<string | null>(null);
+    const [sessions, setSessions] = useState<TraceSession[]>([]);
+    // null follows the live session
+    const [sessionId, setSessionId] = useState<string | null>(null);
+    const [stats, setStats] = useState<TraceStats | null>(null);
+
+    const loadSessions = async () => {
+        try {
+            setSessions(await invoke<TraceSession[]>("list_trace_sessions"));
+        } catch (err) {
+            console.error("Failed to load trace sessions:", err);
+        }
+    };
+
+    useEffect(() => {
+        loadSessions();
+    }, []);
 
     // Load initial traces
     useEffect(() => {
         const loadTraces = async () => {
             try {
-                const level = levelFilter === "ALL" ? null : levelFilter;
-                const result = await invoke<TraceEvent[]>("get_traces", { level });
-                setTraces(result);
+                const filter = {
+                    level: levelFilter === "ALL" ? null : levelFilter,
+                    target: targetFilter || null,
+                    limit: PAGE_SIZE,
+                    offset: page * PAGE_SIZE,
+                    newest_first: true,
+                };
+                const result = sessionId
+                    ? await invoke<TracePage>("get_session_traces", { sessionId, filter })
+                    : await invoke<TracePage>("get_traces", { filter });
+                setTraces(result.events.reverse());
+                setTotal(result.total);
+                if (!sessionId) {
+                    setStats(await invoke<TraceStats>("get_trace_stats"));
+                }
             } catch (err) {
                 console.error("Failed to load traces:", err);
             }
         };
 
         loadTraces();
+        if (sessionId) return;
         const interval = setInterval(loadTraces, 1000);
         return () => clearInterval(interval);
-    }, [levelFilter]);
+    }, [levelFilter, targetFilter, sessionId, page]);
 
-    // Listen for real-time trace events
+    // Stream real-time trace events while showing the newest page, only at
+    // the levels being displayed
     useEffect(() => {
-        const unlisten = listen<TraceEvent>("trace-event", (event) => {
-            setTraces((prev) => [...prev, event.payload]);
+        if (sessionId || page > 0) return;
+        invoke("set_trace_stream_level", { level: levelFilter === "ALL" ? "DEBUG" : levelFilter })
+            .catch((err) => console.error("Failed to subscribe to traces:", err));
+        const unlisten = listen<TraceEvent[]>("trace-events", (event) => {
+            const batch = event.payload.filter((trace) =>
+                (levelFilter === "ALL" || trace.level === levelFilter) &&
+                (!targetFilter || trace.target.startsWith(targetFilter))
+            );
+            if (batch.length > 0) {
+                setTraces((prev) => [...prev, ...batch].slice(-PAGE_SIZE));
+            }
         });
 
         return () => {
+            invoke("set_trace_stream_level", { level: null }).catch(() => {});
             unlisten.then((fn) => fn());
         };
-    }, []);
+    }, [sessionId, page, levelFilter, targetFilter]);
 
     // Auto-scroll to bottom
     useEffect(() => {
@@ -78,11 +145,21 @@ export function TraceViewer() {
         try {
             await invoke("clear_traces");
             setTraces([]);
+            setTotal(0);
+            setPage(0);
         } catch (err) {
             console.error("Failed to clear traces:", err);
         }
     };
 
+    const parseFields = (fields: string): Record<string, unknown> => {
+        try {
+            return JSON.parse(fields);
+        } catch {
+            return {};
+        }
+    };
+
     const filteredTraces = traces.filter((trace) => {
         if (filter && !trace.message.toLowerCase().includes(filter.toLowerCase()) &&
             !trace.target.toLowerCase().includes(filter.toLowerCase())) {
@@ -91,6 +168,24 @@ export function TraceViewer() {
         return true;
     });
 
+    // Span nesting, so child spans are indented beneath their parents
+    const spanParents = new Map<string, string | null>();
+    for (const trace of traces) {
+        const fields = parseFields(trace.fields);
+        if (typeof fields.span_id === "string") {
+            spanParents.set(fields.span_id, (fields.parent_span_id as string | null) ?? null);
+        }
+    }
+    const spanDepth = (spanId: unknown) => {
+        let depth = 0;
+        let parent = typeof spanId === "string" ? spanParents.get(spanId) : null;
+        while (parent && depth < 32) {
+            depth++;
+            parent = spanParents.get(parent);
+        }
+        return depth;
+    };
+
     const formatTimestamp = (ts: number) => {
         const date = new Date(ts);
         return date.toLocaleTimeString("en-US", {
@@ -101,13 +196,6 @@ export function TraceViewer() {
         }) + "." + String(date.getMilliseconds()).padStart(3, "0");
     };
 
-    const parseFields = (fields: string): Record<string, unknown> => {
-        try {
-            return JSON.parse(fields);
-        } catch {
-            return {};
-        }
-    };
 
     return (
         <div className="flex flex-col h-full bg-gray-950 rounded-xl border border-gray-800 overflow-hidden">
@@ -121,8 +209,45 @@ export function TraceViewer() {
                     <span className="text-xs text-gray-500">
                         {filteredTraces.length} events
                     </span>
+                    {!sessionId && stats && stats.evicted > 0 && (
+                        <span
+                            className="text-xs text-gray-500"
+                            title="Older events are kept in the trace history"
+                        >
+                            · {stats.evicted} older
+                        </span>
+                    )}
+                    {stats && stats.dropped > 0 && (
+                        <span
+                            className="text-xs text-red-400"
+                            title="Events that could not be saved to the trace database"
+                        >
+                            · {stats.dropped} dropped
+                        </span>
+                    )}
                 </div>
                 <div className="flex items-center gap-2">
+                    {total > PAGE_SIZE && (
+                        <>
+                            <button
+                                onClick={() => setPage(page + 1)}
+                                disabled={(page + 1) * PAGE_SIZE >= total}
+                                className="px-2 py-1 text-xs bg-gray-800 text-gray-300 rounded hover:bg-gray-700 disabled:opacity-40 transition-colors"
+                            >
+                                Older
+                            </button>
+                            <span className="text-xs text-gray-500">
+                                {Math.max(total - (page + 1) * PAGE_SIZE, 0) + 1}–{total - page * PAGE_SIZE} of {total}
+                            </span>
+                            <button
+                                onClick={() => setPage(page - 1)}
+                                disabled={page === 0}
+                                className="px-2 py-1 text-xs bg-gray-800 text-gray-300 rounded hover:bg-gray-700 disabled:opacity-40 transition-colors"
+                            >
+                                Newer
+                            </button>
+                        </>
+                    )}
                     <button
                         onClick={handleClear}
                         className="px-2 py-1 text-xs bg-red-500/20 text-red-400 rounded hover:bg-red-500/30 transition-colors"
@@ -141,9 +266,39 @@ export function TraceViewer() {
                     placeholder="Filter traces..."
                     className="flex-1 bg-gray-800 border border-gray-700 rounded px-2 py-1 text-xs text-white placeholder:text-gray-500 focus:outline-none focus:ring-1 focus:ring-blue-500"
                 />
+                <input
+                    type="text"
+                    value={targetFilter}
+                    onChange={(e) => {
+                        setTargetFilter(e.target.value);
+                        setPage(0);
+                    }}
+                    placeholder="Target, e.g. nexus::browser"
+                    className="w-44 bg-gray-800 border border-gray-700 rounded px-2 py-1 text-xs text-white placeholder:text-gray-500 focus:outline-none focus:ring-1 focus:ring-blue-500"
+                />
+                <select
+                    value={sessionId ?? ""}
+                    onFocus={loadSessions}
+                    onChange={(e) => {
+                        setSessionId(e.target.value || null);
+                        setPage(0);
+                    }}
+                    className="bg-gray-800 border border-gray-700 rounded px-2 py-1 text-xs text-white focus:outline-none focus:ring-1 focus:ring-blue-500"
+                >
+                    <option value="">Live session</option>
+                    {sessions.map((s) => (
+                        <option key={s.session_id} value={s.session_id}>
+                            {new Date(s.started_at).toLocaleString()} ({s.event_count}
+                            {s.run_ids.length > 0 && `, ${s.run_ids.length} run${s.run_ids.length === 1 ? "" : "s"}`})
+                        </option>
+                    ))}
+                </select>
                 <select
                     value={levelFilter}
-                    onChange={(e) => setLevelFilter(e.target.value)}
+                    onChange={(e) => {
+                        setLevelFilter(e.target.value);
+                        setPage(0);
+                    }}
                     className="bg-gray-800 border border-gray-700 rounded px-2 py-1 text-xs text-white focus:outline-none focus:ring-1 focus:ring-blue-500"
                 >
                     <option value="ALL">All Levels</option>
@@ -178,6 +333,7 @@ export function TraceViewer() {
                             const fields = parseFields(trace.fields);
                             const hasFields = Object.keys(fields).length > 0;
                             const isExpanded = expandedId === trace.id;
+                            const duration = typeof fields.duration_ms === "number" ? fields.duration_ms : null;
 
                             return (
                                 <div
@@ -204,10 +360,20 @@ export function TraceViewer() {
                                         </span>
 
                                         {/* Message */}
-                                        <span className="text-xs text-white/80 flex-1 truncate">
+                                        <span
+                                            className="text-xs text-white/80 flex-1 truncate"
+                                            style={{ paddingLeft: spanDepth(fields.span_id) * 12 }}
+                                        >
                                             {trace.message}
                                         </span>
 
+                                        {/* Span duration */}
+                                        {duration !== null && (
+                                            <span className="text-[10px] text-emerald-400 font-mono shrink-0">
+                                                {duration < 1000 ? `${duration} ms` : `${(duration / 1000).toFixed(1)} s`}
+                                            </span>
+                                        )}
+
                                         {/* Expand indicator */}
                                         {hasFields && (
                                             <span className="text-gray-500 text-xs">